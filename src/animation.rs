@@ -112,6 +112,10 @@ impl<V: Lerpable> Eased<V> {
         self.target_idx = target_idx;
     }
 
+    pub fn target_idx(&self) -> usize {
+        self.target_idx
+    }
+
     pub fn is_running(&self) -> bool {
         self.progress < 1.0
     }
@@ -0,0 +1,358 @@
+//! Tracks and controls the default audio devices.
+//!
+//! The actual protocol is hidden behind [`AudioBackend`], so that the rest of limbo only ever
+//! deals with [`AudioState`] snapshots and [`AudioCommand`]s.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Mutex, watch};
+
+use crate::message::Message;
+
+mod pulse;
+
+/// Snapshot of a sink or source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Device {
+    /// Index of the device on the server.
+    pub index: u32,
+    /// Volume, where `1.0` is 100%. May exceed `1.0` when the device is boosted.
+    pub volume: f32,
+    pub muted: bool,
+    /// Whether the active port is headphones or a headset.
+    pub headphones: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AudioState {
+    /// The default sink, `None` if there is none or the server is unreachable.
+    pub sink: Option<Device>,
+    /// The default source, `None` if there is none or the server is unreachable.
+    pub source: Option<Device>,
+    /// Whether any application is currently recording from a (non-monitor) source.
+    pub recording: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AudioCommand {
+    SetSinkVolume(f32),
+    /// Change the sink volume by the given amount, see [`stepped_volume`].
+    StepSinkVolume(f32),
+    SetSinkMute(bool),
+    SetSourceMute(bool),
+}
+
+/// The volume after stepping `volume` by `delta`. Stepping never boosts above 100%, but keeps an
+/// existing boost.
+///
+/// Steps are applied against the volume on the server rather than the last one seen by the bar, so
+/// that fast scrolling doesn't step from an outdated volume.
+pub fn stepped_volume(volume: f32, delta: f32) -> f32 {
+    (volume + delta).clamp(0.0, volume.max(1.0))
+}
+
+/// A connection to an audio server.
+///
+/// Every connection is only used from a single thread, so implementations may block. Once
+/// [`AudioBackend::wait_for_change`] was called on a connection, no other methods are called on it.
+pub trait AudioBackend: Send + 'static {
+    /// Reads the current state of the default devices.
+    fn query(&mut self) -> std::io::Result<AudioState>;
+
+    /// Blocks until the server reports a change that may affect the state.
+    fn wait_for_change(&mut self) -> std::io::Result<()>;
+
+    /// Applies `command` to the current default devices.
+    fn apply(&mut self, command: AudioCommand) -> std::io::Result<()>;
+}
+
+#[derive(Debug)]
+pub struct Audio {
+    commands: std::sync::mpsc::Sender<AudioCommand>,
+    rx: Arc<Mutex<watch::Receiver<AudioState>>>,
+}
+
+impl Audio {
+    pub fn new() -> Self {
+        Self::with_backend(pulse::PulseBackend::connect)
+    }
+
+    /// Creates the service on top of a custom backend. `connect` is called whenever a new
+    /// connection is needed, e.g. after the server restarted.
+    pub fn with_backend<B, F>(connect: F) -> Self
+    where
+        B: AudioBackend,
+        F: Fn() -> std::io::Result<B> + Send + Sync + 'static,
+    {
+        let connect = Arc::new(connect);
+        let (tx, rx) = watch::channel(AudioState::default());
+        let (commands, commands_rx) = std::sync::mpsc::channel();
+
+        // Events and commands use separate connections, since waiting for events blocks
+        let monitor_connect = connect.clone();
+        std::thread::Builder::new()
+            .name("audio-monitor".to_string())
+            .spawn(move || monitor(&*monitor_connect, tx))
+            .expect("failed to spawn audio monitor thread");
+        std::thread::Builder::new()
+            .name("audio-control".to_string())
+            .spawn(move || control(&*connect, commands_rx))
+            .expect("failed to spawn audio control thread");
+
+        Self {
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn send(&self, command: AudioCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "audio".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::AudioUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Publishes the state to `tx` whenever it changes, reconnecting with backoff on errors.
+fn monitor<B: AudioBackend>(
+    connect: &dyn Fn() -> std::io::Result<B>,
+    tx: watch::Sender<AudioState>,
+) {
+    let mut backoff = MIN_BACKOFF;
+    while !tx.is_closed() {
+        let result = (|| -> std::io::Result<()> {
+            let mut events = connect()?;
+            let mut queries = connect()?;
+            backoff = MIN_BACKOFF;
+            loop {
+                let state = queries.query()?;
+                tx.send_if_modified(|current| {
+                    let modified = *current != state;
+                    *current = state;
+                    modified
+                });
+                events.wait_for_change()?;
+            }
+        })();
+        if let Err(e) = result {
+            log::warn!("audio: {e}, reconnecting in {}s", backoff.as_secs());
+        }
+
+        tx.send_replace(AudioState::default());
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Applies commands from `commands_rx`, connecting lazily.
+fn control<B: AudioBackend>(
+    connect: &dyn Fn() -> std::io::Result<B>,
+    commands_rx: std::sync::mpsc::Receiver<AudioCommand>,
+) {
+    let mut backend: Option<B> = None;
+    for command in commands_rx {
+        // Retry once with a fresh connection, in case the server restarted in the meantime
+        for _ in 0..2 {
+            let result = match &mut backend {
+                Some(backend) => backend.apply(command),
+                None => connect().and_then(|b| backend.insert(b).apply(command)),
+            };
+            match result {
+                Ok(()) => break,
+                Err(e) => {
+                    log::warn!("audio: failed to apply {command:?}: {e}");
+                    backend = None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Condvar;
+
+    use super::*;
+
+    /// A fake audio server shared by all connections of a [`MockBackend`].
+    #[derive(Default)]
+    struct Server {
+        state: AudioState,
+        /// Incremented on every change, to wake up waiting connections.
+        generation: u64,
+        applied: Vec<AudioCommand>,
+        /// Number of connections that were opened.
+        connections: usize,
+        /// Fails the next `apply`, as if the server had restarted.
+        fail_next_apply: bool,
+    }
+
+    #[derive(Default, Clone)]
+    struct MockServer(Arc<(std::sync::Mutex<Server>, Condvar)>);
+
+    impl MockServer {
+        fn with_sink(volume: f32) -> Self {
+            let server = Self::default();
+            server.update(|server| {
+                server.state.sink = Some(Device {
+                    index: 0,
+                    volume,
+                    muted: false,
+                    headphones: false,
+                })
+            });
+            server
+        }
+
+        fn lock(&self) -> std::sync::MutexGuard<'_, Server> {
+            self.0.0.lock().unwrap()
+        }
+
+        fn update(&self, f: impl FnOnce(&mut Server)) {
+            let mut server = self.lock();
+            f(&mut server);
+            server.generation += 1;
+            self.0.1.notify_all();
+        }
+
+        fn connect(&self) -> std::io::Result<MockBackend> {
+            let mut server = self.lock();
+            server.connections += 1;
+            Ok(MockBackend {
+                server: self.clone(),
+                generation: server.generation,
+            })
+        }
+    }
+
+    struct MockBackend {
+        server: MockServer,
+        generation: u64,
+    }
+
+    impl AudioBackend for MockBackend {
+        fn query(&mut self) -> std::io::Result<AudioState> {
+            Ok(self.server.lock().state.clone())
+        }
+
+        fn wait_for_change(&mut self) -> std::io::Result<()> {
+            let (lock, changed) = &*self.server.0;
+            let server = changed
+                .wait_while(lock.lock().unwrap(), |server| {
+                    server.generation == self.generation
+                })
+                .unwrap();
+            self.generation = server.generation;
+            Ok(())
+        }
+
+        fn apply(&mut self, command: AudioCommand) -> std::io::Result<()> {
+            if std::mem::take(&mut self.server.lock().fail_next_apply) {
+                return Err(std::io::Error::other("connection reset"));
+            }
+            self.server.update(|server| {
+                server.applied.push(command);
+                let sink = server.state.sink.as_mut().unwrap();
+                match command {
+                    AudioCommand::SetSinkVolume(volume) => sink.volume = volume,
+                    AudioCommand::StepSinkVolume(delta) => {
+                        sink.volume = stepped_volume(sink.volume, delta)
+                    }
+                    AudioCommand::SetSinkMute(muted) => sink.muted = muted,
+                    AudioCommand::SetSourceMute(_) => {}
+                }
+            });
+            Ok(())
+        }
+    }
+
+    fn audio(server: &MockServer) -> Audio {
+        let server = server.clone();
+        Audio::with_backend(move || server.connect())
+    }
+
+    /// Waits until the published state matches `f`.
+    async fn wait_for(audio: &Audio, f: impl Fn(&AudioState) -> bool) -> AudioState {
+        let mut rx = audio.rx.lock().await;
+        let state = tokio::time::timeout(Duration::from_secs(5), rx.wait_for(|state| f(state)))
+            .await
+            .expect("timed out waiting for the audio state")
+            .unwrap();
+        state.clone()
+    }
+
+    fn sink_volume(state: &AudioState) -> Option<f32> {
+        state.sink.as_ref().map(|sink| sink.volume)
+    }
+
+    #[tokio::test]
+    async fn publishes_server_changes() {
+        let server = MockServer::with_sink(0.5);
+        let audio = audio(&server);
+        wait_for(&audio, |state| sink_volume(state) == Some(0.5)).await;
+
+        server.update(|server| server.state.recording = true);
+        let state = wait_for(&audio, |state| state.recording).await;
+        assert_eq!(sink_volume(&state), Some(0.5));
+    }
+
+    #[tokio::test]
+    async fn steps_from_the_last_requested_volume() {
+        let server = MockServer::with_sink(0.5);
+        let audio = audio(&server);
+        // Sent at once, before any of them is reflected in the published state
+        for _ in 0..3 {
+            audio.send(AudioCommand::StepSinkVolume(0.1));
+        }
+        wait_for(&audio, |state| {
+            sink_volume(state).is_some_and(|volume| (volume - 0.8).abs() < 1e-6)
+        })
+        .await;
+        assert_eq!(
+            server.lock().applied,
+            [AudioCommand::StepSinkVolume(0.1); 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_commands_on_a_new_connection() {
+        let server = MockServer::with_sink(0.5);
+        let audio = audio(&server);
+        server.lock().fail_next_apply = true;
+        audio.send(AudioCommand::SetSinkMute(true));
+        wait_for(&audio, |state| {
+            state.sink.as_ref().is_some_and(|sink| sink.muted)
+        })
+        .await;
+        assert_eq!(server.lock().applied, [AudioCommand::SetSinkMute(true)]);
+        // The monitor's two connections, and the control's first and retried one
+        assert_eq!(server.lock().connections, 4);
+    }
+
+    #[test]
+    fn stepping_keeps_boosts_but_never_boosts() {
+        assert_eq!(stepped_volume(0.5, 0.25), 0.75);
+        assert_eq!(stepped_volume(0.95, 0.1), 1.0);
+        assert_eq!(stepped_volume(0.05, -0.1), 0.0);
+        assert_eq!(stepped_volume(1.5, 0.1), 1.5);
+        assert_eq!(stepped_volume(1.5, -0.25), 1.25);
+    }
+}
@@ -0,0 +1,268 @@
+//! [`AudioBackend`] speaking the PulseAudio native protocol, which is also served by
+//! pipewire-pulse.
+
+use std::ffi::CStr;
+use std::io::{BufReader, Error, ErrorKind, Result};
+use std::os::unix::net::UnixStream;
+
+use pulseaudio::protocol::{self, ChannelVolume, Command, Volume};
+
+use super::{AudioBackend, AudioCommand, AudioState, Device, stepped_volume};
+
+pub struct PulseBackend {
+    socket: BufReader<UnixStream>,
+    protocol_version: u16,
+    /// Sequence number of the next request.
+    seq: u32,
+    /// Whether this connection is subscribed to events, in which case it must not be used for
+    /// requests anymore, since replies would be interleaved with events.
+    subscribed: bool,
+}
+
+impl PulseBackend {
+    pub fn connect() -> Result<Self> {
+        let socket_path = pulseaudio::socket_path_from_env()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "PulseAudio socket not found"))?;
+        let mut socket = BufReader::new(UnixStream::connect(socket_path)?);
+
+        let cookie = pulseaudio::cookie_path_from_env()
+            .and_then(|path| std::fs::read(path).ok())
+            .unwrap_or_default();
+        let auth = protocol::AuthParams {
+            version: protocol::MAX_VERSION,
+            supports_shm: false,
+            supports_memfd: false,
+            cookie,
+        };
+        protocol::write_command_message(
+            socket.get_mut(),
+            0,
+            Command::Auth(auth),
+            protocol::MAX_VERSION,
+        )
+        .map_err(protocol_error)?;
+        let (_, auth_reply) =
+            protocol::read_reply_message::<protocol::AuthReply>(&mut socket, protocol::MAX_VERSION)
+                .map_err(protocol_error)?;
+
+        let mut backend = Self {
+            socket,
+            protocol_version: protocol::MAX_VERSION.min(auth_reply.version),
+            seq: 1,
+            subscribed: false,
+        };
+
+        let mut props = protocol::Props::new();
+        props.set(protocol::Prop::ApplicationName, c"limbo");
+        backend.request::<protocol::SetClientNameReply>(Command::SetClientName(props))?;
+
+        Ok(backend)
+    }
+
+    fn next_seq(&mut self) -> u32 {
+        let seq = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        seq
+    }
+
+    fn write(&mut self, command: Command) -> Result<u32> {
+        let seq = self.next_seq();
+        protocol::write_command_message(self.socket.get_mut(), seq, command, self.protocol_version)
+            .map_err(protocol_error)?;
+        Ok(seq)
+    }
+
+    /// Sends `command` and reads its reply.
+    fn request<R: protocol::CommandReply>(&mut self, command: Command) -> Result<R> {
+        self.write(command)?;
+        let (_, reply) = protocol::read_reply_message::<R>(&mut self.socket, self.protocol_version)
+            .map_err(protocol_error)?;
+        Ok(reply)
+    }
+
+    /// Sends `command`, which only replies with an acknowledgement.
+    fn request_ack(&mut self, command: Command) -> Result<()> {
+        self.write(command)?;
+        protocol::read_ack_message(&mut self.socket).map_err(protocol_error)?;
+        Ok(())
+    }
+
+    fn default_sink(&mut self) -> Result<Option<protocol::SinkInfo>> {
+        let server_info = self.request::<protocol::ServerInfo>(Command::GetServerInfo)?;
+        let Some(name) = server_info.default_sink_name else {
+            return Ok(None);
+        };
+        self.request::<protocol::SinkInfo>(Command::GetSinkInfo(protocol::GetSinkInfo {
+            index: None,
+            name: Some(name),
+        }))
+        .map(Some)
+    }
+
+    fn default_source(&mut self) -> Result<Option<protocol::SourceInfo>> {
+        let server_info = self.request::<protocol::ServerInfo>(Command::GetServerInfo)?;
+        let Some(name) = server_info.default_source_name else {
+            return Ok(None);
+        };
+        self.request::<protocol::SourceInfo>(Command::GetSourceInfo(protocol::GetSourceInfo {
+            index: None,
+            name: Some(name),
+        }))
+        .map(Some)
+    }
+
+    /// Sets the volume of the default sink, given its current one.
+    fn set_sink_volume(&mut self, volume: impl FnOnce(f32) -> f32) -> Result<()> {
+        let Some(sink) = self.default_sink()? else {
+            return Ok(());
+        };
+        let volume = volume(volume_to_f32(&sink.cvolume));
+        self.request_ack(Command::SetSinkVolume(protocol::SetDeviceVolumeParams {
+            device_index: Some(sink.index),
+            device_name: None,
+            volume: scale_volume(&sink.cvolume, volume),
+        }))
+    }
+
+    /// Whether any application has an active recording stream on a source that isn't a monitor
+    /// of a sink.
+    fn recording(&mut self) -> Result<bool> {
+        let sources = self.request::<protocol::SourceInfoList>(Command::GetSourceInfoList)?;
+        let outputs =
+            self.request::<protocol::SourceOutputInfoList>(Command::GetSourceOutputInfoList)?;
+
+        Ok(outputs.iter().any(|output| {
+            let is_monitor = sources
+                .iter()
+                .find(|source| source.index == output.source_index)
+                .is_none_or(|source| source.monitor_of_sink_index.is_some());
+            // Level meters like pavucontrol's use peak detection streams, which aren't recordings
+            let is_peak_detect = output
+                .props
+                .get(protocol::Prop::MediaName)
+                .is_some_and(|name| name.to_bytes().starts_with(b"Peak detect"));
+            !output.corked && !is_monitor && !is_peak_detect
+        }))
+    }
+}
+
+impl AudioBackend for PulseBackend {
+    fn query(&mut self) -> Result<AudioState> {
+        let sink = self.default_sink()?.map(|sink| Device {
+            index: sink.index,
+            volume: volume_to_f32(&sink.cvolume),
+            muted: sink.muted,
+            headphones: is_headphones(
+                sink.active_port.as_deref(),
+                sink.props.get(protocol::Prop::DeviceFormFactor),
+            ),
+        });
+        let source = self.default_source()?.map(|source| Device {
+            index: source.index,
+            volume: volume_to_f32(&source.cvolume),
+            muted: source.muted,
+            headphones: is_headphones(
+                source.active_port.as_deref(),
+                source.props.get(protocol::Prop::DeviceFormFactor),
+            ),
+        });
+        let recording = self.recording()?;
+
+        Ok(AudioState {
+            sink,
+            source,
+            recording,
+        })
+    }
+
+    fn wait_for_change(&mut self) -> Result<()> {
+        if !self.subscribed {
+            self.request_ack(Command::Subscribe(
+                protocol::SubscriptionMask::SINK
+                    | protocol::SubscriptionMask::SOURCE
+                    | protocol::SubscriptionMask::SOURCE_OUTPUT
+                    | protocol::SubscriptionMask::SERVER,
+            ))?;
+            self.subscribed = true;
+        }
+
+        loop {
+            let (_, command) =
+                protocol::read_command_message(&mut self.socket, self.protocol_version)
+                    .map_err(protocol_error)?;
+            if let Command::SubscribeEvent(_) = command {
+                return Ok(());
+            }
+        }
+    }
+
+    fn apply(&mut self, command: AudioCommand) -> Result<()> {
+        match command {
+            AudioCommand::SetSinkVolume(volume) => self.set_sink_volume(|_| volume),
+            AudioCommand::StepSinkVolume(delta) => {
+                self.set_sink_volume(|volume| stepped_volume(volume, delta))
+            }
+            AudioCommand::SetSinkMute(mute) => {
+                let Some(sink) = self.default_sink()? else {
+                    return Ok(());
+                };
+                self.request_ack(Command::SetSinkMute(protocol::SetDeviceMuteParams {
+                    device_index: Some(sink.index),
+                    device_name: None,
+                    mute,
+                }))
+            }
+            AudioCommand::SetSourceMute(mute) => {
+                let Some(source) = self.default_source()? else {
+                    return Ok(());
+                };
+                self.request_ack(Command::SetSourceMute(protocol::SetDeviceMuteParams {
+                    device_index: Some(source.index),
+                    device_name: None,
+                    mute,
+                }))
+            }
+        }
+    }
+}
+
+fn protocol_error(e: protocol::ProtocolError) -> Error {
+    Error::other(e)
+}
+
+/// Average volume of all channels, where `1.0` is the server's normal volume.
+fn volume_to_f32(cvolume: &ChannelVolume) -> f32 {
+    let channels = cvolume.channels();
+    if channels.is_empty() {
+        return 0.0;
+    }
+    let sum = channels.iter().map(|v| v.as_u32() as f32).sum::<f32>();
+    sum / channels.len() as f32 / Volume::NORM.as_u32() as f32
+}
+
+/// Returns `cvolume` scaled such that its average is `volume`, preserving the balance between
+/// channels.
+fn scale_volume(cvolume: &ChannelVolume, volume: f32) -> ChannelVolume {
+    let target = (volume.max(0.0) * Volume::NORM.as_u32() as f32) as u32;
+    let current = (volume_to_f32(cvolume) * Volume::NORM.as_u32() as f32) as u32;
+
+    let mut scaled = ChannelVolume::empty();
+    for channel in cvolume.channels() {
+        let value = if current == 0 {
+            target
+        } else {
+            (channel.as_u32() as u64 * target as u64 / current as u64) as u32
+        };
+        scaled.push(Volume::from_u32_clamped(value));
+    }
+    scaled
+}
+
+/// Whether a device is headphones, based on its active port and form factor.
+fn is_headphones(active_port: Option<&CStr>, form_factor: Option<&CStr>) -> bool {
+    let matches = |s: Option<&CStr>| {
+        s.map(|s| s.to_string_lossy().to_lowercase())
+            .is_some_and(|s| s.contains("headphone") || s.contains("headset"))
+    };
+    matches(active_port) || matches(form_factor)
+}
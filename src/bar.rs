@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use iced::event::{PlatformSpecific, wayland};
+use iced::keyboard;
 use iced::platform_specific::shell::commands::layer_surface::{
     destroy_layer_surface, get_layer_surface,
 };
@@ -13,12 +15,15 @@ use sctk::shell::wlr_layer::{Anchor, KeyboardInteractivity, Layer};
 
 use crate::GlobalState;
 use crate::animation::{Eased, Easing};
-use crate::components::{icon, side};
+use crate::components::side;
 use crate::config::Config;
 use crate::config::types::ModuleName;
 use crate::desktop_environment::WorkspaceInfo;
 use crate::message::Message;
-use crate::sections::{Clock, Sysmon, TrayView, Workspaces};
+use crate::popup::{Popup, PopupKind};
+use crate::sections::{
+    AppLauncher, Battery, Clock, Music, Notifications, QuickSettings, Sysmon, Todo, Workspaces,
+};
 
 pub struct Bar {
     /// window id of the bar's layer surface.
@@ -27,12 +32,18 @@ pub struct Bar {
     output_name: String,
     size: Option<Size>,
     background_alpha_factor: Eased<f32>,
+    popup: Option<Popup>,
 
     config: Rc<Config>,
+    app_launcher: AppLauncher,
     workspaces: Workspaces,
+    battery: Battery,
     clock: Clock,
+    music: Music,
+    notifications: Notifications,
     sysmon: Sysmon,
-    tray_view: TrayView,
+    quick_settings: QuickSettings,
+    todo: Todo,
 }
 
 impl Bar {
@@ -54,12 +65,18 @@ impl Bar {
                     200.,
                     &[1.0, 0.0],
                 ),
+                popup: None,
 
                 config: global_state.config.clone(),
-                workspaces: Workspaces::new(output_name, global_state),
+                app_launcher: AppLauncher::new(id, global_state),
+                workspaces: Workspaces::new(output_name.clone(), global_state),
+                battery: Battery::new(global_state),
                 clock: Clock::new(global_state),
+                music: Music::new(id, global_state),
+                notifications: Notifications::new(id, global_state),
                 sysmon: Sysmon::new(global_state),
-                tray_view: TrayView::new(global_state),
+                quick_settings: QuickSettings::new(id, output_name, global_state),
+                todo: Todo::new(id, global_state),
             },
             get_layer_surface(SctkLayerSurfaceSettings {
                 id,
@@ -78,15 +95,63 @@ impl Bar {
     }
 
     pub fn destroy(self) -> Task<Message> {
-        destroy_layer_surface(self.id)
+        let popup_task = self.popup.map_or(Task::none(), Popup::destroy);
+        Task::batch([popup_task, destroy_layer_surface(self.id)])
     }
 
-    pub fn update(&mut self, message: &Message) {
+    pub fn update(&mut self, message: &Message) -> Task<Message> {
+        self.app_launcher.update(message);
         self.workspaces.update(message);
+        self.battery.update(message);
         self.clock.update(message);
+        self.music.update(message);
+        self.notifications.update(message);
         self.sysmon.update(message);
-        self.tray_view.update(message);
+        self.quick_settings.update(message);
+        self.todo.update(message);
         match message {
+            Message::TogglePopup(bar_id, kind) if *bar_id == self.id => {
+                return self.toggle_popup(*kind);
+            }
+            Message::ClosePopup(bar_id) if *bar_id == self.id => {
+                return self.close_popup();
+            }
+            Message::Iced(
+                window_id,
+                Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }),
+            ) if self.popup_id() == Some(*window_id) => {
+                if *key == keyboard::Key::Named(keyboard::key::Named::Escape) {
+                    return self.close_popup();
+                }
+                if self.popup_open(PopupKind::AppLauncher) {
+                    self.app_launcher.key_pressed(key, *modifiers);
+                }
+                if self.popup_open(PopupKind::QuickSettings) {
+                    return self
+                        .quick_settings
+                        .panel_key_pressed(key, *modifiers)
+                        .map_or_else(Task::none, Task::done);
+                }
+            }
+            Message::Iced(window_id, Event::Window(window::Event::Opened { .. }))
+                if self.popup_id() == Some(*window_id)
+                    && self.popup_open(PopupKind::AppLauncher) =>
+            {
+                return self.app_launcher.focus();
+            }
+            Message::LaunchApp { .. } if self.popup_open(PopupKind::AppLauncher) => {
+                return self.close_popup();
+            }
+            Message::Iced(
+                _,
+                Event::PlatformSpecific(PlatformSpecific::Wayland(wayland::Event::Layer(
+                    wayland::LayerEvent::Done,
+                    _,
+                    id,
+                ))),
+            ) if self.popup.as_ref().is_some_and(|p| p.id == *id) => {
+                self.popup = None;
+            }
             Message::AnimationTick => {
                 self.background_alpha_factor.update();
             }
@@ -101,40 +166,92 @@ impl Bar {
             }
             _ => (),
         };
+        Task::none()
+    }
+
+    fn toggle_popup(&mut self, kind: PopupKind) -> Task<Message> {
+        let was_open = self.popup_open(kind);
+        let close_task = self.close_popup();
+        if was_open {
+            return close_task;
+        }
+
+        let (popup, open_task) = Popup::open(
+            kind,
+            self.module_alignment(kind.module()),
+            self.wl_output.clone(),
+        );
+        self.popup = Some(popup);
+        Task::batch([close_task, open_task])
+    }
+
+    fn close_popup(&mut self) -> Task<Message> {
+        self.popup.take().map_or(Task::none(), Popup::destroy)
+    }
+
+    /// Whether the bar's open popup is of `kind`.
+    pub fn popup_open(&self, kind: PopupKind) -> bool {
+        self.popup.as_ref().is_some_and(|p| p.kind == kind)
+    }
+
+    /// The window id of the bar's open popup, if any.
+    pub fn popup_id(&self) -> Option<window::Id> {
+        self.popup.as_ref().map(|p| p.id)
+    }
+
+    /// Horizontal alignment of a popup opened from `module`, such that it is placed below it.
+    fn module_alignment(&self, module: ModuleName) -> Alignment {
+        let modules = &self.config.bar.modules;
+        if modules.left.contains(&module) {
+            Alignment::Start
+        } else if modules.center.contains(&module) {
+            Alignment::Center
+        } else {
+            Alignment::End
+        }
+    }
+
+    pub fn popup_view(&self) -> Element<'_, Message> {
+        let Some(popup) = &self.popup else {
+            return iced::widget::Space::new(Length::Shrink, Length::Shrink).into();
+        };
+
+        let content = match popup.kind {
+            PopupKind::TrayDrawer
+            | PopupKind::Network
+            | PopupKind::Caffeine
+            | PopupKind::Notifications
+            | PopupKind::PowerProfiles
+            | PopupKind::Bluetooth
+            | PopupKind::QuickSettings => self.quick_settings.popup_view(popup.kind),
+            PopupKind::Music => self.music.popup_view(),
+            PopupKind::AppLauncher => self.app_launcher.popup_view(),
+            PopupKind::Todo => self.todo.popup_view(),
+            PopupKind::Weather | PopupKind::Github | PopupKind::Todoist => {
+                self.notifications.popup_view(popup.kind)
+            }
+        };
+        popup.view(&self.config, content, Message::ClosePopup(self.id))
     }
 
     pub fn view(&self) -> Element<'_, Message> {
         let background_alpha_factor = self.background_alpha_factor.get();
 
         let mk_side = |modules: &Vec<ModuleName>| {
-            Row::from_iter(modules.iter().map(|module| {
-                match module {
-                    ModuleName::AppLauncher => self
-                        .config
-                        .section(icon("nix-snowflake-white", None))
-                        .into(),
-                    ModuleName::Battery => self
-                        .config
-                        .section(icon("nix-snowflake-white", None))
-                        .into(),
+            Row::from_iter(modules.iter().filter_map(|module| {
+                Some(match module {
+                    ModuleName::AppLauncher => self.app_launcher.view(),
+                    ModuleName::Battery => self.battery.view()?,
                     ModuleName::Clock => self.clock.view(),
-                    ModuleName::Music => self
-                        .config
-                        .section(icon("nix-snowflake-white", None))
-                        .into(),
-                    ModuleName::Notifications => self
-                        .config
-                        .section(icon("nix-snowflake-white", None))
-                        .into(),
-                    // NOTE: temporary until full quick settings is impemented
-                    ModuleName::QuickSettings => self.tray_view.view(),
+                    ModuleName::Music => self.music.view()?,
+                    ModuleName::Notifications => self.notifications.view(),
+                    ModuleName::QuickSettings => self
+                        .quick_settings
+                        .view(self.popup.as_ref().map(|p| p.kind)),
                     ModuleName::Sysmon => self.sysmon.view(),
-                    ModuleName::Todo => self
-                        .config
-                        .section(icon("nix-snowflake-white", None))
-                        .into(),
+                    ModuleName::Todo => self.todo.view()?,
                     ModuleName::Workspaces => self.workspaces.view(),
-                }
+                })
             }))
             .spacing(12)
         };
@@ -165,7 +282,7 @@ impl Bar {
     }
 
     pub fn subscription(&self) -> iced::Subscription<Message> {
-        self.clock.subscription()
+        iced::Subscription::batch([self.clock.subscription(), self.music.subscription()])
     }
 
     /// Whether the window has opened, indicated by receiving an
@@ -175,7 +292,9 @@ impl Bar {
     }
 
     pub fn animation_running(&self) -> bool {
-        self.background_alpha_factor.is_running() || self.workspaces.animation_running()
+        self.background_alpha_factor.is_running()
+            || self.workspaces.animation_running()
+            || self.quick_settings.animation_running()
     }
 }
 
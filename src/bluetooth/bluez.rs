@@ -0,0 +1,37 @@
+//! Proxies for the parts of the BlueZ D-Bus API used by limbo.
+//!
+//! See <https://git.kernel.org/pub/scm/bluetooth/bluez.git/tree/doc>
+
+use zbus::proxy;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::ObjectPath;
+
+pub const BLUEZ_SERVICE: &str = "org.bluez";
+pub const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+pub const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+pub const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+
+#[proxy(interface = "org.bluez.Adapter1", default_service = "org.bluez")]
+pub trait Adapter {
+    #[zbus(property)]
+    fn set_powered(&self, powered: bool) -> zbus::Result<()>;
+}
+
+#[proxy(interface = "org.bluez.Device1", default_service = "org.bluez")]
+pub trait Device {
+    fn connect(&self) -> zbus::Result<()>;
+
+    fn disconnect(&self) -> zbus::Result<()>;
+}
+
+/// Builds a proxy for the object at `path` without caching its properties.
+pub async fn uncached<'a, P>(connection: &zbus::Connection, path: ObjectPath<'a>) -> zbus::Result<P>
+where
+    P: proxy::Defaults + From<zbus::Proxy<'a>>,
+{
+    zbus::proxy::Builder::<P>::new(connection)
+        .path(path)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+}
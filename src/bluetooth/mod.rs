@@ -0,0 +1,446 @@
+//! Follows the Bluetooth adapter and paired devices through BlueZ.
+//!
+//! Only one adapter is controlled, the first one by object path, which is kept for as long as
+//! it exists.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use iced::futures::{FutureExt, StreamExt};
+use tokio::sync::{Mutex, mpsc, watch};
+use zbus::fdo;
+use zbus::names::OwnedInterfaceName;
+use zbus::zvariant::{ObjectPath, OwnedValue};
+
+use crate::message::Message;
+
+mod bluez;
+
+use bluez::{
+    ADAPTER_INTERFACE, AdapterProxy, BATTERY_INTERFACE, BLUEZ_SERVICE, DEVICE_INTERFACE,
+    DeviceProxy, uncached,
+};
+
+/// Changes usually come in bursts of signals, so the state is only re-read once they settle.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BluetoothDevice {
+    /// D-Bus object path of the device.
+    pub path: String,
+    pub name: String,
+    pub connected: bool,
+    /// Battery charge in percent, if the device reports it.
+    pub battery: Option<u8>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BluetoothState {
+    /// D-Bus object path of the controlled adapter, `None` while there is none or BlueZ isn't
+    /// running.
+    pub adapter: Option<String>,
+    pub powered: bool,
+    /// Devices paired with the adapter, the connected ones first and then by name.
+    pub devices: Vec<BluetoothDevice>,
+}
+
+#[derive(Debug, Clone)]
+pub enum BluetoothCommand {
+    SetPowered(bool),
+    /// Connect the device with this object path.
+    Connect(String),
+    Disconnect(String),
+}
+
+#[derive(Debug)]
+pub struct Bluetooth {
+    commands: mpsc::UnboundedSender<BluetoothCommand>,
+    rx: Arc<Mutex<watch::Receiver<BluetoothState>>>,
+}
+
+impl Bluetooth {
+    pub fn new() -> Self {
+        Self::with_address(None)
+    }
+
+    /// Creates the service for the BlueZ instance on the bus at `address`, the system bus if
+    /// `None`.
+    pub fn with_address(address: Option<String>) -> Self {
+        let (tx, rx) = watch::channel(BluetoothState::default());
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(address, tx, commands_rx));
+        Self {
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn send(&self, command: BluetoothCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "bluetooth".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::BluetoothUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+async fn run(
+    address: Option<String>,
+    tx: watch::Sender<BluetoothState>,
+    mut commands_rx: mpsc::UnboundedReceiver<BluetoothCommand>,
+) {
+    const MIN_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    let mut backoff = MIN_BACKOFF;
+    while !tx.is_closed() {
+        match run_client(address.as_deref(), &tx, &mut commands_rx).await {
+            Ok(()) => {
+                log::info!("bluetooth: connection closed, reconnecting");
+                backoff = MIN_BACKOFF;
+            }
+            Err(e) => log::warn!("bluetooth: {e}, retrying in {}s", backoff.as_secs()),
+        }
+        tx.send_replace(BluetoothState::default());
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+async fn run_client(
+    address: Option<&str>,
+    tx: &watch::Sender<BluetoothState>,
+    commands_rx: &mut mpsc::UnboundedReceiver<BluetoothCommand>,
+) -> zbus::Result<()> {
+    let builder = match address {
+        Some(address) => zbus::connection::Builder::address(address)?,
+        None => zbus::connection::Builder::system()?,
+    };
+    let connection = builder.build().await?;
+
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .sender(BLUEZ_SERVICE)?
+        .build();
+    let mut signals = zbus::MessageStream::for_match_rule(rule, &connection, None).await?;
+    let objects = fdo::ObjectManagerProxy::builder(&connection)
+        .destination(BLUEZ_SERVICE)?
+        .path("/")?
+        .build()
+        .await?;
+
+    loop {
+        let adapter = tx.borrow().adapter.clone();
+        let state = query(&objects, adapter.as_deref()).await?;
+        tx.send_if_modified(|current| {
+            let modified = *current != state;
+            *current = state;
+            modified
+        });
+
+        tokio::select! {
+            signal = signals.next() => {
+                if signal.is_none() {
+                    return Ok(());
+                }
+                tokio::time::sleep(DEBOUNCE).await;
+                // Drain the burst that arrived in the meantime
+                while let Some(Some(_)) = signals.next().now_or_never() {}
+            }
+            Some(command) = commands_rx.recv() => {
+                let adapter = tx.borrow().adapter.clone();
+                apply(&connection, adapter.as_deref(), command).await;
+            }
+        }
+    }
+}
+
+type Properties = HashMap<String, OwnedValue>;
+
+/// The properties of `interface` of an object, if it implements it.
+fn interface<'a>(
+    interfaces: &'a HashMap<OwnedInterfaceName, Properties>,
+    interface: &str,
+) -> Option<&'a Properties> {
+    interfaces
+        .iter()
+        .find_map(|(name, properties)| (name.as_str() == interface).then_some(properties))
+}
+
+fn flag(properties: &Properties, name: &str) -> bool {
+    properties
+        .get(name)
+        .and_then(|v| v.downcast_ref::<bool>().ok())
+        .unwrap_or(false)
+}
+
+fn string(properties: &Properties, name: &str) -> Option<String> {
+    properties
+        .get(name)
+        .and_then(|v| v.downcast_ref::<&str>().ok())
+        .map(str::to_string)
+}
+
+/// Reads the state of the adapter at `current`, or else of the first adapter by path.
+async fn query(
+    objects: &fdo::ObjectManagerProxy<'_>,
+    current: Option<&str>,
+) -> zbus::Result<BluetoothState> {
+    let managed = objects.get_managed_objects().await?;
+
+    let adapters = managed
+        .iter()
+        .filter_map(|(path, interfaces)| Some((path, interface(interfaces, ADAPTER_INTERFACE)?)))
+        .collect::<Vec<_>>();
+    let adapter = adapters
+        .iter()
+        .find(|(path, _)| Some(path.as_str()) == current)
+        .or_else(|| adapters.iter().min_by_key(|(path, _)| path.as_str()));
+    let Some((adapter_path, adapter)) = adapter else {
+        return Ok(BluetoothState::default());
+    };
+
+    let mut devices = managed
+        .iter()
+        .filter_map(|(path, interfaces)| {
+            let device = interface(interfaces, DEVICE_INTERFACE)?;
+            let paired_with = device
+                .get("Adapter")
+                .and_then(|v| v.downcast_ref::<ObjectPath>().ok());
+            if !flag(device, "Paired") || paired_with.as_deref() != Some(adapter_path.as_str()) {
+                return None;
+            }
+            Some(BluetoothDevice {
+                path: path.to_string(),
+                name: string(device, "Alias")
+                    .or_else(|| string(device, "Name"))
+                    .or_else(|| string(device, "Address"))
+                    .unwrap_or_default(),
+                connected: flag(device, "Connected"),
+                battery: interface(interfaces, BATTERY_INTERFACE)
+                    .and_then(|battery| battery.get("Percentage"))
+                    .and_then(|v| v.downcast_ref::<u8>().ok()),
+            })
+        })
+        .collect::<Vec<_>>();
+    devices.sort_by(|a, b| b.connected.cmp(&a.connected).then(a.name.cmp(&b.name)));
+
+    Ok(BluetoothState {
+        adapter: Some(adapter_path.to_string()),
+        powered: flag(adapter, "Powered"),
+        devices,
+    })
+}
+
+/// Applies `command`, powering the adapter at `adapter`. Devices are connected and
+/// disconnected in the background, since that can take a while.
+async fn apply(connection: &zbus::Connection, adapter: Option<&str>, command: BluetoothCommand) {
+    let (device, connected) = match command {
+        BluetoothCommand::SetPowered(powered) => {
+            let Some(adapter) = adapter else {
+                return;
+            };
+            if let Err(e) = set_powered(connection, adapter, powered).await {
+                log::warn!("bluetooth: failed to set powered to {powered}: {e}");
+            }
+            return;
+        }
+        BluetoothCommand::Connect(device) => (device, true),
+        BluetoothCommand::Disconnect(device) => (device, false),
+    };
+    let connection = connection.clone();
+    tokio::spawn(async move {
+        if let Err(e) = set_connected(&connection, &device, connected).await {
+            let action = if connected { "connect" } else { "disconnect" };
+            log::warn!("bluetooth: failed to {action} {device}: {e}");
+        }
+    });
+}
+
+async fn set_powered(
+    connection: &zbus::Connection,
+    adapter: &str,
+    powered: bool,
+) -> zbus::Result<()> {
+    uncached::<AdapterProxy>(connection, ObjectPath::try_from(adapter)?)
+        .await?
+        .set_powered(powered)
+        .await
+}
+
+async fn set_connected(
+    connection: &zbus::Connection,
+    device: &str,
+    connected: bool,
+) -> zbus::Result<()> {
+    let device = uncached::<DeviceProxy>(connection, ObjectPath::try_from(device)?).await?;
+    if connected {
+        device.connect().await
+    } else {
+        device.disconnect().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use tokio::sync::Notify;
+    use zbus::object_server::SignalEmitter;
+    use zbus::zvariant::OwnedObjectPath;
+
+    use super::*;
+    use crate::testing::PrivateBus;
+
+    struct FakeAdapter {
+        powered: bool,
+    }
+
+    #[zbus::interface(name = "org.bluez.Adapter1")]
+    impl FakeAdapter {
+        #[zbus(property)]
+        fn powered(&self) -> bool {
+            self.powered
+        }
+
+        #[zbus(property)]
+        fn set_powered(&mut self, powered: bool) {
+            self.powered = powered;
+        }
+    }
+
+    /// A paired device, which connects once `connectable` is notified.
+    struct FakeDevice {
+        alias: &'static str,
+        adapter: OwnedObjectPath,
+        connected: AtomicBool,
+        connectable: Arc<Notify>,
+    }
+
+    #[zbus::interface(name = "org.bluez.Device1")]
+    impl FakeDevice {
+        async fn connect(
+            &self,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> fdo::Result<()> {
+            self.connectable.notified().await;
+            self.connected.store(true, Ordering::SeqCst);
+            self.connected_changed(&emitter).await?;
+            Ok(())
+        }
+
+        fn disconnect(&self) {}
+
+        #[zbus(property)]
+        fn alias(&self) -> &str {
+            self.alias
+        }
+
+        #[zbus(property)]
+        fn adapter(&self) -> OwnedObjectPath {
+            self.adapter.clone()
+        }
+
+        #[zbus(property)]
+        fn paired(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn connected(&self) -> bool {
+            self.connected.load(Ordering::SeqCst)
+        }
+    }
+
+    async fn wait_until(
+        rx: &mut watch::Receiver<BluetoothState>,
+        done: impl Fn(&BluetoothState) -> bool,
+    ) -> BluetoothState {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let state = rx.borrow_and_update().clone();
+                if done(&state) {
+                    return state;
+                }
+                rx.changed().await.unwrap();
+            }
+        })
+        .await
+        .expect("timed out waiting for the bluetooth state")
+    }
+
+    #[tokio::test]
+    async fn controls_the_first_adapter_and_its_devices() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let bluez = bus.connection().await;
+        let server = bluez.object_server();
+        server.at("/", fdo::ObjectManager).await.unwrap();
+        let connectable = Arc::new(Notify::new());
+        for (adapter, device) in [("hci1", "Keyboard"), ("hci0", "Headphones")] {
+            let path = format!("/org/bluez/{adapter}");
+            server
+                .at(path.as_str(), FakeAdapter { powered: true })
+                .await
+                .unwrap();
+            let device = FakeDevice {
+                alias: device,
+                adapter: OwnedObjectPath::try_from(path.as_str()).unwrap(),
+                connected: AtomicBool::new(false),
+                connectable: connectable.clone(),
+            };
+            server
+                .at(format!("{path}/dev_{}", device.alias), device)
+                .await
+                .unwrap();
+        }
+        bluez.request_name(BLUEZ_SERVICE).await.unwrap();
+
+        let bluetooth = Bluetooth::with_address(Some(bus.address.clone()));
+        let mut rx = bluetooth.rx.lock().await.clone();
+        let state = wait_until(&mut rx, |state| state.adapter.is_some()).await;
+        // The keyboard is paired with the other adapter
+        assert_eq!(
+            state,
+            BluetoothState {
+                adapter: Some("/org/bluez/hci0".to_string()),
+                powered: true,
+                devices: vec![BluetoothDevice {
+                    path: "/org/bluez/hci0/dev_Headphones".to_string(),
+                    name: "Headphones".to_string(),
+                    connected: false,
+                    battery: None,
+                }],
+            }
+        );
+
+        // Connecting takes a while, which doesn't hold up other commands
+        bluetooth.send(BluetoothCommand::Connect(state.devices[0].path.clone()));
+        bluetooth.send(BluetoothCommand::SetPowered(false));
+        wait_until(&mut rx, |state| !state.powered).await;
+        let hci1 = server
+            .interface::<_, FakeAdapter>("/org/bluez/hci1")
+            .await
+            .unwrap();
+        assert!(hci1.get().await.powered);
+
+        connectable.notify_one();
+        let state = wait_until(&mut rx, |state| state.devices[0].connected).await;
+        assert_eq!(state.adapter.as_deref(), Some("/org/bluez/hci0"));
+    }
+}
@@ -0,0 +1,281 @@
+//! Backlight devices from `/sys/class/backlight`.
+
+use std::path::{Path, PathBuf};
+
+use iced::futures::{Stream, StreamExt, stream};
+use inotify::{EventStream, Inotify, WatchDescriptor, WatchMask};
+use zbus::proxy;
+
+pub const SYSFS_BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backlight {
+    /// Name of the device in `/sys/class/backlight`.
+    pub device: String,
+    pub brightness: u32,
+    pub max_brightness: u32,
+}
+
+/// Reads the preferred backlight device below `root`.
+///
+/// Like systemd, firmware devices are preferred over platform devices, which are preferred over
+/// raw devices.
+pub fn read(root: &Path) -> Option<Backlight> {
+    let mut devices = std::fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .map(|path| {
+            let priority = match read_string(&path.join("type")).as_deref() {
+                Some("firmware") => 0,
+                Some("platform") => 1,
+                _ => 2,
+            };
+            (priority, path)
+        })
+        .collect::<Vec<_>>();
+    devices.sort();
+
+    devices.into_iter().find_map(|(_, path)| read_device(&path))
+}
+
+/// Streams the preferred backlight device below `root`, first the current one and then whenever
+/// it changed.
+///
+/// The brightness files of the device are watched with inotify, which reports writes through
+/// sysfs as well as changes the driver announces, e.g. after brightness hotkeys.
+pub fn watch(root: PathBuf) -> impl Stream<Item = Option<Backlight>> {
+    stream::unfold(Watcher::new(root), |mut watcher| async move {
+        let backlight = watcher.next().await?;
+        Some((backlight, watcher))
+    })
+}
+
+struct Watcher {
+    root: PathBuf,
+    /// `None` until the first read, or if inotify isn't available.
+    events: Option<EventStream<[u8; 1024]>>,
+    /// Watches of the brightness files of the current device.
+    device_watches: Vec<WatchDescriptor>,
+    /// `None` until the first read.
+    last: Option<Option<Backlight>>,
+}
+
+impl Watcher {
+    fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            events: None,
+            device_watches: Vec::new(),
+            last: None,
+        }
+    }
+
+    /// Waits until the device changed, or returns the current one on the first call.
+    async fn next(&mut self) -> Option<Option<Backlight>> {
+        if self.last.is_none() {
+            // Inotify needs the runtime, which is only guaranteed once polled
+            self.events = watch_root(&self.root)
+                .inspect_err(|e| {
+                    log::warn!("brightness: failed to watch {}: {e}", self.root.display())
+                })
+                .ok();
+        }
+
+        loop {
+            if self.last.is_some() {
+                // Without inotify, the first read is all there is
+                match self.events.as_mut()?.next().await? {
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::warn!("brightness: failed to read inotify events: {e}");
+                        return None;
+                    }
+                }
+            }
+
+            let root = self.root.clone();
+            let current = tokio::task::spawn_blocking(move || read(&root))
+                .await
+                .ok()
+                .flatten();
+            if self.last.as_ref() == Some(&current) {
+                continue;
+            }
+
+            let device = current.as_ref().map(|b| b.device.as_str());
+            let last_device = self
+                .last
+                .as_ref()
+                .map(|last| last.as_ref().map(|b| &*b.device));
+            if last_device != Some(device) {
+                self.watch_device(device);
+            }
+            self.last = Some(current.clone());
+            return Some(current);
+        }
+    }
+
+    /// Moves the watches of the brightness files to `device`.
+    fn watch_device(&mut self, device: Option<&str>) {
+        let Some(events) = &self.events else {
+            return;
+        };
+        let mut watches = events.watches();
+        for watch in self.device_watches.drain(..) {
+            // Fails if the device is gone, which removes its watches anyway
+            let _ = watches.remove(watch);
+        }
+        let Some(device) = device else {
+            return;
+        };
+        for file in ["brightness", "actual_brightness"] {
+            let path = self.root.join(device).join(file);
+            match watches.add(&path, WatchMask::MODIFY) {
+                Ok(watch) => self.device_watches.push(watch),
+                Err(e) => log::debug!("brightness: failed to watch {}: {e}", path.display()),
+            }
+        }
+    }
+}
+
+/// Creates the inotify event stream, watching `root` for devices coming and going.
+fn watch_root(root: &Path) -> std::io::Result<EventStream<[u8; 1024]>> {
+    let inotify = Inotify::init()?;
+    inotify.watches().add(
+        root,
+        WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVED_TO | WatchMask::MOVED_FROM,
+    )?;
+    inotify.into_event_stream([0; 1024])
+}
+
+fn read_device(path: &Path) -> Option<Backlight> {
+    let max_brightness = read_string(&path.join("max_brightness"))?.parse().ok()?;
+    if max_brightness == 0 {
+        return None;
+    }
+    // `actual_brightness` reflects what the hardware reports, but isn't provided by all drivers
+    let brightness = read_string(&path.join("actual_brightness"))
+        .or_else(|| read_string(&path.join("brightness")))?
+        .parse()
+        .ok()?;
+    Some(Backlight {
+        device: path.file_name()?.to_string_lossy().into_owned(),
+        brightness,
+        max_brightness,
+    })
+}
+
+/// Reads the device at `path` with the brightness last requested, which `actual_brightness` may
+/// only reflect later.
+pub fn read_requested(path: &Path) -> Option<Backlight> {
+    let max_brightness = read_string(&path.join("max_brightness"))?.parse().ok()?;
+    if max_brightness == 0 {
+        return None;
+    }
+    Some(Backlight {
+        device: path.file_name()?.to_string_lossy().into_owned(),
+        brightness: read_string(&path.join("brightness"))?.parse().ok()?,
+        max_brightness,
+    })
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+#[proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+pub trait LogindSession {
+    /// Sets the brightness of a device, without requiring root.
+    fn set_brightness(&self, subsystem: &str, name: &str, brightness: u32) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn add_device(sysfs: &TempDir, name: &str, kind: &str, brightness: u32, max: u32) {
+        sysfs.write(format!("{name}/type"), kind);
+        sysfs.write(format!("{name}/max_brightness"), format!("{max}\n"));
+        sysfs.write(format!("{name}/brightness"), format!("{brightness}\n"));
+        sysfs.write(
+            format!("{name}/actual_brightness"),
+            format!("{brightness}\n"),
+        );
+    }
+
+    fn backlight(device: &str, brightness: u32, max_brightness: u32) -> Option<Backlight> {
+        Some(Backlight {
+            device: device.to_string(),
+            brightness,
+            max_brightness,
+        })
+    }
+
+    #[test]
+    fn prefers_firmware_over_platform_over_raw_devices() {
+        let sysfs = TempDir::new();
+        add_device(&sysfs, "intel_backlight", "raw", 200, 400);
+        assert_eq!(read(sysfs.path()), backlight("intel_backlight", 200, 400));
+        add_device(&sysfs, "platform_backlight", "platform", 3, 10);
+        assert_eq!(read(sysfs.path()), backlight("platform_backlight", 3, 10));
+        add_device(&sysfs, "acpi_video0", "firmware", 50, 100);
+        assert_eq!(read(sysfs.path()), backlight("acpi_video0", 50, 100));
+    }
+
+    #[test]
+    fn skips_devices_without_a_range() {
+        let sysfs = TempDir::new();
+        add_device(&sysfs, "acpi_video0", "firmware", 0, 0);
+        add_device(&sysfs, "intel_backlight", "raw", 200, 400);
+        assert_eq!(read(sysfs.path()), backlight("intel_backlight", 200, 400));
+    }
+
+    #[test]
+    fn falls_back_to_the_requested_brightness() {
+        let sysfs = TempDir::new();
+        add_device(&sysfs, "intel_backlight", "raw", 200, 400);
+        std::fs::remove_file(sysfs.path().join("intel_backlight/actual_brightness")).unwrap();
+        sysfs.write("intel_backlight/brightness", "300\n");
+        assert_eq!(read(sysfs.path()), backlight("intel_backlight", 300, 400));
+    }
+
+    #[tokio::test]
+    async fn watches_brightness_and_devices() {
+        let sysfs = TempDir::new();
+        let mut updates = Box::pin(watch(sysfs.path().to_path_buf()));
+        let mut next = async || {
+            tokio::time::timeout(Duration::from_secs(5), updates.next())
+                .await
+                .expect("timed out waiting for a brightness update")
+                .unwrap()
+        };
+        assert_eq!(next().await, None);
+
+        // Devices appear at once, as in sysfs
+        let staging = TempDir::new();
+        add_device(&staging, "intel_backlight", "raw", 200, 400);
+        std::fs::rename(
+            staging.path().join("intel_backlight"),
+            sysfs.path().join("intel_backlight"),
+        )
+        .unwrap();
+        assert_eq!(next().await, backlight("intel_backlight", 200, 400));
+
+        // Written in place, as the kernel updates the attribute
+        let path = sysfs.path().join("intel_backlight/actual_brightness");
+        let mut file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+        file.write_all(b"100\n").unwrap();
+        assert_eq!(next().await, backlight("intel_backlight", 100, 400));
+    }
+}
@@ -0,0 +1,620 @@
+//! Brightness of external monitors over DDC/CI.
+//!
+//! Monitors are reached through the i2c buses of the graphics card, and matched to their
+//! `wl_output` by comparing the EDID read over the bus with the ones the drm connectors report.
+//!
+//! See the VESA DDC/CI and MCCS standards.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, mpsc};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{Mutex, watch};
+
+use crate::message::Message;
+
+use super::Change;
+
+pub const SYSFS_CLASS_DIR: &str = "/sys/class";
+
+/// i2c address of the DDC/CI interface of a monitor.
+const DDC_ADDRESS: u16 = 0x37;
+/// i2c address of the EDID EEPROM of a monitor.
+const EDID_ADDRESS: u16 = 0x50;
+/// Length of the base EDID block, which is enough to tell monitors apart.
+const EDID_LEN: usize = 128;
+
+/// Source address byte of messages sent by the host.
+const HOST_ADDRESS: u8 = 0x51;
+/// Destination address byte of messages sent to the monitor, included in their checksum.
+const DISPLAY_WRITE_ADDRESS: u8 = 0x6e;
+/// Virtual host address included in the checksum of replies.
+const REPLY_CHECKSUM_ADDRESS: u8 = 0x50;
+
+const GET_VCP_OPCODE: u8 = 0x01;
+const GET_VCP_REPLY_OPCODE: u8 = 0x02;
+const SET_VCP_OPCODE: u8 = 0x03;
+/// VCP code of the luminance control.
+const VCP_BRIGHTNESS: u8 = 0x10;
+
+/// Time the monitor needs to prepare a reply, as required by the standard.
+const REPLY_DELAY: Duration = Duration::from_millis(40);
+/// Time the monitor needs to process a set request, as required by the standard.
+const SET_DELAY: Duration = Duration::from_millis(50);
+/// Monitors regularly drop requests, so each one is attempted a few times.
+const ATTEMPTS: usize = 3;
+
+/// Interval at which the brightness of monitors is re-read to catch changes made with their own
+/// buttons, and the connectors are checked for monitors being plugged in.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// An i2c bus, abstracted so that monitors can be simulated.
+pub trait I2cDevice: Send + 'static {
+    /// Selects the address of the device subsequent reads and writes are addressed to.
+    fn set_address(&mut self, address: u16) -> io::Result<()>;
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()>;
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()>;
+}
+
+/// An i2c bus from `/dev/i2c-*`, which requires the `i2c-dev` kernel module.
+#[derive(Debug)]
+pub struct LinuxI2c(File);
+
+impl LinuxI2c {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map(Self)
+    }
+}
+
+impl I2cDevice for LinuxI2c {
+    fn set_address(&mut self, address: u16) -> io::Result<()> {
+        /// `I2C_SLAVE` from `linux/i2c-dev.h`
+        const I2C_SLAVE: libc::Ioctl = 0x0703;
+        // SAFETY: I2C_SLAVE takes the address as its argument and doesn't access any memory
+        let result =
+            unsafe { libc::ioctl(self.0.as_raw_fd(), I2C_SLAVE, address as libc::c_ulong) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.0.read_exact(buf)
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.0.write_all(data)
+    }
+}
+
+/// Brightness of an external monitor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorBrightness {
+    /// Name of the output the monitor is connected to, e.g. `DP-1`.
+    pub output: String,
+    pub brightness: u16,
+    pub max_brightness: u16,
+}
+
+impl MonitorBrightness {
+    /// Brightness, `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        self.brightness as f32 / self.max_brightness as f32
+    }
+
+    /// Raw brightness value for a `0.0..=1.0` fraction.
+    pub fn raw_from_fraction(&self, fraction: f32) -> u16 {
+        (fraction.clamp(0.0, 1.0) * self.max_brightness as f32).round() as u16
+    }
+}
+
+struct Monitor<D> {
+    device: D,
+    state: MonitorBrightness,
+}
+
+#[derive(Debug)]
+pub struct Ddc {
+    commands: mpsc::Sender<(String, Change)>,
+    rx: Arc<Mutex<watch::Receiver<Vec<MonitorBrightness>>>>,
+}
+
+impl Ddc {
+    pub fn new() -> Self {
+        Self::with_buses(PathBuf::from(SYSFS_CLASS_DIR), |bus| {
+            LinuxI2c::open(&Path::new("/dev").join(bus))
+        })
+    }
+
+    /// Creates the service for the i2c buses and drm connectors in `sysfs_class_root`. `open` is
+    /// called with the name of a bus, e.g. `i2c-3`, to open it.
+    pub fn with_buses<D, F>(sysfs_class_root: PathBuf, open: F) -> Self
+    where
+        D: I2cDevice,
+        F: Fn(&str) -> io::Result<D> + Send + 'static,
+    {
+        let (tx, rx) = watch::channel(Vec::new());
+        let (commands, commands_rx) = mpsc::channel();
+
+        // DDC/CI is slow and blocking, so all bus access happens on a dedicated thread
+        std::thread::Builder::new()
+            .name("ddc".to_string())
+            .spawn(move || run(&sysfs_class_root, &open, commands_rx, tx))
+            .expect("failed to spawn ddc thread");
+
+        Self {
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    /// Changes the brightness of the monitor connected to `output`.
+    pub fn change_brightness(&self, output: String, change: Change) {
+        let _ = self.commands.send((output, change));
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "ddc".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::MonitorBrightnessUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+fn run<D: I2cDevice>(
+    sysfs_class_root: &Path,
+    open: &dyn Fn(&str) -> io::Result<D>,
+    commands_rx: mpsc::Receiver<(String, Change)>,
+    tx: watch::Sender<Vec<MonitorBrightness>>,
+) {
+    let publish = |monitors: &[Monitor<D>]| {
+        tx.send_if_modified(|current| {
+            let states = monitors.iter().map(|m| m.state.clone()).collect::<Vec<_>>();
+            let modified = *current != states;
+            *current = states;
+            modified
+        });
+    };
+
+    let mut connectors = Vec::new();
+    let mut monitors = Vec::new();
+    rescan(sysfs_class_root, open, &mut connectors, &mut monitors);
+    publish(&monitors);
+    let mut last_scan = Instant::now();
+
+    loop {
+        let timeout = RESCAN_INTERVAL.saturating_sub(last_scan.elapsed());
+        match commands_rx.recv_timeout(timeout) {
+            Ok(command) => {
+                // Writing is slow, so the changes of a burst are combined into one write per
+                // monitor
+                let mut pending = HashMap::new();
+                for (output, change) in std::iter::once(command).chain(commands_rx.try_iter()) {
+                    let Some(monitor) = monitors.iter().find(|m| m.state.output == output) else {
+                        continue;
+                    };
+                    let state = &monitor.state;
+                    let current = pending.get(&output).copied().unwrap_or(state.brightness);
+                    let brightness = match change {
+                        Change::Set(raw) => raw.min(state.max_brightness as u32) as u16,
+                        Change::Step(delta) => {
+                            let fraction = current as f32 / state.max_brightness as f32;
+                            state.raw_from_fraction(fraction + delta)
+                        }
+                    };
+                    pending.insert(output, brightness);
+                }
+                for (output, brightness) in pending {
+                    let Some(monitor) = monitors.iter_mut().find(|m| m.state.output == output)
+                    else {
+                        continue;
+                    };
+                    match set_vcp(&mut monitor.device, VCP_BRIGHTNESS, brightness) {
+                        Ok(()) => monitor.state.brightness = brightness,
+                        Err(e) => log::warn!("ddc: failed to set brightness of {output}: {e}"),
+                    }
+                }
+                publish(&monitors);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                rescan(sysfs_class_root, open, &mut connectors, &mut monitors);
+                publish(&monitors);
+                last_scan = Instant::now();
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// Updates the brightness of `monitors`, unless the monitors connected to the drm connectors
+/// differ from `connectors`, in which case the buses are probed for them again.
+fn rescan<D: I2cDevice>(
+    sysfs_class_root: &Path,
+    open: &dyn Fn(&str) -> io::Result<D>,
+    connectors: &mut Vec<(String, [u8; EDID_LEN])>,
+    monitors: &mut Vec<Monitor<D>>,
+) {
+    let current = connector_edids(&sysfs_class_root.join("drm"));
+    if current != *connectors {
+        *connectors = current;
+        *monitors = discover(sysfs_class_root, open, connectors);
+        return;
+    }
+    for monitor in monitors {
+        match get_vcp(&mut monitor.device, VCP_BRIGHTNESS) {
+            Ok((brightness, _)) => monitor.state.brightness = brightness,
+            Err(e) => log::debug!(
+                "ddc: failed to read brightness of {}: {e}",
+                monitor.state.output
+            ),
+        }
+    }
+}
+
+/// Finds the monitors supporting brightness control on the i2c buses of graphics cards, and which
+/// of the `connectors` they are connected to.
+fn discover<D: I2cDevice>(
+    sysfs_class_root: &Path,
+    open: &dyn Fn(&str) -> io::Result<D>,
+    connectors: &[(String, [u8; EDID_LEN])],
+) -> Vec<Monitor<D>> {
+    if connectors.is_empty() {
+        return Vec::new();
+    }
+
+    let mut monitors = display_buses(&sysfs_class_root.join("i2c-dev"))
+        .into_iter()
+        .filter_map(|bus| {
+            let mut device = open(&bus)
+                .inspect_err(|e| log::debug!("ddc: failed to open {bus}: {e}"))
+                .ok()?;
+            let edid = read_edid(&mut device).ok()?;
+            let (output, _) = connectors.iter().find(|(_, e)| *e == edid)?;
+            let (brightness, max_brightness) = get_vcp(&mut device, VCP_BRIGHTNESS)
+                .inspect_err(|e| log::debug!("ddc: {output} on {bus} has no brightness: {e}"))
+                .ok()?;
+            if max_brightness == 0 {
+                return None;
+            }
+            Some(Monitor {
+                device,
+                state: MonitorBrightness {
+                    output: output.clone(),
+                    brightness,
+                    max_brightness,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Some cards expose the same monitor on several buses
+    monitors.sort_by(|a, b| a.state.output.cmp(&b.state.output));
+    monitors.dedup_by(|a, b| a.state.output == b.state.output);
+    monitors
+}
+
+/// Names of the i2c buses in `i2c_dev_root` that may have a monitor attached.
+///
+/// SMBus adapters are skipped, since probing them can upset devices like touchpads.
+fn display_buses(i2c_dev_root: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(i2c_dev_root) else {
+        return Vec::new();
+    };
+    let mut buses = entries
+        .flatten()
+        .filter_map(|entry| {
+            let bus = entry.file_name().into_string().ok()?;
+            let name = std::fs::read_to_string(entry.path().join("name")).unwrap_or_default();
+            (!name.starts_with("SMBus")).then_some(bus)
+        })
+        .collect::<Vec<_>>();
+    buses.sort();
+    buses
+}
+
+/// Base EDID blocks of the drm connectors in `drm_root` that have a monitor connected, keyed by
+/// the name of the connector, which is also the name of its `wl_output`. Sorted by name.
+fn connector_edids(drm_root: &Path) -> Vec<(String, [u8; EDID_LEN])> {
+    let Ok(entries) = std::fs::read_dir(drm_root) else {
+        return Vec::new();
+    };
+    let mut connectors = entries
+        .flatten()
+        .filter_map(|entry| {
+            // Connectors are named like `card1-DP-1`
+            let name = entry.file_name().into_string().ok()?;
+            let (card, connector) = name.split_once('-')?;
+            if !card.starts_with("card") {
+                return None;
+            }
+            let edid = std::fs::read(entry.path().join("edid")).ok()?;
+            Some((
+                connector.to_string(),
+                edid.get(..EDID_LEN)?.try_into().ok()?,
+            ))
+        })
+        .collect::<Vec<_>>();
+    connectors.sort();
+    connectors
+}
+
+fn read_edid<D: I2cDevice>(device: &mut D) -> io::Result<[u8; EDID_LEN]> {
+    device.set_address(EDID_ADDRESS)?;
+    device.write(&[0])?;
+    let mut edid = [0; EDID_LEN];
+    device.read(&mut edid)?;
+    Ok(edid)
+}
+
+fn checksum(initial: u8, bytes: &[u8]) -> u8 {
+    bytes.iter().fold(initial, |acc, b| acc ^ b)
+}
+
+/// Sends a DDC/CI message with the given payload.
+fn send<D: I2cDevice>(device: &mut D, payload: &[u8]) -> io::Result<()> {
+    let mut message = Vec::with_capacity(payload.len() + 3);
+    message.push(HOST_ADDRESS);
+    message.push(0x80 | payload.len() as u8);
+    message.extend_from_slice(payload);
+    message.push(checksum(DISPLAY_WRITE_ADDRESS, &message));
+
+    device.set_address(DDC_ADDRESS)?;
+    device.write(&message)
+}
+
+/// Reads the current and maximum value of a continuous VCP feature.
+fn get_vcp<D: I2cDevice>(device: &mut D, code: u8) -> io::Result<(u16, u16)> {
+    retry(|| {
+        send(device, &[GET_VCP_OPCODE, code])?;
+        std::thread::sleep(REPLY_DELAY);
+
+        // source, length, opcode, result, code, type, max (2), current (2), checksum
+        let mut reply = [0; 11];
+        device.read(&mut reply)?;
+        if checksum(REPLY_CHECKSUM_ADDRESS, &reply[..10]) != reply[10] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid reply checksum",
+            ));
+        }
+        if reply[1] != 0x88 || reply[2] != GET_VCP_REPLY_OPCODE || reply[4] != code {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unexpected reply",
+            ));
+        }
+        if reply[3] != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("VCP code {code:#04x} is not supported"),
+            ));
+        }
+
+        let max = u16::from_be_bytes([reply[6], reply[7]]);
+        let current = u16::from_be_bytes([reply[8], reply[9]]);
+        Ok((current, max))
+    })
+}
+
+fn set_vcp<D: I2cDevice>(device: &mut D, code: u8, value: u16) -> io::Result<()> {
+    retry(|| {
+        let [high, low] = value.to_be_bytes();
+        send(device, &[SET_VCP_OPCODE, code, high, low])?;
+        std::thread::sleep(SET_DELAY);
+        Ok(())
+    })
+}
+
+fn retry<T>(mut f: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut result = f();
+    for _ in 1..ATTEMPTS {
+        match result {
+            Err(ref e) if e.kind() != io::ErrorKind::Unsupported => {
+                std::thread::sleep(REPLY_DELAY);
+                result = f();
+            }
+            _ => break,
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    /// A monitor on a simulated i2c bus.
+    #[derive(Debug)]
+    struct FakeMonitor {
+        edid: [u8; EDID_LEN],
+        brightness: u16,
+        max_brightness: u16,
+        edid_reads: usize,
+    }
+
+    #[derive(Debug)]
+    struct FakeBus {
+        monitor: Arc<Mutex<FakeMonitor>>,
+        address: u16,
+        reply: Vec<u8>,
+    }
+
+    impl I2cDevice for FakeBus {
+        fn set_address(&mut self, address: u16) -> io::Result<()> {
+            self.address = address;
+            Ok(())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
+            let reply = std::mem::take(&mut self.reply);
+            if reply.len() != buf.len() {
+                return Err(io::Error::other("unexpected read"));
+            }
+            buf.copy_from_slice(&reply);
+            Ok(())
+        }
+
+        fn write(&mut self, data: &[u8]) -> io::Result<()> {
+            let mut monitor = self.monitor.lock().unwrap();
+            match (self.address, data) {
+                (EDID_ADDRESS, [0]) => {
+                    monitor.edid_reads += 1;
+                    self.reply = monitor.edid.to_vec();
+                }
+                (DDC_ADDRESS, [HOST_ADDRESS, _, GET_VCP_OPCODE, VCP_BRIGHTNESS, _]) => {
+                    let [max_high, max_low] = monitor.max_brightness.to_be_bytes();
+                    let [high, low] = monitor.brightness.to_be_bytes();
+                    let mut reply = vec![
+                        DISPLAY_WRITE_ADDRESS,
+                        0x88,
+                        GET_VCP_REPLY_OPCODE,
+                        0,
+                        VCP_BRIGHTNESS,
+                        0,
+                        max_high,
+                        max_low,
+                        high,
+                        low,
+                    ];
+                    reply.push(checksum(REPLY_CHECKSUM_ADDRESS, &reply));
+                    self.reply = reply;
+                }
+                (
+                    DDC_ADDRESS,
+                    [
+                        HOST_ADDRESS,
+                        _,
+                        SET_VCP_OPCODE,
+                        VCP_BRIGHTNESS,
+                        high,
+                        low,
+                        _,
+                    ],
+                ) => {
+                    monitor.brightness = u16::from_be_bytes([*high, *low]);
+                }
+                _ => return Err(io::Error::other("unexpected write")),
+            }
+            Ok(())
+        }
+    }
+
+    fn edid(serial: u8) -> [u8; EDID_LEN] {
+        let mut edid = [0; EDID_LEN];
+        edid[..8].copy_from_slice(&[0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0]);
+        edid[12] = serial;
+        edid
+    }
+
+    /// A graphics card with `DP-1` connected to `i2c-3`, next to a touchpad on an SMBus adapter.
+    fn fake_system() -> (TempDir, Arc<Mutex<FakeMonitor>>) {
+        let sysfs = TempDir::new();
+        sysfs.write("drm/card1-DP-1/edid", edid(1));
+        sysfs.write("drm/card1-HDMI-A-1/edid", b"");
+        sysfs.write("drm/card1/dev", "226:1");
+        sysfs.write("i2c-dev/i2c-3/name", "AMDGPU DM i2c hw bus 1");
+        sysfs.write("i2c-dev/i2c-7/name", "SMBus I801 adapter at efa0");
+        let monitor = Arc::new(Mutex::new(FakeMonitor {
+            edid: edid(1),
+            brightness: 40,
+            max_brightness: 100,
+            edid_reads: 0,
+        }));
+        (sysfs, monitor)
+    }
+
+    fn opener(monitor: Arc<Mutex<FakeMonitor>>) -> impl Fn(&str) -> io::Result<FakeBus> {
+        move |bus| match bus {
+            "i2c-3" => Ok(FakeBus {
+                monitor: monitor.clone(),
+                address: 0,
+                reply: Vec::new(),
+            }),
+            _ => panic!("{bus} should not be probed"),
+        }
+    }
+
+    fn dp1(brightness: u16) -> MonitorBrightness {
+        MonitorBrightness {
+            output: "DP-1".to_string(),
+            brightness,
+            max_brightness: 100,
+        }
+    }
+
+    fn states(monitors: &[Monitor<FakeBus>]) -> Vec<MonitorBrightness> {
+        monitors.iter().map(|m| m.state.clone()).collect()
+    }
+
+    #[test]
+    fn probes_again_only_when_connectors_change() {
+        let (sysfs, monitor) = fake_system();
+        let open = opener(monitor.clone());
+        let (mut connectors, mut monitors) = (Vec::new(), Vec::new());
+
+        rescan(sysfs.path(), &open, &mut connectors, &mut monitors);
+        assert_eq!(states(&monitors), [dp1(40)]);
+        assert_eq!(monitor.lock().unwrap().edid_reads, 1);
+
+        // Changed with the monitor's buttons
+        monitor.lock().unwrap().brightness = 60;
+        rescan(sysfs.path(), &open, &mut connectors, &mut monitors);
+        assert_eq!(states(&monitors), [dp1(60)]);
+        assert_eq!(monitor.lock().unwrap().edid_reads, 1);
+
+        // Unplugged
+        sysfs.write("drm/card1-DP-1/edid", b"");
+        rescan(sysfs.path(), &open, &mut connectors, &mut monitors);
+        assert!(monitors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sets_the_brightness_of_monitors() {
+        let (sysfs, monitor) = fake_system();
+        let ddc = Ddc::with_buses(sysfs.path().to_path_buf(), opener(monitor.clone()));
+        let mut rx = ddc.rx.lock().await;
+        let wait_for = async |rx: &mut watch::Receiver<Vec<MonitorBrightness>>, expected| {
+            tokio::time::timeout(Duration::from_secs(5), rx.wait_for(|m| *m == expected))
+                .await
+                .expect("timed out waiting for the monitors")
+                .unwrap();
+        };
+        wait_for(&mut rx, vec![dp1(40)]).await;
+
+        ddc.change_brightness("DP-1".to_string(), Change::Set(75));
+        wait_for(&mut rx, vec![dp1(75)]).await;
+        assert_eq!(monitor.lock().unwrap().brightness, 75);
+
+        // Steps add up even before the monitor reports the brightness they led to
+        for _ in 0..3 {
+            ddc.change_brightness("DP-1".to_string(), Change::Step(0.05));
+        }
+        ddc.change_brightness("HDMI-A-1".to_string(), Change::Step(0.05));
+        wait_for(&mut rx, vec![dp1(90)]).await;
+        assert_eq!(monitor.lock().unwrap().brightness, 90);
+    }
+}
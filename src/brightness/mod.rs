@@ -0,0 +1,266 @@
+//! Tracks and controls the brightness of the display backlight and external monitors.
+//!
+//! Backlight brightness is read from sysfs, and written through logind so that limbo doesn't need
+//! write access to sysfs. External monitors are controlled over DDC/CI, see [`ddc`].
+
+use std::path::PathBuf;
+
+use iced::futures::StreamExt;
+use tokio::sync::mpsc;
+
+use crate::message::Message;
+
+mod backlight;
+mod ddc;
+
+pub use backlight::Backlight;
+use backlight::LogindSessionProxy;
+use ddc::Ddc;
+pub use ddc::MonitorBrightness;
+
+/// Exponent mapping perceived brightness to raw brightness, since perceived brightness is
+/// roughly logarithmic in luminance.
+const PERCEPTUAL_EXPONENT: f32 = 2.0;
+
+/// Converts a raw `0.0..=1.0` brightness fraction to a perceived one.
+pub fn to_perceived(fraction: f32) -> f32 {
+    fraction.clamp(0.0, 1.0).powf(1.0 / PERCEPTUAL_EXPONENT)
+}
+
+/// Converts a perceived `0.0..=1.0` brightness to a raw brightness fraction.
+pub fn from_perceived(perceived: f32) -> f32 {
+    perceived.clamp(0.0, 1.0).powf(PERCEPTUAL_EXPONENT)
+}
+
+impl Backlight {
+    /// Perceived brightness, `0.0..=1.0`.
+    pub fn perceived(&self) -> f32 {
+        to_perceived(self.brightness as f32 / self.max_brightness as f32)
+    }
+
+    /// Raw brightness value for a perceived brightness, never turning the backlight fully off.
+    pub fn raw_from_perceived(&self, perceived: f32) -> u32 {
+        let raw = (from_perceived(perceived) * self.max_brightness as f32).round() as u32;
+        raw.clamp(1, self.max_brightness)
+    }
+}
+
+/// A change of the brightness of a device. Changes are applied one after the other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    /// To a raw brightness.
+    Set(u32),
+    /// By a step of the level shown to the user, from the brightness of the device when the step
+    /// is applied, so that fast scrolling doesn't step from an outdated brightness.
+    Step(f32),
+}
+
+#[derive(Debug)]
+pub struct Brightness {
+    sysfs_root: PathBuf,
+    backlight: mpsc::UnboundedSender<(String, Change)>,
+    ddc: Option<Ddc>,
+}
+
+impl Brightness {
+    /// External monitors are only probed for if `monitors` is set, since that means talking to
+    /// every display i2c bus.
+    pub fn new(monitors: bool) -> Self {
+        Self::with_backends(
+            PathBuf::from(backlight::SYSFS_BACKLIGHT_DIR),
+            None,
+            monitors.then(Ddc::new),
+        )
+    }
+
+    /// Creates the service for the backlight devices in `sysfs_root`, talking to the logind
+    /// instance at `logind_address`, and controlling external monitors through `ddc`.
+    pub fn with_backends(
+        sysfs_root: PathBuf,
+        logind_address: Option<String>,
+        ddc: Option<Ddc>,
+    ) -> Self {
+        let (backlight, backlight_rx) = mpsc::unbounded_channel();
+        tokio::spawn(run_backlight(
+            sysfs_root.clone(),
+            logind_address,
+            backlight_rx,
+        ));
+        Self {
+            sysfs_root,
+            backlight,
+            ddc,
+        }
+    }
+
+    /// Changes the brightness of a backlight device.
+    pub fn change_backlight(&self, device: String, change: Change) {
+        let _ = self.backlight.send((device, change));
+    }
+
+    /// Changes the brightness of the external monitor connected to `output`.
+    pub fn change_monitor_brightness(&self, output: String, change: Change) {
+        if let Some(ddc) = &self.ddc {
+            ddc.change_brightness(output, change);
+        }
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::batch([
+            self.backlight_subscription(),
+            self.ddc
+                .as_ref()
+                .map_or_else(iced::Subscription::none, Ddc::subscription),
+        ])
+    }
+
+    fn backlight_subscription(&self) -> iced::Subscription<Message> {
+        #[derive(Hash)]
+        struct BacklightWatch(PathBuf);
+
+        let root = self.sysfs_root.clone();
+        iced::Subscription::run_with_id(
+            BacklightWatch(root.clone()),
+            backlight::watch(root).map(Message::BacklightUpdate),
+        )
+    }
+}
+
+/// Applies the changes of backlight devices through logind, in order. Steps start from the
+/// brightness in sysfs, which logind has written by the time it replies.
+async fn run_backlight(
+    sysfs_root: PathBuf,
+    logind_address: Option<String>,
+    mut changes: mpsc::UnboundedReceiver<(String, Change)>,
+) {
+    let mut logind = None;
+    while let Some((device, change)) = changes.recv().await {
+        let brightness = match change {
+            Change::Set(brightness) => brightness,
+            Change::Step(delta) => {
+                let path = sysfs_root.join(&device);
+                let current = tokio::task::spawn_blocking(move || backlight::read_requested(&path))
+                    .await
+                    .ok()
+                    .flatten();
+                let Some(current) = current else {
+                    log::warn!("brightness: failed to read {device}");
+                    continue;
+                };
+                current.raw_from_perceived(current.perceived() + delta)
+            }
+        };
+        if let Err(e) =
+            set_backlight(&mut logind, logind_address.as_deref(), &device, brightness).await
+        {
+            log::warn!("brightness: failed to set {device} to {brightness}: {e}");
+            // The connection is made again for the next change
+            logind = None;
+        }
+    }
+}
+
+async fn set_backlight(
+    logind: &mut Option<LogindSessionProxy<'static>>,
+    address: Option<&str>,
+    device: &str,
+    brightness: u32,
+) -> zbus::Result<()> {
+    let proxy = match logind {
+        Some(proxy) => proxy,
+        None => {
+            let builder = match address {
+                Some(address) => zbus::connection::Builder::address(address)?,
+                None => zbus::connection::Builder::system()?,
+            };
+            logind.insert(LogindSessionProxy::new(&builder.build().await?).await?)
+        }
+    };
+    proxy.set_brightness("backlight", device, brightness).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::testing::{PrivateBus, TempDir};
+
+    /// Stands in for the logind session of the user, writing the brightness to sysfs like logind.
+    struct FakeSession {
+        sysfs: PathBuf,
+        calls: mpsc::UnboundedSender<(String, String, u32)>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.login1.Session")]
+    impl FakeSession {
+        fn set_brightness(&self, subsystem: String, name: String, brightness: u32) {
+            let path = self.sysfs.join(&name).join("brightness");
+            std::fs::write(path, format!("{brightness}\n")).unwrap();
+            let _ = self.calls.send((subsystem, name, brightness));
+        }
+    }
+
+    #[tokio::test]
+    async fn changes_the_backlight_through_logind_in_order() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let sysfs = TempDir::new();
+        sysfs.write("intel_backlight/max_brightness", "400\n");
+        sysfs.write("intel_backlight/brightness", "400\n");
+        let (calls_tx, mut calls) = mpsc::unbounded_channel();
+        let logind = bus.connection().await;
+        let session = FakeSession {
+            sysfs: sysfs.path().to_path_buf(),
+            calls: calls_tx,
+        };
+        logind
+            .object_server()
+            .at("/org/freedesktop/login1/session/auto", session)
+            .await
+            .unwrap();
+        logind.request_name("org.freedesktop.login1").await.unwrap();
+
+        let brightness =
+            Brightness::with_backends(sysfs.path().to_path_buf(), Some(bus.address.clone()), None);
+        let device = || "intel_backlight".to_string();
+        brightness.change_backlight(device(), Change::Set(100));
+        // A burst of steps, each from the brightness the one before led to
+        for _ in 0..3 {
+            brightness.change_backlight(device(), Change::Step(0.1));
+        }
+        brightness.change_backlight("missing".to_string(), Change::Step(0.1));
+        brightness.change_backlight(device(), Change::Step(-1.0));
+
+        let mut received = Vec::new();
+        while received.len() < 5 {
+            let (subsystem, name, brightness) =
+                tokio::time::timeout(Duration::from_secs(5), calls.recv())
+                    .await
+                    .expect("timed out waiting for logind to be called")
+                    .unwrap();
+            assert_eq!(
+                (subsystem.as_str(), name.as_str()),
+                ("backlight", "intel_backlight")
+            );
+            received.push(brightness);
+        }
+        assert_eq!(received, [100, 144, 196, 256, 1]);
+    }
+
+    #[test]
+    fn never_turns_the_backlight_off() {
+        let backlight = Backlight {
+            device: "intel_backlight".to_string(),
+            brightness: 400,
+            max_brightness: 400,
+        };
+        assert_eq!(backlight.perceived(), 1.0);
+        assert_eq!(backlight.raw_from_perceived(1.0), 400);
+        assert_eq!(backlight.raw_from_perceived(0.5), 100);
+        assert_eq!(backlight.raw_from_perceived(0.0), 1);
+    }
+}
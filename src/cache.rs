@@ -0,0 +1,61 @@
+//! Files limbo keeps below `$XDG_CACHE_HOME/limbo`.
+
+use std::path::{Path, PathBuf};
+
+/// The directory of limbo's cache files, `None` if there is no cache directory.
+pub fn dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("limbo"))
+}
+
+/// A file name for `key`, e.g. a URL, which stays the same across builds and runs, unlike
+/// [`std::hash::DefaultHasher`].
+pub fn file_name(key: &str) -> String {
+    // 64-bit FNV-1a
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// Writes `contents` to `path` through a temporary file, so that readers never see it partly
+/// written.
+pub async fn write(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    let temporary = PathBuf::from(temporary);
+    if let Err(e) = tokio::fs::write(&temporary, contents).await {
+        let _ = tokio::fs::remove_file(&temporary).await;
+        return Err(e);
+    }
+    tokio::fs::rename(&temporary, path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn file_names_are_stable() {
+        assert_eq!(file_name(""), "cbf29ce484222325");
+        assert_eq!(file_name("a"), "af63dc4c8601ec8c");
+        assert_ne!(
+            file_name("https://example.com/1"),
+            file_name("https://example.com/2")
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_through_a_temporary_file() {
+        let dir = TempDir::new();
+        let path = dir.path().join("sub").join("file");
+        write(&path, b"first").await.unwrap();
+        write(&path, b"second").await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+        let entries = std::fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(entries, 1);
+    }
+}
@@ -0,0 +1,255 @@
+//! Caffeine, keeping the session from idling for a while.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use iced::{Task, window};
+use jiff::{SignedDuration, Timestamp, Zoned};
+use sctk::reexports::client::protocol::wl_output::WlOutput;
+use sctk::reexports::client::protocol::wl_surface::WlSurface;
+use sctk::reexports::protocols::wp::idle_inhibit::zv1::client::zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1;
+
+use crate::message::Message;
+
+mod wayland;
+
+use wayland::IdleInhibit;
+
+/// How long caffeine stays active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaffeineTimer {
+    Minutes(u32),
+    UntilMidnight,
+    Indefinite,
+}
+
+impl CaffeineTimer {
+    /// The timers that can be picked, ordered from shortest to longest.
+    pub const ALL: [CaffeineTimer; 4] = [
+        CaffeineTimer::Minutes(30),
+        CaffeineTimer::Minutes(60),
+        CaffeineTimer::UntilMidnight,
+        CaffeineTimer::Indefinite,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            CaffeineTimer::Minutes(60) => "1 hour".to_string(),
+            CaffeineTimer::Minutes(minutes) if minutes % 60 == 0 => {
+                format!("{} hours", minutes / 60)
+            }
+            CaffeineTimer::Minutes(minutes) => format!("{minutes} minutes"),
+            CaffeineTimer::UntilMidnight => "Until midnight".to_string(),
+            CaffeineTimer::Indefinite => "Indefinitely".to_string(),
+        }
+    }
+
+    /// When a timer started at `now` expires, `None` if it never does.
+    fn deadline(self, now: &Zoned) -> Option<Timestamp> {
+        match self {
+            CaffeineTimer::Minutes(minutes) => {
+                Some(now.timestamp() + SignedDuration::from_mins(minutes as i64))
+            }
+            CaffeineTimer::UntilMidnight => now
+                .tomorrow()
+                .and_then(|tomorrow| tomorrow.start_of_day())
+                .map(|midnight| midnight.timestamp())
+                .ok(),
+            CaffeineTimer::Indefinite => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CaffeineState {
+    /// The running timer, `None` while caffeine is off.
+    pub timer: Option<CaffeineTimer>,
+    /// When the running timer expires.
+    pub until: Option<Timestamp>,
+}
+
+impl CaffeineState {
+    pub fn active(&self) -> bool {
+        self.timer.is_some()
+    }
+
+    /// Time left until the running timer expires.
+    pub fn remaining(&self) -> Option<Duration> {
+        let until = self.until?;
+        Some(
+            until
+                .duration_since(Timestamp::now())
+                .try_into()
+                .unwrap_or(Duration::ZERO),
+        )
+    }
+}
+
+/// Inhibits idling through inhibitors on the surfaces of all bars, so that it keeps working
+/// while some outputs are turned off.
+pub struct Caffeine {
+    state: CaffeineState,
+    /// `None` until the first bar is opened, or if the compositor doesn't support it.
+    idle_inhibit: Option<IdleInhibit>,
+    surfaces: HashMap<window::Id, WlSurface>,
+    inhibitors: HashMap<window::Id, ZwpIdleInhibitorV1>,
+}
+
+impl Caffeine {
+    pub fn new() -> Self {
+        Self {
+            state: CaffeineState::default(),
+            idle_inhibit: None,
+            surfaces: HashMap::new(),
+            inhibitors: HashMap::new(),
+        }
+    }
+
+    /// Looks up the surface of the newly opened bar `bar_id`, resulting in
+    /// `Message::BarSurface`.
+    pub fn bar_opened(&mut self, bar_id: window::Id, wl_output: &WlOutput) -> Task<Message> {
+        if self.idle_inhibit.is_none() {
+            self.idle_inhibit = IdleInhibit::connect(wl_output)
+                .inspect_err(|e| log::warn!("caffeine: {e}"))
+                .ok();
+        }
+        let Some(idle_inhibit) = &self.idle_inhibit else {
+            return Task::none();
+        };
+
+        let connection = idle_inhibit.connection().clone();
+        window::run_with_handle(bar_id, move |handle| {
+            wayland::surface_from_handle(&connection, handle)
+        })
+        .and_then(move |surface| Task::done(Message::BarSurface(bar_id, surface)))
+    }
+
+    pub fn add_surface(&mut self, bar_id: window::Id, surface: WlSurface) {
+        self.surfaces.insert(bar_id, surface);
+        self.sync_inhibitors();
+    }
+
+    pub fn bar_closed(&mut self, bar_id: window::Id) {
+        self.surfaces.remove(&bar_id);
+        self.sync_inhibitors();
+    }
+
+    /// Starts `timer`, or turns caffeine off if `None`.
+    pub fn set(&mut self, timer: Option<CaffeineTimer>) -> CaffeineState {
+        self.state = CaffeineState {
+            timer,
+            until: timer.and_then(|timer| timer.deadline(&Zoned::now())),
+        };
+        self.sync_inhibitors();
+        self.state
+    }
+
+    /// Turns caffeine off once its timer expired, returning the new state if it did.
+    pub fn tick(&mut self) -> Option<CaffeineState> {
+        let expired = self.state.remaining()? == Duration::ZERO;
+        expired.then(|| self.set(None))
+    }
+
+    /// Creates or destroys the inhibitors to match the state.
+    fn sync_inhibitors(&mut self) {
+        let Some(idle_inhibit) = &mut self.idle_inhibit else {
+            return;
+        };
+
+        let active = self.state.active();
+        self.inhibitors.retain(|bar_id, inhibitor| {
+            let keep = active && self.surfaces.contains_key(bar_id);
+            if !keep {
+                inhibitor.destroy();
+            }
+            keep
+        });
+        if active {
+            for (bar_id, surface) in &self.surfaces {
+                self.inhibitors
+                    .entry(*bar_id)
+                    .or_insert_with(|| idle_inhibit.inhibit(surface));
+            }
+        }
+        idle_inhibit.flush();
+    }
+
+    /// Checks for an expired timer every second while one is running.
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        if self.state.until.is_some() {
+            iced::time::every(Duration::from_secs(1)).map(|_| Message::CaffeineTick)
+        } else {
+            iced::Subscription::none()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deadline(timer: CaffeineTimer, now: &str) -> Option<Timestamp> {
+        timer.deadline(&now.parse::<Zoned>().unwrap())
+    }
+
+    fn at(time: &str) -> Option<Timestamp> {
+        Some(time.parse().unwrap())
+    }
+
+    #[test]
+    fn expires_after_the_picked_duration() {
+        assert_eq!(
+            deadline(
+                CaffeineTimer::Minutes(90),
+                "2024-06-21T23:00:00+02:00[Europe/Berlin]"
+            ),
+            at("2024-06-22T00:30:00+02:00")
+        );
+        assert_eq!(
+            deadline(
+                CaffeineTimer::Indefinite,
+                "2024-06-21T23:00:00+02:00[Europe/Berlin]"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn expires_at_the_next_local_midnight() {
+        assert_eq!(
+            deadline(
+                CaffeineTimer::UntilMidnight,
+                "2024-06-21T00:00:00+02:00[Europe/Berlin]"
+            ),
+            at("2024-06-22T00:00:00+02:00")
+        );
+        // Berlin changes clocks at night, the day itself is only 23 hours long
+        assert_eq!(
+            deadline(
+                CaffeineTimer::UntilMidnight,
+                "2024-03-31T01:00:00+01:00[Europe/Berlin]"
+            ),
+            at("2024-04-01T00:00:00+02:00")
+        );
+    }
+
+    #[test]
+    fn expires_at_the_start_of_days_without_a_midnight() {
+        // Santiago changes clocks at midnight, so the day starts at 1:00 when entering DST
+        assert_eq!(
+            deadline(
+                CaffeineTimer::UntilMidnight,
+                "2024-09-07T22:00:00-04:00[America/Santiago]"
+            ),
+            at("2024-09-08T01:00:00-03:00")
+        );
+        // and midnight is an hour further away when leaving it
+        assert_eq!(
+            deadline(
+                CaffeineTimer::UntilMidnight,
+                "2024-04-06T22:00:00-03:00[America/Santiago]"
+            ),
+            at("2024-04-07T00:00:00-04:00")
+        );
+    }
+}
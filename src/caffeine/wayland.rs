@@ -0,0 +1,95 @@
+//! Keeps the session awake through `idle-inhibit-unstable-v1`.
+//!
+//! Inhibitors are attached to the bar surfaces, which belong to iced's Wayland connection. The
+//! connection is shared, with an event queue of our own for the objects created here.
+
+use raw_window_handle::{RawWindowHandle, WindowHandle};
+use sctk::reexports::client::globals::{GlobalListContents, registry_queue_init};
+use sctk::reexports::client::protocol::wl_output::WlOutput;
+use sctk::reexports::client::protocol::wl_registry::{self, WlRegistry};
+use sctk::reexports::client::protocol::wl_surface::WlSurface;
+use sctk::reexports::client::{
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle, delegate_noop,
+};
+use sctk::reexports::protocols::wp::idle_inhibit::zv1::client::zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1;
+use sctk::reexports::protocols::wp::idle_inhibit::zv1::client::zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1;
+use wayland_backend::client::ObjectId;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+struct State;
+
+pub struct IdleInhibit {
+    connection: Connection,
+    queue: EventQueue<State>,
+    manager: ZwpIdleInhibitManagerV1,
+}
+
+impl IdleInhibit {
+    /// Binds the idle inhibit manager on the connection `wl_output` belongs to.
+    pub fn connect(wl_output: &WlOutput) -> Result<Self, BoxError> {
+        let backend = wl_output
+            .backend()
+            .upgrade()
+            .ok_or("wayland connection is closed")?;
+        let connection = Connection::from_backend(backend);
+        let (globals, queue) = registry_queue_init::<State>(&connection)?;
+
+        let manager = globals
+            .bind::<ZwpIdleInhibitManagerV1, _, _>(&queue.handle(), 1..=1, ())
+            .map_err(|e| format!("compositor doesn't support idle inhibition: {e}"))?;
+
+        Ok(Self {
+            connection,
+            queue,
+            manager,
+        })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Inhibits idling while `surface` is visible, until the returned inhibitor is destroyed.
+    pub fn inhibit(&self, surface: &WlSurface) -> ZwpIdleInhibitorV1 {
+        self.manager
+            .create_inhibitor(surface, &self.queue.handle(), ())
+    }
+
+    /// Sends the pending requests to the compositor.
+    pub fn flush(&mut self) {
+        // Nothing here has events, but the registry's must not pile up
+        if let Err(e) = self.queue.dispatch_pending(&mut State) {
+            log::warn!("caffeine: {e}");
+        }
+        if let Err(e) = self.connection.flush() {
+            log::warn!("caffeine: {e}");
+        }
+    }
+}
+
+/// The `wl_surface` of a window of iced, which lives on `connection`.
+pub fn surface_from_handle(connection: &Connection, handle: WindowHandle<'_>) -> Option<WlSurface> {
+    let RawWindowHandle::Wayland(handle) = handle.as_raw() else {
+        return None;
+    };
+    // SAFETY: the handle points to the live `wl_proxy` of a surface on `connection`
+    let id = unsafe { ObjectId::from_ptr(WlSurface::interface(), handle.surface.as_ptr().cast()) }
+        .ok()?;
+    WlSurface::from_id(connection, id).ok()
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(State: ZwpIdleInhibitManagerV1);
+delegate_noop!(State: ZwpIdleInhibitorV1);
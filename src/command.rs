@@ -0,0 +1,32 @@
+/// Runs `command` with `sh -c` in the background, logging failures.
+pub fn spawn(command: &str) {
+    let command = command.to_string();
+    tokio::spawn(async move {
+        let status = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .status()
+            .await;
+        match status {
+            Ok(status) if !status.success() => log::warn!("`{command}` exited with {status}"),
+            Err(e) => log::warn!("failed to run `{command}`: {e}"),
+            Ok(_) => {}
+        }
+    });
+}
+
+/// Opens `url` with `xdg-open` in the background, logging failures.
+pub fn open(url: &str) {
+    let url = url.to_string();
+    tokio::spawn(async move {
+        let status = tokio::process::Command::new("xdg-open")
+            .arg(&url)
+            .status()
+            .await;
+        match status {
+            Ok(status) if !status.success() => log::warn!("opening {url} exited with {status}"),
+            Err(e) => log::warn!("failed to open {url}: {e}"),
+            Ok(_) => {}
+        }
+    });
+}
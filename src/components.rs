@@ -1,10 +1,9 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::{LazyLock, Mutex};
+use std::borrow::Cow;
 
 use iced::widget::svg::{Handle, Svg};
-use iced::widget::{Container, container, image, row, svg, text};
+use iced::widget::{Container, container, row, svg, text};
 use iced::{Alignment, Border, Color, Element, Length, Theme};
+use rust_embed::RustEmbed;
 
 use crate::config::Config;
 use crate::icons::{Icons, IconsFilled};
@@ -29,37 +28,31 @@ pub fn text_with_icon<'a, Message: 'a>(
         .into()
 }
 
-static ICON_CACHE: LazyLock<Mutex<HashMap<String, Option<PathBuf>>>> =
-    LazyLock::new(|| Mutex::new(HashMap::new()));
-pub fn system_icon<'a, Message>(name: &str) -> Option<Element<'a, Message>> {
-    let icon_path = ICON_CACHE
-        .lock()
-        .ok()?
-        .entry(name.to_string())
-        .or_insert_with(|| freedesktop_icons::lookup(name).with_size(48).find())
-        .clone()?;
-    Some(image(image::Handle::from_path(icon_path)).into())
+/// Shown in place of icons that aren't bundled, e.g. because of a typo in the config.
+const MISSING_ICON: &str = "help-square-rounded";
+
+fn icon_data<E: RustEmbed>(name: &str) -> Cow<'static, [u8]> {
+    E::get(&format!("{name}.svg"))
+        .or_else(|| E::get(&format!("{MISSING_ICON}.svg")))
+        .expect("the missing icon should be bundled")
+        .data
 }
 
 pub fn icon(name: &'_ str, color: Option<Color>) -> Svg<'static> {
-    svg(Handle::from_memory(
-        Icons::get(&format!("{name}.svg")).unwrap().data,
-    ))
-    .style(move |theme: &Theme, _| svg::Style {
-        color: color.or(Some(theme.palette().text)),
-    })
-    .width(Length::Shrink)
-    .height(Length::Fixed(16.))
+    svg(Handle::from_memory(icon_data::<Icons>(name)))
+        .style(move |theme: &Theme, _| svg::Style {
+            color: color.or(Some(theme.palette().text)),
+        })
+        .width(Length::Shrink)
+        .height(Length::Fixed(16.))
 }
 
 pub fn icon_filled(name: &'_ str, color: Option<Color>) -> Svg<'static> {
-    svg(Handle::from_memory(
-        IconsFilled::get(&format!("{name}.svg")).unwrap().data,
-    ))
-    .style(move |theme: &Theme, _| svg::Style {
-        color: color.or(Some(theme.palette().text)),
-    })
-    .width(Length::Shrink)
+    svg(Handle::from_memory(icon_data::<IconsFilled>(name)))
+        .style(move |theme: &Theme, _| svg::Style {
+            color: color.or(Some(theme.palette().text)),
+        })
+        .width(Length::Shrink)
 }
 
 impl Config {
@@ -88,6 +81,42 @@ impl Config {
             .height(Length::Fill)
     }
 
+    /// Container for popup contents, styled like the bar.
+    /// Container for popups and toasts. `border` replaces the color of the border, which is
+    /// then drawn thicker to stand out.
+    pub fn panel<'a, Message>(
+        &self,
+        content: impl Into<Element<'a, Message>>,
+        border: Option<Color>,
+    ) -> Container<'a, Message> {
+        let background = self
+            .theme
+            .resolve_color(&self.bar.theme.bg)
+            .unwrap_or(Color::from_rgb8(0x1e, 0x1e, 0x2e));
+        let (border_color, border_width) = match border {
+            Some(color) => (color, 2.0),
+            None => (
+                self.theme
+                    .resolve_color(&self.bar.theme.section_bg)
+                    .unwrap_or(Color::from_rgb8(0x2c, 0x2c, 0x3f)),
+                1.0,
+            ),
+        };
+        let radius = iced::Radius::new(self.theme.border_radius * 2.0);
+
+        container(content)
+            .style(move |_| container::Style {
+                background: Some(background.into()),
+                border: Border {
+                    color: border_color,
+                    width: border_width,
+                    radius,
+                },
+                ..Default::default()
+            })
+            .padding(12)
+    }
+
     pub fn icon(&self, _icon: &crate::config::types::Icon) -> Svg<'static> {
         icon(&_icon.name, self.theme.resolve_color(&_icon.color))
     }
@@ -22,6 +22,13 @@ pub struct General {
     pub debug: bool,
 }
 
+impl General {
+    /// Whether a location is configured, the coordinates being `0, 0` otherwise.
+    pub fn located(&self) -> bool {
+        self.lat != 0.0 || self.lon != 0.0
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum TimeFormat {
@@ -176,6 +183,7 @@ pub struct Bar {
     pub app_launcher: AppLauncher,
     pub battery: Battery,
     pub clock: Clock,
+    pub music: Music,
     pub notifications: Notifications,
     pub quick_settings: QuickSettings,
     pub sysmon: Sysmon,
@@ -189,6 +197,8 @@ pub struct BarTheme {
     pub bg: ColorNameOrHex,
     pub section_bg: ColorNameOrHex,
     pub fg: ColorNameOrHex,
+    /// Highlights active and focused controls
+    pub accent: ColorNameOrHex,
 }
 
 impl Default for BarTheme {
@@ -197,11 +207,12 @@ impl Default for BarTheme {
             bg: ColorNameOrHex::name("base"),
             section_bg: ColorNameOrHex::name("core"),
             fg: ColorNameOrHex::name("text"),
+            accent: ColorNameOrHex::name("blue"),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum ModuleName {
     AppLauncher,
@@ -222,6 +233,15 @@ pub struct Modules {
     pub right: Vec<ModuleName>,
 }
 
+impl Modules {
+    /// Whether `module` is shown in any part of the bar.
+    pub fn contains(&self, module: ModuleName) -> bool {
+        [&self.left, &self.center, &self.right]
+            .into_iter()
+            .any(|modules| modules.contains(&module))
+    }
+}
+
 impl Default for Modules {
     fn default() -> Self {
         Self {
@@ -271,6 +291,17 @@ impl Icon {
             color: color.into(),
         }
     }
+
+    /// Picks the icon for `fraction` (`0.0..=1.0`) from a ramp ordered from highest to lowest.
+    pub fn from_ramp(ramp: &[Icon], fraction: f32) -> Option<&Icon> {
+        let idx = ((1.0 - fraction.clamp(0.0, 1.0)) * ramp.len() as f32) as usize;
+        ramp.get(idx.min(ramp.len().saturating_sub(1)))
+    }
+
+    /// Picks the icon for `fraction` (`0.0..=1.0`) from a ramp ordered from lowest to highest.
+    pub fn from_ascending_ramp(ramp: &[Icon], fraction: f32) -> Option<&Icon> {
+        Self::from_ramp(ramp, 1.0 - fraction.clamp(0.0, 1.0))
+    }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -283,6 +314,11 @@ pub struct Text {
 #[serde(rename_all = "camelCase")]
 pub struct AppLauncher {
     pub icon: Icon,
+    /// Command applications with `Terminal=true` are run in, e.g. `foot` or `xterm -e`
+    pub terminal: String,
+    /// Number of applications listed at once
+    pub max_results: usize,
+    /// A primary click command replaces the built-in launcher, e.g. `tofi-drun`
     #[serde(flatten)]
     pub mouse_commands: MouseCommands,
 }
@@ -291,10 +327,9 @@ impl Default for AppLauncher {
     fn default() -> Self {
         Self {
             icon: Icon::new("nix-snowflake-white", ColorNameOrHex::name("text")),
-            mouse_commands: MouseCommands {
-                on_primary_click: Some("tofi-drun".to_string()),
-                ..Default::default()
-            },
+            terminal: "foot".to_string(),
+            max_results: 8,
+            mouse_commands: MouseCommands::default(),
         }
     }
 }
@@ -302,9 +337,24 @@ impl Default for AppLauncher {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Battery {
+    /// Ordered from highest to lowest charge
     pub ramp_icons: Vec<Icon>,
     pub charging_icon: Icon,
     pub full_threshold: u32,
+    /// Percentage at or below which the battery is low while discharging
+    pub low_threshold: u32,
+    /// Percentage at or below which the battery is critical while discharging
+    pub critical_threshold: u32,
+    pub low_color: ColorNameOrHex,
+    pub critical_color: ColorNameOrHex,
+    /// Show a desktop notification when the battery becomes low or critical
+    pub notify: bool,
+    /// Command to run when the battery becomes low
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_low: Option<String>,
+    /// Command to run when the battery becomes critical
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_critical: Option<String>,
     #[serde(flatten)]
     pub mouse_commands: MouseCommands,
 }
@@ -320,6 +370,13 @@ impl Default for Battery {
             ],
             charging_icon: Icon::new("battery-charging", ColorNameOrHex::name("green")),
             full_threshold: 97,
+            low_threshold: 20,
+            critical_threshold: 10,
+            low_color: ColorNameOrHex::name("yellow"),
+            critical_color: ColorNameOrHex::name("red"),
+            notify: true,
+            on_low: None,
+            on_critical: None,
             mouse_commands: Default::default(),
         }
     }
@@ -339,6 +396,30 @@ impl Default for Clock {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Music {
+    pub icon: Icon,
+    /// Players to prefer when several are running, by the suffix of their bus name or their
+    /// identity, e.g. `spotify` or `mpv`
+    pub priority: Vec<String>,
+    /// Titles longer than this many characters scroll
+    pub max_length: usize,
+    /// Tint the popup with the average color of the album art
+    pub tint_popup: bool,
+}
+
+impl Default for Music {
+    fn default() -> Self {
+        Self {
+            icon: Icon::new("music", ColorNameOrHex::name("mauve")),
+            priority: Vec::new(),
+            max_length: 30,
+            tint_popup: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum NotificationSegment {
     Weather,
@@ -442,6 +523,8 @@ pub struct Todoist {
     pub text: Option<Text>,
     /// Get from <https://todoist.com/prefs/integrations>
     pub api_token: String,
+    /// Root of the API
+    pub api_url: String,
     #[serde(flatten)]
     pub mouse_commands: MouseCommands,
 }
@@ -452,6 +535,7 @@ impl Default for Todoist {
             icon: Icon::new("checkbox", ColorNameOrHex::name("red")),
             text: None,
             api_token: String::new(),
+            api_url: "https://api.todoist.com/api/v1".to_string(),
             mouse_commands: Default::default(),
         }
     }
@@ -465,6 +549,8 @@ pub struct Github {
     pub text: Option<Text>,
     /// Classic token with the 'notifications' scope
     pub api_token: String,
+    /// Root of the REST API, e.g. `https://github.example.com/api/v3` for GitHub Enterprise
+    pub api_url: String,
     #[serde(flatten)]
     pub mouse_commands: MouseCommands,
 }
@@ -475,6 +561,7 @@ impl Default for Github {
             icon: Icon::new("brand-github", ColorNameOrHex::name("text")),
             text: None,
             api_token: String::new(),
+            api_url: "https://api.github.com".to_string(),
             mouse_commands: MouseCommands {
                 on_primary_click: Some("xdg-open https://github.com/notifications".to_string()),
                 ..Default::default()
@@ -483,7 +570,7 @@ impl Default for Github {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum QuickSettingSegment {
     Tray,
@@ -496,6 +583,8 @@ pub enum QuickSettingSegment {
     Volume,
     Network,
     Battery,
+    PowerProfile,
+    Bluetooth,
     Toggle,
 }
 
@@ -513,6 +602,8 @@ pub struct QuickSettings {
     pub volume: Volume,
     pub network: Network,
     pub battery: QuickSettingsBattery,
+    pub power_profile: PowerProfile,
+    pub bluetooth: Bluetooth,
     pub toggle: Toggle,
 }
 
@@ -524,10 +615,12 @@ impl Default for QuickSettings {
                 QuickSettingSegment::NightLight,
                 QuickSettingSegment::Brightness,
                 QuickSettingSegment::Caffeine,
+                QuickSettingSegment::PowerProfile,
                 QuickSettingSegment::Dnd,
                 QuickSettingSegment::Mic,
                 QuickSettingSegment::Volume,
                 QuickSettingSegment::Network,
+                QuickSettingSegment::Bluetooth,
                 QuickSettingSegment::Toggle,
             ],
             tray: Default::default(),
@@ -540,43 +633,86 @@ impl Default for QuickSettings {
             volume: Default::default(),
             network: Default::default(),
             battery: Default::default(),
+            power_profile: Default::default(),
+            bluetooth: Default::default(),
             toggle: Default::default(),
         }
     }
 }
 
-#[derive(Default, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Tray {
     pub ignored_apps: Vec<String>,
     pub app_icon_mappings: HashMap<String, Icon>,
+    /// Ids of the items that are always shown on the bar. Other items are tucked away in the
+    /// drawer. When empty, all items except passive ones are shown.
+    pub pinned: Vec<String>,
+    pub drawer: TrayDrawer,
+    /// Color of the outline drawn around items that need attention
+    pub attention_color: ColorNameOrHex,
     #[serde(skip)]
     pub sort_function:
         Option<Box<dyn Fn(&crate::tray::TrayItem, &crate::tray::TrayItem) -> std::cmp::Ordering>>,
 }
 
+impl Default for Tray {
+    fn default() -> Self {
+        Self {
+            ignored_apps: Vec::new(),
+            app_icon_mappings: HashMap::new(),
+            pinned: Vec::new(),
+            drawer: TrayDrawer::Inline,
+            attention_color: ColorNameOrHex::name("red"),
+            sort_function: None,
+        }
+    }
+}
+
 impl core::fmt::Debug for Tray {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         let Tray {
             ignored_apps,
             app_icon_mappings,
+            pinned,
+            drawer,
+            attention_color,
             ..
         } = self;
         f.debug_struct("Tray")
             .field("ignored_apps", &ignored_apps)
             .field("app_icon_mappings", &app_icon_mappings)
+            .field("pinned", &pinned)
+            .field("drawer", &drawer)
+            .field("attention_color", &attention_color)
             .finish()
     }
 }
 
+/// Where unpinned tray items are shown when the drawer is expanded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrayDrawer {
+    /// Slide the items open next to the pinned ones.
+    #[default]
+    Inline,
+    /// Show the items as a grid in a popup below the bar.
+    Popup,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NightLight {
+    /// Shown while off, which it is at startup
     pub off_icon: Icon,
     pub on_icon: Icon,
     pub forced_icon: Icon,
+    /// Color temperature in Kelvin during the day
     pub day_temp: u32,
+    /// Color temperature in Kelvin during the night
     pub night_temp: u32,
+    /// Duration of the transition around sunrise and sunset, computed from `general.lat` and
+    /// `general.lon`. Without them, the night light can only be forced on.
     pub fade_duration_minutes: u32,
     #[serde(flatten)]
     pub mouse_commands: MouseCommands,
@@ -587,7 +723,7 @@ impl Default for NightLight {
         Self {
             off_icon: Icon::new("moon-off", ColorNameOrHex::name("yellow")),
             on_icon: Icon::new("moon", ColorNameOrHex::name("yellow")),
-            forced_icon: Icon::new("moon-start", ColorNameOrHex::name("yellow")),
+            forced_icon: Icon::new("moon-stars", ColorNameOrHex::name("yellow")),
             day_temp: 6500,
             night_temp: 4000,
             fade_duration_minutes: 30,
@@ -598,6 +734,7 @@ impl Default for NightLight {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Brightness {
+    /// Ordered from lowest to highest brightness
     pub ramp_icons: Vec<Icon>,
     pub step: f32,
     #[serde(flatten)]
@@ -623,7 +760,8 @@ impl Default for Brightness {
 pub struct Caffeine {
     pub icon: Icon,
     pub active_icon: Icon,
-    pub toggle_cmd: String,
+    /// Whether to show the time left next to the icon while a timer is running.
+    pub show_remaining: bool,
     #[serde(flatten)]
     pub mouse_commands: MouseCommands,
 }
@@ -633,7 +771,7 @@ impl Default for Caffeine {
         Self {
             icon: Icon::new("mug-off", ColorNameOrHex::name("blue")),
             active_icon: Icon::new("coffee", ColorNameOrHex::name("cyan")),
-            toggle_cmd: "wlinhibit".to_string(),
+            show_remaining: true,
             mouse_commands: Default::default(),
         }
     }
@@ -644,10 +782,21 @@ impl Default for Caffeine {
 pub struct Dnd {
     pub icon: Icon,
     pub dnd_icon: Icon,
-    pub toggle_cmd: String,
-    pub status_cmd: String,
+    /// Notification daemon to control, detected if unset. Ignored when using the built-in
+    /// notification server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daemon: Option<DndDaemon>,
+    /// Mako mode that silences notifications
+    pub mako_mode: String,
     pub history_cmd: String,
     pub dismiss_cmd: String,
+    /// Periods during which do not disturb is turned on
+    pub schedule: Vec<DndSchedule>,
+    /// Turn on do not disturb while the focused window is fullscreen. Only supported on
+    /// Hyprland, since niri doesn't report fullscreen windows.
+    pub when_fullscreen: bool,
+    /// App ids of screen sharing apps. Do not disturb is turned on while one of them is focused.
+    pub screen_share_apps: Vec<String>,
 }
 
 impl Default for Dnd {
@@ -655,19 +804,109 @@ impl Default for Dnd {
         Self {
             icon: Icon::new("bell", ColorNameOrHex::name("red")),
             dnd_icon: Icon::new("bell-off", ColorNameOrHex::name("red")),
-            toggle_cmd: "makoctl mode -t do-not-disturb".to_string(),
-            status_cmd: "makoctl mode".to_string(),
+            daemon: None,
+            mako_mode: "do-not-disturb".to_string(),
             history_cmd: "makoctl restore".to_string(),
             dismiss_cmd: "makoctl dismiss".to_string(),
+            schedule: Vec::new(),
+            when_fullscreen: false,
+            screen_share_apps: Vec::new(),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DndDaemon {
+    Mako,
+    Dunst,
+    Swaync,
+}
+
+/// A period of do not disturb, repeating on the given days.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DndSchedule {
+    /// Days on which the period starts, every day if empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start: TimeOfDay,
+    /// Periods ending before they start run past midnight
+    pub end: TimeOfDay,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<jiff::civil::Weekday> for Weekday {
+    fn from(weekday: jiff::civil::Weekday) -> Self {
+        match weekday {
+            jiff::civil::Weekday::Monday => Weekday::Monday,
+            jiff::civil::Weekday::Tuesday => Weekday::Tuesday,
+            jiff::civil::Weekday::Wednesday => Weekday::Wednesday,
+            jiff::civil::Weekday::Thursday => Weekday::Thursday,
+            jiff::civil::Weekday::Friday => Weekday::Friday,
+            jiff::civil::Weekday::Saturday => Weekday::Saturday,
+            jiff::civil::Weekday::Sunday => Weekday::Sunday,
+        }
+    }
+}
+
+/// Wrapper for [`jiff::civil::Time`] implementing Serialize and Deserialize as `HH:MM`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeOfDay(pub jiff::civil::Time);
+
+impl Serialize for TimeOfDay {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.0.strftime("%H:%M").to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeOfDay {
+    fn deserialize<D>(deserializer: D) -> Result<TimeOfDay, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct TimeOfDayVisitor;
+        impl<'de> serde::de::Visitor<'de> for TimeOfDayVisitor {
+            type Value = TimeOfDay;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a time as HH:MM")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                jiff::civil::Time::strptime("%H:%M", v)
+                    .map(TimeOfDay)
+                    .map_err(|_| serde::de::Error::custom("invalid time, expected HH:MM"))
+            }
+        }
+        deserializer.deserialize_str(TimeOfDayVisitor)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Mic {
     pub icon: Icon,
     pub mute_icon: Icon,
+    /// Shown while any application is recording
+    pub recording_icon: Icon,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub on_secondary_click: Option<String>,
 }
@@ -677,6 +916,7 @@ impl Default for Mic {
         Self {
             icon: Icon::new("microphone", ColorNameOrHex::name("pink")),
             mute_icon: Icon::new("microphone-off", ColorNameOrHex::name("red")),
+            recording_icon: Icon::new("microphone", ColorNameOrHex::name("peach")),
             on_secondary_click: Some("pavucontrol --tab=4".to_string()),
         }
     }
@@ -686,7 +926,19 @@ impl Default for Mic {
 #[serde(rename_all = "camelCase")]
 pub struct Notifs {
     pub icon: Icon,
+    /// Shown while there are unread notifications
     pub notifs_icon: Icon,
+    /// Run limbo's own notification server instead of relying on an external daemon
+    pub server: bool,
+    /// Number of notifications kept in the history of the built-in server
+    pub history_limit: usize,
+    /// Expiry of notifications that leave it to the server, in milliseconds
+    pub default_timeout_ms: u32,
+    /// Maximum number of toasts shown at once
+    pub max_toasts: usize,
+    pub toast_width: f32,
+    /// Border color of critical notifications
+    pub critical_color: ColorNameOrHex,
     pub open_cmd: String,
     pub status_cmd: String,
     pub toggle_cmd: String,
@@ -697,6 +949,12 @@ impl Default for Notifs {
         Self {
             icon: Icon::new("bell", ColorNameOrHex::name("red")),
             notifs_icon: Icon::new("bell-off", ColorNameOrHex::name("red")),
+            server: false,
+            history_limit: 100,
+            default_timeout_ms: 5000,
+            max_toasts: 5,
+            toast_width: 360.0,
+            critical_color: ColorNameOrHex::name("red"),
             open_cmd: "swaync-client -t -sw".to_string(),
             status_cmd: "swaync-client -D".to_string(),
             toggle_cmd: "swaync-client -d".to_string(),
@@ -707,8 +965,10 @@ impl Default for Notifs {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Volume {
+    /// Ordered from highest to lowest volume
     pub ramp_icons: Vec<Icon>,
     pub mute_icon: Icon,
+    /// Ordered from lowest to highest volume
     pub headphones_ramp: Vec<Icon>,
     pub headphones_mute: Icon,
     pub step: f32,
@@ -739,6 +999,7 @@ impl Default for Volume {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Network {
+    /// Ordered from strongest to weakest signal
     pub ramp_icons: Vec<Icon>,
     pub off_icon: Icon,
     pub ethernet_icon: Icon,
@@ -769,6 +1030,7 @@ impl Default for Network {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QuickSettingsBattery {
+    /// Ordered from highest to lowest charge
     pub ramp_icons: Vec<Icon>,
     pub charging_icon: Icon,
 }
@@ -788,6 +1050,55 @@ impl Default for QuickSettingsBattery {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerProfile {
+    pub power_saver_icon: Icon,
+    pub balanced_icon: Icon,
+    pub performance_icon: Icon,
+    /// Shown next to the profile while performance is degraded
+    pub degraded_icon: Icon,
+    /// Shown next to the profile while applications hold it
+    pub hold_icon: Icon,
+    #[serde(flatten)]
+    pub mouse_commands: MouseCommands,
+}
+
+impl Default for PowerProfile {
+    fn default() -> Self {
+        Self {
+            power_saver_icon: Icon::new("leaf", ColorNameOrHex::name("green")),
+            balanced_icon: Icon::new("scale", ColorNameOrHex::name("blue")),
+            performance_icon: Icon::new("rocket", ColorNameOrHex::name("peach")),
+            degraded_icon: Icon::new("alert-triangle", ColorNameOrHex::name("yellow")),
+            hold_icon: Icon::new("lock", ColorNameOrHex::name("text")),
+            mouse_commands: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bluetooth {
+    pub icon: Icon,
+    pub off_icon: Icon,
+    /// Shown while a device is connected
+    pub connected_icon: Icon,
+    #[serde(flatten)]
+    pub mouse_commands: MouseCommands,
+}
+
+impl Default for Bluetooth {
+    fn default() -> Self {
+        Self {
+            icon: Icon::new("bluetooth", ColorNameOrHex::name("blue")),
+            off_icon: Icon::new("bluetooth-off", ColorNameOrHex::name("overlay0")),
+            connected_icon: Icon::new("bluetooth-connected", ColorNameOrHex::name("blue")),
+            mouse_commands: Default::default(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Toggle {
@@ -885,17 +1196,31 @@ impl Default for Ram {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Todo {
+    /// Tasks in the `todo.txt` format, a leading `~/` is expanded
+    pub file: String,
+    /// Sound played when a task is checked off, empty to stay silent
+    pub sound_file: String,
+    /// Sound played when a Todoist task is completed, downloaded once and cached. Empty to stay
+    /// silent
     pub sound_url: String,
+    /// Command the sound files are passed to
+    pub sound_player: String,
     pub icon: Icon,
+    /// Shown in place of the count while tasks are due
+    pub due_icon: Icon,
 }
 
 impl Default for Todo {
     fn default() -> Self {
         Self {
+            file: "~/todo.txt".to_string(),
+            sound_file: "/usr/share/sounds/freedesktop/stereo/complete.oga".to_string(),
             sound_url:
                 "https://todoist.b-cdn.net/assets/sounds/d8040624c9c7c88aa730f73faa60cf39.mp3"
                     .to_string(),
+            sound_player: "paplay".to_string(),
             icon: Icon::new("square", ColorNameOrHex::name("red")),
+            due_icon: Icon::new("calendar-due", ColorNameOrHex::name("red")),
         }
     }
 }
@@ -1,4 +1,4 @@
-use hyprland::data::{Clients, Monitors, WorkspaceRules, Workspaces};
+use hyprland::data::{Client, Clients, FullscreenMode, Monitors, WorkspaceRules, Workspaces};
 use hyprland::dispatch;
 use hyprland::dispatch::WorkspaceIdentifierWithSpecial;
 use hyprland::error::HyprError;
@@ -7,7 +7,7 @@ use hyprland::shared::HyprData;
 use iced::futures::StreamExt;
 use iced::futures::stream::once;
 
-use super::{WorkspaceId, WorkspaceInfo};
+use super::{FocusedWindow, WorkspaceId, WorkspaceInfo};
 use crate::message::Message;
 
 pub struct HyprlandDesktop;
@@ -38,6 +38,9 @@ impl HyprlandDesktop {
         iced::Subscription::run_with_id(
             HyprlandEvents,
             once(async { make_workspace_infos().await.map(Message::WorkspacesChanged) })
+                .chain(once(async {
+                    focused_window().await.map(Message::FocusedWindowChanged)
+                }))
                 .filter_map(|e| async { e })
                 .chain(EventStream::new().filter_map(process_event)),
         )
@@ -55,10 +58,25 @@ async fn process_event(event: Result<HyprEvent, HyprError>) -> Option<Message> {
         | WindowMoved(_) | FloatStateChanged(_) => {
             make_workspace_infos().await.map(Message::WorkspacesChanged)
         }
+        ActiveWindowChanged(_) | FullscreenStateChanged(_) => {
+            focused_window().await.map(Message::FocusedWindowChanged)
+        }
         _ => None,
     }
 }
 
+/// The focused window, `None` if it couldn't be read.
+async fn focused_window() -> Option<Option<FocusedWindow>> {
+    let client = Client::get_active_async().await.ok()?;
+    Some(client.map(|client| FocusedWindow {
+        app_id: client.class,
+        fullscreen: matches!(
+            client.fullscreen,
+            FullscreenMode::Fullscreen | FullscreenMode::MaximizedFullscreen
+        ),
+    }))
+}
+
 async fn make_workspace_infos() -> Option<Vec<WorkspaceInfo>> {
     let monitors = Monitors::get_async()
         .await
@@ -20,6 +20,14 @@ pub struct WorkspaceInfo {
     pub transparent_bar: bool,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusedWindow {
+    /// The app id, or the window class on Hyprland.
+    pub app_id: String,
+    /// Always false on niri, which doesn't report it.
+    pub fullscreen: bool,
+}
+
 pub enum Desktop {
     #[cfg(feature = "hyprland")]
     Hyprland(hyprland_desktop::HyprlandDesktop),
@@ -44,6 +52,16 @@ impl Desktop {
         unreachable!()
     }
 
+    /// Whether [`FocusedWindow::fullscreen`] is reported, which it isn't on niri.
+    pub fn reports_fullscreen(&self) -> bool {
+        match self {
+            #[cfg(feature = "hyprland")]
+            Desktop::Hyprland(_) => true,
+            #[cfg(feature = "niri")]
+            Desktop::Niri(_) => false,
+        }
+    }
+
     pub fn focus_workspace(&mut self, id: WorkspaceId) {
         match self {
             #[cfg(feature = "hyprland")]
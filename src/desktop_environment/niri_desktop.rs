@@ -1,12 +1,12 @@
 use iced::futures::StreamExt;
-use iced::futures::stream::{once, unfold};
+use iced::futures::stream::{iter, once, unfold};
 use niri_ipc::socket::Socket;
 use niri_ipc::state::{EventStreamState, EventStreamStatePart};
 use niri_ipc::{Action, Request, Response};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::{UnixSocket, UnixStream};
 
-use super::{WorkspaceId, WorkspaceInfo};
+use super::{FocusedWindow, WorkspaceId, WorkspaceInfo};
 use crate::message::Message;
 
 pub struct NiriDesktop {
@@ -66,13 +66,15 @@ impl NiriDesktop {
                                 };
                             }
 
-                            Some((
+                            let messages = [
                                 Message::WorkspacesChanged(make_workspace_infos(&state)),
-                                (socket, buf, state),
-                            ))
+                                Message::FocusedWindowChanged(focused_window(&state)),
+                            ];
+                            Some((messages, (socket, buf, state)))
                         },
                     )
-                }),
+                })
+                .flat_map(iter),
         )
     }
 }
@@ -108,6 +110,19 @@ async fn read_event(
     serde_json::from_str(buf).ok()
 }
 
+fn focused_window(state: &EventStreamState) -> Option<FocusedWindow> {
+    state
+        .windows
+        .windows
+        .values()
+        .find(|win| win.is_focused)
+        .map(|win| FocusedWindow {
+            app_id: win.app_id.clone().unwrap_or_default(),
+            // Not part of niri's IPC
+            fullscreen: false,
+        })
+}
+
 fn make_workspace_infos(state: &EventStreamState) -> Vec<WorkspaceInfo> {
     let mut workspace_infos = state
         .workspaces
@@ -0,0 +1,52 @@
+//! Do not disturb through dunst's `paused` property.
+//!
+//! See dunstctl(1).
+
+use iced::futures::stream::{BoxStream, StreamExt};
+use zbus::proxy;
+
+pub const DUNST_SERVICE: &str = "org.freedesktop.Notifications";
+
+#[proxy(
+    interface = "org.dunstproject.cmd0",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+pub trait Dunst {
+    #[zbus(property)]
+    fn paused(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn set_paused(&self, paused: bool) -> zbus::Result<()>;
+}
+
+pub struct Dunst {
+    proxy: DunstProxy<'static>,
+}
+
+impl Dunst {
+    /// Connects to dunst, failing if the notification daemon isn't dunst.
+    pub async fn connect(connection: &zbus::Connection) -> zbus::Result<Self> {
+        let proxy = DunstProxy::new(connection).await?;
+        proxy.paused().await?;
+        Ok(Self { proxy })
+    }
+
+    pub async fn get(&self) -> zbus::Result<bool> {
+        self.proxy.paused().await
+    }
+
+    pub async fn set(&self, enabled: bool) -> zbus::Result<()> {
+        self.proxy.set_paused(enabled).await
+    }
+
+    /// Fires whenever `paused` changed.
+    pub async fn changes(&self) -> zbus::Result<BoxStream<'static, ()>> {
+        Ok(self
+            .proxy
+            .receive_paused_changed()
+            .await
+            .map(|_| ())
+            .boxed())
+    }
+}
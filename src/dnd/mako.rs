@@ -0,0 +1,95 @@
+//! Do not disturb through a mako mode.
+//!
+//! Versions of mako that don't signal changes of the modes are polled every
+//! [`POLL_INTERVAL`] instead, so that modes set with makoctl show up with that delay.
+//!
+//! See mako(1) and makoctl(1).
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use iced::futures::stream::{self, BoxStream, StreamExt};
+use zbus::{fdo, proxy};
+
+pub const MAKO_SERVICE: &str = "org.freedesktop.Notifications";
+const MAKO_PATH: &str = "/fr/emersion/Mako";
+const MAKO_INTERFACE: &str = "fr.emersion.Mako";
+
+/// Interval at which the modes are re-read until mako signals a change.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[proxy(
+    interface = "fr.emersion.Mako",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/fr/emersion/Mako"
+)]
+pub trait Mako {
+    fn list_modes(&self) -> zbus::Result<Vec<String>>;
+
+    fn set_modes(&self, modes: &[&str]) -> zbus::Result<()>;
+}
+
+pub struct Mako {
+    proxy: MakoProxy<'static>,
+    properties: fdo::PropertiesProxy<'static>,
+    /// The mode that silences notifications.
+    mode: String,
+}
+
+impl Mako {
+    /// Connects to mako, failing if the notification daemon isn't mako.
+    pub async fn connect(connection: &zbus::Connection, mode: String) -> zbus::Result<Self> {
+        let proxy = MakoProxy::new(connection).await?;
+        proxy.list_modes().await?;
+        let properties = fdo::PropertiesProxy::builder(connection)
+            .destination(MAKO_SERVICE)?
+            .path(MAKO_PATH)?
+            .build()
+            .await?;
+        Ok(Self {
+            proxy,
+            properties,
+            mode,
+        })
+    }
+
+    pub async fn get(&self) -> zbus::Result<bool> {
+        Ok(self.proxy.list_modes().await?.contains(&self.mode))
+    }
+
+    pub async fn set(&self, enabled: bool) -> zbus::Result<()> {
+        let mut modes = self.proxy.list_modes().await?;
+        modes.retain(|m| *m != self.mode);
+        if enabled {
+            modes.push(self.mode.clone());
+        }
+        let modes = modes.iter().map(String::as_str).collect::<Vec<_>>();
+        self.proxy.set_modes(&modes).await
+    }
+
+    /// Fires whenever the modes may have changed. Polling stops once mako signals a change,
+    /// since it then signals all of them.
+    pub async fn changes(&self) -> zbus::Result<BoxStream<'static, ()>> {
+        let signalled = Arc::new(AtomicBool::new(false));
+        let changed = self
+            .properties
+            .receive_properties_changed()
+            .await?
+            .filter(|signal| {
+                let ours = signal
+                    .args()
+                    .is_ok_and(|args| args.interface_name() == MAKO_INTERFACE);
+                async move { ours }
+            })
+            .map({
+                let signalled = signalled.clone();
+                move |_| signalled.store(true, Ordering::Relaxed)
+            });
+        let poll = stream::unfold(signalled, |signalled| async move {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            (!signalled.load(Ordering::Relaxed)).then_some(((), signalled))
+        });
+        Ok(stream::select(changed, poll).boxed())
+    }
+}
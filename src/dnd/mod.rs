@@ -0,0 +1,451 @@
+//! Do not disturb of external notification daemons, controlled over the session bus.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use iced::futures::StreamExt;
+use iced::futures::stream::{self, BoxStream};
+use tokio::sync::{Mutex, mpsc, watch};
+use zbus::fdo;
+
+use crate::config::types::{Dnd, DndDaemon};
+use crate::message::Message;
+
+mod dunst;
+mod mako;
+mod rules;
+mod swaync;
+
+use dunst::{DUNST_SERVICE, Dunst};
+use mako::{MAKO_SERVICE, Mako};
+pub use rules::DndRules;
+use swaync::{SWAYNC_SERVICE, Swaync};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DndState {
+    /// The daemon that is controlled, `None` if none is running.
+    pub daemon: Option<DndDaemon>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum DndCommand {
+    Set(bool),
+}
+
+/// Settings of the adapters, taken from the config.
+#[derive(Debug, Clone)]
+struct DaemonConfig {
+    daemon: Option<DndDaemon>,
+    mako_mode: String,
+}
+
+#[derive(Debug)]
+pub struct DoNotDisturb {
+    commands: mpsc::UnboundedSender<DndCommand>,
+    rx: Arc<Mutex<watch::Receiver<DndState>>>,
+}
+
+impl DoNotDisturb {
+    pub fn new(config: &Dnd, server: bool) -> Self {
+        Self::with_address(config, server, None)
+    }
+
+    /// Creates the service for the daemons on the bus at `address`, the session bus if `None`.
+    /// Nothing is controlled when the built-in notification server is used.
+    pub fn with_address(config: &Dnd, server: bool, address: Option<String>) -> Self {
+        let (tx, rx) = watch::channel(DndState::default());
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        if !server {
+            let config = DaemonConfig {
+                daemon: config.daemon,
+                mako_mode: config.mako_mode.clone(),
+            };
+            tokio::spawn(run(address, config, tx, commands_rx));
+        }
+        Self {
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn send(&self, command: DndCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "dnd".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(*rx.borrow())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::DndUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+/// One of the supported daemons.
+enum Daemon {
+    Mako(Mako),
+    Dunst(Dunst),
+    Swaync(Swaync),
+}
+
+impl Daemon {
+    async fn connect(
+        connection: &zbus::Connection,
+        kind: DndDaemon,
+        config: &DaemonConfig,
+    ) -> zbus::Result<Self> {
+        Ok(match kind {
+            DndDaemon::Mako => {
+                Self::Mako(Mako::connect(connection, config.mako_mode.clone()).await?)
+            }
+            DndDaemon::Dunst => Self::Dunst(Dunst::connect(connection).await?),
+            DndDaemon::Swaync => Self::Swaync(Swaync::connect(connection).await?),
+        })
+    }
+
+    /// Connects to the configured daemon, or else to the first one that is running.
+    async fn detect(connection: &zbus::Connection, config: &DaemonConfig) -> Option<Self> {
+        let kinds = match config.daemon {
+            Some(kind) => vec![kind],
+            // swaync also owns the notifications name, so it is checked first
+            None => vec![DndDaemon::Swaync, DndDaemon::Mako, DndDaemon::Dunst],
+        };
+        for kind in kinds {
+            match Self::connect(connection, kind, config).await {
+                Ok(daemon) => return Some(daemon),
+                Err(e) => log::debug!("dnd: {kind:?} isn't running ({e})"),
+            }
+        }
+        None
+    }
+
+    fn kind(&self) -> DndDaemon {
+        match self {
+            Self::Mako(_) => DndDaemon::Mako,
+            Self::Dunst(_) => DndDaemon::Dunst,
+            Self::Swaync(_) => DndDaemon::Swaync,
+        }
+    }
+
+    async fn get(&self) -> zbus::Result<bool> {
+        match self {
+            Self::Mako(mako) => mako.get().await,
+            Self::Dunst(dunst) => dunst.get().await,
+            Self::Swaync(swaync) => swaync.get().await,
+        }
+    }
+
+    async fn set(&self, enabled: bool) -> zbus::Result<()> {
+        match self {
+            Self::Mako(mako) => mako.set(enabled).await,
+            Self::Dunst(dunst) => dunst.set(enabled).await,
+            Self::Swaync(swaync) => swaync.set(enabled).await,
+        }
+    }
+
+    async fn changes(&self) -> zbus::Result<BoxStream<'static, ()>> {
+        match self {
+            Self::Mako(mako) => mako.changes().await,
+            Self::Dunst(dunst) => dunst.changes().await,
+            Self::Swaync(swaync) => swaync.changes().await,
+        }
+    }
+}
+
+async fn run(
+    address: Option<String>,
+    config: DaemonConfig,
+    tx: watch::Sender<DndState>,
+    mut commands_rx: mpsc::UnboundedReceiver<DndCommand>,
+) {
+    const MIN_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    let mut backoff = MIN_BACKOFF;
+    while !tx.is_closed() {
+        match run_client(address.as_deref(), &config, &tx, &mut commands_rx).await {
+            Ok(()) => return,
+            Err(e) => log::warn!("dnd: {e}, retrying in {}s", backoff.as_secs()),
+        }
+        tx.send_replace(DndState::default());
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Follows the state of the daemon until limbo shuts down, detecting the daemon again whenever
+/// one starts or exits.
+async fn run_client(
+    address: Option<&str>,
+    config: &DaemonConfig,
+    tx: &watch::Sender<DndState>,
+    commands_rx: &mut mpsc::UnboundedReceiver<DndCommand>,
+) -> zbus::Result<()> {
+    let builder = match address {
+        Some(address) => zbus::connection::Builder::address(address)?,
+        None => zbus::connection::Builder::session()?,
+    };
+    let connection = builder.build().await?;
+
+    let dbus = fdo::DBusProxy::new(&connection).await?;
+    let mut owners = dbus
+        .receive_name_owner_changed()
+        .await?
+        .filter(|signal| {
+            let ours = signal.args().is_ok_and(|args| {
+                [MAKO_SERVICE, DUNST_SERVICE, SWAYNC_SERVICE].contains(&args.name().as_str())
+            });
+            async move { ours }
+        })
+        .boxed();
+
+    loop {
+        let daemon = Daemon::detect(&connection, config).await;
+        let mut changes = match &daemon {
+            Some(daemon) => daemon.changes().await?,
+            None => stream::pending().boxed(),
+        };
+
+        loop {
+            let state = match &daemon {
+                Some(daemon) => DndState {
+                    daemon: Some(daemon.kind()),
+                    enabled: daemon.get().await?,
+                },
+                None => DndState::default(),
+            };
+            tx.send_if_modified(|current| {
+                let modified = *current != state;
+                *current = state;
+                modified
+            });
+
+            tokio::select! {
+                change = changes.next() => {
+                    if change.is_none() {
+                        break;
+                    }
+                }
+                // A daemon started or exited
+                Some(_) = owners.next() => break,
+                command = commands_rx.recv() => {
+                    let Some(DndCommand::Set(enabled)) = command else {
+                        return Ok(());
+                    };
+                    match &daemon {
+                        Some(daemon) => {
+                            if let Err(e) = daemon.set(enabled).await {
+                                log::warn!("dnd: failed to set {:?}: {e}", daemon.kind());
+                            }
+                        }
+                        None => log::warn!("dnd: no notification daemon to control"),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::object_server::SignalEmitter;
+
+    use super::*;
+    use crate::testing::PrivateBus;
+
+    /// Stands in for mako, signalling changes of its modes like newer versions if `signals`.
+    struct FakeMako {
+        modes: Vec<String>,
+        signals: bool,
+    }
+
+    #[zbus::interface(name = "fr.emersion.Mako")]
+    impl FakeMako {
+        fn list_modes(&self) -> Vec<String> {
+            self.modes.clone()
+        }
+
+        async fn set_modes(
+            &mut self,
+            modes: Vec<String>,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> fdo::Result<()> {
+            self.modes = modes;
+            if self.signals {
+                self.modes_changed(&emitter).await?;
+            }
+            Ok(())
+        }
+
+        #[zbus(property)]
+        fn modes(&self) -> Vec<String> {
+            self.modes.clone()
+        }
+    }
+
+    struct FakeDunst {
+        paused: bool,
+    }
+
+    #[zbus::interface(name = "org.dunstproject.cmd0")]
+    impl FakeDunst {
+        #[zbus(property)]
+        fn paused(&self) -> bool {
+            self.paused
+        }
+
+        #[zbus(property)]
+        fn set_paused(&mut self, paused: bool) {
+            self.paused = paused;
+        }
+    }
+
+    struct FakeSwaync {
+        dnd: bool,
+    }
+
+    #[zbus::interface(name = "org.erikreider.swaync.cc")]
+    impl FakeSwaync {
+        fn get_dnd(&self) -> bool {
+            self.dnd
+        }
+
+        async fn set_dnd(
+            &mut self,
+            state: bool,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> fdo::Result<()> {
+            self.dnd = state;
+            Self::subscribe_v2(&emitter, 0, state, false, false).await?;
+            Ok(())
+        }
+
+        #[zbus(signal, name = "SubscribeV2")]
+        async fn subscribe_v2(
+            emitter: &SignalEmitter<'_>,
+            count: u32,
+            dnd: bool,
+            cc_open: bool,
+            inhibited: bool,
+        ) -> zbus::Result<()>;
+    }
+
+    /// Waits until the published state is `expected`.
+    async fn wait_for(rx: &mut watch::Receiver<DndState>, expected: DndState) {
+        let result = tokio::time::timeout(Duration::from_secs(5), async {
+            while *rx.borrow_and_update() != expected {
+                rx.changed().await.unwrap();
+            }
+        })
+        .await;
+        assert!(result.is_ok(), "timed out waiting for {expected:?}");
+    }
+
+    /// Connects to the daemon through `dnd`, turns do not disturb on from limbo and off through
+    /// `other_client`, and checks that both are followed.
+    async fn toggle(
+        bus: &PrivateBus,
+        daemon: DndDaemon,
+        other_client: impl AsyncFnOnce(&zbus::Connection),
+    ) {
+        let dnd = DoNotDisturb::with_address(&Dnd::default(), false, Some(bus.address.clone()));
+        let mut rx = dnd.rx.lock().await.clone();
+        let state = |enabled| DndState {
+            daemon: Some(daemon),
+            enabled,
+        };
+        wait_for(&mut rx, state(false)).await;
+
+        dnd.send(DndCommand::Set(true));
+        wait_for(&mut rx, state(true)).await;
+
+        other_client(&bus.connection().await).await;
+        wait_for(&mut rx, state(false)).await;
+    }
+
+    async fn serve(
+        bus: &PrivateBus,
+        name: &str,
+        path: &str,
+        iface: impl zbus::object_server::Interface,
+    ) -> zbus::Connection {
+        let connection = bus.connection().await;
+        connection.object_server().at(path, iface).await.unwrap();
+        connection.request_name(name).await.unwrap();
+        connection
+    }
+
+    #[tokio::test]
+    async fn controls_mako() {
+        for signals in [true, false] {
+            let Some(bus) = PrivateBus::start() else {
+                return;
+            };
+            let mako = FakeMako {
+                modes: vec!["default".to_string()],
+                signals,
+            };
+            let _mako = serve(&bus, MAKO_SERVICE, "/fr/emersion/Mako", mako).await;
+            toggle(&bus, DndDaemon::Mako, async |connection| {
+                let makoctl = mako::MakoProxy::new(connection).await.unwrap();
+                assert_eq!(
+                    makoctl.list_modes().await.unwrap(),
+                    ["default", "do-not-disturb"]
+                );
+                makoctl.set_modes(&["default"]).await.unwrap();
+            })
+            .await;
+        }
+    }
+
+    #[tokio::test]
+    async fn controls_dunst() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let dunst = FakeDunst { paused: false };
+        let _dunst = serve(&bus, DUNST_SERVICE, "/org/freedesktop/Notifications", dunst).await;
+        toggle(&bus, DndDaemon::Dunst, async |connection| {
+            let dunstctl = dunst::DunstProxy::new(connection).await.unwrap();
+            dunstctl.set_paused(false).await.unwrap();
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn controls_swaync_and_follows_it_exiting() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let swaync = FakeSwaync { dnd: false };
+        let swaync = serve(&bus, SWAYNC_SERVICE, "/org/erikreider/swaync/cc", swaync).await;
+        toggle(&bus, DndDaemon::Swaync, async |connection| {
+            let client = swaync::SwayncProxy::new(connection).await.unwrap();
+            client.set_dnd(false).await.unwrap();
+        })
+        .await;
+
+        let dnd = DoNotDisturb::with_address(&Dnd::default(), false, Some(bus.address.clone()));
+        let mut rx = dnd.rx.lock().await.clone();
+        wait_for(
+            &mut rx,
+            DndState {
+                daemon: Some(DndDaemon::Swaync),
+                enabled: false,
+            },
+        )
+        .await;
+        drop(swaync);
+        wait_for(&mut rx, DndState::default()).await;
+    }
+}
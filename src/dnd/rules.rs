@@ -0,0 +1,160 @@
+//! Turns do not disturb on and off on a schedule, and while fullscreen or screen sharing apps
+//! are focused.
+
+use std::time::Duration;
+
+use jiff::Zoned;
+
+use crate::config::types::{Dnd, DndSchedule, Weekday};
+use crate::desktop_environment::FocusedWindow;
+use crate::message::Message;
+
+/// Interval at which the schedule is re-evaluated.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Whether `now` falls within `period`.
+fn in_period(period: &DndSchedule, now: &Zoned) -> bool {
+    let runs_on = |weekday: jiff::civil::Weekday| {
+        period.days.is_empty() || period.days.contains(&Weekday::from(weekday))
+    };
+    let (start, end, time) = (period.start.0, period.end.0, now.time());
+    let today = now.weekday();
+    if start <= end {
+        runs_on(today) && start <= time && time < end
+    } else {
+        // The part after midnight belongs to the period of the previous day
+        (runs_on(today) && time >= start) || (runs_on(today.previous()) && time < end)
+    }
+}
+
+/// The rules, applied only when their outcome changes. Toggling do not disturb by hand thus
+/// holds until the next transition.
+#[derive(Debug)]
+pub struct DndRules {
+    schedule: Vec<DndSchedule>,
+    when_fullscreen: bool,
+    screen_share_apps: Vec<String>,
+    focused: Option<FocusedWindow>,
+    /// Whether the rules called for do not disturb when they were last evaluated.
+    active: bool,
+}
+
+impl DndRules {
+    pub fn new(config: &Dnd) -> Self {
+        Self {
+            schedule: config.schedule.clone(),
+            when_fullscreen: config.when_fullscreen,
+            screen_share_apps: config.screen_share_apps.clone(),
+            focused: None,
+            active: false,
+        }
+    }
+
+    /// Re-evaluates the rules, returning whether to turn do not disturb on or off.
+    pub fn update(&mut self, message: &Message) -> Option<bool> {
+        match message {
+            Message::FocusedWindowChanged(focused) => self.focused = focused.clone(),
+            Message::DndRulesTick => {}
+            _ => return None,
+        }
+
+        let active = self.evaluate(&Zoned::now());
+        (active != self.active).then(|| {
+            self.active = active;
+            active
+        })
+    }
+
+    fn evaluate(&self, now: &Zoned) -> bool {
+        let focused = self.focused.as_ref().is_some_and(|window| {
+            (self.when_fullscreen && window.fullscreen)
+                || self.screen_share_apps.contains(&window.app_id)
+        });
+        focused || self.schedule.iter().any(|period| in_period(period, now))
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        if self.schedule.is_empty() {
+            iced::Subscription::none()
+        } else {
+            iced::time::every(UPDATE_INTERVAL).map(|_| Message::DndRulesTick)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::civil::Time;
+
+    use super::*;
+    use crate::config::types::TimeOfDay;
+
+    fn period(days: Vec<Weekday>, start: (i8, i8), end: (i8, i8)) -> DndSchedule {
+        DndSchedule {
+            days,
+            start: TimeOfDay(Time::constant(start.0, start.1, 0, 0)),
+            end: TimeOfDay(Time::constant(end.0, end.1, 0, 0)),
+        }
+    }
+
+    /// 2024-06-03 is a Monday.
+    fn at(time: &str) -> Zoned {
+        format!("{time}[UTC]").parse().unwrap()
+    }
+
+    #[test]
+    fn periods_within_a_day() {
+        let work = period(vec![Weekday::Monday, Weekday::Friday], (9, 0), (17, 30));
+        assert!(!in_period(&work, &at("2024-06-03T08:59")));
+        assert!(in_period(&work, &at("2024-06-03T09:00")));
+        assert!(in_period(&work, &at("2024-06-03T17:29")));
+        assert!(!in_period(&work, &at("2024-06-03T17:30")));
+        // Tuesday
+        assert!(!in_period(&work, &at("2024-06-04T12:00")));
+        assert!(in_period(&work, &at("2024-06-07T12:00")));
+
+        let every_day = period(Vec::new(), (12, 0), (13, 0));
+        assert!(in_period(&every_day, &at("2024-06-08T12:30")));
+    }
+
+    #[test]
+    fn periods_past_midnight_belong_to_the_day_they_start() {
+        let night = period(vec![Weekday::Monday], (22, 0), (7, 0));
+        // Monday night
+        assert!(!in_period(&night, &at("2024-06-03T21:59")));
+        assert!(in_period(&night, &at("2024-06-03T22:00")));
+        assert!(in_period(&night, &at("2024-06-03T23:59")));
+        // Tuesday morning
+        assert!(in_period(&night, &at("2024-06-04T00:00")));
+        assert!(in_period(&night, &at("2024-06-04T06:59")));
+        assert!(!in_period(&night, &at("2024-06-04T07:00")));
+        // Monday morning is the end of Sunday night, and Tuesday night doesn't start
+        assert!(!in_period(&night, &at("2024-06-03T03:00")));
+        assert!(!in_period(&night, &at("2024-06-04T23:00")));
+
+        let every_night = period(Vec::new(), (23, 0), (6, 0));
+        assert!(in_period(&every_night, &at("2024-06-03T03:00")));
+        assert!(!in_period(&every_night, &at("2024-06-03T12:00")));
+    }
+
+    #[test]
+    fn applies_only_transitions() {
+        let mut rules = DndRules::new(&Dnd {
+            when_fullscreen: true,
+            screen_share_apps: vec!["zoom".to_string()],
+            ..Default::default()
+        });
+        let focus = |app_id: &str, fullscreen| {
+            Message::FocusedWindowChanged(Some(FocusedWindow {
+                app_id: app_id.to_string(),
+                fullscreen,
+            }))
+        };
+
+        assert_eq!(rules.update(&focus("firefox", false)), None);
+        assert_eq!(rules.update(&focus("mpv", true)), Some(true));
+        assert_eq!(rules.update(&focus("zoom", false)), None);
+        assert_eq!(rules.update(&focus("firefox", false)), Some(false));
+        assert_eq!(rules.update(&Message::FocusedWindowChanged(None)), None);
+    }
+}
@@ -0,0 +1,59 @@
+//! Do not disturb through the SwayNotificationCenter control center.
+//!
+//! See swaync-client(1).
+
+use iced::futures::stream::{self, BoxStream, StreamExt};
+use zbus::proxy;
+
+pub const SWAYNC_SERVICE: &str = "org.erikreider.swaync.cc";
+
+#[proxy(
+    interface = "org.erikreider.swaync.cc",
+    default_service = "org.erikreider.swaync.cc",
+    default_path = "/org/erikreider/swaync/cc"
+)]
+pub trait Swaync {
+    fn get_dnd(&self) -> zbus::Result<bool>;
+
+    fn set_dnd(&self, state: bool) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn subscribe(&self, count: u32, dnd: bool, cc_open: bool) -> zbus::Result<()>;
+
+    /// Replaces `Subscribe` in newer versions.
+    #[zbus(signal, name = "SubscribeV2")]
+    fn subscribe_v2(
+        &self,
+        count: u32,
+        dnd: bool,
+        cc_open: bool,
+        inhibited: bool,
+    ) -> zbus::Result<()>;
+}
+
+pub struct Swaync {
+    proxy: SwayncProxy<'static>,
+}
+
+impl Swaync {
+    pub async fn connect(connection: &zbus::Connection) -> zbus::Result<Self> {
+        let proxy = SwayncProxy::new(connection).await?;
+        proxy.get_dnd().await?;
+        Ok(Self { proxy })
+    }
+
+    pub async fn get(&self) -> zbus::Result<bool> {
+        self.proxy.get_dnd().await
+    }
+
+    pub async fn set(&self, enabled: bool) -> zbus::Result<()> {
+        self.proxy.set_dnd(enabled).await
+    }
+
+    /// Fires whenever the control center state changed.
+    pub async fn changes(&self) -> zbus::Result<BoxStream<'static, ()>> {
+        let v1 = self.proxy.receive_subscribe().await?.map(|_| ());
+        let v2 = self.proxy.receive_subscribe_v2().await?.map(|_| ());
+        Ok(stream::select(v1, v2).boxed())
+    }
+}
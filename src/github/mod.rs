@@ -0,0 +1,427 @@
+//! Unread notifications from the GitHub REST API.
+//!
+//! Polling follows the rules of the API: requests are conditional on `Last-Modified`, so that
+//! unchanged notifications don't count against the rate limit, and the next request waits for
+//! `X-Poll-Interval`.
+//!
+//! See <https://docs.github.com/en/rest/activity/notifications>
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use reqwest::header::{self, HeaderMap};
+use serde::Deserialize;
+use tokio::sync::{Mutex, watch};
+
+use crate::config::types::Github;
+use crate::message::Message;
+
+/// Used until the API asks for a poll interval.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Notifications per page, the maximum the API allows.
+const PAGE_SIZE: usize = 50;
+/// Pages fetched at most, beyond which the count is a lower bound.
+const MAX_PAGES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GithubNotification {
+    pub id: String,
+    /// Full name of the repository, e.g. `rust-lang/rust`.
+    pub repo: String,
+    pub title: String,
+    /// Kind of the subject, e.g. `Issue`, `PullRequest` or `Release`.
+    pub kind: String,
+    /// Web page of the subject.
+    pub url: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GithubState {
+    /// Whether an API token is configured.
+    pub available: bool,
+    /// Unread notifications, most recently updated first.
+    pub notifications: Vec<GithubNotification>,
+    /// Whether the last poll failed, the notifications are then the last known ones.
+    pub error: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiNotification {
+    id: String,
+    repository: ApiRepository,
+    subject: ApiSubject,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiRepository {
+    full_name: String,
+    html_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiSubject {
+    title: String,
+    /// API URL of the subject, missing for some kinds like discussions.
+    url: Option<String>,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Debug)]
+pub struct GithubNotifications {
+    rx: Arc<Mutex<watch::Receiver<GithubState>>>,
+}
+
+impl GithubNotifications {
+    /// Polls the API at `api_url` of the config, unless no token is configured.
+    pub fn new(config: &Github) -> Self {
+        let (tx, rx) = watch::channel(GithubState::default());
+        if !config.api_token.is_empty() {
+            tx.send_modify(|state| state.available = true);
+            let api_url = config.api_url.trim_end_matches('/').to_string();
+            tokio::spawn(run(api_url, config.api_token.clone(), tx));
+        }
+        Self {
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "github".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::GithubUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+/// Result of a poll.
+enum Poll {
+    /// The notifications changed since the last poll.
+    Changed {
+        notifications: Vec<GithubNotification>,
+        last_modified: Option<String>,
+    },
+    NotModified,
+}
+
+async fn run(api_url: String, token: String, tx: watch::Sender<GithubState>) {
+    let client = match reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent("limbo")
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("github: failed to create the HTTP client: {e}");
+            return;
+        }
+    };
+
+    let mut last_modified = None;
+    let mut backoff = DEFAULT_POLL_INTERVAL;
+    while !tx.is_closed() {
+        let delay = match poll(&client, &api_url, &token, last_modified.as_deref()).await {
+            Ok((result, poll_interval)) => {
+                match result {
+                    Poll::Changed {
+                        notifications,
+                        last_modified: modified,
+                    } => {
+                        last_modified = modified;
+                        tx.send_if_modified(|state| {
+                            let changed = state.notifications != notifications || state.error;
+                            state.notifications = notifications;
+                            state.error = false;
+                            changed
+                        });
+                    }
+                    Poll::NotModified => {
+                        tx.send_if_modified(|state| std::mem::take(&mut state.error));
+                    }
+                }
+                backoff = DEFAULT_POLL_INTERVAL;
+                poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL)
+            }
+            Err(e) => {
+                log::warn!(
+                    "github: failed to poll notifications ({e}), retrying in {}s",
+                    backoff.as_secs()
+                );
+                tx.send_if_modified(|state| !std::mem::replace(&mut state.error, true));
+                let delay = backoff;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                delay
+            }
+        };
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Fetches the unread notifications, unless they weren't modified since `last_modified`, along
+/// with the poll interval the API asks for.
+async fn poll(
+    client: &reqwest::Client,
+    api_url: &str,
+    token: &str,
+    last_modified: Option<&str>,
+) -> Result<(Poll, Option<Duration>), Box<dyn std::error::Error + Send + Sync>> {
+    let mut notifications = Vec::new();
+    let mut url = Some(format!("{api_url}/notifications?per_page={PAGE_SIZE}"));
+    let mut first_page = None;
+
+    for _ in 0..MAX_PAGES {
+        let Some(page_url) = url.take() else {
+            break;
+        };
+        let mut request = client
+            .get(&page_url)
+            .bearer_auth(token)
+            .header(header::ACCEPT, "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        // Only the first page tells whether anything changed
+        if first_page.is_none()
+            && let Some(last_modified) = last_modified
+        {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        let response = request.send().await?;
+
+        if first_page.is_none() {
+            let headers = response.headers();
+            let poll_interval = header_str(headers, "x-poll-interval")
+                .and_then(|interval| interval.parse().ok())
+                .map(Duration::from_secs);
+            if response.status() == StatusCode::NOT_MODIFIED {
+                return Ok((Poll::NotModified, poll_interval));
+            }
+            first_page = Some((
+                header_str(headers, header::LAST_MODIFIED.as_str()).map(str::to_string),
+                poll_interval,
+            ));
+        }
+        url = next_page(response.headers());
+
+        let body = response.error_for_status()?.bytes().await?;
+        let page: Vec<ApiNotification> = serde_json::from_slice(&body)?;
+        notifications.extend(
+            page.into_iter()
+                .map(|notification| convert(notification, api_url)),
+        );
+    }
+
+    let (last_modified, poll_interval) = first_page.unwrap_or_default();
+    Ok((
+        Poll::Changed {
+            notifications,
+            last_modified,
+        },
+        poll_interval,
+    ))
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok()
+}
+
+/// The URL of the next page from the `Link` header.
+fn next_page(headers: &HeaderMap) -> Option<String> {
+    header_str(headers, header::LINK.as_str())?
+        .split(',')
+        .find(|link| link.contains("rel=\"next\""))
+        .and_then(|link| {
+            let start = link.find('<')? + 1;
+            let end = link.find('>')?;
+            Some(link.get(start..end)?.to_string())
+        })
+}
+
+fn convert(notification: ApiNotification, api_url: &str) -> GithubNotification {
+    let url = notification
+        .subject
+        .url
+        .as_deref()
+        .and_then(|url| html_url(url, api_url))
+        .unwrap_or(notification.repository.html_url);
+    GithubNotification {
+        id: notification.id,
+        repo: notification.repository.full_name,
+        title: notification.subject.title,
+        kind: notification.subject.kind,
+        url,
+    }
+}
+
+/// The web page of an API URL like `https://api.github.com/repos/owner/repo/pulls/1`.
+///
+/// The web interface is at `github.com` for `api.github.com`, and at the root of the host for
+/// GitHub Enterprise, whose API lives under `/api/v3`.
+fn html_url(url: &str, api_url: &str) -> Option<String> {
+    let path = url.strip_prefix(api_url)?.strip_prefix("/repos/")?;
+    let web_url = match api_url.strip_suffix("/api/v3") {
+        Some(web_url) => web_url.to_string(),
+        None => api_url.replacen("://api.", "://", 1),
+    };
+    // Pull requests are at `pull/1`, and releases are only linked by ID in the API
+    let path = match path.split('/').collect::<Vec<_>>().as_slice() {
+        [owner, repo, "pulls", number] => format!("{owner}/{repo}/pull/{number}"),
+        [owner, repo, "releases", _] => format!("{owner}/{repo}/releases"),
+        [owner, repo, "commits", sha] => format!("{owner}/{repo}/commit/{sha}"),
+        _ => path.to_string(),
+    };
+    Some(format!("{web_url}/{path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{HttpRequest, HttpResponse, HttpStub};
+
+    const LAST_MODIFIED: &str = "Mon, 20 May 2024 10:00:00 GMT";
+
+    fn notification(id: &str, kind: &str, path: &str, base_url: &str) -> String {
+        format!(
+            r#"{{
+                "id": "{id}",
+                "repository": {{
+                    "full_name": "owner/repo",
+                    "html_url": "https://github.com/owner/repo"
+                }},
+                "subject": {{
+                    "title": "Title {id}",
+                    "url": "{base_url}/repos/owner/repo/{path}",
+                    "type": "{kind}"
+                }}
+            }}"#
+        )
+    }
+
+    /// Two pages of notifications, unless they weren't modified since [`LAST_MODIFIED`].
+    fn respond(request: &HttpRequest) -> HttpResponse {
+        let base_url = format!("http://{}", request.header("host").unwrap());
+        if request.header("authorization") != Some("Bearer token") {
+            return HttpResponse::new(401, "");
+        }
+        match request.path.as_str() {
+            "/notifications?per_page=50" => {
+                if request.header("if-modified-since") == Some(LAST_MODIFIED) {
+                    return HttpResponse::new(304, "").header("X-Poll-Interval", "120");
+                }
+                let next = format!("{base_url}/notifications?per_page=50&page=2");
+                let body = format!(
+                    "[{}]",
+                    notification("1", "PullRequest", "pulls/7", &base_url)
+                );
+                HttpResponse::new(200, body)
+                    .header("Last-Modified", LAST_MODIFIED)
+                    .header("X-Poll-Interval", "90")
+                    .header(
+                        "Link",
+                        format!(r#"<{next}>; rel="next", <{next}>; rel="last""#),
+                    )
+            }
+            "/notifications?per_page=50&page=2" => {
+                assert_eq!(request.header("if-modified-since"), None);
+                let body = format!("[{}]", notification("2", "Issue", "issues/8", &base_url));
+                HttpResponse::new(200, body).header("X-Poll-Interval", "30")
+            }
+            _ => HttpResponse::new(404, ""),
+        }
+    }
+
+    #[tokio::test]
+    async fn polls_all_pages_unless_unmodified() {
+        let stub = HttpStub::start(respond).await;
+        let client = reqwest::Client::new();
+
+        let (result, poll_interval) = poll(&client, &stub.url, "token", None).await.unwrap();
+        let Poll::Changed {
+            notifications,
+            last_modified,
+        } = result
+        else {
+            panic!("expected the notifications");
+        };
+        assert_eq!(poll_interval, Some(Duration::from_secs(90)));
+        assert_eq!(last_modified.as_deref(), Some(LAST_MODIFIED));
+        assert_eq!(
+            notifications,
+            [
+                GithubNotification {
+                    id: "1".to_string(),
+                    repo: "owner/repo".to_string(),
+                    title: "Title 1".to_string(),
+                    kind: "PullRequest".to_string(),
+                    url: format!("{}/owner/repo/pull/7", stub.url),
+                },
+                GithubNotification {
+                    id: "2".to_string(),
+                    repo: "owner/repo".to_string(),
+                    title: "Title 2".to_string(),
+                    kind: "Issue".to_string(),
+                    url: format!("{}/owner/repo/issues/8", stub.url),
+                },
+            ]
+        );
+
+        let (result, poll_interval) = poll(&client, &stub.url, "token", last_modified.as_deref())
+            .await
+            .unwrap();
+        assert!(matches!(result, Poll::NotModified));
+        assert_eq!(poll_interval, Some(Duration::from_secs(120)));
+        assert_eq!(stub.requests().len(), 3);
+
+        assert!(poll(&client, &stub.url, "wrong", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn follows_the_notifications() {
+        let stub = HttpStub::start(respond).await;
+        let github = GithubNotifications::new(&Github {
+            api_token: "token".to_string(),
+            api_url: format!("{}/", stub.url),
+            ..Default::default()
+        });
+        let mut rx = github.rx.lock().await.clone();
+        let state = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let state = rx.borrow_and_update().clone();
+                if !state.notifications.is_empty() {
+                    return state;
+                }
+                rx.changed().await.unwrap();
+            }
+        })
+        .await
+        .expect("timed out waiting for the notifications");
+        assert!(state.available && !state.error);
+        assert_eq!(state.notifications.len(), 2);
+    }
+
+    #[test]
+    fn links_to_web_pages() {
+        let api = "https://api.github.com";
+        assert_eq!(
+            html_url(&format!("{api}/repos/o/r/releases/42"), api).as_deref(),
+            Some("https://github.com/o/r/releases")
+        );
+        let enterprise = "https://github.example.com/api/v3";
+        assert_eq!(
+            html_url(&format!("{enterprise}/repos/o/r/commits/abc"), enterprise).as_deref(),
+            Some("https://github.example.com/o/r/commit/abc")
+        );
+        assert_eq!(html_url("https://elsewhere.com/repos/o/r", api), None);
+    }
+}
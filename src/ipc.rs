@@ -0,0 +1,111 @@
+//! Commands for limbo on the session bus, e.g. for compositor keybindings:
+//!
+//! ```sh
+//! busctl --user call org.limbo.Limbo /org/limbo/Limbo org.limbo.Limbo ToggleLauncher
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Mutex, mpsc};
+use zbus::interface;
+
+use crate::message::Message;
+
+const IPC_NAME: &str = "org.limbo.Limbo";
+const IPC_PATH: &str = "/org/limbo/Limbo";
+
+#[derive(Debug, Clone, Copy)]
+pub enum IpcRequest {
+    ToggleLauncher,
+}
+
+struct Interface {
+    requests: mpsc::UnboundedSender<IpcRequest>,
+}
+
+#[interface(name = "org.limbo.Limbo")]
+impl Interface {
+    /// Opens the application launcher on the first output, or closes it.
+    fn toggle_launcher(&self) {
+        let _ = self.requests.send(IpcRequest::ToggleLauncher);
+    }
+}
+
+#[derive(Debug)]
+pub struct Ipc {
+    rx: Arc<Mutex<mpsc::UnboundedReceiver<IpcRequest>>>,
+}
+
+impl Ipc {
+    pub fn new() -> Self {
+        Self::with_address(None)
+    }
+
+    /// Serves the interface on the bus at `address`, the session bus if `None`.
+    pub fn with_address(address: Option<String>) -> Self {
+        let (requests, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(address, requests));
+        Self {
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "ipc".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let request = rx.lock().await.recv().await;
+                request.map(|request| {
+                    let message = match request {
+                        IpcRequest::ToggleLauncher => Message::ToggleLauncher,
+                    };
+                    (message, rx)
+                })
+            }),
+        )
+    }
+}
+
+async fn run(address: Option<String>, requests: mpsc::UnboundedSender<IpcRequest>) {
+    const MIN_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    let mut backoff = MIN_BACKOFF;
+    while !requests.is_closed() {
+        match serve(address.as_deref(), &requests).await {
+            Ok(()) => return,
+            Err(e) => log::warn!(
+                "ipc: failed to serve {IPC_NAME} ({e}), retrying in {}s",
+                backoff.as_secs()
+            ),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Serves the interface until limbo shuts down.
+async fn serve(
+    address: Option<&str>,
+    requests: &mpsc::UnboundedSender<IpcRequest>,
+) -> zbus::Result<()> {
+    let builder = match address {
+        Some(address) => zbus::connection::Builder::address(address)?,
+        None => zbus::connection::Builder::session()?,
+    };
+    let _connection = builder
+        .serve_at(
+            IPC_PATH,
+            Interface {
+                requests: requests.clone(),
+            },
+        )?
+        .name(IPC_NAME)?
+        .build()
+        .await?;
+    log::info!("ipc: serving {IPC_NAME}");
+
+    requests.closed().await;
+    Ok(())
+}
@@ -0,0 +1,456 @@
+//! Desktop entries of the installed applications.
+//!
+//! See <https://specifications.freedesktop.org/desktop-entry-spec/latest/>
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::tray::lookup_system_icon;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesktopAction {
+    pub id: String,
+    pub name: String,
+    pub icon: Option<PathBuf>,
+    pub exec: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesktopEntry {
+    /// Desktop file ID, e.g. `org.gnome.Nautilus.desktop`.
+    pub id: String,
+    /// Path of the desktop file.
+    pub path: PathBuf,
+    pub name: String,
+    pub generic_name: Option<String>,
+    pub comment: Option<String>,
+    pub keywords: Vec<String>,
+    /// The `Icon` key as written, needed for the `%i` field code.
+    pub icon_name: Option<String>,
+    /// The icon resolved from the icon theme.
+    pub icon: Option<PathBuf>,
+    pub exec: String,
+    /// Working directory to launch in.
+    pub working_dir: Option<PathBuf>,
+    /// Whether the application runs in a terminal.
+    pub terminal: bool,
+    pub actions: Vec<DesktopAction>,
+}
+
+/// Directories containing desktop files, most important first.
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    data_home
+        .into_iter()
+        .chain(std::env::split_paths(&data_dirs))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// Reads the entries of all applications that are shown in menus, sorted by name.
+pub fn scan() -> Vec<DesktopEntry> {
+    let locale = Locale::from_env();
+    let desktops = current_desktops();
+
+    // Entries in more important directories shadow those with the same ID, even when hidden
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    for dir in application_dirs() {
+        let mut files = Vec::new();
+        collect_desktop_files(&dir, &dir, &mut files);
+        for (id, path) in files {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            let Ok(contents) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            if let Some(entry) = parse(id, path, &contents, &locale, &desktops) {
+                entries.push(entry);
+            }
+        }
+    }
+    entries.sort_by_cached_key(|entry| entry.name.to_lowercase());
+    entries
+}
+
+/// Collects the desktop files below `dir` along with their IDs, the path relative to `root`
+/// with slashes replaced by dashes.
+fn collect_desktop_files(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_desktop_files(root, &path, files);
+        } else if path.extension().is_some_and(|ext| ext == "desktop")
+            && let Ok(relative) = path.strip_prefix(root)
+        {
+            let id = relative.to_string_lossy().replace('/', "-");
+            files.push((id, path));
+        }
+    }
+}
+
+/// The desktops named in `XDG_CURRENT_DESKTOP`, for `OnlyShowIn` and `NotShowIn`.
+fn current_desktops() -> Vec<String> {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .map(|desktops| desktops.split(':').map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// The locale used to pick localized values, e.g. `Name[de]`.
+struct Locale {
+    /// Locale keys to try, most specific first, e.g. `de_DE` and `de`.
+    keys: Vec<String>,
+}
+
+impl Locale {
+    fn from_env() -> Self {
+        let value = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        // Strip the encoding and modifier, e.g. `de_DE.UTF-8@euro`
+        let locale = value.split(['.', '@']).next().unwrap_or_default();
+        let mut keys = Vec::new();
+        if !locale.is_empty() && locale != "C" && locale != "POSIX" {
+            keys.push(locale.to_string());
+            if let Some((lang, _)) = locale.split_once('_') {
+                keys.push(lang.to_string());
+            }
+        }
+        Self { keys }
+    }
+}
+
+/// The key-value pairs of one group of a desktop file.
+struct Group<'a> {
+    values: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Group<'a> {
+    fn raw(&self, key: &str) -> Option<&'a str> {
+        self.values.get(key).copied()
+    }
+
+    fn string(&self, key: &str) -> Option<String> {
+        self.raw(key)
+            .map(unescape)
+            .filter(|value| !value.is_empty())
+    }
+
+    /// The raw value of `key` in the given locale, falling back to the unlocalized one.
+    fn localized_raw(&self, key: &str, locale: &Locale) -> Option<&'a str> {
+        locale
+            .keys
+            .iter()
+            .find_map(|lang| {
+                self.raw(&format!("{key}[{lang}]"))
+                    .filter(|v| !v.is_empty())
+            })
+            .or_else(|| self.raw(key))
+    }
+
+    fn localized(&self, key: &str, locale: &Locale) -> Option<String> {
+        self.localized_raw(key, locale)
+            .map(unescape)
+            .filter(|value| !value.is_empty())
+    }
+
+    fn bool(&self, key: &str) -> bool {
+        self.raw(key) == Some("true")
+    }
+
+    fn list(&self, key: &str) -> Vec<String> {
+        self.raw(key).map(split_list).unwrap_or_default()
+    }
+
+    /// A localized list, split before unescaping so that `\;` stays part of the items.
+    fn localized_list(&self, key: &str, locale: &Locale) -> Vec<String> {
+        self.localized_raw(key, locale)
+            .map(split_list)
+            .unwrap_or_default()
+    }
+}
+
+/// Splits a desktop file into its groups.
+fn groups(contents: &str) -> HashMap<&str, Group<'_>> {
+    let mut groups = HashMap::new();
+    let mut current: Option<(&str, Group)> = None;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if let Some((name, group)) = current.take() {
+                groups.entry(name).or_insert(group);
+            }
+            current = Some((
+                name,
+                Group {
+                    values: HashMap::new(),
+                },
+            ));
+        } else if let Some((_, group)) = &mut current
+            && let Some((key, value)) = line.split_once('=')
+        {
+            group
+                .values
+                .entry(key.trim_end())
+                .or_insert(value.trim_start());
+        }
+    }
+    if let Some((name, group)) = current {
+        groups.entry(name).or_insert(group);
+    }
+    groups
+}
+
+fn parse(
+    id: String,
+    path: PathBuf,
+    contents: &str,
+    locale: &Locale,
+    desktops: &[String],
+) -> Option<DesktopEntry> {
+    let groups = groups(contents);
+    let main = groups.get("Desktop Entry")?;
+
+    if main.raw("Type") != Some("Application") || main.bool("NoDisplay") || main.bool("Hidden") {
+        return None;
+    }
+    let only_show_in = main.list("OnlyShowIn");
+    if !only_show_in.is_empty() && !only_show_in.iter().any(|d| desktops.contains(d)) {
+        return None;
+    }
+    if main.list("NotShowIn").iter().any(|d| desktops.contains(d)) {
+        return None;
+    }
+    if let Some(try_exec) = main.string("TryExec")
+        && !executable_exists(&try_exec)
+    {
+        return None;
+    }
+
+    let icon_name = main.string("Icon");
+    let actions = main
+        .list("Actions")
+        .into_iter()
+        .filter_map(|action| {
+            let group = groups.get(format!("Desktop Action {action}").as_str())?;
+            Some(DesktopAction {
+                name: group.localized("Name", locale)?,
+                icon: group
+                    .string("Icon")
+                    .or_else(|| icon_name.clone())
+                    .and_then(|icon| lookup_system_icon(&icon)),
+                exec: group.string("Exec")?,
+                id: action,
+            })
+        })
+        .collect();
+
+    Some(DesktopEntry {
+        name: main.localized("Name", locale)?,
+        generic_name: main.localized("GenericName", locale),
+        comment: main.localized("Comment", locale),
+        keywords: main.localized_list("Keywords", locale),
+        icon: icon_name.as_deref().and_then(lookup_system_icon),
+        icon_name,
+        exec: main.string("Exec")?,
+        working_dir: main.string("Path").map(PathBuf::from),
+        terminal: main.bool("Terminal"),
+        actions,
+        id,
+        path,
+    })
+}
+
+/// Whether `program` is an absolute path to a file or found in `PATH`.
+fn executable_exists(program: &str) -> bool {
+    let program = Path::new(program);
+    if program.is_absolute() {
+        return program.exists();
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).exists()))
+}
+
+/// Resolves the escapes of a string value: `\s`, `\n`, `\t`, `\r`, `\\` and, in lists, `\;`.
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some(other @ ('\\' | ';')) => result.push(other),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Splits a `;` separated list, skipping escaped semicolons, and unescapes the items.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ';' => {
+                items.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&value[start..]);
+    items
+        .into_iter()
+        .filter(|item| !item.is_empty())
+        .map(unescape)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEWER: &str = "\
+[Desktop Entry]
+Type=Application
+Name=Image Viewer
+Name[de]=Bildbetrachter
+Name[de_AT]=
+Comment=Views\\s\\simages\\nand photos
+Keywords=picture;photo\\;album;;
+Keywords[fr]=image;photo
+Exec=viewer %U
+Icon=viewer
+Actions=new;broken;
+
+# Comments are skipped
+[Desktop Action new]
+Name=New Window
+Name[de]=Neues Fenster
+Exec=viewer --new-window
+
+[Desktop Action broken]
+Name=Broken
+";
+
+    fn locale(keys: &[&str]) -> Locale {
+        Locale {
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+        }
+    }
+
+    fn parse_in(contents: &str, locale: &Locale, desktops: &[&str]) -> Option<DesktopEntry> {
+        let desktops = desktops.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+        parse(
+            "viewer.desktop".to_string(),
+            PathBuf::from("/usr/share/applications/viewer.desktop"),
+            contents,
+            locale,
+            &desktops,
+        )
+    }
+
+    #[test]
+    fn parses_entries_and_their_actions() {
+        let entry = parse_in(VIEWER, &locale(&[]), &[]).unwrap();
+        assert_eq!(
+            entry,
+            DesktopEntry {
+                id: "viewer.desktop".to_string(),
+                path: PathBuf::from("/usr/share/applications/viewer.desktop"),
+                name: "Image Viewer".to_string(),
+                generic_name: None,
+                comment: Some("Views  images\nand photos".to_string()),
+                keywords: vec!["picture".to_string(), "photo;album".to_string()],
+                icon_name: Some("viewer".to_string()),
+                icon: None,
+                exec: "viewer %U".to_string(),
+                working_dir: None,
+                terminal: false,
+                // Actions without an `Exec` are skipped
+                actions: vec![DesktopAction {
+                    id: "new".to_string(),
+                    name: "New Window".to_string(),
+                    icon: None,
+                    exec: "viewer --new-window".to_string(),
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn prefers_the_most_specific_locale() {
+        // The empty `Name[de_AT]` falls back to `Name[de]`
+        let entry = parse_in(VIEWER, &locale(&["de_AT", "de"]), &[]).unwrap();
+        assert_eq!(entry.name, "Bildbetrachter");
+        assert_eq!(entry.actions[0].name, "Neues Fenster");
+        assert_eq!(entry.keywords, ["picture", "photo;album"]);
+
+        let entry = parse_in(VIEWER, &locale(&["fr_FR", "fr"]), &[]).unwrap();
+        assert_eq!(entry.name, "Image Viewer");
+        assert_eq!(entry.keywords, ["image", "photo"]);
+    }
+
+    #[test]
+    fn skips_entries_not_shown_in_the_desktop() {
+        // Whether the entry is shown with `line` added to it, in `desktops`
+        let shown = |line: &str, desktops: &[&str]| {
+            let contents = VIEWER.replacen("Name=", &format!("{line}\nName="), 1);
+            parse_in(&contents, &locale(&[]), desktops).is_some()
+        };
+
+        assert!(!shown("Hidden=true", &[]));
+        assert!(!shown("NoDisplay=true", &[]));
+        assert!(shown("Hidden=false", &[]));
+
+        assert!(shown("OnlyShowIn=GNOME;niri;", &["niri"]));
+        assert!(!shown("OnlyShowIn=GNOME;", &["niri"]));
+        assert!(!shown("OnlyShowIn=GNOME;", &[]));
+
+        assert!(!shown("NotShowIn=KDE;niri;", &["Hyprland", "niri"]));
+        assert!(shown("NotShowIn=KDE;", &["niri"]));
+
+        assert!(parse_in(&VIEWER.replace("Application", "Link"), &locale(&[]), &[]).is_none());
+        assert!(parse_in(&VIEWER.replace("Exec=", "Run="), &locale(&[]), &[]).is_none());
+    }
+
+    #[test]
+    fn unescapes_values_and_lists() {
+        assert_eq!(unescape(r"a\sb\tc\\s\;\x\"), "a b\tc\\s;\\x\\");
+        assert_eq!(
+            split_list(r"one;two\;three;four\sfive;;six\\;"),
+            ["one", "two;three", "four five", "six\\"]
+        );
+    }
+}
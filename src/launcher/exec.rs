@@ -0,0 +1,197 @@
+//! Expansion of the `Exec` key of desktop entries into a command line.
+//!
+//! See <https://specifications.freedesktop.org/desktop-entry-spec/latest/exec-variables.html>
+
+use std::path::Path;
+use std::process::Stdio;
+
+/// What the field codes of an `Exec` value expand to. No files or URLs are passed, so `%f`,
+/// `%F`, `%u` and `%U` expand to nothing.
+pub struct FieldCodes<'a> {
+    /// Translated name of the application, for `%c`.
+    pub name: &'a str,
+    /// The `Icon` key of the entry, for `%i`.
+    pub icon: Option<&'a str>,
+    /// Path of the desktop file, for `%k`.
+    pub desktop_file: &'a Path,
+}
+
+/// Splits `exec` into its arguments and expands the field codes, `None` if the quoting is
+/// invalid or there is no program.
+pub fn expand(exec: &str, codes: &FieldCodes) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    for (arg, quoted) in tokenize(exec)? {
+        // Codes only expand to several arguments, or to none, when they stand alone
+        if !quoted {
+            match arg.as_str() {
+                "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => continue,
+                "%i" => {
+                    if let Some(icon) = codes.icon {
+                        args.push("--icon".to_string());
+                        args.push(icon.to_string());
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        args.push(expand_inline(&arg, codes));
+    }
+    (!args.is_empty()).then_some(args)
+}
+
+/// Expands the codes inside of an argument, dropping those that don't make sense there.
+fn expand_inline(arg: &str, codes: &FieldCodes) -> String {
+    let mut result = String::with_capacity(arg.len());
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => result.push('%'),
+            Some('c') => result.push_str(codes.name),
+            Some('k') => result.push_str(&codes.desktop_file.to_string_lossy()),
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Splits `exec` at unquoted whitespace into arguments, each with whether it was quoted.
+///
+/// Inside of double quotes, a backslash escapes `"`, `` ` ``, `$` and `\`.
+fn tokenize(exec: &str) -> Option<Vec<(String, bool)>> {
+    let mut args = Vec::new();
+    let mut current: Option<(String, bool)> = None;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let (arg, quoted) = current.get_or_insert_with(Default::default);
+                *quoted = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            escaped @ ('"' | '`' | '$' | '\\') => arg.push(escaped),
+                            other => {
+                                arg.push('\\');
+                                arg.push(other);
+                            }
+                        },
+                        other => arg.push(other),
+                    }
+                }
+            }
+            c if c.is_whitespace() => args.extend(current.take()),
+            c => current.get_or_insert_with(Default::default).0.push(c),
+        }
+    }
+    args.extend(current);
+    Some(args)
+}
+
+/// Runs `args` detached from limbo in `working_dir`, logging failures.
+pub fn launch(args: Vec<String>, working_dir: Option<&Path>) {
+    let Some((program, rest)) = args.split_first() else {
+        return;
+    };
+    let mut command = tokio::process::Command::new(program);
+    command
+        .args(rest)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // Keep the application alive when limbo exits and out of its signals
+        .process_group(0);
+    if let Some(dir) = working_dir {
+        command.current_dir(dir);
+    }
+
+    let line = args.join(" ");
+    match command.spawn() {
+        Ok(mut child) => {
+            tokio::spawn(async move {
+                match child.wait().await {
+                    Ok(status) if !status.success() => {
+                        log::debug!("launcher: `{line}` exited with {status}")
+                    }
+                    Err(e) => log::warn!("launcher: failed to wait for `{line}`: {e}"),
+                    Ok(_) => {}
+                }
+            });
+        }
+        Err(e) => log::warn!("launcher: failed to run `{line}`: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_with(exec: &str, icon: Option<&str>) -> Option<Vec<String>> {
+        let codes = FieldCodes {
+            name: "Files",
+            icon,
+            desktop_file: Path::new("/usr/share/applications/files.desktop"),
+        };
+        expand(exec, &codes)
+    }
+
+    #[test]
+    fn drops_file_codes() {
+        assert_eq!(
+            expand_with("files %U --new-window %f", None).unwrap(),
+            ["files", "--new-window"]
+        );
+        // Inside of an argument, the code is dropped rather than the argument
+        assert_eq!(
+            expand_with("files --open=%f --urls=%U", None).unwrap(),
+            ["files", "--open=", "--urls="]
+        );
+        assert_eq!(expand_with("%F", None), None);
+    }
+
+    #[test]
+    fn expands_the_icon_only_when_there_is_one() {
+        assert_eq!(
+            expand_with("files %i", Some("folder")).unwrap(),
+            ["files", "--icon", "folder"]
+        );
+        assert_eq!(expand_with("files %i", None).unwrap(), ["files"]);
+    }
+
+    #[test]
+    fn expands_inline_codes() {
+        assert_eq!(
+            expand_with("files --title=%c %k 100%%", None).unwrap(),
+            [
+                "files",
+                "--title=Files",
+                "/usr/share/applications/files.desktop",
+                "100%"
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_quoted_arguments() {
+        assert_eq!(
+            expand_with(r#"sh -c "echo \"a b\" \`c\` \$d \\ \n""#, None).unwrap(),
+            ["sh", "-c", r#"echo "a b" `c` $d \ \n"#]
+        );
+        assert_eq!(
+            expand_with(r#"files --name="my "files"#, None).unwrap(),
+            ["files", "--name=my files"]
+        );
+    }
+
+    #[test]
+    fn rejects_unbalanced_quotes() {
+        assert_eq!(expand_with(r#"files "--name"#, None), None);
+        assert_eq!(expand_with(r#"files "--name\""#, None), None);
+        assert_eq!(expand_with(r#"files "--name\"#, None), None);
+    }
+}
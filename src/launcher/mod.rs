@@ -0,0 +1,194 @@
+//! Application launcher backend: the installed applications and how often each was launched.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::sync::{Mutex, mpsc, watch};
+
+use crate::message::Message;
+
+mod desktop_entry;
+mod exec;
+mod search;
+
+pub use desktop_entry::DesktopEntry;
+pub use search::{Match, search};
+
+use exec::FieldCodes;
+
+#[derive(Debug, Default, Clone)]
+pub struct LauncherState {
+    /// Applications shown in menus, sorted by name.
+    pub entries: Arc<Vec<DesktopEntry>>,
+    /// Number of launches by desktop file ID.
+    pub usage: Arc<HashMap<String, u32>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum LauncherCommand {
+    /// Rescan the desktop entries, e.g. after an application was installed.
+    Refresh,
+    /// Launch the entry with the given desktop file ID, or one of its actions.
+    Launch { id: String, action: Option<String> },
+}
+
+#[derive(Debug)]
+pub struct Launcher {
+    commands: mpsc::UnboundedSender<LauncherCommand>,
+    rx: Arc<Mutex<watch::Receiver<LauncherState>>>,
+}
+
+impl Launcher {
+    /// `terminal` is the command applications with `Terminal=true` are run in, e.g. `foot` or
+    /// `xterm -e`.
+    pub fn new(terminal: &str) -> Self {
+        let (tx, rx) = watch::channel(LauncherState::default());
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        let terminal = terminal.split_whitespace().map(str::to_string).collect();
+        tokio::spawn(run(terminal, tx, commands_rx));
+        Self {
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn send(&self, command: LauncherCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "launcher".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::LauncherUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+async fn run(
+    terminal: Vec<String>,
+    tx: watch::Sender<LauncherState>,
+    mut commands_rx: mpsc::UnboundedReceiver<LauncherCommand>,
+) {
+    let usage_path = usage_path();
+    let mut usage = match &usage_path {
+        Some(path) => load_usage(path).await,
+        None => HashMap::new(),
+    };
+    let mut entries = scan().await;
+    tx.send_replace(LauncherState {
+        entries: entries.clone(),
+        usage: Arc::new(usage.clone()),
+    });
+
+    while let Some(command) = commands_rx.recv().await {
+        match command {
+            LauncherCommand::Refresh => {
+                entries = scan().await;
+                tx.send_modify(|state| state.entries = entries.clone());
+            }
+            LauncherCommand::Launch { id, action } => {
+                let Some(entry) = entries.iter().find(|entry| entry.id == id) else {
+                    log::warn!("launcher: no application {id}");
+                    continue;
+                };
+                launch(entry, action.as_deref(), &terminal);
+
+                *usage.entry(id).or_default() += 1;
+                if let Some(path) = &usage_path
+                    && let Err(e) = save_usage(path, &usage).await
+                {
+                    log::warn!("launcher: failed to save {}: {e}", path.display());
+                }
+                tx.send_modify(|state| state.usage = Arc::new(usage.clone()));
+            }
+        }
+    }
+}
+
+/// Reads the desktop entries on a blocking thread, since it touches many files.
+async fn scan() -> Arc<Vec<DesktopEntry>> {
+    let entries = tokio::task::spawn_blocking(desktop_entry::scan)
+        .await
+        .unwrap_or_default();
+    log::debug!("launcher: found {} applications", entries.len());
+    Arc::new(entries)
+}
+
+fn launch(entry: &DesktopEntry, action: Option<&str>, terminal: &[String]) {
+    let exec = match action {
+        Some(action) => match entry.actions.iter().find(|a| a.id == action) {
+            Some(action) => &action.exec,
+            None => {
+                log::warn!("launcher: {} has no action {action}", entry.id);
+                return;
+            }
+        },
+        None => &entry.exec,
+    };
+    let codes = FieldCodes {
+        name: &entry.name,
+        icon: entry.icon_name.as_deref(),
+        desktop_file: &entry.path,
+    };
+    let Some(args) = exec::expand(exec, &codes) else {
+        log::warn!("launcher: invalid Exec of {}: {exec}", entry.id);
+        return;
+    };
+
+    if entry.terminal {
+        exec::launch(
+            terminal.iter().cloned().chain(args).collect(),
+            entry.working_dir.as_deref(),
+        );
+    } else {
+        exec::launch(args, entry.working_dir.as_deref());
+    }
+}
+
+/// File the launch counts are kept in, as lines of count and desktop file ID.
+fn usage_path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .map(|dir| dir.join("limbo").join("launcher-usage"))
+}
+
+async fn load_usage(path: &Path) -> HashMap<String, u32> {
+    let contents = match tokio::fs::read_to_string(path).await {
+        Ok(contents) => contents,
+        Err(e) => {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("launcher: failed to read {}: {e}", path.display());
+            }
+            return HashMap::new();
+        }
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (count, id) = line.split_once('\t')?;
+            Some((id.to_string(), count.parse().ok()?))
+        })
+        .collect()
+}
+
+async fn save_usage(path: &Path, usage: &HashMap<String, u32>) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let contents: String = usage
+        .iter()
+        .map(|(id, count)| format!("{count}\t{id}\n"))
+        .collect();
+    tokio::fs::write(path, contents).await
+}
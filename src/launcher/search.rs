@@ -0,0 +1,246 @@
+//! Fuzzy matching of desktop entries against the query of the launcher.
+
+use std::collections::HashMap;
+
+use super::desktop_entry::DesktopEntry;
+
+/// Weight of how often an application was launched, added to the score of its matches.
+const USAGE_WEIGHT: f32 = 4.0;
+
+/// A result of a search: an entry, or one of its actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    /// Index of the entry.
+    pub entry: usize,
+    /// Index of the action of the entry, `None` for the application itself.
+    pub action: Option<usize>,
+}
+
+/// The best `limit` matches of `query`, best first.
+///
+/// Without a query, the applications are listed by how often they were launched. Actions are
+/// only listed once something was typed, e.g. `firefox private`.
+pub fn search(
+    entries: &[DesktopEntry],
+    usage: &HashMap<String, u32>,
+    query: &str,
+    limit: usize,
+) -> Vec<Match> {
+    let query = query.trim().to_lowercase();
+    let usage_bonus = |entry: &DesktopEntry| {
+        let count = usage.get(&entry.id).copied().unwrap_or(0);
+        (count as f32).ln_1p() * USAGE_WEIGHT
+    };
+
+    let mut scored = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if query.is_empty() {
+            scored.push((
+                usage_bonus(entry),
+                Match {
+                    entry: i,
+                    action: None,
+                },
+            ));
+            continue;
+        }
+        if let Some(score) = entry_score(&query, entry) {
+            scored.push((
+                score + usage_bonus(entry),
+                Match {
+                    entry: i,
+                    action: None,
+                },
+            ));
+        }
+        for (j, action) in entry.actions.iter().enumerate() {
+            // Matched against both names, so that the query can narrow down the application
+            let name = format!("{} {}", entry.name, action.name);
+            if let Some(score) = fuzzy_score(&query, &name) {
+                scored.push((
+                    score + usage_bonus(entry),
+                    Match {
+                        entry: i,
+                        action: Some(j),
+                    },
+                ));
+            }
+        }
+    }
+
+    // Stable, so that ties keep the alphabetical order of the entries
+    scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    scored.truncate(limit);
+    scored.into_iter().map(|(_, m)| m).collect()
+}
+
+/// The best score of `query` against the fields of `entry`, weighted by the field.
+fn entry_score(query: &str, entry: &DesktopEntry) -> Option<f32> {
+    let fields = [
+        (Some(entry.name.as_str()), 1.0),
+        (entry.generic_name.as_deref(), 0.8),
+        (entry.comment.as_deref(), 0.5),
+    ];
+    fields
+        .into_iter()
+        .filter_map(|(field, weight)| Some(fuzzy_score(query, field?)? * weight))
+        .chain(
+            entry
+                .keywords
+                .iter()
+                .filter_map(|keyword| Some(fuzzy_score(query, keyword)? * 0.8)),
+        )
+        .max_by(f32::total_cmp)
+}
+
+/// Scores `query`, which must be lowercase, as a subsequence of `candidate`, `None` if it isn't
+/// one.
+///
+/// Characters matching consecutively or at the start of a word score higher, as does a match
+/// at the start of the candidate.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<f32> {
+    let candidate = candidate.to_lowercase();
+    if candidate.starts_with(query) {
+        return Some(100.0 + query.chars().count() as f32 * 2.0);
+    }
+
+    let mut score = 0.0;
+    let mut query_chars = query.chars().peekable();
+    let mut previous: Option<char> = None;
+    let mut consecutive = false;
+    for c in candidate.chars() {
+        let Some(&wanted) = query_chars.peek() else {
+            break;
+        };
+        if c == wanted {
+            let word_start = previous.is_none_or(|p| !p.is_alphanumeric());
+            score += 1.0;
+            if consecutive {
+                score += 2.0;
+            }
+            if word_start {
+                score += 3.0;
+            }
+            query_chars.next();
+            consecutive = true;
+        } else {
+            consecutive = false;
+        }
+        previous = Some(c);
+    }
+    if query_chars.peek().is_some() {
+        return None;
+    }
+    // Prefer shorter candidates where the match covers more of the text
+    Some(score - candidate.chars().count() as f32 * 0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::super::desktop_entry::DesktopAction;
+    use super::*;
+
+    fn entry(name: &str, keywords: &[&str], actions: &[&str]) -> DesktopEntry {
+        let id = format!("{}.desktop", name.to_lowercase());
+        DesktopEntry {
+            path: PathBuf::from("/usr/share/applications").join(&id),
+            id,
+            name: name.to_string(),
+            generic_name: None,
+            comment: None,
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            icon_name: None,
+            icon: None,
+            exec: name.to_lowercase(),
+            working_dir: None,
+            terminal: false,
+            actions: actions
+                .iter()
+                .map(|action| DesktopAction {
+                    id: action.to_lowercase(),
+                    name: action.to_string(),
+                    icon: None,
+                    exec: name.to_lowercase(),
+                })
+                .collect(),
+        }
+    }
+
+    fn entries() -> Vec<DesktopEntry> {
+        vec![
+            entry("Files", &["folder"], &[]),
+            entry("Firefox", &["browser", "web"], &["New Private Window"]),
+            entry("Terminal", &["shell"], &[]),
+        ]
+    }
+
+    fn usage(counts: &[(&str, u32)]) -> HashMap<String, u32> {
+        counts
+            .iter()
+            .map(|(id, count)| (id.to_string(), *count))
+            .collect()
+    }
+
+    fn app(entry: usize) -> Match {
+        Match {
+            entry,
+            action: None,
+        }
+    }
+
+    #[test]
+    fn lists_the_most_used_applications_without_a_query() {
+        let usage = usage(&[("terminal.desktop", 10), ("firefox.desktop", 2)]);
+        assert_eq!(
+            search(&entries(), &usage, " ", 10),
+            [app(2), app(1), app(0)]
+        );
+        assert_eq!(search(&entries(), &usage, "", 1), [app(2)]);
+    }
+
+    #[test]
+    fn ranks_matches_by_score_and_usage() {
+        let entries = entries();
+        let private = Match {
+            entry: 1,
+            action: Some(0),
+        };
+        assert_eq!(
+            search(&entries, &HashMap::new(), "fi", 10),
+            [app(0), app(1), private]
+        );
+        // Equally good matches are ranked by how often they were launched
+        let usage = usage(&[("firefox.desktop", 3)]);
+        assert_eq!(
+            search(&entries, &usage, "fi", 10),
+            [app(1), private, app(0)]
+        );
+        // Keywords match too, whatever the case of the query
+        assert_eq!(search(&entries, &usage, "WEB", 10), [app(1)]);
+        assert!(search(&entries, &usage, "xyz", 10).is_empty());
+    }
+
+    #[test]
+    fn lists_actions_only_for_queries() {
+        let private = Match {
+            entry: 1,
+            action: Some(0),
+        };
+        assert_eq!(
+            search(&entries(), &HashMap::new(), "firefox priv", 10),
+            [private]
+        );
+        assert!(!search(&entries(), &HashMap::new(), "", 10).contains(&private));
+    }
+
+    #[test]
+    fn prefers_prefixes_and_word_starts() {
+        let prefix = fuzzy_score("fi", "Files").unwrap();
+        let word_start = fuzzy_score("nw", "New Window").unwrap();
+        let scattered = fuzzy_score("nw", "Unknown").unwrap();
+        assert!(prefix > word_start && word_start > scattered);
+        assert_eq!(fuzzy_score("sf", "Files"), None);
+    }
+}
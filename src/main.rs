@@ -2,23 +2,67 @@ use std::rc::Rc;
 
 use iced::daemon::{Appearance, DefaultStyle};
 use iced::event::{PlatformSpecific, wayland};
+use iced::keyboard;
 use iced::theme::Palette;
 use iced::{Color, Element, Event, Settings, Task, Theme, window};
 
+use crate::audio::{Audio, AudioCommand, AudioState};
+use crate::bluetooth::{Bluetooth, BluetoothCommand, BluetoothState};
+use crate::brightness::{Backlight, Brightness, MonitorBrightness};
+use crate::caffeine::{Caffeine, CaffeineState};
+use crate::config::types::{ModuleName, NotificationSegment, QuickSettingSegment};
 use crate::desktop_environment::{Desktop, WorkspaceInfo};
+use crate::dnd::{DndCommand, DndRules, DndState, DoNotDisturb};
+use crate::github::{GithubNotifications, GithubState};
+use crate::ipc::Ipc;
+use crate::launcher::{Launcher, LauncherCommand, LauncherState};
 use crate::message::Message;
+use crate::mpris::{Mpris, MprisCommand, MprisState};
+use crate::network::{Network, NetworkCommand, NetworkState};
+use crate::night_light::{NightLight, NightLightState};
+use crate::notifications::{NotificationCommand, NotificationServer, NotificationsState};
+use crate::popup::PopupKind;
+use crate::power_profiles::{PowerProfiles, PowerProfilesState};
+use crate::power_supply::{BatteryState, PowerSupply};
 use crate::sections::{SysInfo, Sysmon};
+use crate::toasts::Toasts;
+use crate::todo::{TodoCommand, TodoList, TodoState};
+use crate::todoist::{TodoistCommand, TodoistState, TodoistTasks};
 use crate::tray::{Tray, TrayItem};
+use crate::weather::{Weather, WeatherState};
 
 mod animation;
+mod audio;
 mod bar;
+mod bluetooth;
+mod brightness;
+mod cache;
+mod caffeine;
+mod command;
 mod components;
 mod config;
 mod desktop_environment;
+mod dnd;
+mod github;
 mod icons;
+mod ipc;
+mod launcher;
 mod message;
+mod mpris;
+mod network;
+mod night_light;
+mod notifications;
+mod popup;
+mod power_profiles;
+mod power_supply;
 mod sections;
+#[cfg(test)]
+mod testing;
+mod toasts;
+mod todo;
+mod todoist;
 mod tray;
+mod weather;
 
 use bar::Bar;
 use config::Config;
@@ -36,6 +80,14 @@ pub async fn main() -> iced::Result {
 
     let config = Config::load().unwrap_or_default();
 
+    let default_log_level = if config.general.debug {
+        "debug"
+    } else {
+        "info"
+    };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_log_level))
+        .init();
+
     let font_name = config.theme.font.clone().leak();
     iced::daemon("limbo", Limbo::update, Limbo::view)
         .settings(Settings {
@@ -65,26 +117,116 @@ pub struct GlobalState {
     workspace_infos: Vec<WorkspaceInfo>,
     sysinfo: SysInfo,
     tray_items: Vec<TrayItem>,
+    mpris: MprisState,
+    audio: AudioState,
+    backlight: Option<Backlight>,
+    monitor_brightness: Vec<MonitorBrightness>,
+    network: NetworkState,
+    battery: Option<BatteryState>,
+    power_profiles: PowerProfilesState,
+    bluetooth: BluetoothState,
+    night_light: NightLightState,
+    caffeine: CaffeineState,
+    notifications: NotificationsState,
+    dnd: DndState,
+    launcher: LauncherState,
+    todo: TodoState,
+    weather: WeatherState,
+    github: GithubState,
+    todoist: TodoistState,
 }
 
 struct Limbo {
     global_state: GlobalState,
     bars: Vec<Bar>,
+    toasts: Toasts,
     desktop: Desktop,
     tray: Tray,
+    mpris: Mpris,
+    audio: Audio,
+    brightness: Brightness,
+    network: Network,
+    power_supply: PowerSupply,
+    power_profiles: PowerProfiles,
+    bluetooth: Bluetooth,
+    night_light: NightLight,
+    caffeine: Caffeine,
+    notifications: NotificationServer,
+    dnd: DoNotDisturb,
+    dnd_rules: DndRules,
+    launcher: Launcher,
+    ipc: Ipc,
+    todo: TodoList,
+    weather: Weather,
+    github: GithubNotifications,
+    todoist: TodoistTasks,
 }
 
 impl Limbo {
     fn new(config: Config) -> (Self, Task<Message>) {
+        let power_supply = PowerSupply::new(&config.bar.battery);
+        let quick_settings = &config.bar.quick_settings.segments;
+        // The panel opened from the toggle shows every quick setting
+        let shows_quick_setting = |segment| {
+            config.bar.modules.contains(ModuleName::QuickSettings)
+                && [segment, QuickSettingSegment::Toggle]
+                    .iter()
+                    .any(|segment| quick_settings.contains(segment))
+        };
+        let brightness = Brightness::new(shows_quick_setting(QuickSettingSegment::Brightness));
+        let power_profiles =
+            PowerProfiles::new(shows_quick_setting(QuickSettingSegment::PowerProfile));
+        let night_light = NightLight::new(&config);
+        let notifications = NotificationServer::new(&config.bar.quick_settings.notifs);
+        let mpris = Mpris::new(config.bar.music.priority.clone());
+        let desktop = Desktop::new();
+        if config.bar.quick_settings.dnd.when_fullscreen && !desktop.reports_fullscreen() {
+            log::warn!("dnd: whenFullscreen has no effect, the compositor doesn't report it");
+        }
+        let dnd_rules = DndRules::new(&config.bar.quick_settings.dnd);
+        let launcher = Launcher::new(&config.bar.app_launcher.terminal);
+        let todo = TodoList::new(&config.bar.todo);
+        let segments = &config.bar.notifications.segments;
+        let weather = Weather::new(
+            &config.general,
+            config.bar.modules.contains(ModuleName::Notifications)
+                && segments.contains(&NotificationSegment::Weather),
+        );
+        let github = GithubNotifications::new(&config.bar.notifications.github);
+        let todoist = TodoistTasks::new(&config.bar.notifications.todoist, &config.bar.todo);
+        let dnd = DoNotDisturb::new(
+            &config.bar.quick_settings.dnd,
+            config.bar.quick_settings.notifs.server,
+        );
+        let global_state = GlobalState {
+            config: Rc::new(config),
+            ..Default::default()
+        };
         (
             Self {
-                global_state: GlobalState {
-                    config: Rc::new(config),
-                    ..Default::default()
-                },
+                toasts: Toasts::new(&global_state),
+                global_state,
                 bars: Vec::new(),
-                desktop: Desktop::new(),
+                desktop,
                 tray: Tray::new(),
+                mpris,
+                audio: Audio::new(),
+                brightness,
+                network: Network::new(),
+                power_supply,
+                power_profiles,
+                bluetooth: Bluetooth::new(),
+                night_light,
+                caffeine: Caffeine::new(),
+                notifications,
+                dnd,
+                dnd_rules,
+                launcher,
+                ipc: Ipc::new(),
+                todo,
+                weather,
+                github,
+                todoist,
             },
             Task::none(),
         )
@@ -97,14 +239,33 @@ impl Limbo {
                     wayland::Event::Output(_, _)
                     | wayland::Event::Layer(wayland::LayerEvent::Done, _, _),
                 ))
-                | Event::Window(window::Event::Opened { .. }) => {
+                | Event::Window(window::Event::Opened { .. })
+                | Event::Keyboard(keyboard::Event::KeyPressed { .. }) => {
                     Some(Message::Iced(window_id, evt))
                 }
                 _ => None,
             }),
             Sysmon::subscription(&self.global_state.config),
             self.tray.subscription(),
+            self.mpris.subscription(),
+            self.audio.subscription(),
+            self.brightness.subscription(),
+            self.network.subscription(),
+            self.power_supply.subscription(),
+            self.power_profiles.subscription(),
+            self.bluetooth.subscription(),
+            self.night_light.subscription(),
+            self.caffeine.subscription(),
+            self.notifications.subscription(),
+            self.dnd.subscription(),
+            self.dnd_rules.subscription(),
             self.desktop.subscription(),
+            self.launcher.subscription(),
+            self.ipc.subscription(),
+            self.todo.subscription(),
+            self.weather.subscription(),
+            self.github.subscription(),
+            self.todoist.subscription(),
         ];
 
         if self.animation_running() {
@@ -117,11 +278,18 @@ impl Limbo {
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
-        for bar in self.bars.iter_mut() {
-            bar.update(&message);
-        }
+        let bar_tasks = Task::batch(
+            self.bars
+                .iter_mut()
+                .map(|bar| bar.update(&message))
+                .collect::<Vec<_>>(),
+        );
+        // Toasts are shown below the bar of the first output
+        let toasts_task = self
+            .toasts
+            .update(&message, self.bars.first().map(|bar| &bar.wl_output));
 
-        match message {
+        let task = match message {
             Message::Iced(_, Event::PlatformSpecific(PlatformSpecific::Wayland(evt))) => {
                 match evt {
                     wayland::Event::Output(
@@ -138,17 +306,29 @@ impl Limbo {
                         }
                     }
                     wayland::Event::Output(wayland::OutputEvent::Removed, wl_output) => {
-                        let removed_bars =
-                            self.bars.extract_if(.., |bar| bar.wl_output == wl_output);
-                        Task::batch(removed_bars.map(|bar| bar.destroy()))
+                        let removed_bars = self
+                            .bars
+                            .extract_if(.., |bar| bar.wl_output == wl_output)
+                            .collect::<Vec<_>>();
+                        for bar in &removed_bars {
+                            self.caffeine.bar_closed(bar.id);
+                        }
+                        Task::batch(removed_bars.into_iter().map(|bar| bar.destroy()))
                     }
                     wayland::Event::Layer(wayland::LayerEvent::Done, _wl_surface, id) => {
                         self.bars.retain(|bar| bar.id != id);
+                        self.caffeine.bar_closed(id);
                         Task::none()
                     }
                     _ => Task::none(),
                 }
             }
+            Message::Iced(window_id, Event::Window(window::Event::Opened { .. })) => {
+                match self.bars.iter().find(|bar| bar.id == window_id) {
+                    Some(bar) => self.caffeine.bar_opened(bar.id, &bar.wl_output),
+                    None => Task::none(),
+                }
+            }
             Message::WorkspacesChanged(workspace_infos) => {
                 self.global_state.workspace_infos = workspace_infos;
                 Task::none()
@@ -169,17 +349,278 @@ impl Limbo {
                 self.global_state.tray_items = tray_items;
                 Task::none()
             }
+            Message::MprisUpdate(mpris) => {
+                self.global_state.mpris = mpris;
+                Task::none()
+            }
+            Message::MediaPlayPause => {
+                self.mpris.send(MprisCommand::PlayPause);
+                Task::none()
+            }
+            Message::MediaNext => {
+                self.mpris.send(MprisCommand::Next);
+                Task::none()
+            }
+            Message::MediaPrevious => {
+                self.mpris.send(MprisCommand::Previous);
+                Task::none()
+            }
+            Message::SwitchPlayer { forward } => {
+                self.mpris.send(MprisCommand::Switch { forward });
+                Task::none()
+            }
+            Message::SeekPlayer(position) => {
+                self.mpris.send(MprisCommand::Seek(position));
+                Task::none()
+            }
+            Message::SetPlayerVolume(volume) => {
+                self.mpris.send(MprisCommand::SetVolume(volume));
+                Task::none()
+            }
+            Message::SetPlayerShuffle(shuffle) => {
+                self.mpris.send(MprisCommand::SetShuffle(shuffle));
+                Task::none()
+            }
+            Message::SetPlayerLoop(status) => {
+                self.mpris.send(MprisCommand::SetLoop(status));
+                Task::none()
+            }
+            Message::AudioUpdate(audio) => {
+                self.global_state.audio = audio;
+                Task::none()
+            }
+            Message::SetSinkVolume(volume) => {
+                self.audio.send(AudioCommand::SetSinkVolume(volume));
+                Task::none()
+            }
+            Message::StepSinkVolume(delta) => {
+                self.audio.send(AudioCommand::StepSinkVolume(delta));
+                Task::none()
+            }
+            Message::SetSinkMute(mute) => {
+                self.audio.send(AudioCommand::SetSinkMute(mute));
+                Task::none()
+            }
+            Message::SetSourceMute(mute) => {
+                self.audio.send(AudioCommand::SetSourceMute(mute));
+                Task::none()
+            }
+            Message::BacklightUpdate(backlight) => {
+                self.global_state.backlight = backlight;
+                Task::none()
+            }
+            Message::ChangeBacklight { device, change } => {
+                self.brightness.change_backlight(device, change);
+                Task::none()
+            }
+            Message::MonitorBrightnessUpdate(monitor_brightness) => {
+                self.global_state.monitor_brightness = monitor_brightness;
+                Task::none()
+            }
+            Message::ChangeMonitorBrightness { output, change } => {
+                self.brightness.change_monitor_brightness(output, change);
+                Task::none()
+            }
+            Message::NetworkUpdate(network) => {
+                self.global_state.network = network;
+                Task::none()
+            }
+            Message::ConnectWifi(ssid) => {
+                self.network.send(NetworkCommand::Connect(ssid));
+                Task::none()
+            }
+            Message::DisconnectWifi => {
+                self.network.send(NetworkCommand::Disconnect);
+                Task::none()
+            }
+            Message::BatteryUpdate(battery) => {
+                self.global_state.battery = battery;
+                Task::none()
+            }
+            Message::PowerProfilesUpdate(power_profiles) => {
+                self.global_state.power_profiles = power_profiles;
+                Task::none()
+            }
+            Message::SetPowerProfile(profile) => {
+                self.power_profiles.set(profile);
+                Task::none()
+            }
+            Message::BluetoothUpdate(bluetooth) => {
+                self.global_state.bluetooth = bluetooth;
+                Task::none()
+            }
+            Message::SetBluetoothPowered(powered) => {
+                self.bluetooth.send(BluetoothCommand::SetPowered(powered));
+                Task::none()
+            }
+            Message::ConnectBluetooth(path) => {
+                self.bluetooth.send(BluetoothCommand::Connect(path));
+                Task::none()
+            }
+            Message::DisconnectBluetooth(path) => {
+                self.bluetooth.send(BluetoothCommand::Disconnect(path));
+                Task::none()
+            }
+            Message::NightLightUpdate(night_light) => {
+                self.global_state.night_light = night_light;
+                Task::none()
+            }
+            Message::CycleNightLight => {
+                self.night_light.cycle_mode();
+                Task::none()
+            }
+            Message::BarSurface(bar_id, surface) => {
+                // The bar may have been closed while its surface was looked up
+                if self.bars.iter().any(|bar| bar.id == bar_id) {
+                    self.caffeine.add_surface(bar_id, surface);
+                }
+                Task::none()
+            }
+            Message::CaffeineUpdate(caffeine) => {
+                self.global_state.caffeine = caffeine;
+                Task::none()
+            }
+            Message::SetCaffeine(timer) => {
+                Task::done(Message::CaffeineUpdate(self.caffeine.set(timer)))
+            }
+            Message::CaffeineTick => match self.caffeine.tick() {
+                Some(caffeine) => Task::done(Message::CaffeineUpdate(caffeine)),
+                None => Task::none(),
+            },
+            Message::NotificationsUpdate(notifications) => {
+                self.global_state.notifications = notifications;
+                Task::none()
+            }
+            Message::TogglePopup(bar_id, PopupKind::Notifications) => {
+                // The bars were updated already, so the popup is open unless it was just closed
+                let opened = self
+                    .bars
+                    .iter()
+                    .any(|bar| bar.id == bar_id && bar.popup_open(PopupKind::Notifications));
+                if opened {
+                    self.notifications.send(NotificationCommand::MarkAllRead);
+                }
+                Task::none()
+            }
+            Message::DismissNotification(id) => {
+                self.notifications.send(NotificationCommand::Dismiss(id));
+                Task::none()
+            }
+            Message::InvokeNotificationAction { id, key } => {
+                self.notifications
+                    .send(NotificationCommand::InvokeAction { id, key });
+                Task::none()
+            }
+            Message::RemoveNotification(id) => {
+                self.notifications.send(NotificationCommand::Remove(id));
+                Task::none()
+            }
+            Message::ClearNotifications => {
+                self.notifications.send(NotificationCommand::ClearAll);
+                Task::none()
+            }
+            Message::FocusedWindowChanged(_) | Message::DndRulesTick => {
+                match self.dnd_rules.update(&message) {
+                    Some(dnd) => Task::done(Message::SetDnd(dnd)),
+                    None => Task::none(),
+                }
+            }
+            Message::DndUpdate(dnd) => {
+                self.global_state.dnd = dnd;
+                Task::none()
+            }
+            Message::SetDnd(dnd) => {
+                if self.global_state.config.bar.quick_settings.notifs.server {
+                    self.notifications.send(NotificationCommand::SetDnd(dnd));
+                } else {
+                    self.dnd.send(DndCommand::Set(dnd));
+                }
+                Task::none()
+            }
+            Message::ToggleLauncher => match self.bars.first() {
+                Some(bar) => Task::done(Message::TogglePopup(bar.id, PopupKind::AppLauncher)),
+                None => Task::none(),
+            },
+            Message::TogglePopup(_, PopupKind::AppLauncher) => {
+                // Pick up applications installed since the last time
+                self.launcher.send(LauncherCommand::Refresh);
+                Task::none()
+            }
+            Message::LauncherUpdate(launcher) => {
+                self.global_state.launcher = launcher;
+                Task::none()
+            }
+            Message::LaunchApp { id, action } => {
+                self.launcher.send(LauncherCommand::Launch { id, action });
+                Task::none()
+            }
+            Message::TodoUpdate(todo) => {
+                self.global_state.todo = todo;
+                Task::none()
+            }
+            Message::AddTodo(text) => {
+                self.todo.send(TodoCommand::Add(text));
+                Task::none()
+            }
+            Message::SetTodoDone { line, raw, done } => {
+                self.todo.send(TodoCommand::SetDone { line, raw, done });
+                Task::none()
+            }
+            Message::SetTodoPriority {
+                line,
+                raw,
+                priority,
+            } => {
+                self.todo.send(TodoCommand::SetPriority {
+                    line,
+                    raw,
+                    priority,
+                });
+                Task::none()
+            }
+            Message::WeatherUpdate(weather) => {
+                self.global_state.weather = weather;
+                Task::none()
+            }
+            Message::GithubUpdate(github) => {
+                self.global_state.github = github;
+                Task::none()
+            }
+            Message::TodoistUpdate(todoist) => {
+                self.global_state.todoist = todoist;
+                Task::none()
+            }
+            Message::CompleteTodoistTask(id) => {
+                self.todoist.send(TodoistCommand::Complete(id));
+                Task::none()
+            }
+            Message::RunCommand(cmd) => {
+                command::spawn(&cmd);
+                Task::none()
+            }
+            Message::OpenUrl(url) => {
+                command::open(&url);
+                Task::none()
+            }
             _ => Task::none(),
-        }
+        };
+
+        Task::batch([bar_tasks, toasts_task, task])
     }
 
     fn view(&self, window_id: window::Id) -> Element<'_, Message> {
+        if self.toasts.id == Some(window_id) {
+            return self.toasts.view();
+        }
+        if let Some(bar) = self.bars.iter().find(|b| b.id == window_id) {
+            return bar.view();
+        }
         let bar = self
             .bars
             .iter()
-            .find(|b| b.id == window_id)
-            .expect("All windows are bars");
-        bar.view()
+            .find(|b| b.popup_id() == Some(window_id))
+            .expect("All windows are bars, popups or toasts");
+        bar.popup_view()
     }
 
     fn theme(&self, _window_id: window::Id) -> Theme {
@@ -197,13 +638,18 @@ impl Limbo {
                 0x2e as f32 / 255.0,
             ));
 
+        let primary = cfg
+            .theme
+            .resolve_color(&cfg.bar.theme.accent)
+            .unwrap_or(Color::from_rgb8(0x89, 0xb4, 0xfa));
+
         Theme::custom(
             "internal".to_string(),
             Palette {
                 text,
                 background,
+                primary,
                 // Unused
-                primary: Color::BLACK,
                 success: Color::BLACK,
                 danger: Color::BLACK,
             },
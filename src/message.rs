@@ -1,22 +1,172 @@
+use std::time::Duration;
+
 use iced::id::Id;
 use iced::{Event, window};
+use sctk::reexports::client::protocol::wl_surface::WlSurface;
 
-use crate::desktop_environment::{WorkspaceId, WorkspaceInfo};
+use crate::audio::AudioState;
+use crate::bluetooth::BluetoothState;
+use crate::brightness::{Backlight, Change, MonitorBrightness};
+use crate::caffeine::{CaffeineState, CaffeineTimer};
+use crate::desktop_environment::{FocusedWindow, WorkspaceId, WorkspaceInfo};
+use crate::dnd::DndState;
+use crate::github::GithubState;
+use crate::launcher::LauncherState;
+use crate::mpris::{LoopStatus, MprisState};
+use crate::network::NetworkState;
+use crate::night_light::NightLightState;
+use crate::notifications::NotificationsState;
+use crate::popup::PopupKind;
+use crate::power_profiles::{PowerProfile, PowerProfilesState};
+use crate::power_supply::BatteryState;
 use crate::sections::SysInfo;
+use crate::todo::TodoState;
+use crate::todoist::TodoistState;
+use crate::weather::WeatherState;
 
 #[derive(Debug, Clone)]
 pub enum Message {
     Iced(window::Id, Event),
+    /// Run a user-configured shell command.
+    RunCommand(String),
+    /// Open a URL in the default browser.
+    OpenUrl(String),
 
     WorkspacesChanged(Vec<WorkspaceInfo>),
+    FocusedWindowChanged(Option<FocusedWindow>),
     FocusWorkspace(WorkspaceId),
     CycleWorkspace { forward: bool },
 
     ClockToggleExpanded(Id),
     ClockTick(jiff::Zoned),
 
+    /// Open or close a popup of the bar with the given window id.
+    TogglePopup(window::Id, PopupKind),
+    /// Close the open popup of the bar with the given window id.
+    ClosePopup(window::Id),
+
     SysinfoUpdate(SysInfo),
     TrayItemsUpdate(Vec<crate::tray::TrayItem>),
+    /// Expand or collapse the inline tray drawer of the bar with the given window id.
+    TrayToggleDrawer(window::Id),
+
+    MprisUpdate(MprisState),
+    MediaPlayPause,
+    MediaNext,
+    MediaPrevious,
+    /// Show and control the next or previous media player.
+    SwitchPlayer {
+        forward: bool,
+    },
+    SeekPlayer(Duration),
+    /// The seek slider in the music popup of the bar with the given window id was dragged, the
+    /// player only seeks once it is released.
+    DragSeekPlayer(window::Id, Duration),
+    /// Set the volume of the active player, between 0.0 and 1.0.
+    SetPlayerVolume(f64),
+    SetPlayerShuffle(bool),
+    SetPlayerLoop(LoopStatus),
+    /// Advances the title marquee and the progress of the music module.
+    MusicTick,
+
+    AudioUpdate(AudioState),
+    SetSinkVolume(f32),
+    /// Change the sink volume by the given amount, from its current volume.
+    StepSinkVolume(f32),
+    SetSinkMute(bool),
+    SetSourceMute(bool),
+
+    BacklightUpdate(Option<Backlight>),
+    ChangeBacklight {
+        device: String,
+        change: Change,
+    },
+    MonitorBrightnessUpdate(Vec<MonitorBrightness>),
+    /// Change the brightness of the external monitor connected to `output`.
+    ChangeMonitorBrightness {
+        output: String,
+        change: Change,
+    },
+
+    NetworkUpdate(NetworkState),
+    /// Connect to the saved wifi network with the given SSID.
+    ConnectWifi(String),
+    DisconnectWifi,
+
+    BatteryUpdate(Option<BatteryState>),
+
+    PowerProfilesUpdate(PowerProfilesState),
+    SetPowerProfile(PowerProfile),
+
+    BluetoothUpdate(BluetoothState),
+    SetBluetoothPowered(bool),
+    /// Connect the paired device with the given object path.
+    ConnectBluetooth(String),
+    DisconnectBluetooth(String),
+
+    NightLightUpdate(NightLightState),
+    /// Switch the night light to its next mode: auto, forced, off.
+    CycleNightLight,
+
+    /// The `wl_surface` of the bar with the given window id, once it is opened.
+    BarSurface(window::Id, WlSurface),
+    CaffeineUpdate(CaffeineState),
+    /// Start the given caffeine timer, or turn caffeine off if `None`.
+    SetCaffeine(Option<CaffeineTimer>),
+    CaffeineTick,
+
+    NotificationsUpdate(NotificationsState),
+    /// Close the toast of a notification, keeping it in the history.
+    DismissNotification(u32),
+    InvokeNotificationAction {
+        id: u32,
+        key: String,
+    },
+    RemoveNotification(u32),
+    ClearNotifications,
+    DndUpdate(DndState),
+    /// Re-evaluate the do not disturb schedule.
+    DndRulesTick,
+    /// Turn do not disturb on or off, on the built-in server or the external daemon.
+    SetDnd(bool),
+
+    /// Open or close the application launcher on the first output.
+    ToggleLauncher,
+    LauncherUpdate(LauncherState),
+    /// The search text of the launcher changed.
+    LauncherQuery(String),
+    /// Launch the application with the given desktop file ID, or one of its actions.
+    LaunchApp {
+        id: String,
+        action: Option<String>,
+    },
+
+    TodoUpdate(TodoState),
+    /// The text of the new task in the todo popup changed.
+    TodoInput(String),
+    /// Add a task to the todo list, optionally starting with a priority like `(A)`.
+    AddTodo(String),
+    /// Check off or reopen the task on `line`, if it still reads `raw`.
+    SetTodoDone {
+        line: usize,
+        raw: String,
+        done: bool,
+    },
+    SetTodoPriority {
+        line: usize,
+        raw: String,
+        priority: Option<char>,
+    },
+
+    WeatherUpdate(WeatherState),
+
+    GithubUpdate(GithubState),
+
+    TodoistUpdate(TodoistState),
+    /// Complete the Todoist task with the given ID.
+    CompleteTodoistTask(String),
 
+    /// Does nothing, e.g. to keep presses from reaching the widgets below.
+    Noop,
     AnimationTick,
 }
@@ -0,0 +1,345 @@
+//! Album art from `mpris:artUrl`, with remote images downloaded into the cache directory.
+//!
+//! Art is fetched in the background, players showing none until it is ready.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use iced::Color;
+use tokio::sync::mpsc;
+
+use crate::cache;
+
+/// Number of resolved URLs kept in memory.
+const MEMORY_CACHE_SIZE: usize = 64;
+/// Number of downloaded images kept on disk, the oldest ones being removed first.
+const DISK_CACHE_SIZE: usize = 200;
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a URL that couldn't be fetched is left alone.
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Art {
+    /// Local path of the image.
+    pub path: PathBuf,
+    /// Average color of the image.
+    pub color: Option<Color>,
+}
+
+/// A fetch that finished, with the art if it succeeded.
+pub type Fetched = (String, Option<Art>);
+
+#[derive(Debug)]
+pub struct ArtCache {
+    client: reqwest::Client,
+    /// Directory remote images are downloaded into, `None` if there is no cache directory.
+    dir: Option<PathBuf>,
+    resolved: HashMap<String, Art>,
+    pending: HashSet<String>,
+    failed: HashMap<String, Instant>,
+    fetched: mpsc::UnboundedSender<Fetched>,
+}
+
+impl ArtCache {
+    /// The cache and the receiver of its finished fetches, which are to be passed to
+    /// [`ArtCache::finish`].
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<Fetched>) {
+        Self::with_dir(cache::dir().map(|dir| dir.join("art")))
+    }
+
+    fn with_dir(dir: Option<PathBuf>) -> (Self, mpsc::UnboundedReceiver<Fetched>) {
+        let (fetched, fetched_rx) = mpsc::unbounded_channel();
+        let cache = Self {
+            client: reqwest::Client::builder()
+                .timeout(DOWNLOAD_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            dir,
+            resolved: HashMap::new(),
+            pending: HashSet::new(),
+            failed: HashMap::new(),
+            fetched,
+        };
+        (cache, fetched_rx)
+    }
+
+    /// The art at `url`, a `file://` or `http(s)://` URL, if it was already fetched, otherwise
+    /// starts fetching it.
+    pub fn get(&mut self, url: &str) -> Option<Art> {
+        if let Some(art) = self.resolved.get(url) {
+            return Some(art.clone());
+        }
+        if self.pending.contains(url)
+            || self
+                .failed
+                .get(url)
+                .is_some_and(|failed| failed.elapsed() < RETRY_DELAY)
+        {
+            return None;
+        }
+
+        self.pending.insert(url.to_string());
+        let client = self.client.clone();
+        let dir = self.dir.clone();
+        let fetched = self.fetched.clone();
+        let url = url.to_string();
+        tokio::spawn(async move {
+            let art = fetch(&client, dir.as_deref(), &url).await;
+            let _ = fetched.send((url, art));
+        });
+        None
+    }
+
+    /// Records a finished fetch, returning whether it brought new art.
+    pub fn finish(&mut self, (url, art): Fetched) -> bool {
+        self.pending.remove(&url);
+        let Some(art) = art else {
+            self.failed.insert(url, Instant::now());
+            return false;
+        };
+        self.failed.remove(&url);
+        if self.resolved.len() >= MEMORY_CACHE_SIZE {
+            self.resolved.clear();
+        }
+        self.resolved.insert(url, art);
+        true
+    }
+}
+
+async fn fetch(client: &reqwest::Client, dir: Option<&Path>, url: &str) -> Option<Art> {
+    let path = if let Some(path) = url.strip_prefix("file://") {
+        let path = PathBuf::from(percent_decode(path));
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            log::debug!("mpris: art {} doesn't exist", path.display());
+            return None;
+        }
+        path
+    } else if url.starts_with("http://") || url.starts_with("https://") {
+        match download(client, dir?, url).await {
+            Ok(path) => path,
+            Err(e) => {
+                log::warn!("mpris: failed to download art from {url}: {e}");
+                return None;
+            }
+        }
+    } else {
+        return None;
+    };
+    let color = average_color(path.clone()).await;
+    Some(Art { path, color })
+}
+
+/// Downloads `url` into `dir` unless it is already there.
+async fn download(
+    client: &reqwest::Client,
+    dir: &Path,
+    url: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    let path = dir.join(cache::file_name(url));
+    if tokio::fs::try_exists(&path).await? {
+        return Ok(path);
+    }
+
+    let bytes = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    cache::write(&path, &bytes).await?;
+    if let Err(e) = prune(dir, DISK_CACHE_SIZE).await {
+        log::warn!("mpris: failed to prune {}: {e}", dir.display());
+    }
+    Ok(path)
+}
+
+/// Removes the oldest files of `dir` beyond the `keep` newest ones.
+async fn prune(dir: &Path, keep: usize) -> std::io::Result<()> {
+    let mut files = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        if metadata.is_file() {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((modified, entry.path()));
+        }
+    }
+    if files.len() <= keep {
+        return Ok(());
+    }
+    files.sort_unstable_by(|a, b| b.cmp(a));
+    for (_, path) in &files[keep..] {
+        tokio::fs::remove_file(path).await?;
+    }
+    Ok(())
+}
+
+/// Average color of the image at `path`, decoded on a blocking thread.
+async fn average_color(path: PathBuf) -> Option<Color> {
+    tokio::task::spawn_blocking(move || decode_average_color(&path))
+        .await
+        .ok()
+        .flatten()
+}
+
+fn decode_average_color(path: &Path) -> Option<Color> {
+    // Downloaded art has no extension to tell its format
+    let image = image::ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(image::ImageError::from)
+        .and_then(|reader| reader.decode());
+    let image = match image {
+        Ok(image) => image,
+        Err(e) => {
+            log::debug!("mpris: can't decode art {}: {e}", path.display());
+            return None;
+        }
+    };
+    // The thumbnail is small enough to average every pixel of
+    let thumbnail = image.thumbnail(16, 16).to_rgb8();
+    let count = thumbnail.pixels().len().max(1) as u32;
+    let [r, g, b] = thumbnail.pixels().fold([0u32; 3], |[r, g, b], pixel| {
+        [
+            r + pixel[0] as u32,
+            g + pixel[1] as u32,
+            b + pixel[2] as u32,
+        ]
+    });
+    Some(Color::from_rgb8(
+        (r / count) as u8,
+        (g / count) as u8,
+        (b / count) as u8,
+    ))
+}
+
+/// Decodes the `%XX` escapes of a URL path.
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::testing::{HttpResponse, HttpStub, TempDir};
+
+    fn red_png() -> Vec<u8> {
+        let mut png = Vec::new();
+        image::RgbImage::from_pixel(4, 4, image::Rgb([255, 0, 0]))
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    async fn next(fetched_rx: &mut mpsc::UnboundedReceiver<Fetched>) -> Fetched {
+        tokio::time::timeout(Duration::from_secs(5), fetched_rx.recv())
+            .await
+            .expect("timed out waiting for the art")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn downloads_art_in_the_background_once() {
+        let png = red_png();
+        let stub = HttpStub::start(move |request| match request.path.as_str() {
+            "/cover.png" => HttpResponse::new(200, png.clone()),
+            _ => HttpResponse::new(404, ""),
+        })
+        .await;
+        let dir = TempDir::new();
+        let url = format!("{}/cover.png", stub.url);
+
+        let (mut cache, mut fetched_rx) = ArtCache::with_dir(Some(dir.path().to_path_buf()));
+        assert_eq!(cache.get(&url), None);
+        // Asking again while it downloads doesn't download it twice
+        assert_eq!(cache.get(&url), None);
+        assert!(cache.finish(next(&mut fetched_rx).await));
+        let art = cache.get(&url).unwrap();
+        assert_eq!(art.path, dir.path().join(cache::file_name(&url)));
+        assert_eq!(art.color, Some(Color::from_rgb8(255, 0, 0)));
+
+        // Another run finds it on disk
+        let (mut cache, mut fetched_rx) = ArtCache::with_dir(Some(dir.path().to_path_buf()));
+        assert_eq!(cache.get(&url), None);
+        assert!(cache.finish(next(&mut fetched_rx).await));
+        assert_eq!(cache.get(&url), Some(art));
+        assert_eq!(stub.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn failures_are_not_cached() {
+        let stub = HttpStub::start(|_| HttpResponse::new(404, "")).await;
+        let dir = TempDir::new();
+        let url = format!("{}/missing.png", stub.url);
+
+        let (mut cache, mut fetched_rx) = ArtCache::with_dir(Some(dir.path().to_path_buf()));
+        assert_eq!(cache.get(&url), None);
+        assert!(!cache.finish(next(&mut fetched_rx).await));
+        // It is left alone for a while, but nothing was stored
+        assert_eq!(cache.get(&url), None);
+        assert!(cache.resolved.is_empty() && cache.pending.is_empty());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+        assert_eq!(stub.requests().len(), 1);
+
+        let missing = format!("file://{}/missing%20art.png", dir.path().display());
+        assert_eq!(cache.get(&missing), None);
+        assert!(!cache.finish(next(&mut fetched_rx).await));
+
+        dir.write("missing art.png", red_png());
+        cache.failed.clear();
+        assert_eq!(cache.get(&missing), None);
+        assert!(cache.finish(next(&mut fetched_rx).await));
+        let art = cache.get(&missing).unwrap();
+        assert_eq!(art.path, dir.path().join("missing art.png"));
+    }
+
+    #[tokio::test]
+    async fn prunes_the_oldest_files() {
+        let dir = TempDir::new();
+        let now = SystemTime::now();
+        for (name, age) in [("new", 0), ("old", 20), ("middle", 10)] {
+            dir.write(name, "");
+            std::fs::File::options()
+                .write(true)
+                .open(dir.path().join(name))
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+        }
+        prune(dir.path(), 2).await.unwrap();
+        let mut left: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["middle", "new"]);
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("/a%20b%2Fc%zz"), "/a b/c%zz");
+    }
+}
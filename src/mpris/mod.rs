@@ -0,0 +1,758 @@
+//! Follows and controls media players through MPRIS.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use iced::futures::{FutureExt, StreamExt};
+use tokio::sync::{Mutex, mpsc, watch};
+use zbus::fdo;
+use zbus::names::InterfaceName;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{ObjectPath, OwnedValue};
+
+use crate::message::Message;
+
+mod art;
+mod player;
+
+pub use art::Art;
+use art::ArtCache;
+use player::{
+    MPRIS_PATH, MPRIS_PREFIX, MediaPlayer2Proxy, PLAYER_INTERFACE, PlayerProxy, uncached,
+};
+
+/// Changes usually come in bursts of signals, so the state is only re-read once they settle.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+impl PlaybackStatus {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "Playing" => PlaybackStatus::Playing,
+            "Paused" => PlaybackStatus::Paused,
+            _ => PlaybackStatus::Stopped,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopStatus {
+    None,
+    Track,
+    Playlist,
+}
+
+impl LoopStatus {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "None" => Some(LoopStatus::None),
+            "Track" => Some(LoopStatus::Track),
+            "Playlist" => Some(LoopStatus::Playlist),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            LoopStatus::None => "None",
+            LoopStatus::Track => "Track",
+            LoopStatus::Playlist => "Playlist",
+        }
+    }
+
+    /// The status after this one: none, playlist, track.
+    pub fn next(self) -> Self {
+        match self {
+            LoopStatus::None => LoopStatus::Playlist,
+            LoopStatus::Playlist => LoopStatus::Track,
+            LoopStatus::Track => LoopStatus::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Player {
+    /// Bus name of the player, e.g. `org.mpris.MediaPlayer2.spotify`.
+    pub bus_name: String,
+    /// Human readable name of the player, e.g. `Spotify`.
+    pub identity: String,
+    pub status: PlaybackStatus,
+    pub title: String,
+    pub artists: Vec<String>,
+    /// Object path identifying the track, needed for seeking.
+    track_id: Option<String>,
+    pub art: Option<Art>,
+    /// Length of the track, if known.
+    pub length: Option<Duration>,
+    /// Playback position at `sampled_at`.
+    position: Duration,
+    sampled_at: Instant,
+    /// Playback speed, 1.0 being normal.
+    pub rate: f64,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub can_play_pause: bool,
+    pub can_seek: bool,
+    /// Volume of the player between 0.0 and 1.0, `None` if unsupported.
+    pub volume: Option<f64>,
+    /// `None` if the player doesn't support shuffling.
+    pub shuffle: Option<bool>,
+    /// `None` if the player doesn't support looping.
+    pub loop_status: Option<LoopStatus>,
+}
+
+// Not derived, as resampling an unchanged player must not publish it again
+impl PartialEq for Player {
+    fn eq(&self, other: &Self) -> bool {
+        let Player {
+            bus_name,
+            identity,
+            status,
+            title,
+            artists,
+            track_id,
+            art,
+            length,
+            position,
+            sampled_at: _,
+            rate,
+            can_go_next,
+            can_go_previous,
+            can_play_pause,
+            can_seek,
+            volume,
+            shuffle,
+            loop_status,
+        } = self;
+        *bus_name == other.bus_name
+            && *identity == other.identity
+            && *status == other.status
+            && *title == other.title
+            && *artists == other.artists
+            && *track_id == other.track_id
+            && *art == other.art
+            && *length == other.length
+            && *position == other.position
+            && *rate == other.rate
+            && *can_go_next == other.can_go_next
+            && *can_go_previous == other.can_go_previous
+            && *can_play_pause == other.can_play_pause
+            && *can_seek == other.can_seek
+            && *volume == other.volume
+            && *shuffle == other.shuffle
+            && *loop_status == other.loop_status
+    }
+}
+
+impl Player {
+    /// The playback position at `now`, interpolated from the last sampled position, as players
+    /// don't signal its changes.
+    pub fn position(&self, now: Instant) -> Duration {
+        let position = if self.status == PlaybackStatus::Playing {
+            self.position
+                + now
+                    .duration_since(self.sampled_at)
+                    .mul_f64(self.rate.max(0.0))
+        } else {
+            self.position
+        };
+        match self.length {
+            Some(length) => position.min(length),
+            None => position,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MprisState {
+    /// The running players, ordered by the configured priority and then by bus name.
+    pub players: Vec<Player>,
+    /// Index of the player that is shown and controlled.
+    pub active: Option<usize>,
+}
+
+impl MprisState {
+    pub fn active(&self) -> Option<&Player> {
+        self.players.get(self.active?)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MprisCommand {
+    PlayPause,
+    Next,
+    Previous,
+    /// Show and control the next or previous player.
+    Switch {
+        forward: bool,
+    },
+    /// Moves to the position in the current track.
+    Seek(Duration),
+    /// From 0 to 1.
+    SetVolume(f64),
+    SetShuffle(bool),
+    SetLoop(LoopStatus),
+}
+
+#[derive(Debug)]
+pub struct Mpris {
+    commands: mpsc::UnboundedSender<MprisCommand>,
+    rx: Arc<Mutex<watch::Receiver<MprisState>>>,
+}
+
+impl Mpris {
+    /// `priority` lists players by bus name suffix or identity, e.g. `spotify` or `mpv`. Of the
+    /// running players, the first playing one in this order is active, unless one was switched to.
+    pub fn new(priority: Vec<String>) -> Self {
+        Self::with_address(priority, None)
+    }
+
+    /// Creates the service for the players on the bus at `address`, the session bus if `None`.
+    pub fn with_address(priority: Vec<String>, address: Option<String>) -> Self {
+        let (tx, rx) = watch::channel(MprisState::default());
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(address, priority, tx, commands_rx));
+        Self {
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn send(&self, command: MprisCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "mpris".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::MprisUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+async fn run(
+    address: Option<String>,
+    priority: Vec<String>,
+    tx: watch::Sender<MprisState>,
+    mut commands_rx: mpsc::UnboundedReceiver<MprisCommand>,
+) {
+    const MIN_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    let mut backoff = MIN_BACKOFF;
+    while !tx.is_closed() {
+        match run_client(address.as_deref(), &priority, &tx, &mut commands_rx).await {
+            Ok(()) => return,
+            Err(e) => log::warn!("mpris: {e}, retrying in {}s", backoff.as_secs()),
+        }
+        tx.send_replace(MprisState::default());
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Follows the players until limbo shuts down.
+async fn run_client(
+    address: Option<&str>,
+    priority: &[String],
+    tx: &watch::Sender<MprisState>,
+    commands_rx: &mut mpsc::UnboundedReceiver<MprisCommand>,
+) -> zbus::Result<()> {
+    let builder = match address {
+        Some(address) => zbus::connection::Builder::address(address)?,
+        None => zbus::connection::Builder::session()?,
+    };
+    let connection = builder.build().await?;
+
+    let dbus = fdo::DBusProxy::new(&connection).await?;
+    let mut owners = dbus
+        .receive_name_owner_changed()
+        .await?
+        .filter(|signal| {
+            let ours = signal
+                .args()
+                .is_ok_and(|args| args.name().starts_with(MPRIS_PREFIX));
+            async move { ours }
+        })
+        .boxed();
+    // Property changes and seeks of all players
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .path(MPRIS_PATH)?
+        .build();
+    let mut signals = zbus::MessageStream::for_match_rule(rule, &connection, None).await?;
+
+    // The player picked by switching, which stays active for as long as it runs
+    let mut selected: Option<String> = None;
+    let (mut art, mut fetched_rx) = ArtCache::new();
+
+    // Whether the players have to be read again
+    let mut stale = true;
+    loop {
+        if stale {
+            let players = query(&connection, &dbus, priority, &mut art).await?;
+            // Pausing the active player mustn't make another one active
+            let previous = tx.borrow().active().map(|p| p.bus_name.clone());
+            let find = |bus_name: &Option<String>| {
+                let bus_name = bus_name.as_ref()?;
+                players.iter().position(|p| p.bus_name == *bus_name)
+            };
+            let active = find(&selected)
+                .or_else(|| {
+                    players
+                        .iter()
+                        .position(|p| p.status == PlaybackStatus::Playing)
+                })
+                .or_else(|| find(&previous))
+                .or_else(|| (!players.is_empty()).then_some(0));
+            let state = MprisState { players, active };
+            tx.send_if_modified(|current| {
+                let modified = *current != state;
+                *current = state;
+                modified
+            });
+        }
+
+        stale = tokio::select! {
+            signal = signals.next() => {
+                if signal.is_none() {
+                    return Ok(());
+                }
+                tokio::time::sleep(DEBOUNCE).await;
+                // Drain the burst that arrived in the meantime
+                while let Some(Some(_)) = signals.next().now_or_never() {}
+                true
+            }
+            // A player started or exited
+            Some(_) = owners.next() => true,
+            // Art that couldn't be fetched changes nothing
+            Some(fetched) = fetched_rx.recv() => art.finish(fetched),
+            command = commands_rx.recv() => {
+                let Some(command) = command else {
+                    return Ok(());
+                };
+                let state = tx.borrow().clone();
+                if let MprisCommand::Switch { forward } = command {
+                    selected = switch(&state, forward);
+                } else if let Some(player) = state.active()
+                    && let Err(e) = apply(&connection, player, &command).await
+                {
+                    let name = &player.bus_name;
+                    log::warn!("mpris: failed to apply {command:?} to {name}: {e}");
+                }
+                true
+            }
+        };
+    }
+}
+
+/// Bus name of the player after or before the active one, wrapping around.
+fn switch(state: &MprisState, forward: bool) -> Option<String> {
+    let len = state.players.len();
+    let active = state.active?;
+    let index = if forward {
+        (active + 1) % len
+    } else {
+        (active + len - 1) % len
+    };
+    Some(state.players[index].bus_name.clone())
+}
+
+async fn apply(
+    connection: &zbus::Connection,
+    state: &Player,
+    command: &MprisCommand,
+) -> zbus::Result<()> {
+    let player = uncached::<PlayerProxy>(connection, &state.bus_name).await?;
+    match command {
+        MprisCommand::PlayPause => player.play_pause().await,
+        MprisCommand::Next => player.next().await,
+        MprisCommand::Previous => player.previous().await,
+        MprisCommand::Switch { .. } => Ok(()),
+        MprisCommand::Seek(position) => {
+            let Some(track_id) = &state.track_id else {
+                return Err(zbus::Error::Failure("the track has no id".to_string()));
+            };
+            let position = position.as_micros() as i64;
+            player
+                .set_position(&ObjectPath::try_from(track_id.as_str())?, position)
+                .await
+        }
+        MprisCommand::SetVolume(volume) => player.set_volume(volume.clamp(0.0, 1.0)).await,
+        MprisCommand::SetShuffle(shuffle) => player.set_shuffle(*shuffle).await,
+        MprisCommand::SetLoop(status) => player.set_loop_status(status.name()).await,
+    }
+}
+
+async fn query(
+    connection: &zbus::Connection,
+    dbus: &fdo::DBusProxy<'_>,
+    priority: &[String],
+    art: &mut ArtCache,
+) -> zbus::Result<Vec<Player>> {
+    let mut players = Vec::new();
+    for name in dbus.list_names().await? {
+        if !name.starts_with(MPRIS_PREFIX) {
+            continue;
+        }
+        match query_player(connection, name.as_str(), art).await {
+            Ok(player) => players.push(player),
+            Err(e) => log::debug!("mpris: ignoring {name}: {e}"),
+        }
+    }
+    players.sort_by_cached_key(|player| (rank(player, priority), player.bus_name.clone()));
+    Ok(players)
+}
+
+async fn query_player(
+    connection: &zbus::Connection,
+    bus_name: &str,
+    art: &mut ArtCache,
+) -> zbus::Result<Player> {
+    // Reading the properties one by one takes a dozen round trips per player
+    let properties = fdo::PropertiesProxy::builder(connection)
+        .destination(bus_name)?
+        .path(MPRIS_PATH)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    let mut all = properties
+        .get_all(InterfaceName::from_static_str_unchecked(PLAYER_INTERFACE))
+        .await?;
+    let status = property::<String>(&mut all, "PlaybackStatus")
+        .ok_or_else(|| zbus::Error::Failure("the player has no playback status".to_string()))?;
+    let metadata =
+        property::<HashMap<String, OwnedValue>>(&mut all, "Metadata").unwrap_or_default();
+    let identity = match uncached::<MediaPlayer2Proxy>(connection, bus_name).await {
+        Ok(root) => root.identity().await.ok(),
+        Err(_) => None,
+    };
+    let art = match string(&metadata, "mpris:artUrl") {
+        Some(url) => art.get(&url),
+        None => None,
+    };
+
+    Ok(Player {
+        bus_name: bus_name.to_string(),
+        identity: identity.unwrap_or_else(|| bus_name.trim_start_matches(MPRIS_PREFIX).to_string()),
+        status: PlaybackStatus::from_name(&status),
+        title: string(&metadata, "xesam:title").unwrap_or_default(),
+        artists: metadata
+            .get("xesam:artist")
+            .and_then(|v| v.try_clone().ok())
+            .and_then(|v| Vec::<String>::try_from(v).ok())
+            .unwrap_or_default(),
+        track_id: metadata
+            .get("mpris:trackid")
+            .and_then(|v| v.downcast_ref::<ObjectPath>().ok())
+            .map(|path| path.to_string()),
+        art,
+        length: metadata
+            .get("mpris:length")
+            .and_then(|v| {
+                // Some players send the length unsigned, against the spec
+                v.downcast_ref::<i64>()
+                    .ok()
+                    .or_else(|| v.downcast_ref::<u64>().ok().map(|length| length as i64))
+            })
+            .filter(|length| *length > 0)
+            .map(|length| Duration::from_micros(length as u64)),
+        // Not every player supports reading the position
+        position: Duration::from_micros(
+            property::<i64>(&mut all, "Position").unwrap_or(0).max(0) as u64
+        ),
+        sampled_at: Instant::now(),
+        rate: property(&mut all, "Rate").unwrap_or(1.0),
+        can_go_next: property(&mut all, "CanGoNext").unwrap_or(false),
+        can_go_previous: property(&mut all, "CanGoPrevious").unwrap_or(false),
+        can_play_pause: property(&mut all, "CanPlay").unwrap_or(false)
+            || property(&mut all, "CanPause").unwrap_or(false),
+        can_seek: property(&mut all, "CanSeek").unwrap_or(false),
+        volume: property(&mut all, "Volume"),
+        shuffle: property(&mut all, "Shuffle"),
+        loop_status: property::<String>(&mut all, "LoopStatus")
+            .and_then(|name| LoopStatus::from_name(&name)),
+    })
+}
+
+/// Takes the property `name` out of the ones read with `GetAll`, if it has the expected type.
+fn property<T>(properties: &mut HashMap<String, OwnedValue>, name: &str) -> Option<T>
+where
+    T: TryFrom<OwnedValue>,
+{
+    properties
+        .remove(name)
+        .and_then(|value| T::try_from(value).ok())
+}
+
+/// Position of `player` in `priority`, players that aren't listed coming last.
+///
+/// Entries match the bus name suffix, ignoring instance suffixes like in
+/// `firefox.instance_1_42`, or the identity, both case-insensitively.
+fn rank(player: &Player, priority: &[String]) -> usize {
+    let name = player.bus_name.trim_start_matches(MPRIS_PREFIX);
+    let base = name.split('.').next().unwrap_or(name);
+    priority
+        .iter()
+        .position(|entry| {
+            entry.eq_ignore_ascii_case(name)
+                || entry.eq_ignore_ascii_case(base)
+                || entry.eq_ignore_ascii_case(&player.identity)
+        })
+        .unwrap_or(priority.len())
+}
+
+fn string(metadata: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    metadata
+        .get(key)
+        .and_then(|v| v.downcast_ref::<&str>().ok())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use zbus::object_server::SignalEmitter;
+    use zbus::zvariant::Value;
+
+    use super::*;
+    use crate::testing::PrivateBus;
+
+    struct FakeRoot {
+        identity: &'static str,
+    }
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2")]
+    impl FakeRoot {
+        #[zbus(property)]
+        fn identity(&self) -> &str {
+            self.identity
+        }
+    }
+
+    struct FakePlayer {
+        title: &'static str,
+        playing: AtomicBool,
+    }
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        async fn play_pause(
+            &self,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> fdo::Result<()> {
+            self.playing.fetch_xor(true, Ordering::SeqCst);
+            self.playback_status_changed(&emitter).await?;
+            Ok(())
+        }
+
+        #[zbus(property)]
+        fn playback_status(&self) -> &str {
+            if self.playing.load(Ordering::SeqCst) {
+                "Playing"
+            } else {
+                "Paused"
+            }
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            let entries = [
+                ("xesam:title", Value::from(self.title)),
+                ("xesam:artist", Value::from(vec!["Someone", "Someone Else"])),
+                (
+                    "mpris:trackid",
+                    Value::from(ObjectPath::try_from("/track/1").unwrap()),
+                ),
+                ("mpris:length", Value::from(180_000_000i64)),
+            ];
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), OwnedValue::try_from(value).unwrap()))
+                .collect()
+        }
+
+        #[zbus(property)]
+        fn position(&self) -> i64 {
+            42_000_000
+        }
+
+        #[zbus(property)]
+        fn can_go_next(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn loop_status(&self) -> &str {
+            "Playlist"
+        }
+    }
+
+    /// Runs a player owning `org.mpris.MediaPlayer2.{name}` on `bus`.
+    async fn start_player(
+        bus: &PrivateBus,
+        name: &str,
+        identity: &'static str,
+        title: &'static str,
+    ) -> zbus::Connection {
+        let connection = bus.connection().await;
+        let server = connection.object_server();
+        server.at(MPRIS_PATH, FakeRoot { identity }).await.unwrap();
+        let player = FakePlayer {
+            title,
+            playing: AtomicBool::new(false),
+        };
+        server.at(MPRIS_PATH, player).await.unwrap();
+        connection
+            .request_name(format!("{MPRIS_PREFIX}{name}"))
+            .await
+            .unwrap();
+        connection
+    }
+
+    async fn wait_until(
+        rx: &mut watch::Receiver<MprisState>,
+        done: impl Fn(&MprisState) -> bool,
+    ) -> MprisState {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let state = rx.borrow_and_update().clone();
+                if done(&state) {
+                    return state;
+                }
+                rx.changed().await.unwrap();
+            }
+        })
+        .await
+        .expect("timed out waiting for the mpris state")
+    }
+
+    #[tokio::test]
+    async fn reads_and_controls_players() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let _first = start_player(&bus, "first", "First", "Song").await;
+        let _second = start_player(&bus, "second.instance_1", "Second", "Other Song").await;
+
+        let mpris = Mpris::with_address(vec!["second".to_string()], Some(bus.address.clone()));
+        let mut rx = mpris.rx.lock().await.clone();
+        let state = wait_until(&mut rx, |state| state.players.len() == 2).await;
+        // Neither plays, so the preferred one is active
+        let player = state.active().unwrap();
+        assert_eq!(player.bus_name, "org.mpris.MediaPlayer2.second.instance_1");
+        assert_eq!(player.identity, "Second");
+        assert_eq!(player.status, PlaybackStatus::Paused);
+        assert_eq!(player.title, "Other Song");
+        assert_eq!(player.artists, ["Someone", "Someone Else"]);
+        assert_eq!(player.track_id.as_deref(), Some("/track/1"));
+        assert_eq!(player.length, Some(Duration::from_secs(180)));
+        assert_eq!(player.position, Duration::from_secs(42));
+        assert!(player.can_go_next);
+        assert!(!player.can_go_previous);
+        assert_eq!(player.volume, None);
+        assert_eq!(player.loop_status, Some(LoopStatus::Playlist));
+
+        mpris.send(MprisCommand::Switch { forward: true });
+        let state = wait_until(&mut rx, |state| state.active == Some(1)).await;
+        assert_eq!(state.active().unwrap().title, "Song");
+
+        // The signal of the player's change is followed
+        mpris.send(MprisCommand::PlayPause);
+        let state = wait_until(&mut rx, |state| {
+            state.players[1].status == PlaybackStatus::Playing
+        })
+        .await;
+        assert_eq!(state.active, Some(1));
+    }
+
+    #[test]
+    fn ignores_when_players_were_sampled() {
+        let player = Player {
+            bus_name: "org.mpris.MediaPlayer2.test".to_string(),
+            identity: "Test".to_string(),
+            status: PlaybackStatus::Paused,
+            title: "Song".to_string(),
+            artists: Vec::new(),
+            track_id: None,
+            art: None,
+            length: None,
+            position: Duration::from_secs(1),
+            sampled_at: Instant::now(),
+            rate: 1.0,
+            can_go_next: false,
+            can_go_previous: false,
+            can_play_pause: true,
+            can_seek: false,
+            volume: None,
+            shuffle: None,
+            loop_status: None,
+        };
+        let resampled = Player {
+            sampled_at: player.sampled_at + Duration::from_secs(1),
+            ..player.clone()
+        };
+        assert_eq!(player, resampled);
+        assert_ne!(
+            player,
+            Player {
+                position: Duration::from_secs(2),
+                ..resampled
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn a_playing_player_becomes_active() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let _first = start_player(&bus, "first", "First", "Song").await;
+        let mpris = Mpris::with_address(Vec::new(), Some(bus.address.clone()));
+        let mut rx = mpris.rx.lock().await.clone();
+        wait_until(&mut rx, |state| state.active == Some(0)).await;
+
+        let second = start_player(&bus, "second", "Second", "Other Song").await;
+        wait_until(&mut rx, |state| state.players.len() == 2).await;
+        let player = PlayerProxy::builder(&second)
+            .destination("org.mpris.MediaPlayer2.second")
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        player.play_pause().await.unwrap();
+        let state = wait_until(&mut rx, |state| state.active == Some(1)).await;
+        assert_eq!(state.active().unwrap().identity, "Second");
+
+        // Pausing it keeps it active
+        player.play_pause().await.unwrap();
+        let state = wait_until(&mut rx, |state| {
+            state.players[1].status == PlaybackStatus::Paused
+        })
+        .await;
+        assert_eq!(state.active, Some(1));
+
+        drop(player);
+        drop(second);
+        let state = wait_until(&mut rx, |state| state.players.len() == 1).await;
+        assert_eq!(state.active, Some(0));
+    }
+}
@@ -0,0 +1,98 @@
+//! Proxies for the MPRIS D-Bus interfaces.
+//!
+//! See <https://specifications.freedesktop.org/mpris-spec/latest/>
+
+use std::collections::HashMap;
+
+use zbus::proxy;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{ObjectPath, OwnedValue};
+
+/// Prefix of the bus names of all MPRIS players.
+pub const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+pub const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+pub const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+pub trait MediaPlayer2 {
+    #[zbus(property)]
+    fn identity(&self) -> zbus::Result<String>;
+}
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+pub trait Player {
+    fn next(&self) -> zbus::Result<()>;
+
+    fn previous(&self) -> zbus::Result<()>;
+
+    fn play_pause(&self) -> zbus::Result<()>;
+
+    /// Seeks `track_id` to `position` in microseconds.
+    fn set_position(&self, track_id: &ObjectPath<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+
+    /// In microseconds.
+    #[zbus(property)]
+    fn position(&self) -> zbus::Result<i64>;
+
+    #[zbus(property)]
+    fn rate(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn volume(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn set_volume(&self, volume: f64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn shuffle(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn set_shuffle(&self, shuffle: bool) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn loop_status(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn set_loop_status(&self, loop_status: &str) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_play(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_pause(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_seek(&self) -> zbus::Result<bool>;
+}
+
+/// Builds a proxy for the player owning `bus_name` without caching its properties.
+///
+/// Players don't emit changes of `Position`, so cached values would go stale.
+pub async fn uncached<'a, P>(connection: &zbus::Connection, bus_name: &'a str) -> zbus::Result<P>
+where
+    P: proxy::Defaults + From<zbus::Proxy<'a>>,
+{
+    zbus::proxy::Builder::<P>::new(connection)
+        .destination(bus_name)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+}
@@ -0,0 +1,599 @@
+//! Follows the network state through NetworkManager.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use iced::futures::{FutureExt, StreamExt};
+use tokio::sync::{Mutex, mpsc, watch};
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+
+use crate::message::Message;
+
+mod nm;
+
+use nm::{
+    AccessPointProxy, ConnectionProxy, DeviceProxy, NetworkManagerProxy, SettingsProxy,
+    WirelessProxy, uncached,
+};
+
+/// Changes usually come in bursts of signals, so the state is only re-read once they settle.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionKind {
+    Wifi,
+    Ethernet,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessPoint {
+    pub ssid: String,
+    /// Signal quality in percent.
+    pub strength: u8,
+    pub secured: bool,
+    /// Whether this is the network the wifi device is currently connected to.
+    pub active: bool,
+    /// Whether there is a saved connection for this network.
+    pub saved: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NetworkState {
+    /// Type of the primary connection, `None` when offline or NetworkManager isn't running.
+    pub primary: Option<ConnectionKind>,
+    /// Signal quality of the active access point in percent.
+    pub wifi_strength: Option<u8>,
+    /// Whether there is a wifi device.
+    pub has_wifi: bool,
+    /// Networks in range, the active one first and then by signal quality.
+    pub access_points: Vec<AccessPoint>,
+}
+
+#[derive(Debug, Clone)]
+pub enum NetworkCommand {
+    /// Activate the saved connection of the network with this SSID.
+    Connect(String),
+    /// Deactivate the wifi connection.
+    Disconnect,
+}
+
+#[derive(Debug)]
+pub struct Network {
+    commands: mpsc::UnboundedSender<NetworkCommand>,
+    rx: Arc<Mutex<watch::Receiver<NetworkState>>>,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self::with_address(None)
+    }
+
+    /// Creates the service for the NetworkManager instance on the bus at `address`, the system
+    /// bus if `None`.
+    pub fn with_address(address: Option<String>) -> Self {
+        let (tx, rx) = watch::channel(NetworkState::default());
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(address, tx, commands_rx));
+        Self {
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn send(&self, command: NetworkCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "network".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::NetworkUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+async fn run(
+    address: Option<String>,
+    tx: watch::Sender<NetworkState>,
+    mut commands_rx: mpsc::UnboundedReceiver<NetworkCommand>,
+) {
+    const MIN_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    let mut backoff = MIN_BACKOFF;
+    while !tx.is_closed() {
+        match run_client(address.as_deref(), &tx, &mut commands_rx).await {
+            Ok(()) => {
+                log::info!("network: connection closed, reconnecting");
+                backoff = MIN_BACKOFF;
+            }
+            Err(e) => log::warn!("network: {e}, retrying in {}s", backoff.as_secs()),
+        }
+        tx.send_replace(NetworkState::default());
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+async fn run_client(
+    address: Option<&str>,
+    tx: &watch::Sender<NetworkState>,
+    commands_rx: &mut mpsc::UnboundedReceiver<NetworkCommand>,
+) -> zbus::Result<()> {
+    let builder = match address {
+        Some(address) => zbus::connection::Builder::address(address)?,
+        None => zbus::connection::Builder::system()?,
+    };
+    let connection = builder.build().await?;
+
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .sender(nm::NM_SERVICE)?
+        .build();
+    let mut signals = zbus::MessageStream::for_match_rule(rule, &connection, None).await?;
+
+    let mut cache = Cache::default();
+    loop {
+        let state = query(&connection, &mut cache).await?;
+        tx.send_if_modified(|current| {
+            let modified = *current != state;
+            *current = state;
+            modified
+        });
+
+        tokio::select! {
+            signal = signals.next() => {
+                let Some(signal) = signal else {
+                    return Ok(());
+                };
+                if let Ok(signal) = signal {
+                    cache.invalidate(&signal);
+                }
+                tokio::time::sleep(DEBOUNCE).await;
+                // Drain the burst that arrived in the meantime
+                while let Some(Some(signal)) = signals.next().now_or_never() {
+                    if let Ok(signal) = signal {
+                        cache.invalidate(&signal);
+                    }
+                }
+            }
+            Some(command) = commands_rx.recv() => {
+                if let Err(e) = apply(&connection, &command).await {
+                    log::warn!("network: failed to apply {command:?}: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// What is costly to re-read after every change: the properties of every access point in range
+/// and the settings of every saved connection.
+#[derive(Debug, Default)]
+struct Cache {
+    /// Access points by path, `None` for hidden networks.
+    access_points: HashMap<OwnedObjectPath, Option<AccessPoint>>,
+    /// Saved wifi connections by SSID, `None` until read.
+    saved: Option<HashMap<String, OwnedObjectPath>>,
+}
+
+impl Cache {
+    /// Forgets what `signal` reports a change of.
+    fn invalidate(&mut self, signal: &zbus::Message) {
+        let header = signal.header();
+        let Some(interface) = header.interface() else {
+            return;
+        };
+        match interface.as_str() {
+            // Connections added or removed, or their settings updated
+            "org.freedesktop.NetworkManager.Settings"
+            | "org.freedesktop.NetworkManager.Settings.Connection" => self.saved = None,
+            // Property changes, of which only the ones of access points are cached
+            "org.freedesktop.DBus.Properties" | "org.freedesktop.NetworkManager.AccessPoint" => {
+                if let Some(path) = header.path() {
+                    self.access_points
+                        .remove(&OwnedObjectPath::from(path.to_owned()));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+async fn query(connection: &zbus::Connection, cache: &mut Cache) -> zbus::Result<NetworkState> {
+    let nm = NetworkManagerProxy::builder(connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+
+    let primary = match nm.primary_connection_type().await?.as_str() {
+        "" => None,
+        "802-11-wireless" => Some(ConnectionKind::Wifi),
+        "802-3-ethernet" => Some(ConnectionKind::Ethernet),
+        _ => Some(ConnectionKind::Other),
+    };
+    let saved = match &mut cache.saved {
+        Some(saved) => saved,
+        saved @ None => saved.insert(saved_connections(connection).await?),
+    };
+
+    let devices = wifi_devices(connection, &nm).await?;
+    let mut wifi_strength = None;
+    let mut access_points = Vec::new();
+    let mut in_range = HashSet::new();
+    for device in &devices {
+        let wireless = uncached::<WirelessProxy>(connection, device).await?;
+        let active_path = wireless.active_access_point().await?;
+        for path in wireless.access_points().await? {
+            in_range.insert(path.clone());
+            let cached = match cache.access_points.get(&path) {
+                Some(cached) => cached.clone(),
+                // Access points may vanish while they are being read
+                None => match access_point(connection, &path).await {
+                    Ok(ap) => cache
+                        .access_points
+                        .entry(path.clone())
+                        .or_insert(ap)
+                        .clone(),
+                    Err(_) => continue,
+                },
+            };
+            let Some(mut ap) = cached else {
+                continue;
+            };
+            ap.active = path == active_path;
+            ap.saved = saved.contains_key(&ap.ssid);
+            if ap.active {
+                wifi_strength = Some(ap.strength);
+            }
+            access_points.push(ap);
+        }
+    }
+
+    cache
+        .access_points
+        .retain(|path, _| in_range.contains(path));
+
+    access_points.sort_by(|a, b| b.active.cmp(&a.active).then(b.strength.cmp(&a.strength)));
+    // Networks are often made up of several access points, only keep the best one
+    let mut seen = HashSet::new();
+    access_points.retain(|ap| seen.insert(ap.ssid.clone()));
+
+    Ok(NetworkState {
+        primary,
+        wifi_strength,
+        has_wifi: !devices.is_empty(),
+        access_points,
+    })
+}
+
+/// Reads an access point, `None` if it belongs to a hidden network.
+async fn access_point(
+    connection: &zbus::Connection,
+    path: &OwnedObjectPath,
+) -> zbus::Result<Option<AccessPoint>> {
+    let ap = uncached::<AccessPointProxy>(connection, path).await?;
+    let ssid = String::from_utf8_lossy(&ap.ssid().await?).into_owned();
+    if ssid.is_empty() {
+        return Ok(None);
+    }
+    let secured = ap.flags().await? & nm::AP_FLAGS_PRIVACY != 0
+        || ap.wpa_flags().await? != 0
+        || ap.rsn_flags().await? != 0;
+    Ok(Some(AccessPoint {
+        ssid,
+        strength: ap.strength().await?,
+        secured,
+        active: false,
+        saved: false,
+    }))
+}
+
+async fn wifi_devices(
+    connection: &zbus::Connection,
+    nm: &NetworkManagerProxy<'_>,
+) -> zbus::Result<Vec<OwnedObjectPath>> {
+    let mut devices = Vec::new();
+    for path in nm.devices().await? {
+        let device = uncached::<DeviceProxy>(connection, &path).await?;
+        if device.device_type().await? == nm::DEVICE_TYPE_WIFI {
+            devices.push(path);
+        }
+    }
+    Ok(devices)
+}
+
+/// Saved wifi connections by SSID.
+async fn saved_connections(
+    connection: &zbus::Connection,
+) -> zbus::Result<HashMap<String, OwnedObjectPath>> {
+    let settings = SettingsProxy::builder(connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+
+    let mut connections = HashMap::new();
+    for path in settings.list_connections().await? {
+        let proxy = uncached::<ConnectionProxy>(connection, &path).await?;
+        let Ok(settings) = proxy.get_settings().await else {
+            continue;
+        };
+        let ssid = settings
+            .get("802-11-wireless")
+            .and_then(|wireless| wireless.get("ssid"))
+            .and_then(|ssid| ssid.try_clone().ok())
+            .and_then(|ssid| Vec::<u8>::try_from(ssid).ok());
+        if let Some(ssid) = ssid {
+            connections.insert(String::from_utf8_lossy(&ssid).into_owned(), path);
+        }
+    }
+    Ok(connections)
+}
+
+async fn apply(connection: &zbus::Connection, command: &NetworkCommand) -> zbus::Result<()> {
+    let nm = NetworkManagerProxy::builder(connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    let devices = wifi_devices(connection, &nm).await?;
+
+    match command {
+        NetworkCommand::Connect(ssid) => {
+            let Some(saved) = saved_connections(connection).await?.remove(ssid) else {
+                return Ok(());
+            };
+            let Some(device) = devices.first() else {
+                return Ok(());
+            };
+            // "/" lets NetworkManager pick the best access point of the network
+            nm.activate_connection(&saved, device, &ObjectPath::from_static_str_unchecked("/"))
+                .await?;
+        }
+        NetworkCommand::Disconnect => {
+            for device in devices {
+                let active = uncached::<DeviceProxy>(connection, &device)
+                    .await?
+                    .active_connection()
+                    .await?;
+                if active.as_str() != "/" {
+                    nm.deactivate_connection(&active).await?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use zbus::object_server::SignalEmitter;
+    use zbus::zvariant::OwnedValue;
+
+    use super::*;
+    use crate::testing::PrivateBus;
+
+    const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+    const DEVICE_PATH: &str = "/org/freedesktop/NetworkManager/Devices/1";
+    const CONNECTION_PATH: &str = "/org/freedesktop/NetworkManager/Settings/1";
+
+    fn ap_path(i: usize) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(format!("/org/freedesktop/NetworkManager/AccessPoint/{i}"))
+            .unwrap()
+    }
+
+    fn path(path: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(path).unwrap()
+    }
+
+    struct FakeNetworkManager;
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager")]
+    impl FakeNetworkManager {
+        fn activate_connection(
+            &self,
+            connection: OwnedObjectPath,
+            _device: OwnedObjectPath,
+            _specific_object: OwnedObjectPath,
+        ) -> OwnedObjectPath {
+            connection
+        }
+
+        fn deactivate_connection(&self, _active_connection: OwnedObjectPath) {}
+
+        #[zbus(property)]
+        fn devices(&self) -> Vec<OwnedObjectPath> {
+            vec![path(DEVICE_PATH)]
+        }
+
+        #[zbus(property)]
+        fn primary_connection_type(&self) -> String {
+            "802-11-wireless".to_string()
+        }
+    }
+
+    struct FakeDevice;
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Device")]
+    impl FakeDevice {
+        #[zbus(property)]
+        fn device_type(&self) -> u32 {
+            nm::DEVICE_TYPE_WIFI
+        }
+
+        #[zbus(property)]
+        fn active_connection(&self) -> OwnedObjectPath {
+            path("/")
+        }
+    }
+
+    struct FakeWireless;
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Device.Wireless")]
+    impl FakeWireless {
+        #[zbus(property)]
+        fn access_points(&self) -> Vec<OwnedObjectPath> {
+            (0..3).map(ap_path).collect()
+        }
+
+        #[zbus(property)]
+        fn active_access_point(&self) -> OwnedObjectPath {
+            ap_path(0)
+        }
+    }
+
+    struct FakeAccessPoint {
+        ssid: &'static str,
+        strength: u8,
+        secured: bool,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+    impl FakeAccessPoint {
+        #[zbus(property)]
+        fn ssid(&self) -> Vec<u8> {
+            self.ssid.as_bytes().to_vec()
+        }
+
+        #[zbus(property)]
+        fn strength(&self) -> u8 {
+            self.strength
+        }
+
+        #[zbus(property)]
+        fn flags(&self) -> u32 {
+            0
+        }
+
+        #[zbus(property)]
+        fn wpa_flags(&self) -> u32 {
+            0
+        }
+
+        #[zbus(property)]
+        fn rsn_flags(&self) -> u32 {
+            if self.secured { 0x100 } else { 0 }
+        }
+    }
+
+    struct FakeSettings;
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Settings")]
+    impl FakeSettings {
+        fn list_connections(&self) -> Vec<OwnedObjectPath> {
+            vec![path(CONNECTION_PATH)]
+        }
+    }
+
+    /// A saved connection of the "home" network, counting how often its settings are read.
+    struct FakeConnection(Arc<AtomicUsize>);
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Settings.Connection")]
+    impl FakeConnection {
+        fn get_settings(&self) -> HashMap<String, HashMap<String, OwnedValue>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let ssid = OwnedValue::try_from(zbus::zvariant::Value::from(b"home".to_vec())).unwrap();
+            HashMap::from([(
+                "802-11-wireless".to_string(),
+                HashMap::from([("ssid".to_string(), ssid)]),
+            )])
+        }
+    }
+
+    async fn next_state(rx: &mut watch::Receiver<NetworkState>) -> NetworkState {
+        tokio::time::timeout(Duration::from_secs(5), rx.changed())
+            .await
+            .expect("timed out waiting for the network state")
+            .unwrap();
+        rx.borrow_and_update().clone()
+    }
+
+    #[tokio::test]
+    async fn follows_access_points_and_reads_settings_once() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let nm = bus.connection().await;
+        let server = nm.object_server();
+        server.at(NM_PATH, FakeNetworkManager).await.unwrap();
+        server.at(DEVICE_PATH, FakeDevice).await.unwrap();
+        server.at(DEVICE_PATH, FakeWireless).await.unwrap();
+        let access_points = [("home", 70, true), ("cafe", 40, false), ("", 90, true)];
+        for (i, (ssid, strength, secured)) in access_points.into_iter().enumerate() {
+            let ap = FakeAccessPoint {
+                ssid,
+                strength,
+                secured,
+            };
+            server.at(ap_path(i), ap).await.unwrap();
+        }
+        server
+            .at("/org/freedesktop/NetworkManager/Settings", FakeSettings)
+            .await
+            .unwrap();
+        let reads = Arc::new(AtomicUsize::new(0));
+        server
+            .at(CONNECTION_PATH, FakeConnection(reads.clone()))
+            .await
+            .unwrap();
+        nm.request_name(nm::NM_SERVICE).await.unwrap();
+
+        let network = Network::with_address(Some(bus.address.clone()));
+        let mut rx = network.rx.lock().await.clone();
+        let state = next_state(&mut rx).await;
+        // The hidden network is left out
+        assert_eq!(
+            state,
+            NetworkState {
+                primary: Some(ConnectionKind::Wifi),
+                wifi_strength: Some(70),
+                has_wifi: true,
+                access_points: vec![
+                    AccessPoint {
+                        ssid: "home".to_string(),
+                        strength: 70,
+                        secured: true,
+                        active: true,
+                        saved: true,
+                    },
+                    AccessPoint {
+                        ssid: "cafe".to_string(),
+                        strength: 40,
+                        secured: false,
+                        active: false,
+                        saved: false,
+                    },
+                ],
+            }
+        );
+
+        // Only the access point that changed is read again
+        let cafe = server
+            .interface::<_, FakeAccessPoint>(ap_path(1))
+            .await
+            .unwrap();
+        cafe.get_mut().await.strength = 80;
+        let emitter = SignalEmitter::new(&nm, ap_path(1)).unwrap();
+        cafe.get().await.strength_changed(&emitter).await.unwrap();
+
+        let state = next_state(&mut rx).await;
+        // The active network stays first
+        assert_eq!(state.access_points[1].ssid, "cafe");
+        assert_eq!(state.access_points[1].strength, 80);
+        assert_eq!(state.wifi_strength, Some(70));
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,119 @@
+//! Proxies for the parts of the NetworkManager D-Bus API used by limbo.
+//!
+//! See <https://networkmanager.dev/docs/api/latest/spec.html>
+
+use std::collections::HashMap;
+
+use zbus::proxy;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
+
+pub const NM_SERVICE: &str = "org.freedesktop.NetworkManager";
+
+/// `NM_DEVICE_TYPE_WIFI`
+pub const DEVICE_TYPE_WIFI: u32 = 2;
+/// `NM_802_11_AP_FLAGS_PRIVACY`
+pub const AP_FLAGS_PRIVACY: u32 = 0x1;
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+pub trait NetworkManager {
+    fn activate_connection(
+        &self,
+        connection: &ObjectPath<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    fn deactivate_connection(&self, active_connection: &ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    #[zbus(property)]
+    fn primary_connection_type(&self) -> zbus::Result<String>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Device {
+    #[zbus(property)]
+    fn device_type(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn active_connection(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device.Wireless",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Wireless {
+    #[zbus(property)]
+    fn access_points(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    #[zbus(property)]
+    fn active_access_point(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.AccessPoint",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait AccessPoint {
+    #[zbus(property)]
+    fn ssid(&self) -> zbus::Result<Vec<u8>>;
+
+    /// Signal quality in percent.
+    #[zbus(property)]
+    fn strength(&self) -> zbus::Result<u8>;
+
+    #[zbus(property)]
+    fn flags(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn wpa_flags(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn rsn_flags(&self) -> zbus::Result<u32>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager/Settings"
+)]
+pub trait Settings {
+    fn list_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings.Connection",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Connection {
+    fn get_settings(&self) -> zbus::Result<HashMap<String, HashMap<String, OwnedValue>>>;
+}
+
+/// Creates a proxy for the object at `path` that doesn't cache properties.
+///
+/// The service follows the changes itself, and caching would subscribe to the property changes of
+/// every object, which NetworkManager emits a lot of.
+pub async fn uncached<'a, P>(
+    connection: &zbus::Connection,
+    path: &OwnedObjectPath,
+) -> zbus::Result<P>
+where
+    P: proxy::Defaults + From<zbus::Proxy<'a>>,
+{
+    zbus::proxy::Builder::<P>::new(connection)
+        .path((**path).clone())?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+}
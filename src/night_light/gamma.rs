@@ -0,0 +1,86 @@
+//! Color temperature to gamma ramp conversion.
+
+/// Temperature at which the display is left unchanged.
+pub const NEUTRAL_TEMPERATURE: u32 = 6500;
+
+/// Approximate color of a black body at `kelvin`, with each channel in `0.0..=1.0`.
+///
+/// See <https://tannerhelland.com/2012/09/18/convert-temperature-rgb-algorithm-code.html>
+fn blackbody(kelvin: u32) -> [f64; 3] {
+    let t = kelvin.clamp(1000, 40000) as f64 / 100.0;
+
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (t - 60.0).powf(-0.1332047592)
+    };
+    let green = if t <= 66.0 {
+        99.4708025861 * t.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (t - 60.0).powf(-0.0755148492)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (t - 10.0).ln() - 305.0447927307
+    };
+
+    [red, green, blue].map(|c| (c / 255.0).clamp(0.0, 1.0))
+}
+
+/// White point for `kelvin`, relative to [`NEUTRAL_TEMPERATURE`] so that it leaves the display
+/// unchanged.
+pub fn white_point(kelvin: u32) -> [f64; 3] {
+    let color = blackbody(kelvin);
+    let neutral = blackbody(NEUTRAL_TEMPERATURE);
+    [0, 1, 2].map(|i| (color[i] / neutral[i]).clamp(0.0, 1.0))
+}
+
+/// Gamma table for `kelvin` with `size` entries per channel, laid out as the red, green and
+/// blue ramps after each other.
+pub fn gamma_table(size: usize, kelvin: u32) -> Vec<u16> {
+    let white_point = white_point(kelvin);
+    let max = size.saturating_sub(1).max(1) as f64;
+    white_point
+        .iter()
+        .flat_map(|channel| {
+            (0..size).map(move |i| (i as f64 / max * channel * u16::MAX as f64).round() as u16)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neutral_temperature_is_the_identity() {
+        assert_eq!(white_point(NEUTRAL_TEMPERATURE), [1.0; 3]);
+        assert_eq!(
+            gamma_table(3, NEUTRAL_TEMPERATURE),
+            [0, 32768, 65535, 0, 32768, 65535, 0, 32768, 65535]
+        );
+    }
+
+    #[test]
+    fn warm_temperatures_dim_blue_the_most() {
+        let table = gamma_table(256, 3000);
+        assert_eq!(table.len(), 3 * 256);
+        let [red, green, blue] = [table[255], table[511], table[767]];
+        assert_eq!(red, u16::MAX);
+        assert!(blue < green && green < red, "{red} {green} {blue}");
+        // Each ramp starts at black and rises
+        for ramp in table.chunks(256) {
+            assert_eq!(ramp[0], 0);
+            assert!(ramp.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+    }
+
+    #[test]
+    fn handles_tiny_tables() {
+        assert!(gamma_table(0, 4000).is_empty());
+        assert_eq!(gamma_table(1, NEUTRAL_TEMPERATURE), [0, 0, 0]);
+    }
+}
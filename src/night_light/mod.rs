@@ -0,0 +1,282 @@
+//! Built-in night light, warming the color temperature of all outputs between sunset and
+//! sunrise.
+
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+
+use jiff::{Timestamp, Zoned};
+use tokio::sync::{Mutex, watch};
+
+use crate::config::Config;
+use crate::message::Message;
+
+mod gamma;
+mod solar;
+mod wayland;
+
+use solar::Daylight;
+
+/// Interval at which the temperature is recomputed.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NightLightMode {
+    /// Follow the sun.
+    Auto,
+    /// Always use the night temperature.
+    Forced,
+    /// Leave the outputs alone, until the night light is turned on.
+    #[default]
+    Off,
+}
+
+impl NightLightMode {
+    /// The mode after this one when cycling through them, skipping `Auto` unless `auto`.
+    pub fn next(self, auto: bool) -> Self {
+        match self {
+            NightLightMode::Off if auto => NightLightMode::Auto,
+            NightLightMode::Off | NightLightMode::Auto => NightLightMode::Forced,
+            NightLightMode::Forced => NightLightMode::Off,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NightLightState {
+    pub mode: NightLightMode,
+    /// Color temperature currently applied, `None` while the outputs are left unchanged.
+    pub temperature: Option<u32>,
+}
+
+/// When and how warm the night light is.
+#[derive(Debug, Clone)]
+struct Schedule {
+    lat: f64,
+    lon: f64,
+    day_temp: u32,
+    night_temp: u32,
+    /// Duration of the transition, centered on sunrise and sunset.
+    fade: Duration,
+}
+
+impl Schedule {
+    fn temperature_at(&self, now: &Zoned) -> u32 {
+        let daylight = solar::daylight(now.date(), self.lat, self.lon);
+        let day = day_fraction(now.timestamp(), daylight, self.fade);
+        let (day_temp, night_temp) = (self.day_temp as f64, self.night_temp as f64);
+        (night_temp + (day_temp - night_temp) * day).round() as u32
+    }
+}
+
+/// How much of the day temperature applies at `now`, from `0.0` at night to `1.0` during the
+/// day, fading linearly around sunrise and sunset.
+fn day_fraction(now: Timestamp, daylight: Daylight, fade: Duration) -> f64 {
+    let (sunrise, sunset) = match daylight {
+        Daylight::Normal { sunrise, sunset } => (sunrise, sunset),
+        Daylight::PolarDay => return 1.0,
+        Daylight::PolarNight => return 0.0,
+    };
+    let fade = fade.as_secs_f64().max(1.0);
+    let since_sunrise = now.duration_since(sunrise).as_secs_f64();
+    let until_sunset = sunset.duration_since(now).as_secs_f64();
+
+    let rise = (since_sunrise / fade + 0.5).clamp(0.0, 1.0);
+    let set = (until_sunset / fade + 0.5).clamp(0.0, 1.0);
+    rise.min(set)
+}
+
+#[derive(Debug)]
+pub struct NightLight {
+    mode: NightLightMode,
+    /// Whether the auto mode is available, which needs the location.
+    auto: bool,
+    commands: mpsc::Sender<NightLightMode>,
+    rx: Arc<Mutex<watch::Receiver<NightLightState>>>,
+}
+
+impl NightLight {
+    pub fn new(config: &Config) -> Self {
+        let cfg = &config.bar.quick_settings.night_light;
+        let schedule = Schedule {
+            lat: config.general.lat,
+            lon: config.general.lon,
+            day_temp: cfg.day_temp,
+            night_temp: cfg.night_temp,
+            fade: Duration::from_secs(cfg.fade_duration_minutes as u64 * 60),
+        };
+
+        let auto = config.general.located();
+        if !auto {
+            log::info!("night light: no location configured, only the forced mode is available");
+        }
+        let mode = NightLightMode::default();
+        let (tx, rx) = watch::channel(NightLightState {
+            mode,
+            temperature: None,
+        });
+        let (commands, commands_rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("night-light".to_string())
+            .spawn(move || run(schedule, mode, commands_rx, tx))
+            .expect("failed to spawn night light thread");
+
+        Self {
+            mode,
+            auto,
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    /// Switches to the next mode: auto, forced, off. Auto is skipped without a location.
+    pub fn cycle_mode(&mut self) {
+        self.mode = self.mode.next(self.auto);
+        let _ = self.commands.send(self.mode);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "night-light".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(*rx.borrow())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::NightLightUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+fn run(
+    schedule: Schedule,
+    mut mode: NightLightMode,
+    commands_rx: mpsc::Receiver<NightLightMode>,
+    tx: watch::Sender<NightLightState>,
+) {
+    // Connected once needed, and again after failures, e.g. when the compositor restarted
+    let mut controls: Option<wayland::GammaControls> = None;
+    let mut connect_failed = false;
+
+    loop {
+        let temperature = match mode {
+            NightLightMode::Off => None,
+            NightLightMode::Forced => Some(schedule.night_temp),
+            NightLightMode::Auto => {
+                let temperature = schedule.temperature_at(&Zoned::now());
+                // Release the outputs during the day, so other clients may adjust their gamma
+                (temperature != schedule.day_temp
+                    || schedule.day_temp != gamma::NEUTRAL_TEMPERATURE)
+                    .then_some(temperature)
+            }
+        };
+
+        if controls.is_none() && temperature.is_some() {
+            match wayland::GammaControls::connect() {
+                Ok(connected) => {
+                    controls = Some(connected);
+                    connect_failed = false;
+                }
+                // Only warned about once while it keeps failing
+                Err(e) if !connect_failed => {
+                    log::warn!("night light: {e}");
+                    connect_failed = true;
+                }
+                Err(e) => log::debug!("night light: {e}"),
+            }
+        }
+
+        let result = controls.as_mut().map(|c| c.apply(temperature));
+        if let Some(Err(e)) = result {
+            log::warn!("night light: {e}");
+            controls = None;
+        }
+        tx.send_if_modified(|current| {
+            let state = NightLightState {
+                mode,
+                temperature: controls.as_ref().and(temperature),
+            };
+            let modified = *current != state;
+            *current = state;
+            modified
+        });
+
+        match commands_rx.recv_timeout(UPDATE_INTERVAL) {
+            Ok(new_mode) => mode = new_mode,
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> Timestamp {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn fades_around_sunrise_and_sunset() {
+        let daylight = Daylight::Normal {
+            sunrise: at("2024-06-21T04:00:00Z"),
+            sunset: at("2024-06-21T20:00:00Z"),
+        };
+        let fade = Duration::from_secs(30 * 60);
+        let fraction = |time| day_fraction(at(time), daylight, fade);
+
+        assert_eq!(fraction("2024-06-21T02:00:00Z"), 0.0);
+        assert_eq!(fraction("2024-06-21T03:45:00Z"), 0.0);
+        assert_eq!(fraction("2024-06-21T04:00:00Z"), 0.5);
+        assert_eq!(fraction("2024-06-21T04:15:00Z"), 1.0);
+        assert_eq!(fraction("2024-06-21T12:00:00Z"), 1.0);
+        assert_eq!(fraction("2024-06-21T19:52:30Z"), 0.75);
+        assert_eq!(fraction("2024-06-21T22:00:00Z"), 0.0);
+
+        assert_eq!(
+            day_fraction(at("2024-06-21T00:00:00Z"), Daylight::PolarDay, fade),
+            1.0
+        );
+        assert_eq!(
+            day_fraction(at("2024-06-21T12:00:00Z"), Daylight::PolarNight, fade),
+            0.0
+        );
+    }
+
+    #[test]
+    fn interpolates_the_temperature() {
+        let schedule = Schedule {
+            lat: 52.52,
+            lon: 13.405,
+            day_temp: 6500,
+            night_temp: 4000,
+            fade: Duration::from_secs(30 * 60),
+        };
+        let temperature = |time: &str| schedule.temperature_at(&time.parse::<Zoned>().unwrap());
+        assert_eq!(
+            temperature("2024-06-21T12:00:00+02:00[Europe/Berlin]"),
+            6500
+        );
+        assert_eq!(
+            temperature("2024-06-21T23:00:00+02:00[Europe/Berlin]"),
+            4000
+        );
+        let dusk = temperature("2024-06-21T21:33:00+02:00[Europe/Berlin]");
+        assert!((5000..=5500).contains(&dusk), "{dusk}");
+    }
+
+    #[test]
+    fn starts_off_and_skips_auto_without_a_location() {
+        let mode = NightLightMode::default();
+        assert_eq!(mode, NightLightMode::Off);
+        assert_eq!(mode.next(true), NightLightMode::Auto);
+        assert_eq!(mode.next(true).next(true), NightLightMode::Forced);
+        assert_eq!(mode.next(false), NightLightMode::Forced);
+        assert_eq!(mode.next(false).next(false), NightLightMode::Off);
+    }
+}
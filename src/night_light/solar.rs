@@ -0,0 +1,116 @@
+//! Offline sunrise and sunset times.
+//!
+//! See <https://en.wikipedia.org/wiki/Sunrise_equation>
+
+use jiff::Timestamp;
+use jiff::civil::Date;
+
+/// Julian date of the unix epoch.
+const UNIX_EPOCH_JULIAN_DATE: f64 = 2440587.5;
+/// Julian date of the J2000 epoch.
+const J2000: f64 = 2451545.0;
+const SECONDS_PER_DAY: f64 = 86400.0;
+/// Altitude of the sun's center at sunrise and sunset, accounting for refraction and the sun's
+/// radius.
+const SUNRISE_ALTITUDE: f64 = -0.833;
+const EARTH_TILT: f64 = 23.4397;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Daylight {
+    Normal {
+        sunrise: Timestamp,
+        sunset: Timestamp,
+    },
+    /// The sun doesn't set on this day.
+    PolarDay,
+    /// The sun doesn't rise on this day.
+    PolarNight,
+}
+
+/// Sunrise and sunset on `date` at latitude `lat` and longitude `lon` (east positive), in
+/// degrees.
+pub fn daylight(date: Date, lat: f64, lon: f64) -> Daylight {
+    let days_since_unix_epoch = (date - Date::constant(1970, 1, 1)).get_days() as f64;
+    // Days since J2000 at noon of `date`
+    let n = (days_since_unix_epoch + UNIX_EPOCH_JULIAN_DATE + 0.5 - J2000).round();
+
+    let mean_solar_time = n - lon / 360.0;
+    let mean_anomaly = (357.5291 + 0.98560028 * mean_solar_time).rem_euclid(360.0);
+    let m = mean_anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.0200 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic_longitude = (mean_anomaly + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit =
+        J2000 + mean_solar_time + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * EARTH_TILT.to_radians().sin()).asin();
+    let lat = lat.to_radians();
+    let cos_hour_angle = (SUNRISE_ALTITUDE.to_radians().sin() - lat.sin() * declination.sin())
+        / (lat.cos() * declination.cos());
+    if cos_hour_angle > 1.0 {
+        return Daylight::PolarNight;
+    }
+    if cos_hour_angle < -1.0 {
+        return Daylight::PolarDay;
+    }
+
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+    Daylight::Normal {
+        sunrise: julian_to_timestamp(transit - hour_angle / 360.0),
+        sunset: julian_to_timestamp(transit + hour_angle / 360.0),
+    }
+}
+
+fn julian_to_timestamp(julian_date: f64) -> Timestamp {
+    let seconds = ((julian_date - UNIX_EPOCH_JULIAN_DATE) * SECONDS_PER_DAY).round() as i64;
+    Timestamp::from_second(seconds).unwrap_or(Timestamp::UNIX_EPOCH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that `actual` is within a few minutes of `expected`, which is how precise the
+    /// equation is.
+    fn assert_close(actual: Timestamp, expected: &str) {
+        let expected = expected.parse::<Timestamp>().unwrap();
+        let difference = actual.duration_since(expected).as_secs().abs();
+        assert!(difference <= 3 * 60, "{actual} is not close to {expected}");
+    }
+
+    #[test]
+    fn computes_sunrise_and_sunset() {
+        // Berlin at the summer solstice
+        let Daylight::Normal { sunrise, sunset } =
+            daylight(Date::constant(2024, 6, 21), 52.52, 13.405)
+        else {
+            panic!("the sun should rise and set in Berlin");
+        };
+        assert_close(sunrise, "2024-06-21T02:43:00Z");
+        assert_close(sunset, "2024-06-21T19:33:00Z");
+
+        // New York in winter, west of Greenwich
+        let Daylight::Normal { sunrise, sunset } =
+            daylight(Date::constant(2024, 12, 21), 40.7128, -74.006)
+        else {
+            panic!("the sun should rise and set in New York");
+        };
+        assert_close(sunrise, "2024-12-21T12:16:00Z");
+        assert_close(sunset, "2024-12-21T21:32:00Z");
+    }
+
+    #[test]
+    fn knows_polar_days_and_nights() {
+        // Tromsø
+        let (lat, lon) = (69.6496, 18.956);
+        assert_eq!(
+            daylight(Date::constant(2024, 6, 21), lat, lon),
+            Daylight::PolarDay
+        );
+        assert_eq!(
+            daylight(Date::constant(2024, 12, 21), lat, lon),
+            Daylight::PolarNight
+        );
+    }
+}
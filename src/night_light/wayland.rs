@@ -0,0 +1,223 @@
+//! Applies gamma tables to all outputs through `wlr-gamma-control-unstable-v1`.
+//!
+//! This uses its own Wayland connection, since the protocol isn't exposed by iced.
+
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::os::fd::{AsFd, FromRawFd};
+
+use sctk::reexports::client::globals::{GlobalListContents, registry_queue_init};
+use sctk::reexports::client::protocol::wl_output::WlOutput;
+use sctk::reexports::client::protocol::wl_registry::{self, WlRegistry};
+use sctk::reexports::client::{
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle, delegate_noop,
+};
+use sctk::reexports::protocols_wlr::gamma_control::v1::client::zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1;
+use sctk::reexports::protocols_wlr::gamma_control::v1::client::zwlr_gamma_control_v1::{
+    self, ZwlrGammaControlV1,
+};
+
+use super::gamma;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug)]
+struct Output {
+    /// Name of the output's registry global.
+    global: u32,
+    wl_output: WlOutput,
+    control: Option<ZwlrGammaControlV1>,
+    gamma_size: Option<u32>,
+    /// Temperature of the gamma table last set on the control.
+    applied: Option<u32>,
+    /// Whether the compositor refused the last gamma control for this output, e.g. because
+    /// another client holds it. Controls are requested again on every update regardless.
+    failed: bool,
+}
+
+impl Output {
+    fn release(&mut self) {
+        if let Some(control) = self.control.take() {
+            control.destroy();
+        }
+        self.gamma_size = None;
+        self.applied = None;
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    outputs: Vec<Output>,
+}
+
+pub struct GammaControls {
+    connection: Connection,
+    queue: EventQueue<State>,
+    manager: ZwlrGammaControlManagerV1,
+    state: State,
+}
+
+impl GammaControls {
+    pub fn connect() -> Result<Self, BoxError> {
+        let connection = Connection::connect_to_env()?;
+        let (globals, queue) = registry_queue_init::<State>(&connection)?;
+        let qh = queue.handle();
+
+        let manager = globals
+            .bind::<ZwlrGammaControlManagerV1, _, _>(&qh, 1..=1, ())
+            .map_err(|e| format!("compositor doesn't support gamma control: {e}"))?;
+        let outputs = globals.contents().with_list(|list| {
+            list.iter()
+                .filter(|global| global.interface == WlOutput::interface().name)
+                .map(|global| Output {
+                    global: global.name,
+                    wl_output: globals.registry().bind(global.name, 1, &qh, ()),
+                    control: None,
+                    gamma_size: None,
+                    applied: None,
+                    failed: false,
+                })
+                .collect()
+        });
+
+        Ok(Self {
+            connection,
+            queue,
+            manager,
+            state: State { outputs },
+        })
+    }
+
+    /// Sets the gamma of all outputs to `temperature`, or restores their original gamma if
+    /// `None`.
+    pub fn apply(&mut self, temperature: Option<u32>) -> Result<(), BoxError> {
+        // Pick up added and removed outputs
+        self.queue.roundtrip(&mut self.state)?;
+
+        let Some(temperature) = temperature else {
+            self.state.outputs.iter_mut().for_each(Output::release);
+            self.connection.flush()?;
+            return Ok(());
+        };
+
+        let qh = self.queue.handle();
+        let mut created = false;
+        for output in &mut self.state.outputs {
+            if output.control.is_none() {
+                output.control = Some(self.manager.get_gamma_control(
+                    &output.wl_output,
+                    &qh,
+                    output.global,
+                ));
+                created = true;
+            }
+        }
+        if created {
+            // Receive the gamma sizes of the new controls
+            self.queue.roundtrip(&mut self.state)?;
+        }
+
+        for output in &mut self.state.outputs {
+            let (Some(control), Some(size)) = (&output.control, output.gamma_size) else {
+                continue;
+            };
+            if output.applied == Some(temperature) {
+                continue;
+            }
+            let table = gamma::gamma_table(size as usize, temperature);
+            control.set_gamma(gamma_file(&table)?.as_fd());
+            output.applied = Some(temperature);
+        }
+        self.connection.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes `table` to an anonymous file, which is how gamma tables are passed to the compositor.
+fn gamma_file(table: &[u16]) -> io::Result<File> {
+    // SAFETY: the name is a valid C string
+    let fd = unsafe { libc::memfd_create(c"limbo-gamma".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `fd` was just created and is owned by nothing else
+    let mut file = unsafe { File::from_raw_fd(fd) };
+
+    let bytes = table
+        .iter()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect::<Vec<_>>();
+    file.write_all(&bytes)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name, interface, ..
+            } if interface == WlOutput::interface().name => {
+                state.outputs.push(Output {
+                    global: name,
+                    wl_output: registry.bind(name, 1, qh, ()),
+                    control: None,
+                    gamma_size: None,
+                    applied: None,
+                    failed: false,
+                });
+            }
+            wl_registry::Event::GlobalRemove { name } => {
+                state.outputs.retain_mut(|output| {
+                    if output.global != name {
+                        return true;
+                    }
+                    output.release();
+                    false
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, u32> for State {
+    fn event(
+        state: &mut Self,
+        _control: &ZwlrGammaControlV1,
+        event: zwlr_gamma_control_v1::Event,
+        global: &u32,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(output) = state.outputs.iter_mut().find(|o| o.global == *global) else {
+            return;
+        };
+        match event {
+            zwlr_gamma_control_v1::Event::GammaSize { size } => {
+                output.gamma_size = Some(size);
+                output.failed = false;
+            }
+            zwlr_gamma_control_v1::Event::Failed => {
+                if output.failed {
+                    log::debug!("night light: gamma control failed again for output {global}");
+                } else {
+                    log::warn!("night light: gamma control failed for output {global}");
+                }
+                output.release();
+                output.failed = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(State: ignore WlOutput);
+delegate_noop!(State: ZwlrGammaControlManagerV1);
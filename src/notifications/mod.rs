@@ -0,0 +1,433 @@
+//! Built-in notification server, owning `org.freedesktop.Notifications` on the session bus.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use jiff::Timestamp;
+use tokio::sync::{Mutex, mpsc, watch};
+
+use crate::config::types::Notifs;
+use crate::message::Message;
+
+mod server;
+
+use server::{SERVER_NAME, SERVER_PATH, Server};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl From<u8> for Urgency {
+    fn from(urgency: u8) -> Self {
+        match urgency {
+            0 => Urgency::Low,
+            2 => Urgency::Critical,
+            _ => Urgency::Normal,
+        }
+    }
+}
+
+/// Icon of a notification, resolved once when it is received.
+#[derive(Debug, Clone)]
+pub enum NotificationIcon {
+    /// Path to an icon file, either given directly or from the icon theme.
+    Path(PathBuf),
+    /// Decoded raw image data.
+    Image(iced::widget::image::Handle),
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub id: u32,
+    pub app_name: String,
+    pub icon: Option<NotificationIcon>,
+    pub summary: String,
+    pub body: String,
+    /// Pairs of action key and label.
+    pub actions: Vec<(String, String)>,
+    pub urgency: Urgency,
+    pub received: Timestamp,
+    /// When the notification expires, `None` if it stays until it is dismissed.
+    pub expires: Option<Timestamp>,
+    /// Whether the notification expired or was dismissed. Closed notifications stay in the
+    /// history, but are no longer shown as toasts.
+    pub closed: bool,
+    /// Whether the notification stays open when one of its actions is invoked.
+    pub resident: bool,
+    /// Whether the notification arrived during do not disturb, and isn't shown as a toast.
+    pub silenced: bool,
+}
+
+impl Notification {
+    /// Whether the notification is shown as a toast.
+    pub fn toast(&self) -> bool {
+        !self.closed && !self.silenced
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct NotificationsState {
+    /// Received notifications, newest first.
+    pub history: Vec<Notification>,
+    /// Number of notifications received since the history was last marked as read.
+    pub unread: usize,
+    /// Whether do not disturb is on, silencing all but critical notifications.
+    pub dnd: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum NotificationCommand {
+    /// Close the notification, keeping it in the history.
+    Dismiss(u32),
+    InvokeAction {
+        id: u32,
+        key: String,
+    },
+    /// Remove the notification from the history.
+    Remove(u32),
+    /// Remove all notifications from the history.
+    ClearAll,
+    MarkAllRead,
+    SetDnd(bool),
+}
+
+/// Settings of the server, taken from the config.
+#[derive(Debug, Clone)]
+struct ServerConfig {
+    /// Number of notifications kept in the history.
+    history_limit: usize,
+    /// Expiry of notifications that leave it to the server.
+    default_timeout_ms: u32,
+}
+
+#[derive(Debug)]
+pub struct NotificationServer {
+    commands: mpsc::UnboundedSender<NotificationCommand>,
+    rx: Arc<Mutex<watch::Receiver<NotificationsState>>>,
+}
+
+impl NotificationServer {
+    pub fn new(config: &Notifs) -> Self {
+        Self::with_address(config, None)
+    }
+
+    /// Creates the server on the bus at `address`, the session bus if `None`. The server is
+    /// only started if it is enabled in `config`.
+    pub fn with_address(config: &Notifs, address: Option<String>) -> Self {
+        let (tx, rx) = watch::channel(NotificationsState::default());
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        if config.server {
+            let config = ServerConfig {
+                history_limit: config.history_limit,
+                default_timeout_ms: config.default_timeout_ms,
+            };
+            tokio::spawn(run(address, config, tx, commands_rx));
+        }
+        Self {
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn send(&self, command: NotificationCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "notifications".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::NotificationsUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+async fn run(
+    address: Option<String>,
+    config: ServerConfig,
+    tx: watch::Sender<NotificationsState>,
+    mut commands_rx: mpsc::UnboundedReceiver<NotificationCommand>,
+) {
+    const MIN_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    let mut backoff = MIN_BACKOFF;
+    while !tx.is_closed() {
+        match serve(address.as_deref(), &config, &tx, &mut commands_rx).await {
+            Ok(()) => return,
+            Err(e) => log::warn!(
+                "notifications: failed to serve {SERVER_NAME} ({e}), retrying in {}s",
+                backoff.as_secs()
+            ),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Serves the notification interface until limbo shuts down.
+async fn serve(
+    address: Option<&str>,
+    config: &ServerConfig,
+    tx: &watch::Sender<NotificationsState>,
+    commands_rx: &mut mpsc::UnboundedReceiver<NotificationCommand>,
+) -> zbus::Result<()> {
+    let builder = match address {
+        Some(address) => zbus::connection::Builder::address(address)?,
+        None => zbus::connection::Builder::session()?,
+    };
+    let connection = builder
+        .serve_at(SERVER_PATH, Server::new(config.clone(), tx.clone()))?
+        .name(SERVER_NAME)?
+        .build()
+        .await?;
+    log::info!("notifications: serving {SERVER_NAME}");
+
+    let iface = connection
+        .object_server()
+        .interface::<_, Server>(SERVER_PATH)
+        .await?;
+    let mut state_rx = tx.subscribe();
+    loop {
+        // Recomputed after every change, since new notifications may expire sooner
+        let next_expiry = iface.get().await.next_expiry();
+        let expiry = async {
+            match next_expiry {
+                Some(expiry) => {
+                    let delay = expiry
+                        .duration_since(Timestamp::now())
+                        .try_into()
+                        .unwrap_or(Duration::ZERO);
+                    tokio::time::sleep(delay).await;
+                }
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            command = commands_rx.recv() => {
+                let Some(command) = command else {
+                    return Ok(());
+                };
+                iface
+                    .get_mut()
+                    .await
+                    .apply(command, iface.signal_emitter())
+                    .await?;
+            }
+            _ = state_rx.changed() => {}
+            _ = expiry => iface.get_mut().await.expire(iface.signal_emitter()).await?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use iced::futures::StreamExt;
+    use zbus::zvariant::Value;
+
+    use super::*;
+    use crate::testing::PrivateBus;
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.Notifications",
+        default_service = "org.freedesktop.Notifications",
+        default_path = "/org/freedesktop/Notifications"
+    )]
+    trait Client {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            app_name: &str,
+            replaces_id: u32,
+            app_icon: &str,
+            summary: &str,
+            body: &str,
+            actions: &[&str],
+            hints: HashMap<&str, Value<'_>>,
+            expire_timeout: i32,
+        ) -> zbus::Result<u32>;
+
+        fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+        fn get_server_information(&self) -> zbus::Result<(String, String, String, String)>;
+
+        #[zbus(signal)]
+        fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+
+        #[zbus(signal)]
+        fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+    }
+
+    /// Waits until the published state satisfies `done`.
+    async fn wait_until(
+        rx: &mut watch::Receiver<NotificationsState>,
+        done: impl Fn(&NotificationsState) -> bool,
+    ) -> NotificationsState {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let state = rx.borrow_and_update().clone();
+                if done(&state) {
+                    return state;
+                }
+                rx.changed().await.unwrap();
+            }
+        })
+        .await
+        .expect("timed out waiting for the notifications state")
+    }
+
+    async fn next<T>(stream: &mut (impl iced::futures::Stream<Item = T> + Unpin)) -> T {
+        tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for a signal")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_clients_on_a_private_bus() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let config = Notifs {
+            server: true,
+            ..Default::default()
+        };
+        let server = NotificationServer::with_address(&config, Some(bus.address.clone()));
+        let mut rx = server.rx.lock().await.clone();
+
+        let connection = bus.connection().await;
+        let client = ClientProxy::new(&connection).await.unwrap();
+        // The server owns its name once it is ready
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.get_server_information().await.is_err() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("timed out waiting for the server");
+        let mut closed = client.receive_notification_closed().await.unwrap();
+        let mut invoked = client.receive_action_invoked().await.unwrap();
+
+        let critical = HashMap::from([("urgency", Value::U8(2))]);
+        let id = client
+            .notify(
+                "app",
+                0,
+                "",
+                "one",
+                "",
+                &["open", "Open"],
+                critical.clone(),
+                -1,
+            )
+            .await
+            .unwrap();
+        // Replacing keeps the id and doesn't count as unread again
+        let replaced = client
+            .notify(
+                "app",
+                id,
+                "",
+                "two",
+                "body",
+                &["open", "Open"],
+                critical,
+                -1,
+            )
+            .await
+            .unwrap();
+        assert_eq!(replaced, id);
+        let state = wait_until(&mut rx, |state| {
+            state.history.first().is_some_and(|n| n.summary == "two")
+        })
+        .await;
+        assert_eq!(state.unread, 1);
+        assert_eq!(state.history.len(), 1);
+        let notification = &state.history[0];
+        assert_eq!(notification.urgency, Urgency::Critical);
+        // Critical notifications don't expire
+        assert_eq!(notification.expires, None);
+        assert_eq!(
+            notification.actions,
+            [("open".to_string(), "Open".to_string())]
+        );
+
+        let expiring = client
+            .notify("app", 0, "", "three", "", &[], HashMap::new(), 50)
+            .await
+            .unwrap();
+        assert_ne!(expiring, id);
+        let args = next(&mut closed).await;
+        let args = args.args().unwrap();
+        assert_eq!((args.id, args.reason), (expiring, 1));
+        let state = wait_until(&mut rx, |state| state.history.iter().any(|n| n.closed)).await;
+        assert_eq!(state.unread, 2);
+        assert!(state.history[0].closed && !state.history[0].toast());
+
+        server.send(NotificationCommand::InvokeAction {
+            id,
+            key: "open".to_string(),
+        });
+        let args = next(&mut invoked).await;
+        let args = args.args().unwrap();
+        assert_eq!((args.id, args.action_key.as_str()), (id, "open"));
+        let args = next(&mut closed).await;
+        let args = args.args().unwrap();
+        assert_eq!((args.id, args.reason), (id, 2));
+
+        client.close_notification(expiring).await.unwrap();
+        server.send(NotificationCommand::MarkAllRead);
+        let state = wait_until(&mut rx, |state| state.unread == 0).await;
+        assert_eq!(state.history.iter().map(|n| n.id).collect::<Vec<_>>(), [id]);
+    }
+
+    #[tokio::test]
+    async fn silences_all_but_critical_notifications_during_dnd() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let config = Notifs {
+            server: true,
+            ..Default::default()
+        };
+        let server = NotificationServer::with_address(&config, Some(bus.address.clone()));
+        let mut rx = server.rx.lock().await.clone();
+        server.send(NotificationCommand::SetDnd(true));
+        wait_until(&mut rx, |state| state.dnd).await;
+
+        let connection = bus.connection().await;
+        let client = ClientProxy::new(&connection).await.unwrap();
+        for (summary, urgency) in [("normal", 1), ("critical", 2)] {
+            let hints = HashMap::from([("urgency", Value::U8(urgency))]);
+            client
+                .notify("app", 0, "", summary, "", &[], hints, 0)
+                .await
+                .unwrap();
+        }
+        let state = wait_until(&mut rx, |state| state.history.len() == 2).await;
+        let toasts = state
+            .history
+            .iter()
+            .filter(|n| n.toast())
+            .map(|n| n.summary.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(toasts, ["critical"]);
+        assert_eq!(state.unread, 2);
+    }
+}
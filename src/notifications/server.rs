@@ -0,0 +1,335 @@
+//! The `org.freedesktop.Notifications` interface.
+//!
+//! See <https://specifications.freedesktop.org/notification-spec/latest/>
+
+use std::collections::HashMap;
+
+use jiff::{SignedDuration, Timestamp};
+use tokio::sync::watch;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::{fdo, interface};
+
+use super::{
+    Notification, NotificationCommand, NotificationIcon, NotificationsState, ServerConfig, Urgency,
+};
+
+pub const SERVER_NAME: &str = "org.freedesktop.Notifications";
+pub const SERVER_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Why a notification was closed, as sent in `NotificationClosed`.
+#[derive(Debug, Clone, Copy)]
+#[repr(u32)]
+pub enum CloseReason {
+    Expired = 1,
+    Dismissed = 2,
+    Closed = 3,
+}
+
+#[derive(Debug)]
+pub struct Server {
+    config: ServerConfig,
+    last_id: u32,
+    state: NotificationsState,
+    tx: watch::Sender<NotificationsState>,
+}
+
+impl Server {
+    pub fn new(config: ServerConfig, tx: watch::Sender<NotificationsState>) -> Self {
+        Self {
+            config,
+            last_id: 0,
+            state: NotificationsState::default(),
+            tx,
+        }
+    }
+
+    fn publish(&self) {
+        self.tx.send_replace(self.state.clone());
+    }
+
+    /// The earliest expiry of the notifications that are still open.
+    pub fn next_expiry(&self) -> Option<Timestamp> {
+        self.state
+            .history
+            .iter()
+            .filter(|n| !n.closed)
+            .filter_map(|n| n.expires)
+            .min()
+    }
+
+    /// Closes the notifications that reached their expiry. They are kept in the history.
+    pub async fn expire(&mut self, emitter: &SignalEmitter<'_>) -> zbus::Result<()> {
+        let now = Timestamp::now();
+        let expired = self
+            .state
+            .history
+            .iter()
+            .filter(|n| !n.closed && n.expires.is_some_and(|t| t <= now))
+            .map(|n| n.id)
+            .collect::<Vec<_>>();
+        for id in expired {
+            self.close(id, CloseReason::Expired, emitter).await?;
+        }
+        Ok(())
+    }
+
+    /// Closes the notification `id`, keeping it in the history.
+    async fn close(
+        &mut self,
+        id: u32,
+        reason: CloseReason,
+        emitter: &SignalEmitter<'_>,
+    ) -> zbus::Result<()> {
+        let Some(notification) = self
+            .state
+            .history
+            .iter_mut()
+            .find(|n| n.id == id && !n.closed)
+        else {
+            return Ok(());
+        };
+        notification.closed = true;
+        self.publish();
+        Self::notification_closed(emitter, id, reason as u32).await
+    }
+
+    /// Removes the notification `id` from the history, closing it if it is still open.
+    async fn remove(
+        &mut self,
+        id: u32,
+        reason: CloseReason,
+        emitter: &SignalEmitter<'_>,
+    ) -> zbus::Result<()> {
+        self.close(id, reason, emitter).await?;
+        self.state.history.retain(|n| n.id != id);
+        self.state.unread = self.state.unread.min(self.state.history.len());
+        self.publish();
+        Ok(())
+    }
+
+    pub async fn apply(
+        &mut self,
+        command: NotificationCommand,
+        emitter: &SignalEmitter<'_>,
+    ) -> zbus::Result<()> {
+        match command {
+            NotificationCommand::Dismiss(id) => {
+                self.close(id, CloseReason::Dismissed, emitter).await?;
+            }
+            NotificationCommand::InvokeAction { id, key } => {
+                let Some(notification) = self.state.history.iter().find(|n| n.id == id) else {
+                    return Ok(());
+                };
+                let resident = notification.resident;
+                Self::action_invoked(emitter, id, &key).await?;
+                if !resident {
+                    self.close(id, CloseReason::Dismissed, emitter).await?;
+                }
+            }
+            NotificationCommand::Remove(id) => {
+                self.remove(id, CloseReason::Dismissed, emitter).await?;
+            }
+            NotificationCommand::ClearAll => {
+                let ids = self.state.history.iter().map(|n| n.id).collect::<Vec<_>>();
+                for id in ids {
+                    self.remove(id, CloseReason::Dismissed, emitter).await?;
+                }
+            }
+            NotificationCommand::MarkAllRead => {
+                self.state.unread = 0;
+                self.publish();
+            }
+            NotificationCommand::SetDnd(dnd) => {
+                self.state.dnd = dnd;
+                self.publish();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[interface(name = "org.freedesktop.Notifications")]
+impl Server {
+    fn get_capabilities(&self) -> Vec<&str> {
+        vec!["actions", "body", "icon-static", "persistence"]
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &mut self,
+        app_name: String,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        expire_timeout: i32,
+    ) -> u32 {
+        let urgency = hints
+            .get("urgency")
+            .and_then(|v| v.downcast_ref::<u8>().ok())
+            .map_or(Urgency::Normal, Urgency::from);
+        let timeout = match expire_timeout {
+            // Critical notifications stay until they are dismissed
+            -1 if urgency == Urgency::Critical => None,
+            -1 => Some(self.config.default_timeout_ms as i64),
+            0 => None,
+            timeout => Some(timeout as i64),
+        };
+        let received = Timestamp::now();
+
+        let replaced = self
+            .state
+            .history
+            .iter()
+            .position(|n| replaces_id != 0 && n.id == replaces_id);
+        let id = match replaced {
+            Some(_) => replaces_id,
+            None => {
+                self.last_id = self.last_id.wrapping_add(1).max(1);
+                self.last_id
+            }
+        };
+
+        let notification = Notification {
+            id,
+            app_name,
+            icon: notification_icon(app_icon, &hints),
+            summary,
+            body,
+            actions: actions
+                .chunks_exact(2)
+                .map(|pair| (pair[0].clone(), pair[1].clone()))
+                .collect(),
+            urgency,
+            received,
+            expires: timeout.map(|ms| received + SignedDuration::from_millis(ms)),
+            closed: false,
+            resident: hints
+                .get("resident")
+                .and_then(|v| v.downcast_ref::<bool>().ok())
+                .unwrap_or(false),
+            // Critical notifications are shown even while do not disturb is on
+            silenced: self.state.dnd && urgency != Urgency::Critical,
+        };
+
+        // Replaced notifications move to the top, but only new ones count as unread
+        match replaced {
+            Some(index) => {
+                self.state.history.remove(index);
+            }
+            None => self.state.unread += 1,
+        }
+        self.state.history.insert(0, notification);
+        self.state.history.truncate(self.config.history_limit);
+        self.state.unread = self.state.unread.min(self.state.history.len());
+        self.publish();
+        id
+    }
+
+    async fn close_notification(
+        &mut self,
+        id: u32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        let Some(index) = self.state.history.iter().position(|n| n.id == id) else {
+            return Ok(());
+        };
+        let notification = self.state.history.remove(index);
+        self.publish();
+        if !notification.closed {
+            Self::notification_closed(&emitter, id, CloseReason::Closed as u32).await?;
+        }
+        Ok(())
+    }
+
+    #[zbus(out_args("name", "vendor", "version", "spec_version"))]
+    fn get_server_information(&self) -> (&str, &str, &str, &str) {
+        ("limbo", "limbo", env!("CARGO_PKG_VERSION"), "1.2")
+    }
+
+    #[zbus(signal)]
+    pub async fn notification_closed(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    pub async fn action_invoked(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        action_key: &str,
+    ) -> zbus::Result<()>;
+}
+
+/// The icon of a notification, from in order of preference the `image-data` hint, the
+/// `image-path` hint, and `app_icon`.
+fn notification_icon(
+    app_icon: &str,
+    hints: &HashMap<String, OwnedValue>,
+) -> Option<NotificationIcon> {
+    let hint = |names: &[&str]| names.iter().find_map(|name| hints.get(*name));
+
+    hint(&["image-data", "image_data"])
+        .and_then(decode_image_data)
+        .map(NotificationIcon::Image)
+        .or_else(|| {
+            hint(&["image-path", "image_path"])
+                .and_then(|v| v.downcast_ref::<&str>().ok())
+                .and_then(lookup_icon)
+        })
+        .or_else(|| lookup_icon(app_icon))
+        .or_else(|| {
+            hint(&["icon_data"])
+                .and_then(decode_image_data)
+                .map(NotificationIcon::Image)
+        })
+}
+
+/// Resolves an icon given as an icon name, a path or a `file://` URI.
+fn lookup_icon(icon: &str) -> Option<NotificationIcon> {
+    let icon = icon.strip_prefix("file://").unwrap_or(icon);
+    if icon.is_empty() {
+        return None;
+    }
+    crate::tray::lookup_system_icon(icon).map(NotificationIcon::Path)
+}
+
+/// Converts raw image data, `(iiibiiay)` of width, height, rowstride, whether it has an alpha
+/// channel, bits per sample, channels and the pixels, into RGBA.
+fn decode_image_data(value: &OwnedValue) -> Option<iced::widget::image::Handle> {
+    let value = Value::from(value.try_clone().ok()?);
+    let (width, height, rowstride, has_alpha, bits_per_sample, channels, data) =
+        <(i32, i32, i32, bool, i32, i32, Vec<u8>)>::try_from(value).ok()?;
+
+    let expected_channels = if has_alpha { 4 } else { 3 };
+    if width <= 0 || height <= 0 || bits_per_sample != 8 || channels != expected_channels {
+        return None;
+    }
+    let (width, height, rowstride, channels) = (
+        width as usize,
+        height as usize,
+        rowstride as usize,
+        channels as usize,
+    );
+    if rowstride < width * channels || data.len() < rowstride * (height - 1) + width * channels {
+        return None;
+    }
+
+    let rgba = (0..height)
+        .flat_map(|y| {
+            let row = &data[y * rowstride..][..width * channels];
+            row.chunks_exact(channels)
+                .flat_map(|px| [px[0], px[1], px[2], if has_alpha { px[3] } else { 255 }])
+        })
+        .collect::<Vec<_>>();
+
+    Some(iced::widget::image::Handle::from_rgba(
+        width as u32,
+        height as u32,
+        rgba,
+    ))
+}
@@ -0,0 +1,150 @@
+use iced::platform_specific::shell::commands::layer_surface::{
+    destroy_layer_surface, get_layer_surface,
+};
+use iced::runtime::platform_specific::wayland::layer_surface::{
+    IcedMargin, IcedOutput, SctkLayerSurfaceSettings,
+};
+use iced::widget::{container, mouse_area};
+use iced::{Alignment, Element, Length, Task, window};
+use sctk::reexports::client::protocol::wl_output::WlOutput;
+use sctk::shell::wlr_layer::{Anchor, KeyboardInteractivity, Layer};
+
+use crate::config::Config;
+use crate::config::types::ModuleName;
+use crate::message::Message;
+
+/// The different popups that can be opened from the bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopupKind {
+    TrayDrawer,
+    Network,
+    Caffeine,
+    Notifications,
+    PowerProfiles,
+    Bluetooth,
+    /// Album art and playback controls of the active media player.
+    Music,
+    /// The dropdown panel of the quick settings.
+    QuickSettings,
+    /// Search of the installed applications.
+    AppLauncher,
+    /// Tasks of the todo list.
+    Todo,
+    /// Current weather and the forecast of the next days.
+    Weather,
+    /// Unread GitHub notifications.
+    Github,
+    /// Todoist tasks due today or overdue.
+    Todoist,
+}
+
+impl PopupKind {
+    /// The bar module that owns the popup, used to align the popup below it.
+    pub fn module(self) -> ModuleName {
+        match self {
+            PopupKind::TrayDrawer
+            | PopupKind::Network
+            | PopupKind::Caffeine
+            | PopupKind::Notifications
+            | PopupKind::PowerProfiles
+            | PopupKind::Bluetooth
+            | PopupKind::QuickSettings => ModuleName::QuickSettings,
+            PopupKind::Music => ModuleName::Music,
+            PopupKind::AppLauncher => ModuleName::AppLauncher,
+            PopupKind::Todo => ModuleName::Todo,
+            PopupKind::Weather | PopupKind::Github | PopupKind::Todoist => {
+                ModuleName::Notifications
+            }
+        }
+    }
+
+    /// The quick settings panel and the launcher take the keyboard as soon as they open, so that
+    /// they can be used right away. Other popups only get it when clicked.
+    fn keyboard_interactivity(self) -> KeyboardInteractivity {
+        match self {
+            PopupKind::QuickSettings | PopupKind::AppLauncher => KeyboardInteractivity::Exclusive,
+            PopupKind::TrayDrawer
+            | PopupKind::Network
+            | PopupKind::Caffeine
+            | PopupKind::Notifications
+            | PopupKind::PowerProfiles
+            | PopupKind::Bluetooth
+            | PopupKind::Music
+            | PopupKind::Todo
+            | PopupKind::Weather
+            | PopupKind::Github
+            | PopupKind::Todoist => KeyboardInteractivity::OnDemand,
+        }
+    }
+}
+
+/// A popup belonging to a bar.
+///
+/// Popups are transparent layer surfaces covering the whole output below the bar, with the
+/// actual content aligned under the module that opened it. Clicking anywhere outside of the
+/// content or pressing escape closes the popup.
+#[derive(Debug)]
+pub struct Popup {
+    /// window id of the popup's layer surface.
+    pub id: window::Id,
+    pub kind: PopupKind,
+    alignment: Alignment,
+}
+
+impl Popup {
+    pub fn open(
+        kind: PopupKind,
+        alignment: Alignment,
+        wl_output: WlOutput,
+    ) -> (Self, Task<Message>) {
+        let id = window::Id::unique();
+        (
+            Self {
+                id,
+                kind,
+                alignment,
+            },
+            get_layer_surface(SctkLayerSurfaceSettings {
+                id,
+                layer: Layer::Top,
+                keyboard_interactivity: kind.keyboard_interactivity(),
+                input_zone: None,
+                anchor: Anchor::TOP | Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT,
+                output: IcedOutput::Output(wl_output),
+                namespace: "limbo:popup".to_string(),
+                margin: IcedMargin::default(),
+                size: Some((None, None)),
+                // Respect the bar's exclusive zone so the popup starts right below it
+                exclusive_zone: 0,
+                size_limits: iced::Limits::NONE,
+            }),
+        )
+    }
+
+    pub fn destroy(self) -> Task<Message> {
+        destroy_layer_surface(self.id)
+    }
+
+    /// Wraps the popup's `content` so that it is positioned below its module and clicking
+    /// outside of it sends `on_dismiss`.
+    pub fn view<'a>(
+        &self,
+        config: &Config,
+        content: impl Into<Element<'a, Message>>,
+        on_dismiss: Message,
+    ) -> Element<'a, Message> {
+        // Presses on the content itself must not bubble up to the dismiss area
+        let content = mouse_area(config.panel(content, None)).on_press(Message::Noop);
+
+        mouse_area(
+            container(content)
+                .align_x(self.alignment)
+                .align_y(Alignment::Start)
+                .padding([6, 8])
+                .width(Length::Fill)
+                .height(Length::Fill),
+        )
+        .on_press(on_dismiss)
+        .into()
+    }
+}
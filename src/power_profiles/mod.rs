@@ -0,0 +1,407 @@
+//! Follows and switches the power profile through power-profiles-daemon.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use iced::futures::StreamExt;
+use tokio::sync::{Mutex, mpsc, watch};
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::OwnedValue;
+
+use crate::message::Message;
+
+mod ppd;
+
+use ppd::PowerProfilesProxy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerProfile {
+    PowerSaver,
+    Balanced,
+    Performance,
+}
+
+impl PowerProfile {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "power-saver" => Some(PowerProfile::PowerSaver),
+            "balanced" => Some(PowerProfile::Balanced),
+            "performance" => Some(PowerProfile::Performance),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PowerProfile::PowerSaver => "power-saver",
+            PowerProfile::Balanced => "balanced",
+            PowerProfile::Performance => "performance",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerProfile::PowerSaver => "Power saver",
+            PowerProfile::Balanced => "Balanced",
+            PowerProfile::Performance => "Performance",
+        }
+    }
+}
+
+/// A request of an application to keep a profile active.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileHold {
+    pub application_id: String,
+    pub profile: PowerProfile,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PowerProfilesState {
+    /// The active profile, `None` while power-profiles-daemon isn't running.
+    pub active: Option<PowerProfile>,
+    /// The profiles supported on this system.
+    pub available: Vec<PowerProfile>,
+    /// Why the performance profile is degraded, e.g. `lap-detected` or
+    /// `high-operating-temperature`.
+    pub degraded: Option<String>,
+    pub holds: Vec<ProfileHold>,
+}
+
+impl PowerProfilesState {
+    /// The available profile after the active one, wrapping around.
+    pub fn next(&self) -> Option<PowerProfile> {
+        let active = self.active?;
+        let index = self.available.iter().position(|p| *p == active)?;
+        Some(self.available[(index + 1) % self.available.len()])
+    }
+}
+
+#[derive(Debug)]
+pub struct PowerProfiles {
+    commands: mpsc::UnboundedSender<PowerProfile>,
+    rx: Arc<Mutex<watch::Receiver<PowerProfilesState>>>,
+}
+
+impl PowerProfiles {
+    /// Follows power-profiles-daemon on the system bus, unless the power profile isn't `shown`.
+    pub fn new(shown: bool) -> Self {
+        if !shown {
+            let (commands, _) = mpsc::unbounded_channel();
+            let (_, rx) = watch::channel(PowerProfilesState::default());
+            return Self {
+                commands,
+                rx: Arc::new(Mutex::new(rx)),
+            };
+        }
+        Self::with_address(None)
+    }
+
+    /// Creates the service for the power-profiles-daemon on the bus at `address`, the system bus
+    /// if `None`.
+    pub fn with_address(address: Option<String>) -> Self {
+        let (tx, rx) = watch::channel(PowerProfilesState::default());
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(address, tx, commands_rx));
+        Self {
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn set(&self, profile: PowerProfile) {
+        let _ = self.commands.send(profile);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "power-profiles".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::PowerProfilesUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+async fn run(
+    address: Option<String>,
+    tx: watch::Sender<PowerProfilesState>,
+    mut commands_rx: mpsc::UnboundedReceiver<PowerProfile>,
+) {
+    const MIN_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    let mut backoff = MIN_BACKOFF;
+    let mut missing = false;
+    while !tx.is_closed() {
+        match run_client(address.as_deref(), &tx, &mut commands_rx).await {
+            Ok(()) => return,
+            // Many systems don't have the daemon at all, which isn't worth a warning every minute
+            Err(zbus::Error::FDO(e)) if matches!(*e, zbus::fdo::Error::ServiceUnknown(_)) => {
+                if !std::mem::replace(&mut missing, true) {
+                    log::debug!("power profiles: power-profiles-daemon isn't running");
+                }
+            }
+            Err(e) => {
+                missing = false;
+                log::warn!("power profiles: {e}, retrying in {}s", backoff.as_secs());
+            }
+        }
+        tx.send_replace(PowerProfilesState::default());
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Follows the daemon until limbo shuts down or the daemon exits.
+async fn run_client(
+    address: Option<&str>,
+    tx: &watch::Sender<PowerProfilesState>,
+    commands_rx: &mut mpsc::UnboundedReceiver<PowerProfile>,
+) -> zbus::Result<()> {
+    let builder = match address {
+        Some(address) => zbus::connection::Builder::address(address)?,
+        None => zbus::connection::Builder::system()?,
+    };
+    let connection = builder.build().await?;
+
+    let proxy = PowerProfilesProxy::builder(&connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    let properties = zbus::fdo::PropertiesProxy::builder(&connection)
+        .destination(proxy.inner().destination().to_owned())?
+        .path(proxy.inner().path().to_owned())?
+        .build()
+        .await?;
+    let mut changes = properties.receive_properties_changed().await?;
+    let mut owner_changes = proxy.inner().receive_owner_changed().await?;
+
+    loop {
+        let state = query(&proxy).await?;
+        tx.send_if_modified(|current| {
+            let modified = *current != state;
+            *current = state;
+            modified
+        });
+
+        tokio::select! {
+            change = changes.next() => {
+                if change.is_none() {
+                    return Ok(());
+                }
+            }
+            Some(owner) = owner_changes.next() => {
+                if owner.is_none() {
+                    return Err(zbus::Error::Failure(
+                        "power-profiles-daemon exited".to_string(),
+                    ));
+                }
+            }
+            command = commands_rx.recv() => {
+                let Some(profile) = command else {
+                    return Ok(());
+                };
+                if let Err(e) = proxy.set_active_profile(profile.name()).await {
+                    log::warn!("power profiles: failed to switch to {}: {e}", profile.name());
+                }
+            }
+        }
+    }
+}
+
+async fn query(proxy: &PowerProfilesProxy<'_>) -> zbus::Result<PowerProfilesState> {
+    let degraded = proxy.performance_degraded().await?;
+    let available = proxy
+        .profiles()
+        .await?
+        .iter()
+        .filter_map(|profile| string(profile, "Profile"))
+        .filter_map(|name| PowerProfile::from_name(&name))
+        .collect::<Vec<_>>();
+    let holds = proxy
+        .active_profile_holds()
+        .await?
+        .iter()
+        .filter_map(|hold| {
+            Some(ProfileHold {
+                application_id: string(hold, "ApplicationId").unwrap_or_default(),
+                profile: PowerProfile::from_name(&string(hold, "Profile")?)?,
+                reason: string(hold, "Reason").unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    Ok(PowerProfilesState {
+        active: PowerProfile::from_name(&proxy.active_profile().await?),
+        available,
+        degraded: (!degraded.is_empty()).then_some(degraded),
+        holds,
+    })
+}
+
+fn string(dict: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    dict.get(key)
+        .and_then(|v| v.downcast_ref::<&str>().ok())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::zvariant::Value;
+
+    use super::*;
+    use crate::testing::PrivateBus;
+
+    const SERVICE: &str = "net.hadess.PowerProfiles";
+    const PATH: &str = "/net/hadess/PowerProfiles";
+
+    struct FakeDaemon {
+        active: String,
+        degraded: String,
+        holds: Vec<HashMap<String, OwnedValue>>,
+    }
+
+    #[zbus::interface(name = "net.hadess.PowerProfiles")]
+    impl FakeDaemon {
+        #[zbus(property)]
+        fn active_profile(&self) -> String {
+            self.active.clone()
+        }
+
+        #[zbus(property)]
+        fn set_active_profile(&mut self, profile: String) {
+            self.active = profile;
+        }
+
+        #[zbus(property)]
+        fn performance_degraded(&self) -> String {
+            self.degraded.clone()
+        }
+
+        #[zbus(property)]
+        fn profiles(&self) -> Vec<HashMap<String, OwnedValue>> {
+            ["power-saver", "balanced", "unknown"]
+                .into_iter()
+                .map(|profile| dict(&[("Profile", profile), ("Driver", "placeholder")]))
+                .collect()
+        }
+
+        #[zbus(property)]
+        fn active_profile_holds(&self) -> Vec<HashMap<String, OwnedValue>> {
+            self.holds.clone()
+        }
+    }
+
+    fn dict(entries: &[(&str, &str)]) -> HashMap<String, OwnedValue> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), Value::from(*value).try_into().unwrap()))
+            .collect()
+    }
+
+    async fn wait_until(
+        rx: &mut watch::Receiver<PowerProfilesState>,
+        done: impl Fn(&PowerProfilesState) -> bool,
+    ) -> PowerProfilesState {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let state = rx.borrow_and_update().clone();
+                if done(&state) {
+                    return state;
+                }
+                rx.changed().await.unwrap();
+            }
+        })
+        .await
+        .expect("timed out waiting for the power profiles state")
+    }
+
+    #[tokio::test]
+    async fn follows_and_switches_the_profile() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let daemon = bus.connection().await;
+        let server = daemon.object_server();
+        let fake = FakeDaemon {
+            active: "balanced".to_string(),
+            degraded: String::new(),
+            holds: Vec::new(),
+        };
+        server.at(PATH, fake).await.unwrap();
+        daemon.request_name(SERVICE).await.unwrap();
+
+        let profiles = PowerProfiles::with_address(Some(bus.address.clone()));
+        let mut rx = profiles.rx.lock().await.clone();
+        let state = wait_until(&mut rx, |state| state.active.is_some()).await;
+        assert_eq!(
+            state,
+            PowerProfilesState {
+                active: Some(PowerProfile::Balanced),
+                available: vec![PowerProfile::PowerSaver, PowerProfile::Balanced],
+                degraded: None,
+                holds: Vec::new(),
+            }
+        );
+
+        profiles.set(PowerProfile::PowerSaver);
+        wait_until(&mut rx, |state| {
+            state.active == Some(PowerProfile::PowerSaver)
+        })
+        .await;
+        let fake = server.interface::<_, FakeDaemon>(PATH).await.unwrap();
+        assert_eq!(fake.get().await.active, "power-saver");
+
+        {
+            let mut daemon = fake.get_mut().await;
+            daemon.degraded = "lap-detected".to_string();
+            daemon.holds = vec![
+                dict(&[
+                    ("ApplicationId", "org.gnome.Software"),
+                    ("Profile", "performance"),
+                    ("Reason", "Installing updates"),
+                ]),
+                // Holds of profiles limbo doesn't know are skipped
+                dict(&[("ApplicationId", "other"), ("Profile", "unknown")]),
+            ];
+        }
+        let emitter = fake.signal_emitter();
+        fake.get()
+            .await
+            .performance_degraded_changed(emitter)
+            .await
+            .unwrap();
+        fake.get()
+            .await
+            .active_profile_holds_changed(emitter)
+            .await
+            .unwrap();
+        let state = wait_until(&mut rx, |state| !state.holds.is_empty()).await;
+        assert_eq!(state.degraded.as_deref(), Some("lap-detected"));
+        assert_eq!(
+            state.holds,
+            [ProfileHold {
+                application_id: "org.gnome.Software".to_string(),
+                profile: PowerProfile::Performance,
+                reason: "Installing updates".to_string(),
+            }]
+        );
+
+        // The state is reset while the daemon is gone, and restored once it's back
+        daemon.release_name(SERVICE).await.unwrap();
+        wait_until(&mut rx, |state| *state == PowerProfilesState::default()).await;
+        daemon.request_name(SERVICE).await.unwrap();
+        let state = wait_until(&mut rx, |state| state.active.is_some()).await;
+        assert_eq!(state.active, Some(PowerProfile::PowerSaver));
+    }
+}
@@ -0,0 +1,33 @@
+//! Proxy for power-profiles-daemon.
+//!
+//! See <https://gitlab.freedesktop.org/upower/power-profiles-daemon>
+
+use std::collections::HashMap;
+
+use zbus::proxy;
+use zbus::zvariant::OwnedValue;
+
+#[proxy(
+    interface = "net.hadess.PowerProfiles",
+    default_service = "net.hadess.PowerProfiles",
+    default_path = "/net/hadess/PowerProfiles"
+)]
+pub trait PowerProfiles {
+    #[zbus(property)]
+    fn active_profile(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn set_active_profile(&self, profile: &str) -> zbus::Result<()>;
+
+    /// Why the performance profile is degraded, empty if it isn't.
+    #[zbus(property)]
+    fn performance_degraded(&self) -> zbus::Result<String>;
+
+    /// Dictionaries with at least the `Profile` and `Driver` keys.
+    #[zbus(property)]
+    fn profiles(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+
+    /// Dictionaries with the `ApplicationId`, `Profile` and `Reason` keys.
+    #[zbus(property)]
+    fn active_profile_holds(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
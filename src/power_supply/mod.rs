@@ -0,0 +1,413 @@
+//! Tracks the charge of the system batteries.
+//!
+//! UPower is used when it is running, otherwise batteries are read from sysfs directly.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::{Mutex, watch};
+use zbus::zvariant::Value;
+
+use crate::config::types::Battery;
+use crate::message::Message;
+
+mod sysfs;
+mod upower;
+
+/// Interval at which sysfs is polled, which doesn't notify about changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Interval at which UPower is looked for again while falling back to sysfs.
+const UPOWER_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChargeState {
+    Charging,
+    Discharging,
+    Full,
+    /// Plugged in, but neither charging nor full, e.g. due to a charge limit.
+    NotCharging,
+}
+
+/// All batteries of the system, aggregated into one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryState {
+    /// Charge in percent.
+    pub percentage: f32,
+    pub state: ChargeState,
+    pub time_to_empty: Option<Duration>,
+    pub time_to_full: Option<Duration>,
+}
+
+/// How low the battery is, ordered by severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BatteryLevel {
+    Normal,
+    Low,
+    Critical,
+}
+
+impl BatteryLevel {
+    /// The level of `state`. Batteries are never low while they are charging.
+    pub fn of(state: &BatteryState, low_threshold: u32, critical_threshold: u32) -> Self {
+        if state.state != ChargeState::Discharging {
+            BatteryLevel::Normal
+        } else if state.percentage <= critical_threshold as f32 {
+            BatteryLevel::Critical
+        } else if state.percentage <= low_threshold as f32 {
+            BatteryLevel::Low
+        } else {
+            BatteryLevel::Normal
+        }
+    }
+}
+
+/// What to do when the battery becomes low or critical.
+#[derive(Debug, Clone)]
+struct Alerts {
+    low_threshold: u32,
+    critical_threshold: u32,
+    notify: bool,
+    on_low: Option<String>,
+    on_critical: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct PowerSupply {
+    rx: Arc<Mutex<watch::Receiver<Option<BatteryState>>>>,
+}
+
+impl PowerSupply {
+    pub fn new(config: &Battery) -> Self {
+        Self::with_sources(config, None, PathBuf::from(sysfs::SYSFS_POWER_SUPPLY_DIR))
+    }
+
+    /// Creates the service for the UPower instance on the bus at `upower_address`, the system
+    /// bus if `None`, falling back to the batteries in `sysfs_root`.
+    pub fn with_sources(
+        config: &Battery,
+        upower_address: Option<String>,
+        sysfs_root: PathBuf,
+    ) -> Self {
+        let (tx, rx) = watch::channel(None);
+        let alerts = Alerts {
+            low_threshold: config.low_threshold,
+            critical_threshold: config.critical_threshold,
+            notify: config.notify,
+            on_low: config.on_low.clone(),
+            on_critical: config.on_critical.clone(),
+        };
+        tokio::spawn(alert(tx.subscribe(), alerts));
+        tokio::spawn(run(upower_address, sysfs_root, tx));
+        Self {
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "power-supply".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::BatteryUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+async fn run(
+    upower_address: Option<String>,
+    sysfs_root: PathBuf,
+    tx: watch::Sender<Option<BatteryState>>,
+) {
+    while !tx.is_closed() {
+        if let Err(e) = upower::watch(upower_address.as_deref(), &tx).await {
+            log::info!("battery: UPower unavailable ({e}), reading sysfs instead");
+        }
+
+        let retry_at = Instant::now() + UPOWER_RETRY_INTERVAL;
+        while Instant::now() < retry_at && !tx.is_closed() {
+            let root = sysfs_root.clone();
+            let state = tokio::task::spawn_blocking(move || sysfs::read(&root))
+                .await
+                .ok()
+                .flatten();
+            tx.send_if_modified(|current| {
+                let modified = *current != state;
+                *current = state;
+                modified
+            });
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Fires the configured alerts whenever the battery becomes low or critical.
+async fn alert(mut rx: watch::Receiver<Option<BatteryState>>, alerts: Alerts) {
+    let mut last_level = BatteryLevel::Normal;
+    while rx.changed().await.is_ok() {
+        let Some(state) = rx.borrow_and_update().clone() else {
+            continue;
+        };
+        let level = BatteryLevel::of(&state, alerts.low_threshold, alerts.critical_threshold);
+        // Only alert when the level gets worse, not on every update while it is low
+        if level > last_level {
+            let command = match level {
+                BatteryLevel::Low => alerts.on_low.as_ref(),
+                BatteryLevel::Critical => alerts.on_critical.as_ref(),
+                BatteryLevel::Normal => None,
+            };
+            if let Some(command) = command {
+                crate::command::spawn(command);
+            }
+            if alerts.notify {
+                tokio::spawn(notify(level, state.percentage));
+            }
+        }
+        last_level = level;
+    }
+}
+
+/// Shows a desktop notification about the battery level.
+async fn notify(level: BatteryLevel, percentage: f32) {
+    let (summary, urgency) = match level {
+        BatteryLevel::Critical => ("Battery critically low", 2u8),
+        _ => ("Battery low", 1u8),
+    };
+    let body = format!("{percentage:.0}% remaining");
+    let hints = HashMap::from([("urgency", Value::from(urgency))]);
+
+    let result = async {
+        let connection = zbus::Connection::session().await?;
+        connection
+            .call_method(
+                Some("org.freedesktop.Notifications"),
+                "/org/freedesktop/Notifications",
+                Some("org.freedesktop.Notifications"),
+                "Notify",
+                &(
+                    "limbo",
+                    0u32,
+                    "battery-caution",
+                    summary,
+                    body.as_str(),
+                    Vec::<&str>::new(),
+                    hints,
+                    -1i32,
+                ),
+            )
+            .await
+    }
+    .await;
+    if let Err(e) = result {
+        log::warn!("battery: failed to send notification: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::object_server::SignalEmitter;
+
+    use super::*;
+    use crate::testing::{PrivateBus, TempDir};
+
+    const DISPLAY_DEVICE: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+
+    struct FakeDisplayDevice {
+        percentage: f64,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.UPower.Device")]
+    impl FakeDisplayDevice {
+        #[zbus(property, name = "Type")]
+        fn kind(&self) -> u32 {
+            upower::DEVICE_KIND_BATTERY
+        }
+
+        #[zbus(property)]
+        fn is_present(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn percentage(&self) -> f64 {
+            self.percentage
+        }
+
+        /// Discharging
+        #[zbus(property)]
+        fn state(&self) -> u32 {
+            2
+        }
+
+        #[zbus(property)]
+        fn time_to_empty(&self) -> i64 {
+            (self.percentage * 60.0) as i64
+        }
+
+        #[zbus(property)]
+        fn time_to_full(&self) -> i64 {
+            0
+        }
+    }
+
+    async fn next_state(rx: &mut watch::Receiver<Option<BatteryState>>) -> Option<BatteryState> {
+        tokio::time::timeout(Duration::from_secs(5), rx.changed())
+            .await
+            .expect("timed out waiting for the battery state")
+            .unwrap();
+        rx.borrow_and_update().clone()
+    }
+
+    async fn wait_for(path: &std::path::Path) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !path.exists() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("timed out waiting for {}", path.display()));
+    }
+
+    #[tokio::test]
+    async fn aggregates_sysfs_batteries_without_upower() {
+        let sysfs = TempDir::new();
+        // Reports energy in µWh and power in µW
+        sysfs.write("BAT0/type", "Battery\n");
+        sysfs.write("BAT0/status", "Discharging\n");
+        sysfs.write("BAT0/energy_now", "30000000\n");
+        sysfs.write("BAT0/energy_full", "60000000\n");
+        sysfs.write("BAT0/power_now", "10000000\n");
+        // Reports charge in µAh and a negative current in µA, at 10V
+        sysfs.write("BAT1/type", "Battery\n");
+        sysfs.write("BAT1/status", "Discharging\n");
+        sysfs.write("BAT1/charge_now", "1000000\n");
+        sysfs.write("BAT1/charge_full", "2000000\n");
+        sysfs.write("BAT1/current_now", "-2000000\n");
+        sysfs.write("BAT1/voltage_min_design", "10000000\n");
+        // Neither the battery of a mouse nor the charger count
+        sysfs.write("hidpp_battery_0/type", "Battery\n");
+        sysfs.write("hidpp_battery_0/scope", "Device\n");
+        sysfs.write("hidpp_battery_0/energy_now", "0\n");
+        sysfs.write("hidpp_battery_0/energy_full", "1000000\n");
+        sysfs.write("AC/type", "Mains\n");
+
+        let config = Battery {
+            notify: false,
+            ..Default::default()
+        };
+        let power_supply = PowerSupply::with_sources(
+            &config,
+            Some("unix:path=/nonexistent/limbo-test-bus".to_string()),
+            sysfs.path().to_path_buf(),
+        );
+        let mut rx = power_supply.rx.lock().await.clone();
+        assert_eq!(
+            next_state(&mut rx).await,
+            Some(BatteryState {
+                percentage: 50.0,
+                state: ChargeState::Discharging,
+                // 40Wh left at 30W
+                time_to_empty: Some(Duration::from_secs(80 * 60)),
+                time_to_full: None,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn follows_upower_and_alerts_once_per_level() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let upower = bus.connection().await;
+        upower
+            .object_server()
+            .at(DISPLAY_DEVICE, FakeDisplayDevice { percentage: 15.0 })
+            .await
+            .unwrap();
+        upower.request_name("org.freedesktop.UPower").await.unwrap();
+
+        let dir = TempDir::new();
+        let (low, critical) = (dir.path().join("low"), dir.path().join("critical"));
+        let config = Battery {
+            notify: false,
+            on_low: Some(format!("echo >> {}", low.display())),
+            on_critical: Some(format!("echo >> {}", critical.display())),
+            ..Default::default()
+        };
+        // sysfs isn't read while UPower is running
+        let power_supply =
+            PowerSupply::with_sources(&config, Some(bus.address.clone()), dir.path().join("sys"));
+        let mut rx = power_supply.rx.lock().await.clone();
+        assert_eq!(
+            next_state(&mut rx).await,
+            Some(BatteryState {
+                percentage: 15.0,
+                state: ChargeState::Discharging,
+                time_to_empty: Some(Duration::from_secs(15 * 60)),
+                time_to_full: None,
+            })
+        );
+        wait_for(&low).await;
+
+        let device = upower
+            .object_server()
+            .interface::<_, FakeDisplayDevice>(DISPLAY_DEVICE)
+            .await
+            .unwrap();
+        let emitter = SignalEmitter::new(&upower, DISPLAY_DEVICE).unwrap();
+        for percentage in [12.0, 8.0] {
+            device.get_mut().await.percentage = percentage;
+            device
+                .get()
+                .await
+                .percentage_changed(&emitter)
+                .await
+                .unwrap();
+            let state = next_state(&mut rx).await.unwrap();
+            assert_eq!(state.percentage, percentage as f32);
+        }
+        wait_for(&critical).await;
+        // Staying low didn't run the command again
+        assert_eq!(std::fs::read_to_string(&low).unwrap(), "\n");
+    }
+
+    #[test]
+    fn is_never_low_while_charging() {
+        let state = |percentage, state| BatteryState {
+            percentage,
+            state,
+            time_to_empty: None,
+            time_to_full: None,
+        };
+        let level = |s| BatteryLevel::of(&s, 20, 10);
+        assert_eq!(
+            level(state(21.0, ChargeState::Discharging)),
+            BatteryLevel::Normal
+        );
+        assert_eq!(
+            level(state(20.0, ChargeState::Discharging)),
+            BatteryLevel::Low
+        );
+        assert_eq!(
+            level(state(10.0, ChargeState::Discharging)),
+            BatteryLevel::Critical
+        );
+        assert_eq!(
+            level(state(5.0, ChargeState::Charging)),
+            BatteryLevel::Normal
+        );
+        assert_eq!(
+            level(state(5.0, ChargeState::NotCharging)),
+            BatteryLevel::Normal
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Batteries from `/sys/class/power_supply`, used when UPower isn't running.
+
+use std::path::Path;
+use std::time::Duration;
+
+use super::{BatteryState, ChargeState};
+
+pub const SYSFS_POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Charge of a single battery, in µWh and µW.
+#[derive(Debug)]
+struct Supply {
+    energy_now: f64,
+    energy_full: f64,
+    power: f64,
+    state: ChargeState,
+}
+
+/// Reads all system batteries below `root`, aggregated into one.
+pub fn read(root: &Path) -> Option<BatteryState> {
+    let supplies = std::fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| read_supply(&entry.path()))
+        .collect::<Vec<_>>();
+    aggregate(&supplies)
+}
+
+fn read_supply(path: &Path) -> Option<Supply> {
+    if read_string(&path.join("type")).as_deref() != Some("Battery") {
+        return None;
+    }
+    // Batteries of peripherals like mice are scoped to their device
+    if read_string(&path.join("scope")).as_deref() == Some("Device") {
+        return None;
+    }
+    if read_string(&path.join("present")).as_deref() == Some("0") {
+        return None;
+    }
+
+    let state = match read_string(&path.join("status")).as_deref() {
+        Some("Charging") => ChargeState::Charging,
+        Some("Discharging") => ChargeState::Discharging,
+        Some("Full") => ChargeState::Full,
+        _ => ChargeState::NotCharging,
+    };
+
+    // Drivers either report energy in µWh and power in µW, or charge in µAh and current in µA,
+    // which is converted to energy using the voltage.
+    let read_num = |name: &str| -> Option<f64> { read_string(&path.join(name))?.parse().ok() };
+    let (energy_now, energy_full, power) = if let Some(energy_now) = read_num("energy_now") {
+        (
+            energy_now,
+            read_num("energy_full")?,
+            read_num("power_now").unwrap_or(0.0),
+        )
+    } else {
+        let volts = read_num("voltage_min_design").or_else(|| read_num("voltage_now"))? / 1e6;
+        (
+            read_num("charge_now")? * volts,
+            read_num("charge_full")? * volts,
+            read_num("current_now").unwrap_or(0.0) * volts,
+        )
+    };
+    if energy_full <= 0.0 {
+        return None;
+    }
+
+    Some(Supply {
+        energy_now,
+        energy_full,
+        // Some drivers report a negative current while discharging
+        power: power.abs(),
+        state,
+    })
+}
+
+fn aggregate(supplies: &[Supply]) -> Option<BatteryState> {
+    if supplies.is_empty() {
+        return None;
+    }
+
+    let energy_now = supplies.iter().map(|s| s.energy_now).sum::<f64>();
+    let energy_full = supplies.iter().map(|s| s.energy_full).sum::<f64>();
+    let power = supplies.iter().map(|s| s.power).sum::<f64>();
+
+    let state = if supplies.iter().any(|s| s.state == ChargeState::Charging) {
+        ChargeState::Charging
+    } else if supplies.iter().any(|s| s.state == ChargeState::Discharging) {
+        ChargeState::Discharging
+    } else if supplies.iter().all(|s| s.state == ChargeState::Full) {
+        ChargeState::Full
+    } else {
+        ChargeState::NotCharging
+    };
+
+    let hours =
+        |energy: f64| (power > 0.0).then(|| Duration::from_secs_f64(energy / power * 3600.0));
+    let (time_to_empty, time_to_full) = match state {
+        ChargeState::Discharging => (hours(energy_now), None),
+        // Batteries may report more than their full energy while topping up
+        ChargeState::Charging => (None, hours((energy_full - energy_now).max(0.0))),
+        _ => (None, None),
+    };
+
+    Some(BatteryState {
+        percentage: (energy_now / energy_full * 100.0).clamp(0.0, 100.0) as f32,
+        state,
+        time_to_empty,
+        time_to_full,
+    })
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supply(energy_now: f64, energy_full: f64, power: f64, state: ChargeState) -> Supply {
+        Supply {
+            energy_now,
+            energy_full,
+            power,
+            state,
+        }
+    }
+
+    #[test]
+    fn aggregates_batteries() {
+        let state = aggregate(&[
+            supply(20.0, 50.0, 5.0, ChargeState::Discharging),
+            supply(30.0, 50.0, 5.0, ChargeState::NotCharging),
+        ])
+        .unwrap();
+        assert_eq!(state.percentage, 50.0);
+        assert_eq!(state.state, ChargeState::Discharging);
+        assert_eq!(state.time_to_empty, Some(Duration::from_secs(5 * 3600)));
+        assert_eq!(state.time_to_full, None);
+        assert_eq!(aggregate(&[]), None);
+    }
+
+    #[test]
+    fn charging_past_full_takes_no_time() {
+        let state = aggregate(&[supply(51.0, 50.0, 2.0, ChargeState::Charging)]).unwrap();
+        assert_eq!(state.percentage, 100.0);
+        assert_eq!(state.time_to_full, Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,110 @@
+//! Batteries through UPower, which aggregates them into a single display device.
+//!
+//! See <https://upower.freedesktop.org/docs/Device.html>
+
+use std::time::Duration;
+
+use iced::futures::StreamExt;
+use tokio::sync::watch;
+use zbus::proxy;
+use zbus::proxy::CacheProperties;
+
+use super::{BatteryState, ChargeState};
+
+/// `UP_DEVICE_KIND_BATTERY`
+pub const DEVICE_KIND_BATTERY: u32 = 2;
+
+#[proxy(
+    interface = "org.freedesktop.UPower.Device",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower/devices/DisplayDevice"
+)]
+trait Device {
+    #[zbus(property, name = "Type")]
+    fn kind(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn is_present(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn percentage(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<u32>;
+
+    /// Seconds until empty, `0` if unknown.
+    #[zbus(property)]
+    fn time_to_empty(&self) -> zbus::Result<i64>;
+
+    /// Seconds until full, `0` if unknown.
+    #[zbus(property)]
+    fn time_to_full(&self) -> zbus::Result<i64>;
+}
+
+/// Publishes the battery state to `tx` whenever it changes, until UPower exits.
+pub async fn watch(
+    address: Option<&str>,
+    tx: &watch::Sender<Option<BatteryState>>,
+) -> zbus::Result<()> {
+    let builder = match address {
+        Some(address) => zbus::connection::Builder::address(address)?,
+        None => zbus::connection::Builder::system()?,
+    };
+    let connection = builder.build().await?;
+
+    let device = DeviceProxy::builder(&connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    let properties = zbus::fdo::PropertiesProxy::builder(&connection)
+        .destination(device.inner().destination().to_owned())?
+        .path(device.inner().path().to_owned())?
+        .build()
+        .await?;
+    let mut changes = properties.receive_properties_changed().await?;
+    let mut owner_changes = device.inner().receive_owner_changed().await?;
+
+    loop {
+        let state = query(&device).await?;
+        tx.send_if_modified(|current| {
+            let modified = *current != state;
+            *current = state;
+            modified
+        });
+
+        tokio::select! {
+            change = changes.next() => {
+                if change.is_none() {
+                    return Ok(());
+                }
+            }
+            Some(owner) = owner_changes.next() => {
+                if owner.is_none() {
+                    return Err(zbus::Error::Failure("UPower exited".to_string()));
+                }
+            }
+        }
+    }
+}
+
+async fn query(device: &DeviceProxy<'_>) -> zbus::Result<Option<BatteryState>> {
+    if device.kind().await? != DEVICE_KIND_BATTERY || !device.is_present().await? {
+        return Ok(None);
+    }
+
+    let state = match device.state().await? {
+        1 => ChargeState::Charging,
+        // Discharging or empty
+        2 | 3 => ChargeState::Discharging,
+        4 => ChargeState::Full,
+        _ => ChargeState::NotCharging,
+    };
+    let seconds = |s: i64| (s > 0).then(|| Duration::from_secs(s as u64));
+
+    Ok(Some(BatteryState {
+        percentage: device.percentage().await? as f32,
+        state,
+        time_to_empty: seconds(device.time_to_empty().await?),
+        time_to_full: seconds(device.time_to_full().await?),
+    }))
+}
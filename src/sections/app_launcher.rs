@@ -1 +1,201 @@
+use std::path::Path;
+use std::rc::Rc;
 
+use iced::id::Id;
+use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
+use iced::widget::{Column, column, container, image, mouse_area, row, svg, text, text_input};
+use iced::{Alignment, Element, Length, Task, Theme, window};
+
+use crate::GlobalState;
+use crate::components::icon;
+use crate::config::Config;
+use crate::launcher::{LauncherState, Match, search};
+use crate::message::Message;
+use crate::popup::PopupKind;
+
+const POPUP_WIDTH: f32 = 360.;
+const APP_ICON_SIZE: f32 = 24.;
+
+pub struct AppLauncher {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    state: LauncherState,
+    input_id: Id,
+    query: String,
+    results: Vec<Match>,
+    /// Index of the highlighted result, launched on enter.
+    selected: usize,
+}
+
+impl AppLauncher {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        let mut launcher = Self {
+            bar_id,
+            config: global_state.config.clone(),
+            state: global_state.launcher.clone(),
+            input_id: Id::unique(),
+            query: String::new(),
+            results: Vec::new(),
+            selected: 0,
+        };
+        launcher.search();
+        launcher
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        match message {
+            Message::LauncherUpdate(state) => {
+                self.state = state.clone();
+                self.search();
+            }
+            Message::LauncherQuery(query) => {
+                self.query = query.clone();
+                self.selected = 0;
+                self.search();
+            }
+            // Start over the next time the launcher opens
+            Message::TogglePopup(bar_id, PopupKind::AppLauncher) if *bar_id == self.bar_id => {
+                self.query.clear();
+                self.selected = 0;
+                self.search();
+            }
+            _ => {}
+        }
+    }
+
+    fn search(&mut self) {
+        self.results = search(
+            &self.state.entries,
+            &self.state.usage,
+            &self.query,
+            self.config.bar.app_launcher.max_results,
+        );
+        self.selected = self.selected.min(self.results.len().saturating_sub(1));
+    }
+
+    /// Moves the highlight with the arrow keys and tab.
+    pub fn key_pressed(&mut self, key: &Key, modifiers: Modifiers) {
+        let Key::Named(key) = key else {
+            return;
+        };
+        let len = self.results.len();
+        if len == 0 {
+            return;
+        }
+        self.selected = match key {
+            Named::ArrowUp => (self.selected + len - 1) % len,
+            Named::Tab if modifiers.shift() => (self.selected + len - 1) % len,
+            Named::ArrowDown | Named::Tab => (self.selected + 1) % len,
+            _ => return,
+        };
+    }
+
+    /// Focuses the search field, once the popup has opened.
+    pub fn focus(&self) -> Task<Message> {
+        text_input::focus(self.input_id.clone())
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let cfg = &self.config.bar.app_launcher;
+        let on_press = match &cfg.mouse_commands.on_primary_click {
+            Some(cmd) => Message::RunCommand(cmd.clone()),
+            None => Message::TogglePopup(self.bar_id, PopupKind::AppLauncher),
+        };
+        let mut area =
+            mouse_area(self.config.section(self.config.icon(&cfg.icon))).on_press(on_press);
+        if let Some(cmd) = &cfg.mouse_commands.on_middle_click {
+            area = area.on_middle_press(Message::RunCommand(cmd.clone()));
+        }
+        if let Some(cmd) = &cfg.mouse_commands.on_secondary_click {
+            area = area.on_right_press(Message::RunCommand(cmd.clone()));
+        }
+        area.into()
+    }
+
+    /// The message launching the result at `index`.
+    fn launch_message(&self, index: usize) -> Option<Message> {
+        let m = self.results.get(index)?;
+        let entry = self.state.entries.get(m.entry)?;
+        Some(Message::LaunchApp {
+            id: entry.id.clone(),
+            action: m.action.map(|action| entry.actions[action].id.clone()),
+        })
+    }
+
+    /// Contents of the popup: the search field and the best matching applications.
+    pub fn popup_view(&self) -> Element<'_, Message> {
+        let mut input = text_input("Search applications", &self.query)
+            .id(self.input_id.clone())
+            .on_input(Message::LauncherQuery)
+            .padding(8);
+        if let Some(message) = self.launch_message(self.selected) {
+            input = input.on_submit(message);
+        }
+
+        let dimmed = |theme: &Theme| text::Style {
+            color: Some(theme.palette().text.scale_alpha(0.6)),
+        };
+        let results = self.results.iter().enumerate().filter_map(|(i, m)| {
+            let entry = self.state.entries.get(m.entry)?;
+            let (name, detail, icon_path) = match m.action {
+                Some(action) => {
+                    let action = &entry.actions[action];
+                    (&action.name, Some(&entry.name), action.icon.as_deref())
+                }
+                None => (
+                    &entry.name,
+                    entry.generic_name.as_ref(),
+                    entry.icon.as_deref(),
+                ),
+            };
+
+            let mut label = row![text(name)].spacing(8).align_y(Alignment::Center);
+            if let Some(detail) = detail {
+                label = label.push(text(detail).size(12).style(dimmed));
+            }
+            let selected = i == self.selected;
+            let item = container(
+                row![app_icon(icon_path), label]
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+            )
+            .padding([6, 8])
+            .width(Length::Fill)
+            .style(move |theme: &Theme| {
+                if selected {
+                    container::background(theme.palette().primary.scale_alpha(0.3))
+                } else {
+                    container::Style::default()
+                }
+            });
+            Some(mouse_area(item).on_press(self.launch_message(i)?).into())
+        });
+
+        let mut content = column![input].spacing(8).width(Length::Fixed(POPUP_WIDTH));
+        if self.results.is_empty() {
+            content = content.push(text("No applications found").style(dimmed));
+        } else {
+            content = content.push(Column::from_iter(results).spacing(2));
+        }
+        content.into()
+    }
+}
+
+/// The icon of an application, or a generic one if it has none.
+fn app_icon<'a>(path: Option<&Path>) -> Element<'a, Message> {
+    let size = Length::Fixed(APP_ICON_SIZE);
+    match path {
+        Some(path) if path.extension().is_some_and(|ext| ext == "svg") => {
+            svg(svg::Handle::from_path(path))
+                .width(size)
+                .height(size)
+                .into()
+        }
+        Some(path) => image(image::Handle::from_path(path))
+            .width(size)
+            .height(size)
+            .into(),
+        None => icon("apps", None).width(size).height(size).into(),
+    }
+}
@@ -1 +1,100 @@
+use std::rc::Rc;
+use std::time::Duration;
 
+use iced::widget::{mouse_area, row, text};
+use iced::{Alignment, Color};
+
+use crate::GlobalState;
+use crate::components::icon;
+use crate::config::Config;
+use crate::config::types::Icon;
+use crate::message::Message;
+use crate::power_supply::{BatteryLevel, BatteryState, ChargeState};
+
+use super::run_mouse_commands;
+
+pub struct Battery {
+    config: Rc<Config>,
+    state: Option<BatteryState>,
+}
+
+impl Battery {
+    pub fn new(global_state: &GlobalState) -> Self {
+        Self {
+            config: global_state.config.clone(),
+            state: global_state.battery.clone(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::BatteryUpdate(state) = message {
+            self.state = state.clone();
+        }
+    }
+
+    /// `None` on systems without a battery.
+    pub fn view(&self) -> Option<iced::Element<'_, Message>> {
+        let cfg = &self.config.bar.battery;
+        let state = self.state.as_ref()?;
+
+        let battery_icon = battery_icon(&cfg.ramp_icons, &cfg.charging_icon, state)?;
+        let color = level_color(&self.config, state)
+            .or_else(|| self.config.theme.resolve_color(&battery_icon.color));
+
+        let full = state.state != ChargeState::Discharging
+            && state.percentage >= cfg.full_threshold as f32;
+        let remaining = match state.state {
+            _ if full => None,
+            ChargeState::Discharging => state.time_to_empty,
+            ChargeState::Charging => state.time_to_full,
+            _ => None,
+        };
+        let label = match remaining {
+            Some(remaining) => format!("{:.0}% · {}", state.percentage, format_duration(remaining)),
+            None => format!("{:.0}%", state.percentage),
+        };
+
+        let content = self.config.section(
+            row![icon(&battery_icon.name, color), text(label)]
+                .spacing(6)
+                .align_y(Alignment::Center),
+        );
+
+        let area = run_mouse_commands(mouse_area(content), &cfg.mouse_commands);
+
+        Some(area.into())
+    }
+}
+
+/// The icon for `state`, the charging icon while charging and otherwise picked from `ramp`.
+pub fn battery_icon<'a>(
+    ramp: &'a [Icon],
+    charging_icon: &'a Icon,
+    state: &BatteryState,
+) -> Option<&'a Icon> {
+    if state.state == ChargeState::Charging {
+        Some(charging_icon)
+    } else {
+        Icon::from_ramp(ramp, state.percentage / 100.0)
+    }
+}
+
+/// The configured color for a low or critical battery, `None` if it is neither.
+pub fn level_color(config: &Config, state: &BatteryState) -> Option<Color> {
+    let cfg = &config.bar.battery;
+    match BatteryLevel::of(state, cfg.low_threshold, cfg.critical_threshold) {
+        BatteryLevel::Normal => None,
+        BatteryLevel::Low => config.theme.resolve_color(&cfg.low_color),
+        BatteryLevel::Critical => config.theme.resolve_color(&cfg.critical_color),
+    }
+}
+
+/// Formats a duration like `2h 05m` or `45m`.
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{minutes}m")
+    }
+}
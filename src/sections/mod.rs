@@ -1,9 +1,46 @@
+mod app_launcher;
+mod battery;
 mod clock;
+mod music;
+mod notifications;
 mod quick_settings;
 mod sysmon;
+mod todo;
 mod workspaces;
 
+use iced::widget::MouseArea;
+
+use crate::config::types::MouseCommands;
+use crate::message::Message;
+
+pub use app_launcher::AppLauncher;
+pub use battery::Battery;
 pub use clock::Clock;
-pub use quick_settings::TrayView;
+pub use music::Music;
+pub use notifications::Notifications;
+pub use quick_settings::QuickSettings;
 pub use sysmon::{SysInfo, Sysmon};
+pub use todo::Todo;
 pub use workspaces::Workspaces;
+
+/// Runs the configured commands when `area` is clicked, `on_clicked` being the older name of
+/// `on_primary_click`.
+fn run_mouse_commands<'a>(
+    mut area: MouseArea<'a, Message>,
+    commands: &MouseCommands,
+) -> MouseArea<'a, Message> {
+    if let Some(cmd) = commands
+        .on_primary_click
+        .as_ref()
+        .or(commands.on_clicked.as_ref())
+    {
+        area = area.on_press(Message::RunCommand(cmd.clone()));
+    }
+    if let Some(cmd) = &commands.on_middle_click {
+        area = area.on_middle_press(Message::RunCommand(cmd.clone()));
+    }
+    if let Some(cmd) = &commands.on_secondary_click {
+        area = area.on_right_press(Message::RunCommand(cmd.clone()));
+    }
+    area
+}
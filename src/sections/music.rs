@@ -1 +1,362 @@
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
+use iced::advanced::mouse;
+use iced::widget::{Space, column, container, image, mouse_area, row, slider, svg, text};
+use iced::{Alignment, Element, Length, Theme, window};
+
+use crate::GlobalState;
+use crate::components::icon;
+use crate::config::Config;
+use crate::message::Message;
+use crate::mpris::{LoopStatus, MprisState, PlaybackStatus, Player};
+use crate::popup::PopupKind;
+
+/// Interval at which a long title scrolls by one character.
+const MARQUEE_INTERVAL: Duration = Duration::from_millis(300);
+/// Interval at which the progress is updated while playing.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+/// Gap between the end of a scrolling title and its start.
+const MARQUEE_GAP: &str = "   ";
+const PROGRESS_WIDTH: f32 = 60.;
+/// Width of the progress in the popup of players that can't seek.
+const SEEK_WIDTH: f32 = 220.;
+const POPUP_WIDTH: f32 = 320.;
+const ART_SIZE: f32 = 96.;
+
+pub struct Music {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    state: MprisState,
+    /// Number of characters the title has scrolled by.
+    marquee_offset: usize,
+    /// Time of the last tick, used to interpolate the progress.
+    now: Instant,
+    /// Position the seek slider is dragged to, shown instead of the progress until released.
+    seeking: Option<Duration>,
+}
+
+impl Music {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+            state: global_state.mpris.clone(),
+            marquee_offset: 0,
+            now: Instant::now(),
+            seeking: None,
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        match message {
+            Message::MprisUpdate(state) => {
+                if self.label() != label(state.active()) {
+                    self.marquee_offset = 0;
+                }
+                self.state = state.clone();
+                self.now = Instant::now();
+            }
+            Message::MusicTick => {
+                if self.scrolling() {
+                    self.marquee_offset += 1;
+                }
+                self.now = Instant::now();
+            }
+            Message::DragSeekPlayer(id, position) if *id == self.bar_id => {
+                self.seeking = Some(*position);
+            }
+            Message::SeekPlayer(_) => self.seeking = None,
+            Message::TogglePopup(id, _) | Message::ClosePopup(id) if *id == self.bar_id => {
+                self.seeking = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn label(&self) -> String {
+        label(self.state.active())
+    }
+
+    fn scrolling(&self) -> bool {
+        self.label().chars().count() > self.config.bar.music.max_length
+    }
+
+    /// The visible part of the label, scrolling through it when it is too long.
+    fn marquee(&self) -> String {
+        let label = self.label();
+        let max_length = self.config.bar.music.max_length;
+        let len = label.chars().count();
+        if len <= max_length {
+            return label;
+        }
+        let cycle = len + MARQUEE_GAP.chars().count();
+        format!("{label}{MARQUEE_GAP}{label}")
+            .chars()
+            .skip(self.marquee_offset % cycle)
+            .take(max_length)
+            .collect()
+    }
+
+    /// Hidden while no player is running.
+    pub fn view(&self) -> Option<iced::Element<'_, Message>> {
+        let player = self.state.active()?;
+        let cfg = &self.config.bar.music;
+
+        let control = |name: &str, enabled: bool, message: Message| {
+            let area = mouse_area(icon(name, None));
+            if enabled {
+                area.on_press(message)
+            } else {
+                area
+            }
+        };
+
+        let mut content = row![
+            self.config.icon(&cfg.icon),
+            control(
+                "player-skip-back",
+                player.can_go_previous,
+                Message::MediaPrevious
+            ),
+            control(
+                play_pause_icon(player),
+                player.can_play_pause,
+                Message::MediaPlayPause
+            ),
+            control(
+                "player-skip-forward",
+                player.can_go_next,
+                Message::MediaNext
+            ),
+            mouse_area(text(self.marquee()))
+                .on_press(Message::TogglePopup(self.bar_id, PopupKind::Music)),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+        if let Some(length) = player.length {
+            content = content.push(progress_bar(
+                player.position(self.now),
+                length,
+                PROGRESS_WIDTH,
+            ));
+        }
+
+        let area = mouse_area(self.config.section(content)).on_scroll(|delta| {
+            let y = match delta {
+                mouse::ScrollDelta::Pixels { y, .. } => y,
+                mouse::ScrollDelta::Lines { y, .. } => y,
+            };
+            if y == 0.0 {
+                Message::Noop
+            } else {
+                Message::SwitchPlayer { forward: y < 0.0 }
+            }
+        });
+
+        Some(area.into())
+    }
+
+    /// Contents of the popup: album art, a seekable progress slider, the playback controls with
+    /// shuffle and loop, and the volume of the player.
+    pub fn popup_view(&self) -> Element<'_, Message> {
+        let Some(player) = self.state.active() else {
+            return text("Nothing is playing")
+                .width(Length::Fixed(POPUP_WIDTH))
+                .into();
+        };
+        let dimmed = |theme: &Theme| text::Style {
+            color: Some(theme.palette().text.scale_alpha(0.6)),
+        };
+
+        let art: Element<'_, Message> = match &player.art {
+            Some(art) => image(image::Handle::from_path(art.path.clone()))
+                .width(Length::Fixed(ART_SIZE))
+                .height(Length::Fixed(ART_SIZE))
+                .into(),
+            None => container(icon("disc", None).height(Length::Fixed(32.)))
+                .center(Length::Fixed(ART_SIZE))
+                .into(),
+        };
+        let title = if player.title.is_empty() {
+            &player.identity
+        } else {
+            &player.title
+        };
+        let header = row![
+            art,
+            column![
+                text(title).size(16),
+                text(player.artists.join(", ")),
+                text(&player.identity).size(12).style(dimmed),
+            ]
+            .spacing(4),
+        ]
+        .spacing(12)
+        .align_y(Alignment::Center);
+
+        let mut content = column![header]
+            .spacing(12)
+            .width(Length::Fixed(POPUP_WIDTH));
+
+        if let Some(length) = player.length {
+            let position = self.seeking.unwrap_or_else(|| player.position(self.now));
+            let progress: Element<'_, Message> = if player.can_seek {
+                let bar_id = self.bar_id;
+                // Seeking on every change would flood the player while dragging
+                slider(
+                    0.0..=length.as_secs_f32(),
+                    position.as_secs_f32(),
+                    move |position| {
+                        Message::DragSeekPlayer(bar_id, Duration::from_secs_f32(position))
+                    },
+                )
+                .on_release(Message::SeekPlayer(position))
+                .into()
+            } else {
+                progress_bar(position, length, SEEK_WIDTH)
+            };
+            content = content.push(
+                row![
+                    text(format_time(position)).size(12).style(dimmed),
+                    progress,
+                    text(format_time(length)).size(12).style(dimmed),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        let mut controls = row![].spacing(16).align_y(Alignment::Center);
+        if let Some(shuffle) = player.shuffle {
+            controls = controls.push(
+                mouse_area(toggle_icon("arrows-shuffle", shuffle))
+                    .on_press(Message::SetPlayerShuffle(!shuffle)),
+            );
+        }
+        controls = controls
+            .push(mouse_area(icon("player-skip-back", None)).on_press(Message::MediaPrevious))
+            .push(mouse_area(icon(play_pause_icon(player), None)).on_press(Message::MediaPlayPause))
+            .push(mouse_area(icon("player-skip-forward", None)).on_press(Message::MediaNext));
+        if let Some(status) = player.loop_status {
+            let name = match status {
+                LoopStatus::Track => "repeat-once",
+                LoopStatus::None | LoopStatus::Playlist => "repeat",
+            };
+            controls = controls.push(
+                mouse_area(toggle_icon(name, status != LoopStatus::None))
+                    .on_press(Message::SetPlayerLoop(status.next())),
+            );
+        }
+        content = content.push(container(controls).center_x(Length::Fill));
+
+        if let Some(volume) = player.volume {
+            content = content.push(
+                row![
+                    icon("volume", None),
+                    slider(0.0..=1.0, volume, Message::SetPlayerVolume).step(0.01),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        let tint = player.art.as_ref().and_then(|art| art.color);
+        match tint {
+            Some(color) if self.config.bar.music.tint_popup => {
+                let radius = iced::Radius::new(self.config.theme.border_radius * 2.0);
+                container(content)
+                    .style(move |_| container::Style {
+                        background: Some(color.scale_alpha(0.3).into()),
+                        border: iced::Border {
+                            radius,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .padding(12)
+                    .into()
+            }
+            _ => content.into(),
+        }
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        let playing = self
+            .state
+            .active()
+            .is_some_and(|player| player.status == PlaybackStatus::Playing);
+        if self.scrolling() {
+            iced::time::every(MARQUEE_INTERVAL).map(|_| Message::MusicTick)
+        } else if playing {
+            iced::time::every(PROGRESS_INTERVAL).map(|_| Message::MusicTick)
+        } else {
+            iced::Subscription::none()
+        }
+    }
+}
+
+/// A thin bar of `width` filled up to `position`.
+fn progress_bar<'a>(position: Duration, length: Duration, width: f32) -> Element<'a, Message> {
+    let fraction = position.as_secs_f32() / length.as_secs_f32();
+    let filled = container(Space::new(
+        Length::Fixed(width * fraction.clamp(0.0, 1.0)),
+        Length::Fixed(3.),
+    ))
+    .style(|theme: &Theme| container::background(theme.palette().primary));
+    container(filled)
+        .width(Length::Fixed(width))
+        .style(|theme: &Theme| container::background(theme.palette().text.scale_alpha(0.2)))
+        .into()
+}
+
+fn play_pause_icon(player: &Player) -> &'static str {
+    if player.status == PlaybackStatus::Playing {
+        "player-pause"
+    } else {
+        "player-play"
+    }
+}
+
+/// A control icon in the primary color while `active`, dimmed otherwise.
+fn toggle_icon(name: &str, active: bool) -> svg::Svg<'static> {
+    icon(name, None).style(move |theme: &Theme, _| svg::Style {
+        color: Some(if active {
+            theme.palette().primary
+        } else {
+            theme.palette().text.scale_alpha(0.4)
+        }),
+    })
+}
+
+/// Formats a playback position like `3:07` or `1:02:45`.
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// `title · artists` of `player`, or just the title if there are no artists.
+fn label(player: Option<&Player>) -> String {
+    let Some(player) = player else {
+        return String::new();
+    };
+    let title = if player.title.is_empty() {
+        &player.identity
+    } else {
+        &player.title
+    };
+    if player.artists.is_empty() {
+        title.clone()
+    } else {
+        format!("{title} · {}", player.artists.join(", "))
+    }
+}
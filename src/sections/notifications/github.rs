@@ -1 +1,148 @@
+use std::rc::Rc;
 
+use iced::widget::{Column, container, mouse_area, row, scrollable, text};
+use iced::{Alignment, Color, Element, Length, Theme, window};
+
+use crate::GlobalState;
+use crate::components::icon;
+use crate::config::Config;
+use crate::github::{GithubNotification, GithubState};
+use crate::message::Message;
+use crate::popup::PopupKind;
+
+const POPUP_WIDTH: f32 = 360.;
+const POPUP_MAX_HEIGHT: f32 = 480.;
+
+#[derive(Debug)]
+pub struct GithubView {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    state: GithubState,
+}
+
+impl GithubView {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+            state: global_state.github.clone(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::GithubUpdate(state) = message {
+            self.state = state.clone();
+        }
+    }
+
+    fn text_color(&self) -> Option<Color> {
+        let text = self.config.bar.notifications.github.text.as_ref()?;
+        self.config.theme.resolve_color(text.color.as_ref()?)
+    }
+
+    /// The icon and the number of unread notifications, if any. The icon is dimmed while the
+    /// API can't be reached, and the segment hidden without a token.
+    pub fn view(&self) -> Option<Element<'_, Message>> {
+        if !self.state.available {
+            return None;
+        }
+        let cfg = &self.config.bar.notifications.github;
+
+        let color = self.config.theme.resolve_color(&cfg.icon.color);
+        let color = if self.state.error {
+            color.map(|color| color.scale_alpha(0.4))
+        } else {
+            color
+        };
+        let mut content = row![icon(&cfg.icon.name, color)]
+            .spacing(6)
+            .align_y(Alignment::Center);
+        let unread = self.state.notifications.len();
+        if unread > 0 {
+            let text_color = self.text_color();
+            content =
+                content.push(
+                    text(unread.to_string()).style(move |theme: &Theme| text::Style {
+                        color: text_color.or(Some(theme.palette().text)),
+                    }),
+                );
+        }
+
+        // Without a command, clicking opens the list
+        let popup = Message::TogglePopup(self.bar_id, PopupKind::Github);
+        let mut area = mouse_area(content)
+            .on_press(
+                cfg.mouse_commands
+                    .on_primary_click
+                    .clone()
+                    .map_or(popup.clone(), Message::RunCommand),
+            )
+            .on_right_press(popup);
+        if let Some(cmd) = &cfg.mouse_commands.on_middle_click {
+            area = area.on_middle_press(Message::RunCommand(cmd.clone()));
+        }
+        Some(area.into())
+    }
+
+    /// Contents of the popup: the unread notifications, grouped by repository.
+    pub fn popup_view(&self) -> Element<'_, Message> {
+        let notifications = &self.state.notifications;
+        if notifications.is_empty() {
+            return text("No unread notifications")
+                .width(Length::Fixed(POPUP_WIDTH))
+                .into();
+        }
+
+        // Groups ordered by their most recently updated notification
+        let mut groups: Vec<(&str, Vec<&GithubNotification>)> = Vec::new();
+        for notification in notifications {
+            match groups
+                .iter_mut()
+                .find(|(repo, _)| *repo == notification.repo)
+            {
+                Some((_, notifications)) => notifications.push(notification),
+                None => groups.push((&notification.repo, vec![notification])),
+            }
+        }
+        let groups = Column::from_iter(groups.into_iter().map(|(repo, notifications)| {
+            let title = text(repo).size(12).style(|theme: &Theme| text::Style {
+                color: Some(theme.palette().text.scale_alpha(0.6)),
+            });
+            Column::with_children(
+                std::iter::once(title.into())
+                    .chain(notifications.into_iter().map(notification_view)),
+            )
+            .spacing(8)
+            .into()
+        }))
+        .spacing(16);
+
+        container(scrollable(groups))
+            .width(Length::Fixed(POPUP_WIDTH))
+            .max_height(POPUP_MAX_HEIGHT)
+            .into()
+    }
+}
+
+/// A notification with the icon of its kind, opening its page when clicked.
+fn notification_view(notification: &GithubNotification) -> Element<'_, Message> {
+    let name = match notification.kind.as_str() {
+        "PullRequest" => "git-pull-request",
+        "Issue" => "circle-dot",
+        "Release" => "tag",
+        "Commit" => "git-commit",
+        "Discussion" => "message",
+        "CheckSuite" => "alert-circle",
+        _ => "bell",
+    };
+    mouse_area(
+        row![
+            icon(name, None).width(Length::Fixed(16.)),
+            text(&notification.title).width(Length::Fill),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center),
+    )
+    .on_press(Message::OpenUrl(notification.url.clone()))
+    .into()
+}
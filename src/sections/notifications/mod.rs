@@ -1 +1,78 @@
+use std::rc::Rc;
 
+use iced::widget::Row;
+use iced::{Alignment, Element, window};
+
+use crate::GlobalState;
+use crate::config::Config;
+use crate::config::types::NotificationSegment;
+use crate::message::Message;
+use crate::popup::PopupKind;
+
+mod github;
+mod todoist;
+mod weather;
+
+use github::GithubView;
+use todoist::TodoistView;
+use weather::WeatherView;
+
+/// The strip of segments summarizing things that want attention, like the weather or unread
+/// GitHub notifications.
+#[derive(Debug)]
+pub struct Notifications {
+    config: Rc<Config>,
+    weather: WeatherView,
+    github: GithubView,
+    todoist: TodoistView,
+}
+
+impl Notifications {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            config: global_state.config.clone(),
+            weather: WeatherView::new(bar_id, global_state),
+            github: GithubView::new(bar_id, global_state),
+            todoist: TodoistView::new(bar_id, global_state),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        self.weather.update(message);
+        self.github.update(message);
+        self.todoist.update(message);
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let segments = self
+            .config
+            .bar
+            .notifications
+            .segments
+            .iter()
+            .filter_map(|segment| match segment {
+                NotificationSegment::Weather => Some(self.weather.view()),
+                NotificationSegment::Github => self.github.view(),
+                NotificationSegment::Todoist => self.todoist.view(),
+            });
+
+        self.config
+            .section(
+                Row::from_iter(segments)
+                    .spacing(12)
+                    .align_y(Alignment::Center),
+            )
+            .into()
+    }
+
+    /// Contents of a popup opened from one of the segments.
+    pub fn popup_view(&self, kind: PopupKind) -> Element<'_, Message> {
+        match kind {
+            PopupKind::Weather => self.weather.popup_view(),
+            PopupKind::Github => self.github.popup_view(),
+            PopupKind::Todoist => self.todoist.popup_view(),
+            // Other popups belong to other modules
+            _ => iced::widget::Space::new(iced::Length::Shrink, iced::Length::Shrink).into(),
+        }
+    }
+}
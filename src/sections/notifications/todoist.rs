@@ -0,0 +1,157 @@
+use std::rc::Rc;
+
+use iced::widget::{Column, column, container, mouse_area, row, scrollable, text};
+use iced::{Alignment, Color, Element, Length, Theme, window};
+use jiff::Zoned;
+use jiff::civil::Date;
+
+use crate::GlobalState;
+use crate::components::icon;
+use crate::config::Config;
+use crate::message::Message;
+use crate::popup::PopupKind;
+use crate::todoist::{TodoistState, TodoistTask};
+
+const POPUP_WIDTH: f32 = 320.;
+const POPUP_MAX_HEIGHT: f32 = 480.;
+
+#[derive(Debug)]
+pub struct TodoistView {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    state: TodoistState,
+}
+
+impl TodoistView {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+            state: global_state.todoist.clone(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::TodoistUpdate(state) = message {
+            self.state = state.clone();
+        }
+    }
+
+    fn text_color(&self) -> Option<Color> {
+        let text = self.config.bar.notifications.todoist.text.as_ref()?;
+        self.config.theme.resolve_color(text.color.as_ref()?)
+    }
+
+    /// The icon and the number of tasks due today or overdue. The icon is dimmed while offline,
+    /// and the segment hidden without a token.
+    pub fn view(&self) -> Option<Element<'_, Message>> {
+        if !self.state.available {
+            return None;
+        }
+        let cfg = &self.config.bar.notifications.todoist;
+        let today = Zoned::now().date();
+
+        let color = self.config.theme.resolve_color(&cfg.icon.color);
+        let color = if self.state.offline {
+            color.map(|color| color.scale_alpha(0.4))
+        } else {
+            color
+        };
+        let text_color = self.text_color();
+        let content = row![
+            icon(&cfg.icon.name, color),
+            text(self.state.due(today).count().to_string()).style(move |theme: &Theme| {
+                text::Style {
+                    color: text_color.or(Some(theme.palette().text)),
+                }
+            }),
+        ]
+        .spacing(6)
+        .align_y(Alignment::Center);
+
+        // Without a command, clicking opens the list
+        let popup = Message::TogglePopup(self.bar_id, PopupKind::Todoist);
+        let mut area = mouse_area(content)
+            .on_press(
+                cfg.mouse_commands
+                    .on_primary_click
+                    .clone()
+                    .map_or(popup.clone(), Message::RunCommand),
+            )
+            .on_right_press(popup);
+        if let Some(cmd) = &cfg.mouse_commands.on_middle_click {
+            area = area.on_middle_press(Message::RunCommand(cmd.clone()));
+        }
+        Some(area.into())
+    }
+
+    /// Contents of the popup: the tasks due today or overdue, completed when their checkbox is
+    /// clicked.
+    pub fn popup_view(&self) -> Element<'_, Message> {
+        let today = Zoned::now().date();
+        let overdue_color = self
+            .config
+            .theme
+            .resolve_color(&self.config.bar.notifications.todoist.icon.color);
+
+        let mut content = column![].spacing(12);
+        if self.state.offline {
+            content = content.push(
+                text("Offline, showing the last known tasks")
+                    .size(12)
+                    .style(move |theme: &Theme| text::Style {
+                        color: overdue_color.or(Some(theme.palette().text)),
+                    }),
+            );
+        }
+        let tasks = self.state.due(today).collect::<Vec<_>>();
+        content = if tasks.is_empty() {
+            content.push(text("Nothing due today"))
+        } else {
+            content.push(scrollable(
+                Column::from_iter(
+                    tasks
+                        .into_iter()
+                        .map(|task| task_view(task, today, overdue_color)),
+                )
+                .spacing(4),
+            ))
+        };
+
+        container(content)
+            .width(Length::Fixed(POPUP_WIDTH))
+            .max_height(POPUP_MAX_HEIGHT)
+            .into()
+    }
+}
+
+/// A task with its checkbox, its priority unless it has none, and the due date in
+/// `overdue_color` if it is before `today`.
+fn task_view(
+    task: &TodoistTask,
+    today: Date,
+    overdue_color: Option<Color>,
+) -> Element<'_, Message> {
+    let dimmed = |theme: &Theme| text::Style {
+        color: Some(theme.palette().text.scale_alpha(0.6)),
+    };
+
+    let checkbox =
+        mouse_area(icon("circle", None)).on_press(Message::CompleteTodoistTask(task.id.clone()));
+    let mut content = row![checkbox].spacing(8).align_y(Alignment::Center);
+    // The lowest priority is the default one
+    if task.priority < 4 {
+        content = content.push(text(format!("P{}", task.priority)).size(12).style(dimmed));
+    }
+    content = content.push(text(&task.content).width(Length::Fill));
+    if task.due < today {
+        content = content.push(
+            text(task.due.strftime("%b %-d").to_string())
+                .size(12)
+                .style(move |theme: &Theme| text::Style {
+                    color: overdue_color.or(Some(theme.palette().text.scale_alpha(0.6))),
+                }),
+        );
+    }
+    content.into()
+}
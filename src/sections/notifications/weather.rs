@@ -1 +1,200 @@
+use std::rc::Rc;
 
+use iced::widget::{Column, column, mouse_area, row, text};
+use iced::{Alignment, Color, Element, Length, Theme, window};
+
+use crate::GlobalState;
+use crate::components::icon;
+use crate::config::Config;
+use crate::config::types::{TemperatureType, Unit};
+use crate::message::Message;
+use crate::popup::PopupKind;
+use crate::weather::{Condition, Conditions, DailyForecast, WeatherState};
+
+const POPUP_WIDTH: f32 = 280.;
+
+#[derive(Debug)]
+pub struct WeatherView {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    state: WeatherState,
+}
+
+impl WeatherView {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+            state: global_state.weather.clone(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::WeatherUpdate(state) = message {
+            self.state = state.clone();
+        }
+    }
+
+    /// The icon of the condition in its configured color, and its color.
+    fn condition_icon(&self, condition: Condition, is_day: bool) -> (&'static str, Option<Color>) {
+        let colors = &self.config.bar.notifications.weather.icon.color;
+        let (name, color) = match condition {
+            Condition::Clear if is_day => ("sun", &colors.day),
+            Condition::Clear => ("moon", &colors.night),
+            Condition::PartlyCloudy | Condition::Cloudy => ("cloud", &colors.cloud),
+            Condition::Fog => ("cloud-fog", &colors.fog),
+            Condition::Drizzle | Condition::Rain => ("cloud-rain", &colors.rain),
+            Condition::Snow => ("cloud-snow", &colors.snow),
+            Condition::Thunderstorm => ("cloud-storm", &colors.rain),
+        };
+        (name, self.config.theme.resolve_color(color))
+    }
+
+    fn current_icon(&self, current: &Conditions) -> (&'static str, Option<Color>) {
+        let colors = &self.config.bar.notifications.weather.icon.color;
+        // Wind is only worth showing when nothing falls from the sky
+        let precipitation = matches!(
+            current.condition,
+            Condition::Drizzle | Condition::Rain | Condition::Snow | Condition::Thunderstorm
+        );
+        if current.windy && !precipitation {
+            ("wind", self.config.theme.resolve_color(&colors.wind))
+        } else {
+            self.condition_icon(current.condition, current.is_day)
+        }
+    }
+
+    fn temperature(&self, current: &Conditions) -> f32 {
+        match self.config.bar.notifications.weather.temperature {
+            TemperatureType::Apparent => current.apparent_temperature,
+            TemperatureType::Exact => current.temperature,
+        }
+    }
+
+    fn text_color(&self) -> Option<Color> {
+        let text = self.config.bar.notifications.weather.text.as_ref()?;
+        self.config.theme.resolve_color(text.color.as_ref()?)
+    }
+
+    /// The current condition and temperature, with the error icon while offline.
+    pub fn view(&self) -> Element<'_, Message> {
+        let cfg = &self.config.bar.notifications.weather;
+        let error_color = self.config.theme.resolve_color(&cfg.icon.color.error);
+
+        let (name, color) = match &self.state.current {
+            Some(current) if !self.state.offline => self.current_icon(current),
+            _ => ("cloud-off", error_color),
+        };
+        let mut content = row![icon(name, color)]
+            .spacing(6)
+            .align_y(Alignment::Center);
+        if let Some(current) = &self.state.current {
+            let text_color = self.text_color();
+            content = content.push(text(format!("{:.0}°", self.temperature(current))).style(
+                move |theme: &Theme| text::Style {
+                    color: text_color.or(Some(theme.palette().text)),
+                },
+            ));
+        }
+
+        // Without a command, clicking opens the forecast
+        let popup = Message::TogglePopup(self.bar_id, PopupKind::Weather);
+        let mut area = mouse_area(content)
+            .on_press(
+                cfg.mouse_commands
+                    .on_primary_click
+                    .clone()
+                    .map_or(popup.clone(), Message::RunCommand),
+            )
+            .on_right_press(popup);
+        if let Some(cmd) = &cfg.mouse_commands.on_middle_click {
+            area = area.on_middle_press(Message::RunCommand(cmd.clone()));
+        }
+        area.into()
+    }
+
+    /// Contents of the popup: the current conditions and the forecast of the next days.
+    pub fn popup_view(&self) -> Element<'_, Message> {
+        let Some(current) = &self.state.current else {
+            return text("No weather yet")
+                .width(Length::Fixed(POPUP_WIDTH))
+                .into();
+        };
+        let dimmed = |theme: &Theme| text::Style {
+            color: Some(theme.palette().text.scale_alpha(0.6)),
+        };
+        let (temperature_unit, speed_unit) = match self.config.general.unit {
+            Unit::Metric => ("°C", "km/h"),
+            Unit::Imperial => ("°F", "mph"),
+        };
+
+        let (name, color) = self.current_icon(current);
+        let mut details = column![
+            text(format!("{:.0}{temperature_unit}", current.temperature)).size(20),
+            text(format!(
+                "Feels like {:.0}{temperature_unit} · Wind {:.0} {speed_unit}",
+                current.apparent_temperature, current.wind_speed
+            ))
+            .size(12)
+            .style(dimmed),
+        ]
+        .spacing(2);
+        if self.state.offline {
+            let error = self
+                .config
+                .theme
+                .resolve_color(&self.config.bar.notifications.weather.icon.color.error);
+            details = details.push(
+                text("Offline, showing the last known weather")
+                    .size(12)
+                    .style(move |theme: &Theme| text::Style {
+                        color: error.or(Some(theme.palette().text)),
+                    }),
+            );
+        }
+        let header = row![icon(name, color).width(Length::Fixed(40.)), details]
+            .spacing(12)
+            .align_y(Alignment::Center);
+
+        let days =
+            Column::from_iter(self.state.daily.iter().map(|day| self.day_view(day))).spacing(6);
+
+        column![header, days]
+            .spacing(16)
+            .width(Length::Fixed(POPUP_WIDTH))
+            .into()
+    }
+
+    fn day_view(&self, day: &DailyForecast) -> Element<'_, Message> {
+        let (max, min) = match self.config.bar.notifications.weather.temperature {
+            TemperatureType::Apparent => (day.apparent_max, day.apparent_min),
+            TemperatureType::Exact => (day.max, day.min),
+        };
+        let (name, color) = self.condition_icon(day.condition, true);
+        let dimmed = |theme: &Theme| text::Style {
+            color: Some(theme.palette().text.scale_alpha(0.6)),
+        };
+
+        let mut precipitation = row![].spacing(2).align_y(Alignment::Center);
+        if let Some(probability) = day.precipitation_probability.filter(|p| *p > 0) {
+            let rain = self
+                .config
+                .theme
+                .resolve_color(&self.config.bar.notifications.weather.icon.color.rain);
+            precipitation = precipitation
+                .push(icon("droplet", rain).width(Length::Fixed(12.)))
+                .push(text(format!("{probability}%")).size(12).style(dimmed));
+        }
+
+        row![
+            text(day.date.strftime("%a").to_string()).width(Length::Fixed(40.)),
+            icon(name, color).width(Length::Fixed(20.)),
+            precipitation.width(Length::Fill),
+            text(format!("{min:.0}°")).style(dimmed),
+            text(format!("{max:.0}°")),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center)
+        .into()
+    }
+}
@@ -1 +1,40 @@
+use std::rc::Rc;
 
+use crate::GlobalState;
+use crate::components::icon;
+use crate::config::Config;
+use crate::message::Message;
+use crate::power_supply::BatteryState;
+use crate::sections::battery::{battery_icon, level_color};
+
+#[derive(Debug)]
+pub struct Battery {
+    config: Rc<Config>,
+    state: Option<BatteryState>,
+}
+
+impl Battery {
+    pub fn new(global_state: &GlobalState) -> Self {
+        Self {
+            config: global_state.config.clone(),
+            state: global_state.battery.clone(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::BatteryUpdate(state) = message {
+            self.state = state.clone();
+        }
+    }
+
+    /// `None` on systems without a battery.
+    pub fn view(&self) -> Option<iced::Element<'_, Message>> {
+        let cfg = &self.config.bar.quick_settings.battery;
+        let state = self.state.as_ref()?;
+
+        let battery_icon = battery_icon(&cfg.ramp_icons, &cfg.charging_icon, state)?;
+        let color = level_color(&self.config, state)
+            .or_else(|| self.config.theme.resolve_color(&battery_icon.color));
+        Some(icon(&battery_icon.name, color).into())
+    }
+}
@@ -0,0 +1,149 @@
+use std::rc::Rc;
+
+use iced::widget::{Column, Space, mouse_area, row, text};
+use iced::{Alignment, Element, Length, Theme, window};
+
+use crate::GlobalState;
+use crate::bluetooth::{BluetoothDevice, BluetoothState};
+use crate::components::icon;
+use crate::config::Config;
+use crate::config::types::Icon;
+use crate::message::Message;
+use crate::popup::PopupKind;
+
+use super::panel::Tile;
+
+/// Width of the device list in the popup.
+const POPUP_WIDTH: f32 = 260.;
+
+#[derive(Debug)]
+pub struct BluetoothView {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    state: BluetoothState,
+}
+
+impl BluetoothView {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+            state: global_state.bluetooth.clone(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::BluetoothUpdate(state) = message {
+            self.state = state.clone();
+        }
+    }
+
+    fn connected(&self) -> impl Iterator<Item = &BluetoothDevice> {
+        self.state.devices.iter().filter(|device| device.connected)
+    }
+
+    fn icon(&self) -> &Icon {
+        let cfg = &self.config.bar.quick_settings.bluetooth;
+        if !self.state.powered {
+            &cfg.off_icon
+        } else if self.connected().next().is_some() {
+            &cfg.connected_icon
+        } else {
+            &cfg.icon
+        }
+    }
+
+    /// Hidden while there is no adapter.
+    pub fn view(&self) -> Option<iced::Element<'_, Message>> {
+        self.state.adapter.as_ref()?;
+        let cfg = &self.config.bar.quick_settings.bluetooth;
+
+        let mut content = row![self.config.icon(self.icon())]
+            .spacing(4)
+            .align_y(Alignment::Center);
+        // The battery of the first connected device that reports one, e.g. headphones
+        if let Some(battery) = self.connected().find_map(|device| device.battery) {
+            content = content.push(text(format!("{battery}%")));
+        }
+
+        let mut area = mouse_area(content)
+            .on_press(Message::SetBluetoothPowered(!self.state.powered))
+            .on_right_press(Message::TogglePopup(self.bar_id, PopupKind::Bluetooth));
+        if let Some(cmd) = &cfg.mouse_commands.on_middle_click {
+            area = area.on_middle_press(Message::RunCommand(cmd.clone()));
+        }
+
+        Some(area.into())
+    }
+
+    /// Tile of the quick settings panel, `None` while there is no adapter.
+    pub fn tile(&self) -> Option<Tile<'_>> {
+        self.state.adapter.as_ref()?;
+        let mut connected = self.connected();
+        let status = match (self.state.powered, connected.next(), connected.next()) {
+            (false, _, _) => "Off".to_string(),
+            (true, None, _) => "On".to_string(),
+            (true, Some(device), None) => device.name.clone(),
+            (true, Some(_), Some(_)) => format!("{} devices", 2 + connected.count()),
+        };
+        Some(Tile {
+            icon: self.icon(),
+            label: "Bluetooth",
+            status,
+            active: self.state.powered,
+            on_press: Some(Message::SetBluetoothPowered(!self.state.powered)),
+        })
+    }
+
+    /// Contents of the popup: the paired devices.
+    ///
+    /// Clicking a device connects to it, or disconnects it if it is connected.
+    pub fn popup_view(&self) -> iced::Element<'_, Message> {
+        if !self.state.powered {
+            return text("Bluetooth is off")
+                .width(Length::Fixed(POPUP_WIDTH))
+                .into();
+        }
+        if self.state.devices.is_empty() {
+            return text("No paired devices")
+                .width(Length::Fixed(POPUP_WIDTH))
+                .into();
+        }
+
+        Column::from_iter(
+            self.state
+                .devices
+                .iter()
+                .map(|device| self.device_view(device)),
+        )
+        .spacing(8)
+        .width(Length::Fixed(POPUP_WIDTH))
+        .into()
+    }
+
+    fn device_view<'a>(&'a self, device: &'a BluetoothDevice) -> Element<'a, Message> {
+        let connected = device.connected;
+        let label = text(&device.name)
+            .width(Length::Fill)
+            .style(move |theme: &Theme| text::Style {
+                color: (!connected).then(|| theme.palette().text.scale_alpha(0.5)),
+            });
+
+        let mut entry = row![label].spacing(8).align_y(Alignment::Center);
+        if let Some(battery) = device.battery {
+            entry = entry.push(text(format!("{battery}%")).size(12));
+        }
+        if connected {
+            entry = entry.push(icon("check", None));
+        } else {
+            entry = entry.push(Space::with_width(Length::Fixed(16.)));
+        }
+
+        let message = if connected {
+            Message::DisconnectBluetooth(device.path.clone())
+        } else {
+            Message::ConnectBluetooth(device.path.clone())
+        };
+        mouse_area(entry).on_press(message).into()
+    }
+}
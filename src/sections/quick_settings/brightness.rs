@@ -1 +1,140 @@
+use std::rc::Rc;
 
+use iced::advanced::mouse;
+use iced::widget::mouse_area;
+
+use crate::GlobalState;
+use crate::brightness::{Backlight, Change, MonitorBrightness};
+use crate::config::Config;
+use crate::config::types::Icon;
+use crate::message::Message;
+use crate::sections::run_mouse_commands;
+
+use super::panel::Slider;
+
+/// What the brightness segment of a bar controls.
+#[derive(Debug, Clone)]
+enum Target {
+    /// The external monitor the bar is shown on.
+    Monitor(MonitorBrightness),
+    Backlight(Backlight),
+}
+
+impl Target {
+    /// Brightness as shown to the user, `0.0..=1.0`.
+    fn level(&self) -> f32 {
+        match self {
+            Target::Monitor(monitor) => monitor.fraction(),
+            Target::Backlight(backlight) => backlight.perceived(),
+        }
+    }
+
+    fn set_level(&self, level: f32) -> Message {
+        let raw = match self {
+            Target::Monitor(monitor) => monitor.raw_from_fraction(level) as u32,
+            Target::Backlight(backlight) => backlight.raw_from_perceived(level),
+        };
+        self.change(Change::Set(raw))
+    }
+
+    fn change(&self, change: Change) -> Message {
+        match self {
+            Target::Monitor(monitor) => Message::ChangeMonitorBrightness {
+                output: monitor.output.clone(),
+                change,
+            },
+            Target::Backlight(backlight) => Message::ChangeBacklight {
+                device: backlight.device.clone(),
+                change,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Brightness {
+    config: Rc<Config>,
+    output_name: String,
+    backlight: Option<Backlight>,
+    monitor: Option<MonitorBrightness>,
+}
+
+impl Brightness {
+    pub fn new(output_name: String, global_state: &GlobalState) -> Self {
+        let monitor = find_monitor(&global_state.monitor_brightness, &output_name);
+        Self {
+            config: global_state.config.clone(),
+            output_name,
+            backlight: global_state.backlight.clone(),
+            monitor,
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        match message {
+            Message::BacklightUpdate(backlight) => {
+                self.backlight = backlight.clone();
+            }
+            Message::MonitorBrightnessUpdate(monitors) => {
+                self.monitor = find_monitor(monitors, &self.output_name);
+            }
+            _ => {}
+        }
+    }
+
+    /// The monitor the bar is shown on, or the backlight if that is the built-in panel.
+    fn target(&self) -> Option<Target> {
+        if let Some(monitor) = &self.monitor {
+            return Some(Target::Monitor(monitor.clone()));
+        }
+        let builtin = ["eDP", "LVDS"]
+            .iter()
+            .any(|prefix| self.output_name.starts_with(prefix));
+        self.backlight
+            .clone()
+            .filter(|_| builtin)
+            .map(Target::Backlight)
+    }
+
+    pub fn view(&self) -> Option<iced::Element<'_, Message>> {
+        let cfg = &self.config.bar.quick_settings.brightness;
+        // Hide the segment when there is nothing to control
+        let target = self.target()?;
+
+        let level = target.level();
+        let icon = Icon::from_ascending_ramp(&cfg.ramp_icons, level)?;
+
+        let step = cfg.step;
+        let area = mouse_area(self.config.icon(icon)).on_scroll(move |delta| {
+            let y = match delta {
+                mouse::ScrollDelta::Pixels { y, .. } => y,
+                mouse::ScrollDelta::Lines { y, .. } => y,
+            };
+            if y == 0.0 {
+                return Message::Noop;
+            }
+            // The service steps from the current brightness, which `level` may lag behind
+            target.change(Change::Step(if y > 0.0 { step } else { -step }))
+        });
+
+        Some(run_mouse_commands(area, &cfg.mouse_commands).into())
+    }
+
+    /// Slider of the quick settings panel, `None` when there is nothing to control.
+    pub fn slider(&self) -> Option<Slider<'_>> {
+        let cfg = &self.config.bar.quick_settings.brightness;
+        let target = self.target()?;
+        let level = target.level();
+        Some(Slider {
+            icon: Icon::from_ascending_ramp(&cfg.ramp_icons, level)?,
+            value: level,
+            max: 1.0,
+            step: cfg.step,
+            on_change: Box::new(move |level| target.set_level(level)),
+        })
+    }
+}
+
+fn find_monitor(monitors: &[MonitorBrightness], output_name: &str) -> Option<MonitorBrightness> {
+    monitors.iter().find(|m| m.output == output_name).cloned()
+}
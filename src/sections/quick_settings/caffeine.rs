@@ -1 +1,191 @@
+use std::rc::Rc;
+use std::time::Duration;
 
+use iced::advanced::mouse;
+use iced::widget::{Column, Space, mouse_area, row, text};
+use iced::{Alignment, Element, Length, window};
+
+use crate::GlobalState;
+use crate::caffeine::{CaffeineState, CaffeineTimer};
+use crate::components::icon;
+use crate::config::Config;
+use crate::message::Message;
+use crate::popup::PopupKind;
+use crate::sections::battery::format_duration;
+
+use super::panel::Tile;
+
+/// Width of the timer list in the popup.
+const POPUP_WIDTH: f32 = 180.;
+
+#[derive(Debug)]
+pub struct Caffeine {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    state: CaffeineState,
+}
+
+impl Caffeine {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+            state: global_state.caffeine,
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::CaffeineUpdate(state) = message {
+            self.state = *state;
+        }
+    }
+
+    pub fn view(&self) -> iced::Element<'_, Message> {
+        let cfg = &self.config.bar.quick_settings.caffeine;
+
+        let icon = if self.state.active() {
+            &cfg.active_icon
+        } else {
+            &cfg.icon
+        };
+        let mut content = row![self.config.icon(icon)]
+            .spacing(4)
+            .align_y(Alignment::Center);
+        if let Some(remaining) = self.state.remaining().filter(|_| cfg.show_remaining) {
+            // Round up, so that the timer doesn't show 0m for its last minute
+            content = content.push(text(format_duration(remaining + Duration::from_secs(59))));
+        }
+
+        let timer = self.state.timer;
+        let mut area = mouse_area(content)
+            .on_press(self.toggle())
+            .on_right_press(Message::TogglePopup(self.bar_id, PopupKind::Caffeine))
+            .on_scroll(move |delta| {
+                let y = match delta {
+                    mouse::ScrollDelta::Pixels { y, .. } => y,
+                    mouse::ScrollDelta::Lines { y, .. } => y,
+                };
+                let next = step_timer(timer, y > 0.0);
+                // Don't restart the timer when scrolling past either end
+                if y == 0.0 || next == timer {
+                    Message::Noop
+                } else {
+                    Message::SetCaffeine(next)
+                }
+            });
+        if let Some(cmd) = &cfg.mouse_commands.on_middle_click {
+            area = area.on_middle_press(Message::RunCommand(cmd.clone()));
+        }
+
+        area.into()
+    }
+
+    pub fn tile(&self) -> Tile<'_> {
+        let cfg = &self.config.bar.quick_settings.caffeine;
+        let status = match (self.state.timer, self.state.remaining()) {
+            (_, Some(remaining)) => {
+                format!(
+                    "{} left",
+                    format_duration(remaining + Duration::from_secs(59))
+                )
+            }
+            (Some(timer), None) => timer.label(),
+            (None, None) => "Off".to_string(),
+        };
+        Tile {
+            icon: if self.state.active() {
+                &cfg.active_icon
+            } else {
+                &cfg.icon
+            },
+            label: "Caffeine",
+            status,
+            active: self.state.active(),
+            on_press: Some(self.toggle()),
+        }
+    }
+
+    /// Turns caffeine off, or on indefinitely.
+    fn toggle(&self) -> Message {
+        Message::SetCaffeine(match self.state.timer {
+            Some(_) => None,
+            None => Some(CaffeineTimer::Indefinite),
+        })
+    }
+
+    /// Contents of the popup: the timers to pick from.
+    pub fn popup_view(&self) -> iced::Element<'_, Message> {
+        let entries = CaffeineTimer::ALL
+            .into_iter()
+            .map(|timer| self.timer_view(timer.label(), Some(timer)))
+            .chain(
+                self.state
+                    .active()
+                    .then(|| self.timer_view("Off".to_string(), None)),
+            );
+
+        Column::from_iter(entries)
+            .spacing(8)
+            .width(Length::Fixed(POPUP_WIDTH))
+            .into()
+    }
+
+    fn timer_view(&self, label: String, timer: Option<CaffeineTimer>) -> Element<'_, Message> {
+        let mut entry = row![text(label).width(Length::Fill)]
+            .spacing(8)
+            .align_y(Alignment::Center);
+        if timer.is_some() && self.state.timer == timer {
+            entry = entry.push(icon("check", None));
+        } else {
+            entry = entry.push(Space::with_width(Length::Fixed(16.)));
+        }
+
+        mouse_area(entry)
+            .on_press(Message::SetCaffeine(timer))
+            .into()
+    }
+}
+
+/// The timer after `timer` when scrolling up, or before it when scrolling down, with off below
+/// the shortest timer.
+fn step_timer(timer: Option<CaffeineTimer>, up: bool) -> Option<CaffeineTimer> {
+    let steps = [None]
+        .into_iter()
+        .chain(CaffeineTimer::ALL.map(Some))
+        .collect::<Vec<_>>();
+    let current = steps.iter().position(|t| *t == timer).unwrap_or(0);
+    let next = if up {
+        (current + 1).min(steps.len() - 1)
+    } else {
+        current.saturating_sub(1)
+    };
+    steps[next]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_through_the_timers() {
+        assert_eq!(step_timer(None, true), Some(CaffeineTimer::Minutes(30)));
+        assert_eq!(
+            step_timer(Some(CaffeineTimer::Minutes(60)), true),
+            Some(CaffeineTimer::UntilMidnight)
+        );
+        assert_eq!(
+            step_timer(Some(CaffeineTimer::Indefinite), false),
+            Some(CaffeineTimer::UntilMidnight)
+        );
+        assert_eq!(step_timer(Some(CaffeineTimer::Minutes(30)), false), None);
+    }
+
+    #[test]
+    fn stops_at_off_and_indefinite() {
+        assert_eq!(step_timer(None, false), None);
+        assert_eq!(
+            step_timer(Some(CaffeineTimer::Indefinite), true),
+            Some(CaffeineTimer::Indefinite)
+        );
+    }
+}
@@ -1 +1,63 @@
+use std::rc::Rc;
 
+use iced::widget::mouse_area;
+
+use crate::GlobalState;
+use crate::config::Config;
+use crate::message::Message;
+
+use super::panel::Tile;
+
+#[derive(Debug)]
+pub struct Dnd {
+    config: Rc<Config>,
+    dnd: bool,
+}
+
+impl Dnd {
+    pub fn new(global_state: &GlobalState) -> Self {
+        Self {
+            config: global_state.config.clone(),
+            dnd: if global_state.config.bar.quick_settings.notifs.server {
+                global_state.notifications.dnd
+            } else {
+                global_state.dnd.enabled
+            },
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        let server = self.config.bar.quick_settings.notifs.server;
+        match message {
+            Message::NotificationsUpdate(state) if server => self.dnd = state.dnd,
+            Message::DndUpdate(state) if !server => self.dnd = state.enabled,
+            _ => {}
+        }
+    }
+
+    pub fn view(&self) -> iced::Element<'_, Message> {
+        let cfg = &self.config.bar.quick_settings.dnd;
+
+        let icon = if self.dnd { &cfg.dnd_icon } else { &cfg.icon };
+        let area = mouse_area(self.config.icon(icon)).on_press(Message::SetDnd(!self.dnd));
+        if self.config.bar.quick_settings.notifs.server {
+            area.into()
+        } else {
+            // The history is left to the external notification daemon
+            area.on_middle_press(Message::RunCommand(cfg.dismiss_cmd.clone()))
+                .on_right_press(Message::RunCommand(cfg.history_cmd.clone()))
+                .into()
+        }
+    }
+
+    pub fn tile(&self) -> Tile<'_> {
+        let cfg = &self.config.bar.quick_settings.dnd;
+        Tile {
+            icon: if self.dnd { &cfg.dnd_icon } else { &cfg.icon },
+            label: "Do not disturb",
+            status: if self.dnd { "On" } else { "Off" }.to_string(),
+            active: self.dnd,
+            on_press: Some(Message::SetDnd(!self.dnd)),
+        }
+    }
+}
@@ -1 +1,99 @@
+use std::rc::Rc;
 
+use iced::widget::{mouse_area, row};
+use iced::{Alignment, Length};
+
+use crate::GlobalState;
+use crate::audio::Device;
+use crate::components::icon_filled;
+use crate::config::Config;
+use crate::message::Message;
+
+use super::panel::Tile;
+
+#[derive(Debug)]
+pub struct Mic {
+    config: Rc<Config>,
+    source: Option<Device>,
+    recording: bool,
+}
+
+impl Mic {
+    pub fn new(global_state: &GlobalState) -> Self {
+        Self {
+            config: global_state.config.clone(),
+            source: global_state.audio.source.clone(),
+            recording: global_state.audio.recording,
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::AudioUpdate(state) = message {
+            self.source = state.source.clone();
+            self.recording = state.recording;
+        }
+    }
+
+    pub fn view(&self) -> iced::Element<'_, Message> {
+        let cfg = &self.config.bar.quick_settings.mic;
+
+        let muted = self.source.as_ref().is_none_or(|s| s.muted);
+        let content: iced::Element<'_, Message> = match (muted, self.recording) {
+            // Filled icon, so that it stands out as a privacy indicator
+            (false, true) => self
+                .config
+                .icon_filled(&cfg.recording_icon)
+                .height(Length::Fixed(16.))
+                .into(),
+            // Muting doesn't stop the recording, so it is still indicated
+            (true, true) => row![
+                self.config.icon(&cfg.mute_icon),
+                icon_filled(
+                    "point",
+                    self.config.theme.resolve_color(&cfg.recording_icon.color)
+                )
+                .height(Length::Fixed(8.)),
+            ]
+            .spacing(2)
+            .align_y(Alignment::Center)
+            .into(),
+            (true, false) => self.config.icon(&cfg.mute_icon).into(),
+            (false, false) => self.config.icon(&cfg.icon).into(),
+        };
+
+        let mut area = mouse_area(content);
+        if self.source.is_some() {
+            area = area.on_press(Message::SetSourceMute(!muted));
+        }
+        if let Some(cmd) = &cfg.on_secondary_click {
+            area = area.on_right_press(Message::RunCommand(cmd.clone()));
+        }
+
+        area.into()
+    }
+
+    pub fn tile(&self) -> Tile<'_> {
+        let cfg = &self.config.bar.quick_settings.mic;
+        let muted = self.source.as_ref().is_none_or(|s| s.muted);
+        let status = match &self.source {
+            None => "No microphone".to_string(),
+            Some(_) if muted && self.recording => "Muted, recording".to_string(),
+            Some(_) if muted => "Muted".to_string(),
+            Some(source) => {
+                let volume = (source.volume * 100.0).round();
+                if self.recording {
+                    format!("Recording, {volume}%")
+                } else {
+                    format!("{volume}%")
+                }
+            }
+        };
+        Tile {
+            icon: if muted { &cfg.mute_icon } else { &cfg.icon },
+            label: "Microphone",
+            status,
+            active: !muted,
+            on_press: self.source.as_ref().map(|_| Message::SetSourceMute(!muted)),
+        }
+    }
+}
@@ -1,2 +1,190 @@
+use std::rc::Rc;
+
+use iced::keyboard::{Key, Modifiers};
+use iced::widget::Row;
+use iced::{Alignment, window};
+
+use crate::GlobalState;
+use crate::config::Config;
+use crate::config::types::QuickSettingSegment;
+use crate::message::Message;
+use crate::popup::PopupKind;
+
+mod battery;
+mod bluetooth;
+mod brightness;
+mod caffeine;
+mod dnd;
+mod mic;
+mod network;
+mod night_light;
+mod notifs;
+mod panel;
+mod power_profile;
+mod toggle;
 mod tray;
-pub use tray::TrayView;
+mod volume;
+
+use battery::Battery;
+use bluetooth::BluetoothView;
+use brightness::Brightness;
+use caffeine::Caffeine;
+use dnd::Dnd;
+use mic::Mic;
+use network::Network;
+use night_light::NightLight;
+use notifs::Notifs;
+use panel::Panel;
+use power_profile::PowerProfileView;
+use toggle::Toggle;
+use tray::TrayView;
+use volume::Volume;
+
+/// The strip of small quick setting segments on the bar.
+#[derive(Debug)]
+pub struct QuickSettings {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    tray: TrayView,
+    brightness: Brightness,
+    volume: Volume,
+    mic: Mic,
+    network: Network,
+    battery: Battery,
+    night_light: NightLight,
+    caffeine: Caffeine,
+    notifs: Notifs,
+    dnd: Dnd,
+    power_profile: PowerProfileView,
+    bluetooth: BluetoothView,
+    toggle: Toggle,
+    panel: Panel,
+}
+
+impl QuickSettings {
+    pub fn new(bar_id: window::Id, output_name: String, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+            tray: TrayView::new(bar_id, global_state),
+            brightness: Brightness::new(output_name, global_state),
+            volume: Volume::new(global_state),
+            mic: Mic::new(global_state),
+            network: Network::new(bar_id, global_state),
+            battery: Battery::new(global_state),
+            night_light: NightLight::new(global_state),
+            caffeine: Caffeine::new(bar_id, global_state),
+            notifs: Notifs::new(bar_id, global_state),
+            dnd: Dnd::new(global_state),
+            power_profile: PowerProfileView::new(bar_id, global_state),
+            bluetooth: BluetoothView::new(bar_id, global_state),
+            toggle: Toggle::new(bar_id, global_state),
+            panel: Panel::default(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        self.tray.update(message);
+        self.brightness.update(message);
+        self.volume.update(message);
+        self.mic.update(message);
+        self.network.update(message);
+        self.battery.update(message);
+        self.night_light.update(message);
+        self.caffeine.update(message);
+        self.notifs.update(message);
+        self.dnd.update(message);
+        self.power_profile.update(message);
+        self.bluetooth.update(message);
+        if matches!(message, Message::TogglePopup(bar_id, PopupKind::QuickSettings) if *bar_id == self.bar_id)
+        {
+            self.panel.reset();
+        }
+    }
+
+    /// `open_popup` is the popup of this bar that is currently open, if any.
+    pub fn view(&self, open_popup: Option<PopupKind>) -> iced::Element<'_, Message> {
+        let segments = self
+            .config
+            .bar
+            .quick_settings
+            .segments
+            .iter()
+            .filter_map(|segment| match segment {
+                QuickSettingSegment::Tray => {
+                    Some(self.tray.view(open_popup == Some(PopupKind::TrayDrawer)))
+                }
+                QuickSettingSegment::Brightness => self.brightness.view(),
+                QuickSettingSegment::Volume => Some(self.volume.view()),
+                QuickSettingSegment::Mic => Some(self.mic.view()),
+                QuickSettingSegment::Network => Some(self.network.view()),
+                QuickSettingSegment::Battery => self.battery.view(),
+                QuickSettingSegment::NightLight => Some(self.night_light.view()),
+                QuickSettingSegment::Caffeine => Some(self.caffeine.view()),
+                QuickSettingSegment::Notifs => Some(self.notifs.view()),
+                QuickSettingSegment::Dnd => Some(self.dnd.view()),
+                QuickSettingSegment::PowerProfile => self.power_profile.view(),
+                QuickSettingSegment::Bluetooth => self.bluetooth.view(),
+                QuickSettingSegment::Toggle => Some(
+                    self.toggle
+                        .view(open_popup == Some(PopupKind::QuickSettings)),
+                ),
+            });
+
+        self.config
+            .section(
+                Row::from_iter(segments)
+                    .spacing(12)
+                    .align_y(Alignment::Center),
+            )
+            .into()
+    }
+
+    /// Contents of a popup opened from one of the segments.
+    pub fn popup_view(&self, kind: PopupKind) -> iced::Element<'_, Message> {
+        match kind {
+            PopupKind::TrayDrawer => self.tray.drawer_view(),
+            PopupKind::Network => self.network.popup_view(),
+            PopupKind::Caffeine => self.caffeine.popup_view(),
+            PopupKind::Notifications => self.notifs.popup_view(),
+            PopupKind::PowerProfiles => self.power_profile.popup_view(),
+            PopupKind::Bluetooth => self.bluetooth.popup_view(),
+            PopupKind::QuickSettings => {
+                self.panel
+                    .view(&self.config, self.panel_tiles(), self.panel_sliders())
+            }
+        }
+    }
+
+    /// Handles a key pressed while the panel is open, returning the message to send, if any.
+    pub fn panel_key_pressed(&self, key: &Key, modifiers: Modifiers) -> Option<Message> {
+        self.panel
+            .key_pressed(key, modifiers, self.panel_tiles(), self.panel_sliders())
+    }
+
+    fn panel_tiles(&self) -> Vec<panel::Tile<'_>> {
+        [
+            Some(self.network.tile()),
+            self.bluetooth.tile(),
+            Some(self.dnd.tile()),
+            Some(self.caffeine.tile()),
+            Some(self.night_light.tile()),
+            Some(self.mic.tile()),
+            self.power_profile.tile(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    fn panel_sliders(&self) -> Vec<panel::Slider<'_>> {
+        [self.volume.slider(), self.brightness.slider()]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    pub fn animation_running(&self) -> bool {
+        self.tray.animation_running()
+    }
+}
@@ -1 +1,157 @@
+use std::rc::Rc;
 
+use iced::widget::{Column, Space, mouse_area, row, text};
+use iced::{Alignment, Element, Length, Theme, window};
+
+use crate::GlobalState;
+use crate::components::icon;
+use crate::config::Config;
+use crate::config::types::Icon;
+use crate::message::Message;
+use crate::network::{AccessPoint, ConnectionKind, NetworkState};
+use crate::popup::PopupKind;
+
+use super::panel::Tile;
+
+/// Width of the access point list in the popup.
+const POPUP_WIDTH: f32 = 280.;
+
+#[derive(Debug)]
+pub struct Network {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    state: NetworkState,
+}
+
+impl Network {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+            state: global_state.network.clone(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::NetworkUpdate(state) = message {
+            self.state = state.clone();
+        }
+    }
+
+    pub fn view(&self) -> iced::Element<'_, Message> {
+        let cfg = &self.config.bar.quick_settings.network;
+
+        // Without a command, clicking opens the list
+        let popup = Message::TogglePopup(self.bar_id, PopupKind::Network);
+        let commands = &cfg.mouse_commands;
+        let mut area = mouse_area(self.config.icon(self.icon()))
+            .on_press(
+                commands
+                    .on_primary_click
+                    .clone()
+                    .map_or(popup.clone(), Message::RunCommand),
+            )
+            .on_right_press(
+                commands
+                    .on_secondary_click
+                    .clone()
+                    .map_or(popup, Message::RunCommand),
+            );
+        if let Some(cmd) = &commands.on_middle_click {
+            area = area.on_middle_press(Message::RunCommand(cmd.clone()));
+        }
+
+        area.into()
+    }
+
+    /// Tile of the quick settings panel, opening the list of networks.
+    pub fn tile(&self) -> Tile<'_> {
+        let status = match self.state.primary {
+            Some(ConnectionKind::Wifi) => self
+                .state
+                .access_points
+                .iter()
+                .find(|ap| ap.active)
+                .map_or_else(|| "Connected".to_string(), |ap| ap.ssid.clone()),
+            Some(ConnectionKind::Ethernet) => "Wired".to_string(),
+            Some(ConnectionKind::Other) => "Connected".to_string(),
+            None => "Disconnected".to_string(),
+        };
+        Tile {
+            icon: self.icon(),
+            label: "Network",
+            status,
+            active: self.state.primary.is_some(),
+            on_press: Some(Message::TogglePopup(self.bar_id, PopupKind::Network)),
+        }
+    }
+
+    /// Contents of the popup: the networks in range.
+    ///
+    /// Clicking a saved network connects to it, clicking the active one disconnects.
+    pub fn popup_view(&self) -> iced::Element<'_, Message> {
+        if self.state.access_points.is_empty() {
+            let message = if self.state.has_wifi {
+                "No networks in range"
+            } else {
+                "No wifi device"
+            };
+            return text(message).width(Length::Fixed(POPUP_WIDTH)).into();
+        }
+
+        Column::from_iter(
+            self.state
+                .access_points
+                .iter()
+                .map(|ap| self.access_point_view(ap)),
+        )
+        .spacing(8)
+        .width(Length::Fixed(POPUP_WIDTH))
+        .into()
+    }
+
+    fn access_point_view<'a>(&'a self, ap: &'a AccessPoint) -> Element<'a, Message> {
+        let dimmed = !ap.saved && !ap.active;
+        let label = text(&ap.ssid)
+            .width(Length::Fill)
+            .style(move |theme: &Theme| text::Style {
+                color: dimmed.then(|| theme.palette().text.scale_alpha(0.5)),
+            });
+
+        let mut entry = row![self.config.icon(self.wifi_icon(ap.strength)), label]
+            .spacing(8)
+            .align_y(Alignment::Center);
+        if ap.secured {
+            entry = entry.push(icon("lock", None));
+        }
+        if ap.active {
+            entry = entry.push(icon("check", None));
+        } else {
+            entry = entry.push(Space::with_width(Length::Fixed(16.)));
+        }
+
+        let area = mouse_area(entry);
+        if ap.active {
+            area.on_press(Message::DisconnectWifi).into()
+        } else if ap.saved {
+            area.on_press(Message::ConnectWifi(ap.ssid.clone())).into()
+        } else {
+            area.into()
+        }
+    }
+
+    fn icon(&self) -> &Icon {
+        let cfg = &self.config.bar.quick_settings.network;
+        match self.state.primary {
+            Some(ConnectionKind::Wifi) => self.wifi_icon(self.state.wifi_strength.unwrap_or(0)),
+            Some(ConnectionKind::Ethernet | ConnectionKind::Other) => &cfg.ethernet_icon,
+            None if self.state.has_wifi => &cfg.off_icon,
+            None => &cfg.ethernet_off_icon,
+        }
+    }
+
+    fn wifi_icon(&self, strength: u8) -> &Icon {
+        let cfg = &self.config.bar.quick_settings.network;
+        Icon::from_ramp(&cfg.ramp_icons, strength as f32 / 100.0).unwrap_or(&cfg.off_icon)
+    }
+}
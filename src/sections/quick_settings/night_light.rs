@@ -1 +1,70 @@
+use std::rc::Rc;
 
+use iced::widget::mouse_area;
+
+use crate::GlobalState;
+use crate::config::Config;
+use crate::config::types::Icon;
+use crate::message::Message;
+use crate::night_light::{NightLightMode, NightLightState};
+
+use super::panel::Tile;
+
+#[derive(Debug)]
+pub struct NightLight {
+    config: Rc<Config>,
+    state: NightLightState,
+}
+
+impl NightLight {
+    pub fn new(global_state: &GlobalState) -> Self {
+        Self {
+            config: global_state.config.clone(),
+            state: global_state.night_light,
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::NightLightUpdate(state) = message {
+            self.state = *state;
+        }
+    }
+
+    fn icon(&self) -> &Icon {
+        let cfg = &self.config.bar.quick_settings.night_light;
+        match self.state.mode {
+            NightLightMode::Auto => &cfg.on_icon,
+            NightLightMode::Forced => &cfg.forced_icon,
+            NightLightMode::Off => &cfg.off_icon,
+        }
+    }
+
+    pub fn view(&self) -> iced::Element<'_, Message> {
+        let cfg = &self.config.bar.quick_settings.night_light;
+
+        let mut area = mouse_area(self.config.icon(self.icon())).on_press(Message::CycleNightLight);
+        if let Some(cmd) = &cfg.mouse_commands.on_middle_click {
+            area = area.on_middle_press(Message::RunCommand(cmd.clone()));
+        }
+        if let Some(cmd) = &cfg.mouse_commands.on_secondary_click {
+            area = area.on_right_press(Message::RunCommand(cmd.clone()));
+        }
+
+        area.into()
+    }
+
+    pub fn tile(&self) -> Tile<'_> {
+        let status = match self.state.mode {
+            NightLightMode::Auto => "Sunset to sunrise",
+            NightLightMode::Forced => "Always on",
+            NightLightMode::Off => "Off",
+        };
+        Tile {
+            icon: self.icon(),
+            label: "Night light",
+            status: status.to_string(),
+            active: self.state.mode != NightLightMode::Off,
+            on_press: Some(Message::CycleNightLight),
+        }
+    }
+}
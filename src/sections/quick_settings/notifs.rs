@@ -1 +1,155 @@
+use std::rc::Rc;
 
+use iced::widget::{Column, column, container, mouse_area, row, scrollable, text};
+use iced::{Alignment, Element, Length, Theme, window};
+
+use crate::GlobalState;
+use crate::components::icon;
+use crate::config::Config;
+use crate::config::types::TimeFormat;
+use crate::message::Message;
+use crate::notifications::{Notification, NotificationsState};
+use crate::popup::PopupKind;
+use crate::toasts::notification_icon;
+
+/// Width of the notification history in the popup.
+const POPUP_WIDTH: f32 = 360.;
+/// Height after which the notification history scrolls.
+const POPUP_MAX_HEIGHT: f32 = 600.;
+
+#[derive(Debug)]
+pub struct Notifs {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    state: NotificationsState,
+}
+
+impl Notifs {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+            state: global_state.notifications.clone(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::NotificationsUpdate(state) = message {
+            self.state = state.clone();
+        }
+    }
+
+    pub fn view(&self) -> iced::Element<'_, Message> {
+        let cfg = &self.config.bar.quick_settings.notifs;
+
+        let content = if self.state.unread > 0 {
+            row![
+                self.config.icon(&cfg.notifs_icon),
+                text(self.state.unread.to_string())
+            ]
+            .spacing(4)
+            .align_y(Alignment::Center)
+        } else {
+            row![self.config.icon(&cfg.icon)]
+        };
+
+        let area = mouse_area(content);
+        if cfg.server {
+            area.on_press(Message::TogglePopup(self.bar_id, PopupKind::Notifications))
+                .into()
+        } else {
+            // Leave it to the external notification daemon
+            area.on_press(Message::RunCommand(cfg.open_cmd.clone()))
+                .on_right_press(Message::RunCommand(cfg.toggle_cmd.clone()))
+                .into()
+        }
+    }
+
+    /// Contents of the popup: the notification history, grouped by app.
+    pub fn popup_view(&self) -> iced::Element<'_, Message> {
+        let history = &self.state.history;
+        if history.is_empty() {
+            return text("No notifications")
+                .width(Length::Fixed(POPUP_WIDTH))
+                .into();
+        }
+
+        let header = row![
+            text("Notifications").width(Length::Fill),
+            mouse_area(text("Clear all")).on_press(Message::ClearNotifications),
+        ]
+        .align_y(Alignment::Center);
+
+        // Groups ordered by their newest notification
+        let mut groups: Vec<(&str, Vec<&Notification>)> = Vec::new();
+        for notification in history {
+            match groups
+                .iter_mut()
+                .find(|(app, _)| *app == notification.app_name)
+            {
+                Some((_, notifications)) => notifications.push(notification),
+                None => groups.push((&notification.app_name, vec![notification])),
+            }
+        }
+        let groups = Column::from_iter(groups.into_iter().map(|(app, notifications)| {
+            let app = if app.is_empty() { "Unknown" } else { app };
+            let title = text(app).size(12).style(|theme: &Theme| text::Style {
+                color: Some(theme.palette().text.scale_alpha(0.6)),
+            });
+            Column::with_children(
+                std::iter::once(title.into()).chain(
+                    notifications
+                        .into_iter()
+                        .map(|notification| self.notification_view(notification)),
+                ),
+            )
+            .spacing(8)
+            .into()
+        }))
+        .spacing(16);
+
+        container(column![header, scrollable(groups)].spacing(12))
+            .width(Length::Fixed(POPUP_WIDTH))
+            .max_height(POPUP_MAX_HEIGHT)
+            .into()
+    }
+
+    fn notification_view<'a>(&'a self, notification: &'a Notification) -> Element<'a, Message> {
+        let format = match self.config.general.time_format {
+            TimeFormat::_12h => "%-I:%M %p",
+            TimeFormat::_24h => "%k:%M",
+        };
+        let received = notification
+            .received
+            .to_zoned(jiff::tz::TimeZone::system())
+            .strftime(format)
+            .to_string();
+        let dimmed = |theme: &Theme| text::Style {
+            color: Some(theme.palette().text.scale_alpha(0.6)),
+        };
+
+        let mut content = column![
+            row![
+                text(&notification.summary).width(Length::Fill),
+                text(received).size(12).style(dimmed),
+            ]
+            .spacing(8)
+        ]
+        .spacing(4)
+        .width(Length::Fill);
+        if !notification.body.is_empty() {
+            content = content.push(text(&notification.body).size(13).style(dimmed));
+        }
+
+        let mut entry = row![].spacing(12).align_y(Alignment::Start);
+        if let Some(icon) = &notification.icon {
+            entry = entry.push(notification_icon(icon));
+        }
+        entry
+            .push(content)
+            .push(
+                mouse_area(icon("x", None)).on_press(Message::RemoveNotification(notification.id)),
+            )
+            .into()
+    }
+}
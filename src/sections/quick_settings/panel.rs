@@ -0,0 +1,398 @@
+use std::cell::Cell;
+
+use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
+use iced::widget::{Column, Row, column, container, mouse_area, row, slider, text};
+use iced::{Alignment, Border, Color, Element, Length, Theme};
+
+use crate::config::Config;
+use crate::config::types::Icon;
+use crate::message::Message;
+
+/// Number of columns of the tile grid.
+const TILE_COLUMNS: usize = 2;
+/// Width of a tile.
+const TILE_WIDTH: f32 = 160.;
+const SPACING: f32 = 8.;
+
+/// A large toggle of the panel.
+pub struct Tile<'a> {
+    pub icon: &'a Icon,
+    pub label: &'static str,
+    /// Current state, shown below the label.
+    pub status: String,
+    pub active: bool,
+    pub on_press: Option<Message>,
+}
+
+/// A slider of the panel over `0.0..=max`.
+pub struct Slider<'a> {
+    pub icon: &'a Icon,
+    pub value: f32,
+    pub max: f32,
+    /// Amount the value changes by when adjusted with the keyboard.
+    pub step: f32,
+    pub on_change: Box<dyn Fn(f32) -> Message + 'a>,
+}
+
+/// The dropdown panel of tiles and sliders, opened from the toggle segment.
+///
+/// The panel can be navigated with the keyboard: tab and the arrow keys move the focus, enter
+/// and space press the focused tile, and left and right adjust the focused slider.
+#[derive(Debug, Default)]
+pub struct Panel {
+    /// Index of the focused item, tiles first and then sliders. A cell, since the items borrow
+    /// the segments owning the panel.
+    focus: Cell<Option<usize>>,
+}
+
+impl Panel {
+    /// Clears the focus, for when the panel is opened again.
+    pub fn reset(&self) {
+        self.focus.set(None);
+    }
+
+    /// Moves the focus or acts on the focused item, returning the message to send, if any.
+    pub fn key_pressed(
+        &self,
+        key: &Key,
+        modifiers: Modifiers,
+        tiles: Vec<Tile<'_>>,
+        sliders: Vec<Slider<'_>>,
+    ) -> Option<Message> {
+        let Key::Named(key) = key else {
+            return None;
+        };
+        let len = tiles.len() + sliders.len();
+        if len == 0 {
+            return None;
+        }
+        // The first key press only reveals the focus
+        let Some(focus) = self.focus.get().filter(|focus| *focus < len) else {
+            self.focus.set(Some(0));
+            return None;
+        };
+
+        let on_slider = focus >= tiles.len();
+        let last_row = focus / TILE_COLUMNS == tiles.len().saturating_sub(1) / TILE_COLUMNS;
+        let delta = match key {
+            Named::Tab if modifiers.shift() => -1,
+            Named::Tab => 1,
+            Named::ArrowLeft | Named::ArrowRight if on_slider => {
+                let item = &sliders[focus - tiles.len()];
+                let step = if *key == Named::ArrowRight {
+                    item.step
+                } else {
+                    -item.step
+                };
+                return Some((item.on_change)((item.value + step).clamp(0.0, item.max)));
+            }
+            Named::ArrowLeft => -1,
+            Named::ArrowRight => 1,
+            Named::ArrowUp if on_slider => -1,
+            Named::ArrowDown if on_slider => 1,
+            // Leave the last row of tiles for the first slider
+            Named::ArrowDown if last_row => (tiles.len() - focus) as isize,
+            Named::ArrowUp => -(TILE_COLUMNS as isize),
+            Named::ArrowDown => TILE_COLUMNS as isize,
+            Named::Enter | Named::Space => {
+                return tiles.into_iter().nth(focus).and_then(|tile| tile.on_press);
+            }
+            _ => return None,
+        };
+        self.focus.set(Some(
+            (focus as isize + delta).clamp(0, len as isize - 1) as usize
+        ));
+        None
+    }
+
+    pub fn view<'a>(
+        &self,
+        config: &Config,
+        tiles: Vec<Tile<'a>>,
+        sliders: Vec<Slider<'a>>,
+    ) -> Element<'a, Message> {
+        let tiles_len = tiles.len();
+        let mut tiles = tiles
+            .into_iter()
+            .enumerate()
+            .map(|(i, tile)| self.tile_view(config, tile, self.focus.get() == Some(i)))
+            .peekable();
+
+        let mut rows = Vec::new();
+        while tiles.peek().is_some() {
+            rows.push(
+                Row::from_iter(tiles.by_ref().take(TILE_COLUMNS))
+                    .spacing(SPACING)
+                    .into(),
+            );
+        }
+        let grid = Column::from_vec(rows).spacing(SPACING);
+
+        let sliders = Column::from_iter(sliders.into_iter().enumerate().map(|(i, item)| {
+            self.slider_view(config, item, self.focus.get() == Some(tiles_len + i))
+        }))
+        .spacing(SPACING);
+
+        column![grid, sliders].spacing(16).into()
+    }
+
+    fn tile_view<'a>(
+        &self,
+        config: &Config,
+        tile: Tile<'a>,
+        focused: bool,
+    ) -> Element<'a, Message> {
+        let content = row![
+            config.icon(tile.icon),
+            column![
+                text(tile.label),
+                text(tile.status)
+                    .size(12)
+                    .style(|theme: &Theme| text::Style {
+                        color: Some(theme.palette().text.scale_alpha(0.6)),
+                    }),
+            ]
+            .spacing(2),
+        ]
+        .spacing(12)
+        .align_y(Alignment::Center);
+
+        let inactive = config
+            .theme
+            .resolve_color(&config.bar.theme.section_bg)
+            .unwrap_or(Color::from_rgb8(0x2c, 0x2c, 0x3f));
+        let radius = iced::Radius::new(config.theme.border_radius * 2.0);
+        let active = tile.active;
+        let tile_container = container(content)
+            .style(move |theme: &Theme| {
+                let palette = theme.palette();
+                container::Style {
+                    background: Some(
+                        if active {
+                            palette.primary.scale_alpha(0.35)
+                        } else {
+                            inactive
+                        }
+                        .into(),
+                    ),
+                    border: focus_border(palette.primary, focused, radius),
+                    ..Default::default()
+                }
+            })
+            .padding(12)
+            .width(Length::Fixed(TILE_WIDTH));
+
+        let area = mouse_area(tile_container);
+        match tile.on_press {
+            Some(on_press) => area.on_press(on_press).into(),
+            None => area.into(),
+        }
+    }
+
+    fn slider_view<'a>(
+        &self,
+        config: &Config,
+        item: Slider<'a>,
+        focused: bool,
+    ) -> Element<'a, Message> {
+        let radius = iced::Radius::new(config.theme.border_radius * 2.0);
+        container(
+            row![
+                config.icon(item.icon),
+                slider(0.0..=item.max, item.value, item.on_change).step(0.01),
+            ]
+            .spacing(12)
+            .align_y(Alignment::Center),
+        )
+        .style(move |theme: &Theme| container::Style {
+            border: focus_border(theme.palette().primary, focused, radius),
+            ..Default::default()
+        })
+        .padding([6, 12])
+        .width(Length::Fixed(TILE_WIDTH * TILE_COLUMNS as f32 + SPACING))
+        .into()
+    }
+}
+
+/// Border highlighting the item that has the keyboard focus.
+fn focus_border(color: Color, focused: bool, radius: iced::Radius) -> Border {
+    Border {
+        color,
+        width: if focused { 2.0 } else { 0.0 },
+        radius,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::types::ColorNameOrHex;
+
+    use super::*;
+
+    /// Presses `key` on a panel of `tiles` tiles, pressing each sends its index as a command, and
+    /// sliders at `values` over `0.0..=1.0`.
+    fn press(
+        panel: &Panel,
+        key: Named,
+        modifiers: Modifiers,
+        tiles: usize,
+        values: &[f32],
+    ) -> Option<Message> {
+        let icon = Icon::new("icon", ColorNameOrHex::name("text"));
+        let tiles = (0..tiles)
+            .map(|i| Tile {
+                icon: &icon,
+                label: "Tile",
+                status: String::new(),
+                active: false,
+                on_press: Some(Message::RunCommand(i.to_string())),
+            })
+            .collect();
+        let sliders = values
+            .iter()
+            .map(|&value| Slider {
+                icon: &icon,
+                value,
+                max: 1.0,
+                step: 0.25,
+                on_change: Box::new(Message::SetSinkVolume),
+            })
+            .collect();
+        panel.key_pressed(&Key::Named(key), modifiers, tiles, sliders)
+    }
+
+    /// Moves the focus with `keys`, returning where it ends up after each of them.
+    fn focus_after(panel: &Panel, keys: &[Named], tiles: usize, values: &[f32]) -> Vec<usize> {
+        keys.iter()
+            .map(|key| {
+                assert!(press(panel, *key, Modifiers::default(), tiles, values).is_none());
+                panel.focus.get().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_first_key_only_reveals_the_focus() {
+        let panel = Panel::default();
+        assert!(press(&panel, Named::Enter, Modifiers::default(), 2, &[0.5]).is_none());
+        assert_eq!(panel.focus.get(), Some(0));
+
+        let empty = Panel::default();
+        assert!(press(&empty, Named::Tab, Modifiers::default(), 0, &[]).is_none());
+        assert_eq!(empty.focus.get(), None);
+    }
+
+    #[test]
+    fn tab_moves_across_rows_and_stops_at_the_ends() {
+        let panel = Panel::default();
+        panel.focus.set(Some(0));
+        assert!(press(&panel, Named::Tab, Modifiers::SHIFT, 3, &[0.5]).is_none());
+        assert_eq!(panel.focus.get(), Some(0));
+        assert_eq!(
+            focus_after(&panel, &[Named::Tab; 4], 3, &[0.5]),
+            [1, 2, 3, 3]
+        );
+        assert!(press(&panel, Named::Tab, Modifiers::SHIFT, 3, &[0.5]).is_none());
+        assert_eq!(panel.focus.get(), Some(2));
+    }
+
+    #[test]
+    fn arrows_move_through_the_grid_into_the_sliders() {
+        let panel = Panel::default();
+        panel.focus.set(Some(0));
+        assert_eq!(
+            focus_after(
+                &panel,
+                &[
+                    Named::ArrowRight,
+                    Named::ArrowDown,
+                    Named::ArrowDown,
+                    Named::ArrowDown
+                ],
+                4,
+                &[0.5, 0.5]
+            ),
+            [1, 3, 4, 5]
+        );
+        assert_eq!(
+            focus_after(
+                &panel,
+                &[
+                    Named::ArrowDown,
+                    Named::ArrowUp,
+                    Named::ArrowUp,
+                    Named::ArrowUp
+                ],
+                4,
+                &[0.5, 0.5]
+            ),
+            [5, 4, 3, 1]
+        );
+    }
+
+    #[test]
+    fn arrows_leave_odd_grids_for_the_first_slider() {
+        let panel = Panel::default();
+        panel.focus.set(Some(0));
+        assert_eq!(
+            focus_after(&panel, &[Named::ArrowDown, Named::ArrowDown], 3, &[0.5]),
+            [2, 3]
+        );
+        // Nothing is below the last tile of the first row
+        panel.focus.set(Some(1));
+        assert_eq!(focus_after(&panel, &[Named::ArrowDown], 3, &[0.5]), [3]);
+    }
+
+    #[test]
+    fn moves_between_sliders_without_tiles() {
+        let panel = Panel::default();
+        panel.focus.set(Some(0));
+        assert_eq!(
+            focus_after(
+                &panel,
+                &[
+                    Named::ArrowDown,
+                    Named::ArrowDown,
+                    Named::ArrowUp,
+                    Named::ArrowUp
+                ],
+                0,
+                &[0.5, 0.5]
+            ),
+            [1, 1, 0, 0]
+        );
+    }
+
+    #[test]
+    fn adjusts_the_focused_slider_within_its_range() {
+        let panel = Panel::default();
+        panel.focus.set(Some(1));
+        let adjusted = |key, value| match press(&panel, key, Modifiers::default(), 1, &[value]) {
+            Some(Message::SetSinkVolume(value)) => value,
+            message => panic!("unexpected {message:?}"),
+        };
+        assert_eq!(adjusted(Named::ArrowRight, 0.5), 0.75);
+        assert_eq!(adjusted(Named::ArrowLeft, 0.5), 0.25);
+        assert_eq!(adjusted(Named::ArrowRight, 0.9), 1.0);
+        assert_eq!(adjusted(Named::ArrowLeft, 0.1), 0.0);
+        assert_eq!(panel.focus.get(), Some(1));
+    }
+
+    #[test]
+    fn presses_only_tiles() {
+        let panel = Panel::default();
+        panel.focus.set(Some(1));
+        assert!(matches!(
+            press(&panel, Named::Enter, Modifiers::default(), 2, &[0.5]),
+            Some(Message::RunCommand(command)) if command == "1"
+        ));
+        assert!(matches!(
+            press(&panel, Named::Space, Modifiers::default(), 2, &[0.5]),
+            Some(Message::RunCommand(command)) if command == "1"
+        ));
+        panel.focus.set(Some(2));
+        assert!(press(&panel, Named::Enter, Modifiers::default(), 2, &[0.5]).is_none());
+        assert_eq!(panel.focus.get(), Some(2));
+    }
+}
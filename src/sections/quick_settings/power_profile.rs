@@ -0,0 +1,143 @@
+use std::rc::Rc;
+
+use iced::widget::{Column, Space, column, mouse_area, row, text};
+use iced::{Alignment, Element, Length, Theme, window};
+
+use crate::GlobalState;
+use crate::components::icon;
+use crate::config::Config;
+use crate::config::types::Icon;
+use crate::message::Message;
+use crate::popup::PopupKind;
+use crate::power_profiles::{PowerProfile, PowerProfilesState};
+
+use super::panel::Tile;
+
+/// Width of the profile list in the popup.
+const POPUP_WIDTH: f32 = 240.;
+
+#[derive(Debug)]
+pub struct PowerProfileView {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    state: PowerProfilesState,
+}
+
+impl PowerProfileView {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+            state: global_state.power_profiles.clone(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::PowerProfilesUpdate(state) = message {
+            self.state = state.clone();
+        }
+    }
+
+    fn icon(&self, profile: PowerProfile) -> &Icon {
+        let cfg = &self.config.bar.quick_settings.power_profile;
+        match profile {
+            PowerProfile::PowerSaver => &cfg.power_saver_icon,
+            PowerProfile::Balanced => &cfg.balanced_icon,
+            PowerProfile::Performance => &cfg.performance_icon,
+        }
+    }
+
+    /// Hidden while power-profiles-daemon isn't running.
+    pub fn view(&self) -> Option<iced::Element<'_, Message>> {
+        let cfg = &self.config.bar.quick_settings.power_profile;
+        let active = self.state.active?;
+
+        let mut content = row![self.config.icon(self.icon(active))]
+            .spacing(4)
+            .align_y(Alignment::Center);
+        if self.state.degraded.is_some() {
+            content = content.push(self.config.icon(&cfg.degraded_icon));
+        }
+        if !self.state.holds.is_empty() {
+            content = content.push(self.config.icon(&cfg.hold_icon));
+        }
+
+        let mut area = mouse_area(content)
+            .on_right_press(Message::TogglePopup(self.bar_id, PopupKind::PowerProfiles));
+        if let Some(next) = self.state.next() {
+            area = area.on_press(Message::SetPowerProfile(next));
+        }
+        if let Some(cmd) = &cfg.mouse_commands.on_middle_click {
+            area = area.on_middle_press(Message::RunCommand(cmd.clone()));
+        }
+
+        Some(area.into())
+    }
+
+    /// Tile of the quick settings panel, `None` while power-profiles-daemon isn't running.
+    pub fn tile(&self) -> Option<Tile<'_>> {
+        let active = self.state.active?;
+        Some(Tile {
+            icon: self.icon(active),
+            label: "Power profile",
+            status: active.label().to_string(),
+            active: active != PowerProfile::Balanced,
+            on_press: self.state.next().map(Message::SetPowerProfile),
+        })
+    }
+
+    /// Contents of the popup: the available profiles, why performance is degraded, and the
+    /// applications holding a profile.
+    pub fn popup_view(&self) -> iced::Element<'_, Message> {
+        let dimmed = |theme: &Theme| text::Style {
+            color: Some(theme.palette().text.scale_alpha(0.6)),
+        };
+
+        let mut content = column![
+            Column::from_iter(
+                self.state
+                    .available
+                    .iter()
+                    .map(|profile| self.profile_view(*profile))
+            )
+            .spacing(8)
+        ]
+        .spacing(12)
+        .width(Length::Fixed(POPUP_WIDTH));
+
+        if let Some(reason) = &self.state.degraded {
+            content = content.push(
+                text(format!("Performance degraded: {reason}"))
+                    .size(12)
+                    .style(dimmed),
+            );
+        }
+        for hold in &self.state.holds {
+            let mut line = format!("{} holds {}", hold.application_id, hold.profile.label());
+            if !hold.reason.is_empty() {
+                line = format!("{line}: {}", hold.reason);
+            }
+            content = content.push(text(line).size(12).style(dimmed));
+        }
+
+        content.into()
+    }
+
+    fn profile_view(&self, profile: PowerProfile) -> Element<'_, Message> {
+        let mut entry = row![
+            self.config.icon(self.icon(profile)),
+            text(profile.label()).width(Length::Fill)
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+        if self.state.active == Some(profile) {
+            entry = entry.push(icon("check", None));
+        } else {
+            entry = entry.push(Space::with_width(Length::Fixed(16.)));
+        }
+
+        mouse_area(entry)
+            .on_press(Message::SetPowerProfile(profile))
+            .into()
+    }
+}
@@ -1 +1,34 @@
+use std::rc::Rc;
 
+use iced::widget::mouse_area;
+use iced::window;
+
+use crate::GlobalState;
+use crate::config::Config;
+use crate::message::Message;
+use crate::popup::PopupKind;
+
+/// The chevron opening the quick settings panel.
+#[derive(Debug)]
+pub struct Toggle {
+    bar_id: window::Id,
+    config: Rc<Config>,
+}
+
+impl Toggle {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+        }
+    }
+
+    /// `open` is whether the panel of this bar is currently open.
+    pub fn view(&self, open: bool) -> iced::Element<'_, Message> {
+        let cfg = &self.config.bar.quick_settings.toggle;
+        let icon = if open { &cfg.open_icon } else { &cfg.icon };
+        mouse_area(self.config.icon(icon))
+            .on_press(Message::TogglePopup(self.bar_id, PopupKind::QuickSettings))
+            .into()
+    }
+}
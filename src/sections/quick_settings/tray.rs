@@ -2,41 +2,214 @@
 
 use std::rc::Rc;
 
-use iced::widget::Row;
+use iced::widget::{Column, Row, container, image, mouse_area, svg};
+use iced::{Alignment, Border, Color, Element, Length, window};
 
 use crate::GlobalState;
-use crate::components::system_icon;
+use crate::animation::{Eased, Easing};
 use crate::config::Config;
+use crate::config::types::TrayDrawer;
 use crate::message::Message;
-use crate::tray::TrayItem;
+use crate::popup::PopupKind;
+use crate::tray::{TRAY_ICON_SIZE, TrayIcon, TrayItem};
+
+/// Spacing between tray icons.
+const SPACING: f32 = 12.;
+/// Padding between items that need attention and their outline.
+const ATTENTION_PADDING: f32 = 1.;
+/// Number of columns of the popup drawer grid.
+const GRID_COLUMNS: usize = 4;
 
 #[derive(Debug)]
 pub struct TrayView {
+    bar_id: window::Id,
     config: Rc<Config>,
     items: Vec<TrayItem>,
+    /// 0..=1 expansion of the inline drawer.
+    drawer: Eased<f32>,
 }
 
 impl TrayView {
-    pub fn new(global_state: &GlobalState) -> Self {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
         Self {
+            bar_id,
             config: global_state.config.clone(),
             items: global_state.tray_items.clone(),
+            drawer: Eased::new(0, Easing::Smoothstep, 200., &[0.0, 1.0]),
         }
     }
 
     pub fn update(&mut self, message: &Message) {
-        if let Message::TrayItemsUpdate(items) = message {
-            self.items = items.clone();
+        match message {
+            Message::TrayItemsUpdate(items) => {
+                self.items = items.clone();
+            }
+            Message::TrayToggleDrawer(bar_id) if *bar_id == self.bar_id => {
+                let expanded = self.drawer_expanded();
+                self.drawer.set_target_idx(if expanded { 0 } else { 1 });
+            }
+            Message::AnimationTick => {
+                self.drawer.update();
+            }
+            _ => {}
+        }
+    }
+
+    /// `popup_open` is whether the popup drawer of this bar is currently open.
+    pub fn view(&self, popup_open: bool) -> iced::Element<'_, Message> {
+        let (pinned, unpinned) = self.partitioned_items();
+
+        let mut row = Row::from_iter(pinned.into_iter().filter_map(|item| self.item_view(item)))
+            .spacing(SPACING)
+            .align_y(Alignment::Center);
+
+        if !unpinned.is_empty() {
+            let cfg = &self.config.bar.quick_settings;
+            let (expanded, toggle_message) = match cfg.tray.drawer {
+                TrayDrawer::Inline => {
+                    let (icons, widths): (Vec<_>, Vec<_>) = unpinned
+                        .iter()
+                        .filter_map(|item| Some((self.item_view(item)?, item_width(item))))
+                        .unzip();
+                    let full_width = widths.iter().sum::<f32>()
+                        + widths.len().saturating_sub(1) as f32 * SPACING;
+                    let factor = self.drawer.get();
+                    if factor > 0.0 {
+                        row = row.push(
+                            container(Row::from_vec(icons).spacing(SPACING))
+                                .width(Length::Fixed(full_width * factor))
+                                .clip(true),
+                        );
+                    }
+                    (
+                        self.drawer_expanded(),
+                        Message::TrayToggleDrawer(self.bar_id),
+                    )
+                }
+                TrayDrawer::Popup => (
+                    popup_open,
+                    Message::TogglePopup(self.bar_id, PopupKind::TrayDrawer),
+                ),
+            };
+
+            let chevron = if expanded {
+                &cfg.toggle.open_icon
+            } else {
+                &cfg.toggle.icon
+            };
+            row = row.push(mouse_area(self.config.icon(chevron)).on_press(toggle_message));
+        }
+
+        row.into()
+    }
+
+    /// Contents of the popup drawer: a grid of all unpinned items.
+    pub fn drawer_view(&self) -> iced::Element<'_, Message> {
+        let (_, unpinned) = self.partitioned_items();
+        let mut icons = unpinned
+            .into_iter()
+            .filter_map(|item| self.item_view(item))
+            .peekable();
+
+        let mut rows = Vec::new();
+        while icons.peek().is_some() {
+            rows.push(
+                Row::from_iter(icons.by_ref().take(GRID_COLUMNS))
+                    .spacing(SPACING)
+                    .into(),
+            );
         }
+
+        Column::from_vec(rows).spacing(SPACING).into()
+    }
+
+    pub fn animation_running(&self) -> bool {
+        self.drawer.is_running()
     }
 
-    pub fn view(&self) -> iced::Element<'_, Message> {
-        let icons = self
+    fn drawer_expanded(&self) -> bool {
+        self.drawer.target_idx() == 1
+    }
+
+    /// Splits the items that aren't ignored into pinned and unpinned ones, in display order.
+    ///
+    /// Items that need attention are always shown, even when they aren't pinned.
+    fn partitioned_items(&self) -> (Vec<&TrayItem>, Vec<&TrayItem>) {
+        let cfg = &self.config.bar.quick_settings.tray;
+        let mut items = self
             .items
             .iter()
-            .filter_map(|item| system_icon(item.item.icon_name.as_ref()?))
+            .filter(|item| !cfg.ignored_apps.contains(&item.item.id))
             .collect::<Vec<_>>();
+        if let Some(sort_function) = &cfg.sort_function {
+            items.sort_by(|a, b| sort_function(a, b));
+        }
+
+        items.into_iter().partition(|item| {
+            item.needs_attention()
+                || if cfg.pinned.is_empty() {
+                    !item.is_passive()
+                } else {
+                    cfg.pinned.contains(&item.item.id)
+                }
+        })
+    }
+
+    fn item_view(&self, item: &TrayItem) -> Option<Element<'_, Message>> {
+        let cfg = &self.config.bar.quick_settings.tray;
+
+        let icon: Element<'_, Message> =
+            if let Some(icon) = cfg.app_icon_mappings.get(&item.item.id) {
+                let size = Length::Fixed(TRAY_ICON_SIZE as f32);
+                self.config.icon(icon).width(size).height(size).into()
+            } else {
+                tray_icon(item.current_icon()?)
+            };
+
+        if !item.needs_attention() {
+            return Some(icon);
+        }
 
-        self.config.section(Row::from_vec(icons).spacing(12)).into()
+        let color = self.config.theme.resolve_color(&cfg.attention_color);
+        Some(
+            container(icon)
+                .padding(ATTENTION_PADDING)
+                .style(move |_| container::Style {
+                    border: Border {
+                        color: color.unwrap_or(Color::from_rgb8(0xf3, 0x8b, 0xa8)),
+                        width: 1.5,
+                        radius: 4.0.into(),
+                    },
+                    ..Default::default()
+                })
+                .into(),
+        )
+    }
+}
+
+/// Width of the view of `item`, including the outline of items that need attention.
+fn item_width(item: &TrayItem) -> f32 {
+    let size = TRAY_ICON_SIZE as f32;
+    if item.needs_attention() {
+        size + 2. * ATTENTION_PADDING
+    } else {
+        size
+    }
+}
+
+fn tray_icon<'a>(icon: &TrayIcon) -> Element<'a, Message> {
+    let size = Length::Fixed(TRAY_ICON_SIZE as f32);
+    match icon {
+        TrayIcon::Path(path) if path.extension().is_some_and(|ext| ext == "svg") => {
+            svg(svg::Handle::from_path(path))
+                .width(size)
+                .height(size)
+                .into()
+        }
+        TrayIcon::Path(path) => image(image::Handle::from_path(path))
+            .width(size)
+            .height(size)
+            .into(),
+        TrayIcon::Pixmap(handle) => image(handle.clone()).width(size).height(size).into(),
     }
 }
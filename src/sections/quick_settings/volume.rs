@@ -1 +1,89 @@
+use std::rc::Rc;
 
+use iced::advanced::mouse;
+use iced::widget::mouse_area;
+
+use crate::GlobalState;
+use crate::audio::Device;
+use crate::config::Config;
+use crate::config::types::Icon;
+use crate::message::Message;
+
+use super::panel::Slider;
+
+#[derive(Debug)]
+pub struct Volume {
+    config: Rc<Config>,
+    sink: Option<Device>,
+}
+
+impl Volume {
+    pub fn new(global_state: &GlobalState) -> Self {
+        Self {
+            config: global_state.config.clone(),
+            sink: global_state.audio.sink.clone(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        if let Message::AudioUpdate(state) = message {
+            self.sink = state.sink.clone();
+        }
+    }
+
+    fn icon(&self) -> &Icon {
+        let cfg = &self.config.bar.quick_settings.volume;
+        match &self.sink {
+            None => &cfg.mute_icon,
+            Some(sink) if sink.headphones && (sink.muted || sink.volume == 0.0) => {
+                &cfg.headphones_mute
+            }
+            Some(sink) if sink.muted || sink.volume == 0.0 => &cfg.mute_icon,
+            Some(sink) if sink.headphones => {
+                Icon::from_ascending_ramp(&cfg.headphones_ramp, sink.volume)
+                    .unwrap_or(&cfg.mute_icon)
+            }
+            Some(sink) => Icon::from_ramp(&cfg.ramp_icons, sink.volume).unwrap_or(&cfg.mute_icon),
+        }
+    }
+
+    pub fn view(&self) -> iced::Element<'_, Message> {
+        let cfg = &self.config.bar.quick_settings.volume;
+
+        let mut area = mouse_area(self.config.icon(self.icon()));
+        if let Some(sink) = &self.sink {
+            let (muted, step) = (sink.muted, cfg.step);
+            area = area
+                .on_press(Message::SetSinkMute(!muted))
+                .on_scroll(move |delta| {
+                    let y = match delta {
+                        mouse::ScrollDelta::Pixels { y, .. } => y,
+                        mouse::ScrollDelta::Lines { y, .. } => y,
+                    };
+                    if y == 0.0 {
+                        Message::Noop
+                    } else {
+                        Message::StepSinkVolume(if y > 0.0 { step } else { -step })
+                    }
+                });
+        }
+        if let Some(cmd) = &cfg.on_secondary_click {
+            area = area.on_right_press(Message::RunCommand(cmd.clone()));
+        }
+
+        area.into()
+    }
+
+    /// Slider of the quick settings panel, `None` without an output device.
+    pub fn slider(&self) -> Option<Slider<'_>> {
+        let sink = self.sink.as_ref()?;
+        Some(Slider {
+            icon: self.icon(),
+            value: sink.volume,
+            // Keep an existing boost in range
+            max: sink.volume.max(1.0),
+            step: self.config.bar.quick_settings.volume.step,
+            on_change: Box::new(Message::SetSinkVolume),
+        })
+    }
+}
@@ -128,7 +128,7 @@ impl iced::advanced::subscription::Recipe for SysmonSubscription {
                     .find(|t| LABELS.contains(t.label()))
                     .and_then(|t| t.temperature());
                 if cpu_temp.is_none() {
-                    println!("CPU temperature not found.");
+                    log::warn!("CPU temperature not found.");
                 }
                 let cpu_temp = cpu_temp.unwrap_or_default();
 
@@ -1 +1,177 @@
+use std::rc::Rc;
 
+use iced::widget::{Column, column, container, mouse_area, row, scrollable, text, text_input};
+use iced::{Alignment, Color, Element, Length, Theme, window};
+use jiff::Zoned;
+use jiff::civil::Date;
+
+use crate::GlobalState;
+use crate::components::icon;
+use crate::config::Config;
+use crate::message::Message;
+use crate::popup::PopupKind;
+use crate::todo::{TodoItem, TodoState, next_priority};
+
+const POPUP_WIDTH: f32 = 320.;
+const POPUP_MAX_HEIGHT: f32 = 480.;
+/// Number of completed tasks listed below the open ones.
+const DONE_SHOWN: usize = 5;
+
+pub struct Todo {
+    bar_id: window::Id,
+    config: Rc<Config>,
+    state: TodoState,
+    /// Text of the task being added.
+    input: String,
+}
+
+impl Todo {
+    pub fn new(bar_id: window::Id, global_state: &GlobalState) -> Self {
+        Self {
+            bar_id,
+            config: global_state.config.clone(),
+            state: global_state.todo.clone(),
+            input: String::new(),
+        }
+    }
+
+    pub fn update(&mut self, message: &Message) {
+        match message {
+            Message::TodoUpdate(state) => self.state = state.clone(),
+            Message::TodoInput(input) => self.input = input.clone(),
+            Message::AddTodo(_) => self.input.clear(),
+            _ => {}
+        }
+    }
+
+    /// The number of open tasks, and of those due today or overdue. Hidden while the file can't
+    /// be read.
+    pub fn view(&self) -> Option<Element<'_, Message>> {
+        if !self.state.available {
+            return None;
+        }
+        let cfg = &self.config.bar.todo;
+        let today = Zoned::now().date();
+
+        let mut content = row![
+            self.config.icon(&cfg.icon),
+            text(self.state.open().count().to_string())
+        ]
+        .spacing(4)
+        .align_y(Alignment::Center);
+        let due = self.state.due(today).count();
+        if due > 0 {
+            content = content
+                .push(self.config.icon(&cfg.due_icon))
+                .push(text(due.to_string()));
+        }
+
+        Some(
+            mouse_area(self.config.section(content))
+                .on_press(Message::TogglePopup(self.bar_id, PopupKind::Todo))
+                .into(),
+        )
+    }
+
+    /// Contents of the popup: a field to add tasks, the open tasks by priority and the last
+    /// completed ones.
+    pub fn popup_view(&self) -> Element<'_, Message> {
+        let today = Zoned::now().date();
+        let input = row![
+            text_input("Add a task", &self.input)
+                .on_input(Message::TodoInput)
+                .on_submit(Message::AddTodo(self.input.clone()))
+                .padding(8),
+            mouse_area(icon("plus", None)).on_press(Message::AddTodo(self.input.clone())),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+
+        let mut open = self.state.open().collect::<Vec<_>>();
+        // Tasks without a priority go last, then the earliest due first
+        open.sort_by_key(|item| {
+            (
+                item.priority.is_none(),
+                item.priority,
+                item.due.is_none(),
+                item.due,
+            )
+        });
+        let done = self.state.items.iter().rev().filter(|item| item.done);
+        let due_color = self
+            .config
+            .theme
+            .resolve_color(&self.config.bar.todo.due_icon.color);
+
+        let items = Column::from_iter(
+            open.into_iter()
+                .chain(done.take(DONE_SHOWN))
+                .map(|item| item_view(item, today, due_color)),
+        )
+        .spacing(4);
+
+        let content: Element<'_, Message> = if self.state.items.is_empty() {
+            text("Nothing to do").into()
+        } else {
+            scrollable(items).into()
+        };
+        container(column![input, content].spacing(12))
+            .width(Length::Fixed(POPUP_WIDTH))
+            .max_height(POPUP_MAX_HEIGHT)
+            .into()
+    }
+}
+
+/// A task with its checkbox, and for open tasks the priority, which cycles when clicked, and the
+/// due date in `due_color` once it is reached.
+fn item_view(item: &TodoItem, today: Date, due_color: Option<Color>) -> Element<'_, Message> {
+    let dimmed = |theme: &Theme| text::Style {
+        color: Some(theme.palette().text.scale_alpha(0.6)),
+    };
+
+    let checkbox = mouse_area(icon(
+        if item.done { "square-check" } else { "square" },
+        None,
+    ))
+    .on_press(Message::SetTodoDone {
+        line: item.line,
+        raw: item.raw.clone(),
+        done: !item.done,
+    });
+    let mut content = row![checkbox].spacing(8).align_y(Alignment::Center);
+
+    if item.done {
+        return content
+            .push(text(&item.text).style(dimmed).width(Length::Fill))
+            .into();
+    }
+
+    let priority = mouse_area(
+        container(
+            text(item.priority.map_or("–".to_string(), |p| p.to_string()))
+                .size(12)
+                .style(dimmed),
+        )
+        .center_x(Length::Fixed(16.)),
+    )
+    .on_press(Message::SetTodoPriority {
+        line: item.line,
+        raw: item.raw.clone(),
+        priority: next_priority(item.priority),
+    });
+    content = content
+        .push(priority)
+        .push(text(&item.text).width(Length::Fill));
+    if let Some(due) = item.due {
+        let overdue = due <= today;
+        content = content.push(text(due.strftime("%b %-d").to_string()).size(12).style(
+            move |theme: &Theme| text::Style {
+                color: Some(match due_color {
+                    Some(color) if overdue => color,
+                    _ => theme.palette().text.scale_alpha(0.6),
+                }),
+            },
+        ));
+    }
+    content.into()
+}
@@ -0,0 +1,193 @@
+use std::rc::Rc;
+
+use iced::event::{PlatformSpecific, wayland};
+use iced::platform_specific::shell::commands::layer_surface::{
+    destroy_layer_surface, get_layer_surface,
+};
+use iced::runtime::platform_specific::wayland::layer_surface::{
+    IcedMargin, IcedOutput, SctkLayerSurfaceSettings,
+};
+use iced::widget::{Column, Row, column, image, mouse_area, row, svg, text};
+use iced::{Alignment, Element, Event, Length, Task, Theme, window};
+use sctk::reexports::client::protocol::wl_output::WlOutput;
+use sctk::shell::wlr_layer::{Anchor, KeyboardInteractivity, Layer};
+
+use crate::GlobalState;
+use crate::config::Config;
+use crate::message::Message;
+use crate::notifications::{Notification, NotificationIcon, NotificationsState, Urgency};
+
+/// Size in logical pixels that notification icons are rendered at.
+pub const NOTIFICATION_ICON_SIZE: f32 = 32.;
+
+/// Notification toasts, stacked in a layer surface below the top right of the bar.
+///
+/// The surface only exists while there are toasts to show.
+#[derive(Debug)]
+pub struct Toasts {
+    /// window id of the toasts' layer surface, if it is open.
+    pub id: Option<window::Id>,
+    config: Rc<Config>,
+    state: NotificationsState,
+}
+
+impl Toasts {
+    pub fn new(global_state: &GlobalState) -> Self {
+        Self {
+            id: None,
+            config: global_state.config.clone(),
+            state: global_state.notifications.clone(),
+        }
+    }
+
+    /// Updates the toasts, opening the surface on `wl_output` when there are new ones.
+    pub fn update(&mut self, message: &Message, wl_output: Option<&WlOutput>) -> Task<Message> {
+        match message {
+            Message::NotificationsUpdate(state) => {
+                self.state = state.clone();
+            }
+            Message::Iced(
+                _,
+                Event::PlatformSpecific(PlatformSpecific::Wayland(wayland::Event::Layer(
+                    wayland::LayerEvent::Done,
+                    _,
+                    id,
+                ))),
+            ) if self.id == Some(*id) => {
+                self.id = None;
+            }
+            _ => return Task::none(),
+        }
+
+        let visible = self.state.history.iter().any(Notification::toast);
+        match (self.id, wl_output) {
+            (None, Some(wl_output)) if visible => self.open(wl_output.clone()),
+            (Some(id), _) if !visible => {
+                self.id = None;
+                destroy_layer_surface(id)
+            }
+            _ => Task::none(),
+        }
+    }
+
+    fn open(&mut self, wl_output: WlOutput) -> Task<Message> {
+        let id = window::Id::unique();
+        self.id = Some(id);
+        get_layer_surface(SctkLayerSurfaceSettings {
+            id,
+            layer: Layer::Overlay,
+            keyboard_interactivity: KeyboardInteractivity::None,
+            input_zone: None,
+            anchor: Anchor::TOP | Anchor::RIGHT,
+            output: IcedOutput::Output(wl_output),
+            namespace: "limbo:toasts".to_string(),
+            margin: IcedMargin {
+                top: 6,
+                right: 8,
+                bottom: 0,
+                left: 0,
+            },
+            // Sized to the toasts
+            size: None,
+            // Respect the bar's exclusive zone so the toasts start right below it
+            exclusive_zone: 0,
+            size_limits: iced::Limits::NONE
+                .min_width(1.)
+                .min_height(1.)
+                .max_width(self.config.bar.quick_settings.notifs.toast_width),
+        })
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let cfg = &self.config.bar.quick_settings.notifs;
+        Column::from_iter(
+            self.state
+                .history
+                .iter()
+                .filter(|n| n.toast())
+                .take(cfg.max_toasts)
+                .map(|n| self.toast_view(n)),
+        )
+        .spacing(8)
+        .width(Length::Fixed(cfg.toast_width))
+        .into()
+    }
+
+    /// A single toast. Clicking it dismisses the notification, or invokes its default action.
+    fn toast_view<'a>(&'a self, notification: &'a Notification) -> Element<'a, Message> {
+        let low = notification.urgency == Urgency::Low;
+        let dimmed = move |theme: &Theme| text::Style {
+            color: Some(theme.palette().text.scale_alpha(0.6)),
+        };
+
+        let mut content = column![text(&notification.summary).style(move |theme: &Theme| {
+            text::Style {
+                color: low.then(|| theme.palette().text.scale_alpha(0.8)),
+            }
+        })]
+        .spacing(4)
+        .width(Length::Fill);
+        if !notification.body.is_empty() {
+            content = content.push(text(&notification.body).size(13).style(dimmed));
+        }
+        let actions = notification
+            .actions
+            .iter()
+            .filter(|(key, _)| key != "default")
+            .map(|(key, label)| {
+                mouse_area(self.config.section(text(label)).height(Length::Shrink))
+                    .on_press(Message::InvokeNotificationAction {
+                        id: notification.id,
+                        key: key.clone(),
+                    })
+                    .into()
+            })
+            .collect::<Vec<Element<'a, Message>>>();
+        if !actions.is_empty() {
+            content = content.push(Row::from_vec(actions).spacing(6));
+        }
+
+        let mut toast = row![].spacing(12).align_y(Alignment::Start);
+        if let Some(icon) = &notification.icon {
+            toast = toast.push(notification_icon(icon));
+        }
+        toast = toast.push(content);
+
+        let on_press = if notification.actions.iter().any(|(key, _)| key == "default") {
+            Message::InvokeNotificationAction {
+                id: notification.id,
+                key: "default".to_string(),
+            }
+        } else {
+            Message::DismissNotification(notification.id)
+        };
+        // Critical notifications stand out with a border of their own
+        let border = match notification.urgency {
+            Urgency::Critical => self
+                .config
+                .theme
+                .resolve_color(&self.config.bar.quick_settings.notifs.critical_color),
+            Urgency::Low | Urgency::Normal => None,
+        };
+        mouse_area(self.config.panel(toast, border).width(Length::Fill))
+            .on_press(on_press)
+            .into()
+    }
+}
+
+pub fn notification_icon<'a>(icon: &NotificationIcon) -> Element<'a, Message> {
+    let size = Length::Fixed(NOTIFICATION_ICON_SIZE);
+    match icon {
+        NotificationIcon::Path(path) if path.extension().is_some_and(|ext| ext == "svg") => {
+            svg(svg::Handle::from_path(path))
+                .width(size)
+                .height(size)
+                .into()
+        }
+        NotificationIcon::Path(path) => image(image::Handle::from_path(path))
+            .width(size)
+            .height(size)
+            .into(),
+        NotificationIcon::Image(handle) => image(handle.clone()).width(size).height(size).into(),
+    }
+}
@@ -0,0 +1,429 @@
+//! Tasks from a local file in the `todo.txt` format, see [`todo_txt`].
+//!
+//! The directory of the file is watched with inotify, so that edits by other programs show up
+//! right away, even when they replace the file. Without inotify, the file is re-read periodically.
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use iced::futures::StreamExt;
+use inotify::{EventStream, Inotify, WatchMask};
+use jiff::Zoned;
+use jiff::civil::Date;
+use tokio::sync::{Mutex, mpsc, watch};
+
+use crate::config::types::Todo;
+use crate::message::Message;
+
+mod todo_txt;
+
+pub use todo_txt::{TodoItem, next_priority};
+
+/// Interval at which the file is re-read if it can't be watched.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default, Clone)]
+pub struct TodoState {
+    /// Whether the file could be read. A missing file counts as an empty list.
+    pub available: bool,
+    /// Tasks in the order of the file.
+    pub items: Vec<TodoItem>,
+}
+
+impl TodoState {
+    pub fn open(&self) -> impl Iterator<Item = &TodoItem> {
+        self.items.iter().filter(|item| !item.done)
+    }
+
+    /// Open tasks due `today` or before.
+    pub fn due(&self, today: Date) -> impl Iterator<Item = &TodoItem> {
+        self.open()
+            .filter(move |item| item.due.is_some_and(|due| due <= today))
+    }
+}
+
+/// Changes to a task refer to its line and contents, so that they are dropped if the line was
+/// changed in the meantime.
+#[derive(Debug, Clone)]
+pub enum TodoCommand {
+    Add(String),
+    SetDone {
+        line: usize,
+        raw: String,
+        done: bool,
+    },
+    SetPriority {
+        line: usize,
+        raw: String,
+        priority: Option<char>,
+    },
+}
+
+/// Settings of the list, taken from the config.
+#[derive(Debug, Clone)]
+struct ListConfig {
+    file: PathBuf,
+    /// Sound played when a task is checked off, `None` to stay silent.
+    sound: Option<(String, PathBuf)>,
+}
+
+#[derive(Debug)]
+pub struct TodoList {
+    commands: mpsc::UnboundedSender<TodoCommand>,
+    rx: Arc<Mutex<watch::Receiver<TodoState>>>,
+}
+
+impl TodoList {
+    pub fn new(config: &Todo) -> Self {
+        let (tx, rx) = watch::channel(TodoState::default());
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        let config = ListConfig {
+            file: expand_home(&config.file),
+            sound: (!config.sound_player.is_empty() && !config.sound_file.is_empty())
+                .then(|| (config.sound_player.clone(), expand_home(&config.sound_file))),
+        };
+        tokio::spawn(run(config, tx, commands_rx));
+        Self {
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn send(&self, command: TodoCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "todo".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::TodoUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+async fn run(
+    config: ListConfig,
+    tx: watch::Sender<TodoState>,
+    mut commands_rx: mpsc::UnboundedReceiver<TodoCommand>,
+) {
+    let mut events = watch(&config.file)
+        .inspect_err(|e| {
+            let file = config.file.display();
+            log::warn!("todo: failed to watch {file}, polling it instead: {e}");
+        })
+        .ok();
+    let mut stale = true;
+    loop {
+        if stale {
+            publish(&config.file, &tx).await;
+        }
+
+        stale = tokio::select! {
+            command = commands_rx.recv() => {
+                let Some(command) = command else {
+                    return;
+                };
+                let completed = matches!(command, TodoCommand::SetDone { done: true, .. });
+                match apply(&config.file, command).await {
+                    Ok(true) => {
+                        if completed && let Some((player, sound)) = &config.sound {
+                            play(player, sound);
+                        }
+                    }
+                    Ok(false) => log::debug!("todo: task changed on disk, ignoring the edit"),
+                    Err(e) => log::warn!("todo: failed to update {}: {e}", config.file.display()),
+                }
+                true
+            }
+            changed = changed(&mut events, config.file.file_name()) => changed,
+        };
+    }
+}
+
+/// Creates the inotify event stream, watching the directory of `file` for it being written,
+/// created, replaced or removed.
+fn watch(file: &Path) -> std::io::Result<EventStream<[u8; 1024]>> {
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let inotify = Inotify::init()?;
+    inotify.watches().add(
+        dir,
+        WatchMask::CLOSE_WRITE
+            | WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MOVED_TO
+            | WatchMask::MOVED_FROM,
+    )?;
+    inotify.into_event_stream([0; 1024])
+}
+
+/// Waits for an event of the directory, returning whether it concerns `name`. Without `events`,
+/// waits for the poll interval instead.
+async fn changed(events: &mut Option<EventStream<[u8; 1024]>>, name: Option<&OsStr>) -> bool {
+    let Some(stream) = events else {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        return true;
+    };
+    match stream.next().await {
+        Some(Ok(event)) => event.name.as_deref() == name,
+        Some(Err(e)) => {
+            log::warn!("todo: failed to read inotify events, polling instead: {e}");
+            *events = None;
+            true
+        }
+        None => {
+            *events = None;
+            true
+        }
+    }
+}
+
+async fn publish(file: &Path, tx: &watch::Sender<TodoState>) {
+    let state = match tokio::fs::read_to_string(file).await {
+        Ok(contents) => TodoState {
+            available: true,
+            items: todo_txt::parse(&contents),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => TodoState {
+            available: true,
+            items: Vec::new(),
+        },
+        Err(e) => {
+            log::warn!("todo: failed to read {}: {e}", file.display());
+            TodoState::default()
+        }
+    };
+    tx.send_if_modified(|current| {
+        let changed = current.available != state.available || current.items != state.items;
+        *current = state;
+        changed
+    });
+}
+
+/// Applies `command` to the file, `false` if the task it refers to was changed.
+async fn apply(file: &Path, command: TodoCommand) -> std::io::Result<bool> {
+    let contents = match tokio::fs::read_to_string(file).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let mut lines = contents.lines().map(str::to_string).collect::<Vec<_>>();
+    let today = Zoned::now().date();
+
+    let applied = match command {
+        TodoCommand::Add(text) => {
+            if text.trim().is_empty() {
+                return Ok(true);
+            }
+            lines.push(todo_txt::new_line(&text, today));
+            true
+        }
+        TodoCommand::SetDone { line, raw, done } => edit(&mut lines, line, &raw, |raw| {
+            todo_txt::set_done(raw, done, today)
+        }),
+        TodoCommand::SetPriority {
+            line,
+            raw,
+            priority,
+        } => edit(&mut lines, line, &raw, |raw| {
+            todo_txt::set_priority(raw, priority)
+        }),
+    };
+    if !applied {
+        return Ok(false);
+    }
+
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    write(file, &contents).await?;
+    Ok(true)
+}
+
+/// Replaces the contents of `file` through a temporary file next to it, so that other programs
+/// never read it half written. Symlinks are followed and the permissions of the file are kept.
+async fn write(file: &Path, contents: &str) -> std::io::Result<()> {
+    let file = match tokio::fs::canonicalize(file).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => file.to_path_buf(),
+        Err(e) => return Err(e),
+    };
+    if let Some(dir) = file.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let mut name = OsString::from(".");
+    name.push(file.file_name().unwrap_or_default());
+    name.push(".tmp");
+    let temporary = file.with_file_name(name);
+
+    let result = async {
+        tokio::fs::write(&temporary, contents).await?;
+        if let Ok(metadata) = tokio::fs::metadata(&file).await {
+            tokio::fs::set_permissions(&temporary, metadata.permissions()).await?;
+        }
+        tokio::fs::rename(&temporary, &file).await
+    }
+    .await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&temporary).await;
+    }
+    result
+}
+
+/// Replaces `lines[line]` with `f(raw)`, unless it no longer is `raw`.
+fn edit(lines: &mut [String], line: usize, raw: &str, f: impl FnOnce(&str) -> String) -> bool {
+    match lines
+        .get_mut(line)
+        .filter(|current| current.as_str() == raw)
+    {
+        Some(current) => {
+            *current = f(raw);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Plays `sound` with `player` in the background.
+pub fn play(player: &str, sound: &Path) {
+    let child = tokio::process::Command::new(player)
+        .arg(sound)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match child {
+        Ok(mut child) => {
+            tokio::spawn(async move { child.wait().await });
+        }
+        Err(e) => log::warn!("todo: failed to run {player}: {e}"),
+    }
+}
+
+/// Expands a leading `~/` to the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    async fn wait_until(
+        rx: &mut watch::Receiver<TodoState>,
+        done: impl Fn(&TodoState) -> bool,
+    ) -> TodoState {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let state = rx.borrow_and_update().clone();
+                if done(&state) {
+                    return state;
+                }
+                rx.changed().await.unwrap();
+            }
+        })
+        .await
+        .expect("timed out waiting for the todo state")
+    }
+
+    fn texts(state: &TodoState) -> Vec<&str> {
+        state.items.iter().map(|item| item.text.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn follows_and_edits_the_file() {
+        let dir = TempDir::new();
+        dir.write("todo.txt", "(B) water plants\n");
+        let file = dir.path().join("todo.txt");
+        let list = TodoList::new(&Todo {
+            file: file.to_string_lossy().into_owned(),
+            sound_file: String::new(),
+            ..Default::default()
+        });
+        let mut rx = list.rx.lock().await.clone();
+        let state = wait_until(&mut rx, |state| state.available).await;
+        assert_eq!(texts(&state), ["water plants"]);
+
+        // Editors often replace the file instead of writing it
+        dir.write(".todo.txt.swp", "(B) water plants\ncall mom\n");
+        std::fs::rename(dir.path().join(".todo.txt.swp"), &file).unwrap();
+        let state = wait_until(&mut rx, |state| state.items.len() == 2).await;
+        assert_eq!(texts(&state), ["water plants", "call mom"]);
+
+        let item = state.items[0].clone();
+        list.send(TodoCommand::SetDone {
+            line: item.line,
+            raw: item.raw,
+            done: true,
+        });
+        list.send(TodoCommand::Add("(A) pay rent".to_string()));
+        let state = wait_until(&mut rx, |state| state.items.len() == 3).await;
+        assert!(state.items[0].done);
+        assert_eq!(state.items[2].priority, Some('A'));
+        let today = Zoned::now().date();
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            format!("x {today} water plants pri:B\ncall mom\n(A) {today} pay rent\n")
+        );
+
+        // An edit of a line that changed in the meantime is dropped
+        list.send(TodoCommand::SetPriority {
+            line: 1,
+            raw: "call dad".to_string(),
+            priority: Some('C'),
+        });
+        list.send(TodoCommand::Add("call dad".to_string()));
+        let state = wait_until(&mut rx, |state| state.items.len() == 4).await;
+        assert_eq!(state.items[1].raw, "call mom");
+
+        std::fs::remove_file(&file).unwrap();
+        let state = wait_until(&mut rx, |state| state.items.is_empty()).await;
+        assert!(state.available);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn writes_through_symlinks_and_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new();
+        dir.write("sync/todo.txt", "call mom\n");
+        let target = dir.path().join("sync").join("todo.txt");
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let link = dir.path().join("todo.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        assert!(
+            apply(&link, TodoCommand::Add("pay rent".to_string()))
+                .await
+                .unwrap()
+        );
+        assert!(std::fs::symlink_metadata(&link).unwrap().is_symlink());
+        let metadata = std::fs::metadata(&target).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        let contents = std::fs::read_to_string(&target).unwrap();
+        assert!(contents.starts_with("call mom\n") && contents.ends_with(" pay rent\n"));
+        assert_eq!(
+            std::fs::read_dir(dir.path().join("sync")).unwrap().count(),
+            1
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Parsing and editing of lines in the `todo.txt` format.
+//!
+//! See <https://github.com/todotxt/todo.txt>
+
+use jiff::civil::Date;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TodoItem {
+    /// Index of the line in the file.
+    pub line: usize,
+    /// The line as written, to detect that it was changed by someone else.
+    pub raw: String,
+    pub done: bool,
+    /// Priority from `A`, the highest, to `Z`.
+    pub priority: Option<char>,
+    /// The value of the `due:` tag.
+    pub due: Option<Date>,
+    /// The description, without the completion marker, priority and dates.
+    pub text: String,
+}
+
+impl TodoItem {
+    pub fn parse(line: usize, raw: &str) -> Option<Self> {
+        let mut rest = raw.trim();
+        if rest.is_empty() {
+            return None;
+        }
+
+        let done = rest.starts_with("x ");
+        if done {
+            rest = rest[2..].trim_start();
+            // Completion date, followed by the creation date
+            for _ in 0..2 {
+                rest = strip_date(rest).unwrap_or(rest);
+            }
+        }
+        let priority = parse_priority(rest);
+        if priority.is_some() {
+            rest = rest[3..].trim_start();
+        }
+        if !done {
+            rest = strip_date(rest).unwrap_or(rest);
+        }
+
+        let due = rest
+            .split_whitespace()
+            .find_map(|word| word.strip_prefix("due:"))
+            .and_then(|date| date.parse().ok());
+        Some(Self {
+            line,
+            raw: raw.to_string(),
+            done,
+            // Completed tasks keep their priority as a `pri:` tag
+            priority: priority.or_else(|| done.then(|| pri_tag(rest)).flatten()),
+            due,
+            text: rest.to_string(),
+        })
+    }
+}
+
+/// Parses all tasks of a `todo.txt` file, skipping blank lines.
+pub fn parse(contents: &str) -> Vec<TodoItem> {
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| TodoItem::parse(i, line))
+        .collect()
+}
+
+/// A new task line for `text`, created `today`.
+///
+/// A priority written at the start of the text, like `(A) call mom`, is kept in front of the
+/// creation date.
+pub fn new_line(text: &str, today: Date) -> String {
+    let text = text.trim();
+    match parse_priority(text) {
+        Some(priority) => format!("({priority}) {today} {}", text[3..].trim_start()),
+        None => format!("{today} {text}"),
+    }
+}
+
+/// `raw` checked off on `today`, or reopened if `done` is false.
+pub fn set_done(raw: &str, done: bool, today: Date) -> String {
+    let Some(item) = TodoItem::parse(0, raw) else {
+        return raw.to_string();
+    };
+    if item.done == done {
+        return raw.to_string();
+    }
+    let raw = raw.trim();
+    if done {
+        // The priority moves into a tag, since completed tasks must start with `x`
+        match parse_priority(raw) {
+            Some(priority) => format!("x {today} {} pri:{priority}", raw[3..].trim_start()),
+            None => format!("x {today} {raw}"),
+        }
+    } else {
+        let rest = raw[2..].trim_start();
+        // Only the completion date goes, the creation date is kept
+        let rest = strip_date(rest).unwrap_or(rest);
+        let priority = pri_tag(rest);
+        let words = rest
+            .split_whitespace()
+            .filter(|word| !word.starts_with("pri:"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        match priority {
+            Some(priority) => format!("({priority}) {words}"),
+            None => words,
+        }
+    }
+}
+
+/// `raw` with its priority replaced. Completed tasks are left as they are.
+pub fn set_priority(raw: &str, priority: Option<char>) -> String {
+    let raw = raw.trim();
+    if raw.starts_with("x ") {
+        return raw.to_string();
+    }
+    let rest = match parse_priority(raw) {
+        Some(_) => raw[3..].trim_start(),
+        None => raw,
+    };
+    match priority {
+        Some(priority) => format!("({priority}) {rest}"),
+        None => rest.to_string(),
+    }
+}
+
+/// The priority after `priority` when cycling through `A`, `B`, `C` and none.
+pub fn next_priority(priority: Option<char>) -> Option<char> {
+    match priority {
+        None => Some('A'),
+        Some('A') => Some('B'),
+        Some('B') => Some('C'),
+        Some(_) => None,
+    }
+}
+
+/// The priority at the start of `text`, like `(A) `.
+fn parse_priority(text: &str) -> Option<char> {
+    let bytes = text.as_bytes();
+    match bytes {
+        [b'(', priority @ b'A'..=b'Z', b')', b' ', ..] => Some(*priority as char),
+        _ => None,
+    }
+}
+
+/// The value of a `pri:` tag in `text`.
+fn pri_tag(text: &str) -> Option<char> {
+    text.split_whitespace()
+        .find_map(|word| word.strip_prefix("pri:"))
+        .and_then(|priority| priority.chars().next())
+        .filter(char::is_ascii_uppercase)
+}
+
+/// `text` without a leading `YYYY-MM-DD` date, `None` if it doesn't start with one.
+fn strip_date(text: &str) -> Option<&str> {
+    let (date, rest) = text.split_once(' ').unwrap_or((text, ""));
+    if date.len() != 10 {
+        return None;
+    }
+    date.parse::<Date>().ok()?;
+    Some(rest.trim_start())
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::civil::date;
+
+    use super::*;
+
+    const TODAY: Date = date(2024, 5, 20);
+
+    #[test]
+    fn parses_tasks() {
+        let items = parse(
+            "(A) 2024-05-01 call mom due:2024-05-20 +family\n\
+             \n\
+             x 2024-05-19 2024-05-01 water plants pri:B\n\
+             x done without dates\n\
+             (a) lowercase isn't a priority\n\
+             2024-05-01 due:soon\n",
+        );
+        let item = |line, done, priority, due, text: &str| TodoItem {
+            line,
+            raw: String::new(),
+            done,
+            priority,
+            due,
+            text: text.to_string(),
+        };
+        let items = items
+            .into_iter()
+            .map(|item| TodoItem {
+                raw: String::new(),
+                ..item
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            [
+                item(
+                    0,
+                    false,
+                    Some('A'),
+                    Some(TODAY),
+                    "call mom due:2024-05-20 +family"
+                ),
+                item(2, true, Some('B'), None, "water plants pri:B"),
+                item(3, true, None, None, "done without dates"),
+                item(4, false, None, None, "(a) lowercase isn't a priority"),
+                item(5, false, None, None, "due:soon"),
+            ]
+        );
+    }
+
+    #[test]
+    fn creates_lines() {
+        assert_eq!(new_line(" call mom ", TODAY), "2024-05-20 call mom");
+        assert_eq!(new_line("(B) call mom", TODAY), "(B) 2024-05-20 call mom");
+    }
+
+    #[test]
+    fn checks_off_and_reopens_tasks() {
+        let done = set_done("(A) 2024-05-01 call mom", true, TODAY);
+        assert_eq!(done, "x 2024-05-20 2024-05-01 call mom pri:A");
+        assert_eq!(set_done(&done, true, TODAY), done);
+        assert_eq!(set_done(&done, false, TODAY), "(A) 2024-05-01 call mom");
+        assert_eq!(set_done("call mom", true, TODAY), "x 2024-05-20 call mom");
+        assert_eq!(set_done("x 2024-05-19 call mom", false, TODAY), "call mom");
+    }
+
+    #[test]
+    fn changes_priorities() {
+        assert_eq!(set_priority("call mom", Some('A')), "(A) call mom");
+        assert_eq!(set_priority("(A) call mom", Some('B')), "(B) call mom");
+        assert_eq!(set_priority("(A) call mom", None), "call mom");
+        assert_eq!(set_priority("x call mom", Some('A')), "x call mom");
+
+        let mut priority = None;
+        let cycle = std::iter::from_fn(|| {
+            priority = next_priority(priority);
+            Some(priority)
+        });
+        assert_eq!(
+            cycle.take(4).collect::<Vec<_>>(),
+            [Some('A'), Some('B'), Some('C'), None]
+        );
+    }
+}
@@ -0,0 +1,489 @@
+//! Tasks due today or overdue from the Todoist API.
+//!
+//! The last fetched tasks are cached on disk, so that they are shown while offline and right
+//! after startup.
+//!
+//! See <https://developer.todoist.com/api/v1/>
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use jiff::civil::Date;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, mpsc, watch};
+
+use crate::cache;
+use crate::config::types::{Todo, Todoist};
+use crate::message::Message;
+
+/// Interval at which the tasks are fetched.
+const FETCH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const MIN_BACKOFF: Duration = Duration::from_secs(60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Filter of the fetched tasks.
+const QUERY: &str = "today | overdue";
+/// Tasks per page, the maximum the API allows.
+const PAGE_SIZE: &str = "200";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TodoistTask {
+    pub id: String,
+    pub content: String,
+    /// From 1 for urgent to 4, as shown in the apps.
+    pub priority: u8,
+    pub due: Date,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TodoistState {
+    /// Whether an API token is configured.
+    pub available: bool,
+    /// Earliest due first, then by priority.
+    pub tasks: Vec<TodoistTask>,
+    /// Whether the last fetch failed, the tasks are then the last known ones.
+    pub offline: bool,
+}
+
+impl TodoistState {
+    /// Tasks due `today` or before.
+    pub fn due(&self, today: Date) -> impl Iterator<Item = &TodoistTask> {
+        self.tasks.iter().filter(move |task| task.due <= today)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TodoistCommand {
+    Complete(String),
+}
+
+/// A task as returned by the API, which is also the format of the cache.
+#[derive(Debug, Serialize, Deserialize)]
+struct ApiTask {
+    id: String,
+    content: String,
+    /// From 4 for urgent to 1, the reverse of the apps.
+    priority: u8,
+    due: Option<ApiDue>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ApiDue {
+    /// A date, or a date and time for tasks with a due time.
+    date: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Page {
+    results: Vec<ApiTask>,
+    next_cursor: Option<String>,
+}
+
+/// Sound played when a task is completed.
+#[derive(Debug, Clone)]
+struct Sound {
+    player: String,
+    url: String,
+}
+
+#[derive(Debug)]
+pub struct TodoistTasks {
+    commands: mpsc::UnboundedSender<TodoistCommand>,
+    rx: Arc<Mutex<watch::Receiver<TodoistState>>>,
+}
+
+impl TodoistTasks {
+    /// Fetches from the API at `api_url` of the config, unless no token is configured. The sound
+    /// is taken from the config of the todo module.
+    pub fn new(config: &Todoist, todo: &Todo) -> Self {
+        Self::with_cache_dir(config, todo, cache::dir())
+    }
+
+    /// Keeps the tasks and the sound in `cache_dir`.
+    fn with_cache_dir(config: &Todoist, todo: &Todo, cache_dir: Option<PathBuf>) -> Self {
+        let (tx, rx) = watch::channel(TodoistState::default());
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        if !config.api_token.is_empty() {
+            tx.send_modify(|state| state.available = true);
+            let api_url = config.api_url.trim_end_matches('/').to_string();
+            let sound =
+                (!todo.sound_player.is_empty() && !todo.sound_url.is_empty()).then(|| Sound {
+                    player: todo.sound_player.clone(),
+                    url: todo.sound_url.clone(),
+                });
+            tokio::spawn(run(
+                api_url,
+                config.api_token.clone(),
+                sound,
+                cache_dir,
+                tx,
+                commands_rx,
+            ));
+        }
+        Self {
+            commands,
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn send(&self, command: TodoistCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "todoist".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::TodoistUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+async fn run(
+    api_url: String,
+    token: String,
+    sound: Option<Sound>,
+    cache_dir: Option<PathBuf>,
+    tx: watch::Sender<TodoistState>,
+    mut commands_rx: mpsc::UnboundedReceiver<TodoistCommand>,
+) {
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("todoist: failed to create the HTTP client: {e}");
+            return;
+        }
+    };
+    let cache = cache_dir.as_ref().map(|dir| dir.join("todoist.json"));
+
+    // The cached tasks are shown until the first fetch, as if offline
+    if let Some(tasks) = read_cache(cache.as_deref()).await {
+        tx.send_modify(|state| {
+            state.tasks = convert(tasks);
+            state.offline = true;
+        });
+    }
+
+    let mut delay = Duration::ZERO;
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        tokio::select! {
+            command = commands_rx.recv() => {
+                let Some(TodoistCommand::Complete(id)) = command else {
+                    return;
+                };
+                match complete(&client, &api_url, &token, &id).await {
+                    Ok(()) => {
+                        tx.send_modify(|state| state.tasks.retain(|task| task.id != id));
+                        // Downloading the sound mustn't hold up the tasks
+                        if let Some(sound) = &sound
+                            && let Some(dir) = &cache_dir
+                        {
+                            tokio::spawn(play(client.clone(), sound.clone(), dir.clone()));
+                        }
+                    }
+                    Err(e) => log::warn!("todoist: failed to complete task {id}: {e}"),
+                }
+                // Recurring tasks come back with their next due date
+                delay = Duration::ZERO;
+                continue;
+            }
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        match fetch(&client, &api_url, &token).await {
+            Ok(tasks) => {
+                write_cache(cache.as_deref(), &tasks).await;
+                let tasks = convert(tasks);
+                tx.send_if_modified(|state| {
+                    let changed = state.tasks != tasks || state.offline;
+                    state.tasks = tasks;
+                    state.offline = false;
+                    changed
+                });
+                backoff = MIN_BACKOFF;
+                delay = FETCH_INTERVAL;
+            }
+            Err(e) => {
+                log::warn!(
+                    "todoist: failed to fetch tasks ({e}), retrying in {}s",
+                    backoff.as_secs()
+                );
+                tx.send_if_modified(|state| !std::mem::replace(&mut state.offline, true));
+                delay = backoff;
+                backoff = (backoff * 2).min(FETCH_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Fetches the tasks matching [`QUERY`], following the pages.
+async fn fetch(
+    client: &reqwest::Client,
+    api_url: &str,
+    token: &str,
+) -> Result<Vec<ApiTask>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut tasks = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut params = vec![("query", QUERY), ("limit", PAGE_SIZE)];
+        if let Some(cursor) = &cursor {
+            params.push(("cursor", cursor.as_str()));
+        }
+        let url = reqwest::Url::parse_with_params(&format!("{api_url}/tasks/filter"), &params)?;
+        let body = client
+            .get(url)
+            .bearer_auth(token)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let page: Page = serde_json::from_slice(&body)?;
+        tasks.extend(page.results);
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(tasks),
+        }
+    }
+}
+
+async fn complete(
+    client: &reqwest::Client,
+    api_url: &str,
+    token: &str,
+    id: &str,
+) -> reqwest::Result<()> {
+    client
+        .post(format!("{api_url}/tasks/{id}/close"))
+        .bearer_auth(token)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Tasks with a due date, earliest first and then by priority.
+fn convert(tasks: Vec<ApiTask>) -> Vec<TodoistTask> {
+    let mut tasks = tasks
+        .into_iter()
+        .filter_map(|task| {
+            // Only the date of due times matters
+            let due = task.due?.date.get(..10)?.parse::<Date>().ok()?;
+            Some(TodoistTask {
+                id: task.id,
+                content: task.content,
+                priority: 5 - task.priority.clamp(1, 4),
+                due,
+            })
+        })
+        .collect::<Vec<_>>();
+    tasks.sort_by_key(|task| (task.due, task.priority));
+    tasks
+}
+
+async fn read_cache(path: Option<&Path>) -> Option<Vec<ApiTask>> {
+    let body = tokio::fs::read(path?).await.ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+async fn write_cache(path: Option<&Path>, tasks: &[ApiTask]) {
+    let Some(path) = path else {
+        return;
+    };
+    let result: std::io::Result<()> = async {
+        let body = serde_json::to_vec(tasks)?;
+        cache::write(path, &body).await
+    }
+    .await;
+    if let Err(e) = result {
+        log::warn!("todoist: failed to write {}: {e}", path.display());
+    }
+}
+
+/// Plays the sound, downloading it into `dir` first unless it was already.
+async fn play(client: reqwest::Client, sound: Sound, dir: PathBuf) {
+    let path = sound_path(&dir, &sound.url);
+    if tokio::fs::metadata(&path).await.is_err()
+        && let Err(e) = download(&client, &sound.url, &path).await
+    {
+        log::warn!("todoist: failed to download {}: {e}", sound.url);
+        return;
+    }
+    crate::todo::play(&sound.player, &path);
+}
+
+/// The cache file in `dir` for the sound at `url`, keeping its extension for the player.
+fn sound_path(dir: &Path, url: &str) -> PathBuf {
+    let name = url.rsplit('/').next().unwrap_or_default();
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("sound");
+    dir.join(format!("sound-{}.{extension}", cache::file_name(url)))
+}
+
+async fn download(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let body = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    cache::write(path, &body).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::testing::{HttpRequest, HttpResponse, HttpStub, TempDir};
+
+    fn task(id: &str, content: &str, priority: u8, due: Option<&str>) -> String {
+        let due = match due {
+            Some(date) => format!(r#"{{"date": "{date}"}}"#),
+            None => "null".to_string(),
+        };
+        format!(r#"{{"id": "{id}", "content": "{content}", "priority": {priority}, "due": {due}}}"#)
+    }
+
+    /// Two pages of tasks, the first one going once it is closed.
+    fn respond(request: &HttpRequest, closed: &AtomicBool) -> HttpResponse {
+        // The sound is public
+        if request.path == "/sounds/done.mp3" {
+            return HttpResponse::new(200, "sound");
+        }
+        if request.header("authorization") != Some("Bearer token") {
+            return HttpResponse::new(401, "");
+        }
+        let first_page = "/tasks/filter?query=today+%7C+overdue&limit=200";
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", path) if path == first_page => {
+                let mut results = vec![
+                    task("2", "Later", 1, Some("2024-05-21T10:00:00")),
+                    task("3", "Undated", 4, None),
+                ];
+                if !closed.load(Ordering::SeqCst) {
+                    results.push(task("1", "Urgent", 4, Some("2024-05-21")));
+                }
+                let results = results.join(",");
+                let body = format!(r#"{{"results": [{results}], "next_cursor": "abc"}}"#);
+                HttpResponse::new(200, body)
+            }
+            ("GET", path) if path == format!("{first_page}&cursor=abc") => {
+                let results = task("4", "Overdue", 2, Some("2024-05-20"));
+                let body = format!(r#"{{"results": [{results}], "next_cursor": null}}"#);
+                HttpResponse::new(200, body)
+            }
+            ("POST", "/tasks/1/close") => {
+                closed.store(true, Ordering::SeqCst);
+                HttpResponse::new(204, "")
+            }
+            _ => HttpResponse::new(404, ""),
+        }
+    }
+
+    async fn wait_until(
+        rx: &mut watch::Receiver<TodoistState>,
+        done: impl Fn(&TodoistState) -> bool,
+    ) -> TodoistState {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let state = rx.borrow_and_update().clone();
+                if done(&state) {
+                    return state;
+                }
+                rx.changed().await.unwrap();
+            }
+        })
+        .await
+        .expect("timed out waiting for the tasks")
+    }
+
+    fn ids(state: &TodoistState) -> Vec<&str> {
+        state.tasks.iter().map(|task| task.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn fetches_and_completes_tasks() {
+        let closed = Arc::new(AtomicBool::new(false));
+        let stub = HttpStub::start({
+            let closed = closed.clone();
+            move |request| respond(request, &closed)
+        })
+        .await;
+        let dir = TempDir::new();
+        let config = Todoist {
+            api_token: "token".to_string(),
+            api_url: stub.url.clone(),
+            ..Default::default()
+        };
+        let sound_url = format!("{}/sounds/done.mp3", stub.url);
+        let todo = Todo {
+            sound_url: sound_url.clone(),
+            sound_player: "true".to_string(),
+            ..Default::default()
+        };
+
+        let todoist = TodoistTasks::with_cache_dir(&config, &todo, Some(dir.path().to_path_buf()));
+        let mut rx = todoist.rx.lock().await.clone();
+        let state = wait_until(&mut rx, |state| !state.tasks.is_empty()).await;
+        // Earliest due first, then by priority, without undated tasks
+        assert_eq!(ids(&state), ["4", "1", "2"]);
+        assert_eq!(state.tasks[1].priority, 1);
+        assert!(state.available && !state.offline);
+
+        todoist.send(TodoistCommand::Complete("1".to_string()));
+        let state = wait_until(&mut rx, |state| state.tasks.len() == 2).await;
+        assert_eq!(ids(&state), ["4", "2"]);
+
+        // The sound is downloaded in the background, once
+        let sound = sound_path(dir.path(), &sound_url);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !sound.exists() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("timed out waiting for the sound");
+        assert_eq!(std::fs::read(&sound).unwrap(), b"sound");
+        assert!(sound.to_string_lossy().ends_with(".mp3"));
+
+        // A restart shows the cached tasks until they are fetched, once the refetch after
+        // completing the task is written
+        let cache = dir.path().join("todoist.json");
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while std::fs::read_to_string(&cache).is_ok_and(|cache| cache.contains("Urgent")) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("timed out waiting for the cache");
+        drop(todoist);
+        let config = Todoist {
+            api_url: format!("{}/gone", stub.url),
+            ..config
+        };
+        let todoist = TodoistTasks::with_cache_dir(&config, &todo, Some(dir.path().to_path_buf()));
+        let mut rx = todoist.rx.lock().await.clone();
+        let state = wait_until(&mut rx, |state| !state.tasks.is_empty()).await;
+        assert_eq!(ids(&state), ["4", "2"]);
+        assert!(state.offline);
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use iced::futures::StreamExt;
+use system_tray::client::Client;
+use system_tray::item::{IconPixmap, Status, StatusNotifierItem};
+use system_tray::menu::TrayMenu;
+use tokio::sync::{Mutex, broadcast, watch};
+
+use crate::message::Message;
+
+mod watcher;
+
+/// Size in logical pixels that tray icons are rendered at.
+pub const TRAY_ICON_SIZE: u32 = 16;
+
+/// Icon of a tray item, resolved once when the item is received so that views don't have to
+/// decode pixmaps or hit the filesystem.
+#[derive(Debug, Clone)]
+pub enum TrayIcon {
+    /// Path to an icon file, either from the icon theme or the item's `icon_theme_path`.
+    Path(PathBuf),
+    /// Decoded `icon_pixmap` data.
+    Pixmap(iced::widget::image::Handle),
+}
+
+#[derive(Debug, Clone)]
+pub struct TrayItem {
+    pub item: StatusNotifierItem,
+    pub menu: Option<TrayMenu>,
+    pub icon: Option<TrayIcon>,
+    pub attention_icon: Option<TrayIcon>,
+}
+
+impl From<&(StatusNotifierItem, Option<TrayMenu>)> for TrayItem {
+    fn from(item: &(StatusNotifierItem, Option<TrayMenu>)) -> Self {
+        let sni = &item.0;
+        let theme_path = sni.icon_theme_path.as_deref().filter(|p| !p.is_empty());
+        Self {
+            icon: resolve_icon(
+                sni.icon_name.as_deref(),
+                sni.icon_pixmap.as_deref(),
+                theme_path,
+            ),
+            attention_icon: resolve_icon(
+                sni.attention_icon_name.as_deref(),
+                sni.attention_icon_pixmap.as_deref(),
+                theme_path,
+            ),
+            item: sni.clone(),
+            menu: item.1.clone(),
+        }
+    }
+}
+
+impl TrayItem {
+    pub fn needs_attention(&self) -> bool {
+        matches!(self.item.status, Status::NeedsAttention)
+    }
+
+    pub fn is_passive(&self) -> bool {
+        matches!(self.item.status, Status::Passive)
+    }
+
+    /// The icon to display, taking the item's status into account.
+    pub fn current_icon(&self) -> Option<&TrayIcon> {
+        if self.needs_attention() {
+            self.attention_icon.as_ref().or(self.icon.as_ref())
+        } else {
+            self.icon.as_ref()
+        }
+    }
+}
+
+/// Resolves an icon from, in order of preference, the item's custom theme path, the system icon
+/// theme, and the raw pixmap data.
+fn resolve_icon(
+    name: Option<&str>,
+    pixmaps: Option<&[IconPixmap]>,
+    theme_path: Option<&str>,
+) -> Option<TrayIcon> {
+    let name = name.filter(|n| !n.is_empty());
+    name.and_then(|name| {
+        theme_path
+            .and_then(|dir| find_in_theme_path(Path::new(dir), name))
+            .or_else(|| lookup_system_icon(name))
+    })
+    .map(TrayIcon::Path)
+    .or_else(|| pixmaps.and_then(decode_pixmap))
+}
+
+static SYSTEM_ICON_CACHE: LazyLock<std::sync::Mutex<HashMap<String, Option<PathBuf>>>> =
+    LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// Looks `name` up in the system icon theme, or uses it as is if it is an absolute path.
+pub fn lookup_system_icon(name: &str) -> Option<PathBuf> {
+    // Some apps pass an absolute path as the icon name
+    if Path::new(name).is_absolute() {
+        return Path::new(name).exists().then(|| PathBuf::from(name));
+    }
+
+    SYSTEM_ICON_CACHE
+        .lock()
+        .ok()?
+        .entry(name.to_string())
+        .or_insert_with(|| freedesktop_icons::lookup(name).with_size(48).find())
+        .clone()
+}
+
+/// Searches an item-provided icon theme directory for `name`.
+///
+/// Apps like Electron and Steam point `icon_theme_path` either directly at a directory of icons
+/// or at the root of a theme (`hicolor/48x48/apps/...`), so search a few levels deep and prefer
+/// any scalable icon, then the raster one from the largest size directory.
+fn find_in_theme_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    const MAX_DEPTH: usize = 4;
+
+    fn visit(dir: &Path, name: &str, depth: usize, found: &mut Vec<PathBuf>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if depth < MAX_DEPTH {
+                    visit(&path, name, depth + 1, found);
+                }
+            } else if path.file_stem().is_some_and(|stem| stem == name)
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext == "png" || ext == "svg")
+            {
+                found.push(path);
+            }
+        }
+    }
+
+    let mut found = Vec::new();
+    visit(dir, name, 0, &mut found);
+    // The directory order is arbitrary, so ties go to the first path
+    found.sort();
+    found.into_iter().rev().max_by_key(|path| {
+        let scalable = path.extension().is_some_and(|ext| ext == "svg");
+        (scalable, theme_dir_size(path).unwrap_or(0))
+    })
+}
+
+/// The size of the theme directory `path` is in, e.g. 48 for `48x48/apps` or `48x48@2/apps`.
+fn theme_dir_size(path: &Path) -> Option<u32> {
+    path.ancestors().skip(1).find_map(|dir| {
+        let name = dir.file_name()?.to_str()?;
+        let name = name.split('@').next()?;
+        let (width, height) = name.split_once('x')?;
+        width.parse().ok().filter(|_| width == height)
+    })
+}
+
+/// Picks the pixmap best suited for [`TRAY_ICON_SIZE`] and converts it from the SNI ARGB32
+/// (network byte order) format into RGBA.
+fn decode_pixmap(pixmaps: &[IconPixmap]) -> Option<TrayIcon> {
+    // Render at 2x so that icons stay sharp on scaled outputs
+    let target = (TRAY_ICON_SIZE * 2) as i32;
+
+    let valid = pixmaps.iter().filter(|p| pixel_count(p).is_some());
+    // Smallest pixmap that is at least as large as the target, otherwise the largest one
+    let pixmap = valid
+        .clone()
+        .filter(|p| p.width.min(p.height) >= target)
+        .min_by_key(|p| pixel_count(p))
+        .or_else(|| valid.max_by_key(|p| pixel_count(p)))?;
+
+    let rgba = pixmap
+        .pixels
+        .chunks_exact(4)
+        .flat_map(|argb| [argb[1], argb[2], argb[3], argb[0]])
+        .collect::<Vec<_>>();
+
+    Some(TrayIcon::Pixmap(iced::widget::image::Handle::from_rgba(
+        pixmap.width as u32,
+        pixmap.height as u32,
+        rgba,
+    )))
+}
+
+/// The number of pixels of `pixmap`, unless its size is invalid or doesn't match its data, which
+/// comes straight from the item.
+fn pixel_count(pixmap: &IconPixmap) -> Option<usize> {
+    let width = usize::try_from(pixmap.width).ok().filter(|&w| w > 0)?;
+    let height = usize::try_from(pixmap.height).ok().filter(|&h| h > 0)?;
+    let count = width.checked_mul(height)?;
+    (count.checked_mul(4)? == pixmap.pixels.len()).then_some(count)
+}
+
+#[derive(Debug)]
+pub struct Tray {
+    rx: Arc<Mutex<watch::Receiver<Vec<TrayItem>>>>,
+}
+
+impl Tray {
+    pub fn new() -> Self {
+        let (tx, rx) = watch::channel(vec![]);
+        tokio::spawn(run(tx));
+        Self {
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "tray".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::TrayItemsUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Runs the tray client for the rest of the session, reconnecting whenever the connection to the
+/// watcher is lost.
+async fn run(tx: watch::Sender<Vec<TrayItem>>) {
+    const MIN_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    let mut backoff = MIN_BACKOFF;
+    let mut hosting_watcher = false;
+    while !tx.is_closed() {
+        match run_client(&tx, &mut hosting_watcher).await {
+            Ok(()) if tx.is_closed() => {
+                log::debug!("tray: no longer listened to, stopping");
+                return;
+            }
+            Ok(()) => {
+                log::info!("tray: StatusNotifierWatcher changed, reconnecting");
+                backoff = MIN_BACKOFF;
+            }
+            Err(e) => {
+                log::warn!("tray: {e}, retrying in {}s", backoff.as_secs());
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+        // Items of the old watcher are gone; the new client will resend the current ones
+        tx.send_replace(Vec::new());
+    }
+}
+
+/// Connects a tray client and forwards its items to `tx`.
+///
+/// Returns `Ok` when the watcher went away or was replaced, and the client should reconnect, or
+/// when nobody listens to `tx` anymore.
+async fn run_client(
+    tx: &watch::Sender<Vec<TrayItem>>,
+    hosting_watcher: &mut bool,
+) -> Result<(), BoxError> {
+    let connection = zbus::Connection::session().await?;
+    let dbus = zbus::fdo::DBusProxy::new(&connection).await?;
+
+    let watcher_name = zbus::names::BusName::try_from(watcher::WATCHER_NAME)?;
+    if !dbus.name_has_owner(watcher_name.clone()).await? && !*hosting_watcher {
+        log::info!("tray: no StatusNotifierWatcher running, hosting one");
+        watcher::host().await?;
+        *hosting_watcher = true;
+    }
+    let mut watcher_changes = dbus
+        .receive_name_owner_changed_with_args(&[(0, watcher::WATCHER_NAME)])
+        .await?;
+
+    let client = Client::new().await?;
+    let mut tray_rx = client.subscribe();
+    loop {
+        let items = client
+            .items()
+            .lock()
+            .expect("mutex should not be poisoned")
+            .values()
+            .map(|item| item.into())
+            .collect();
+        if tx.send(items).is_err() {
+            // Nobody is listening anymore
+            return Ok(());
+        }
+
+        tokio::select! {
+            event = tray_rx.recv() => match event {
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::debug!("tray: skipped {skipped} events");
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    return Err("tray client stopped".into());
+                }
+            },
+            Some(_) = watcher_changes.next() => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! A minimal `org.kde.StatusNotifierWatcher`, hosted by limbo when no other watcher is running.
+//!
+//! See <https://www.freedesktop.org/wiki/Specifications/StatusNotifierItem/StatusNotifierWatcher/>
+
+use iced::futures::StreamExt;
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::{fdo, interface};
+
+pub const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+/// Object path used by items that register with a bus name only.
+const DEFAULT_ITEM_PATH: &str = "/StatusNotifierItem";
+
+#[derive(Debug, Default)]
+struct Watcher {
+    /// Registered items as `<bus name><object path>`.
+    items: Vec<String>,
+    /// Bus names of registered hosts.
+    hosts: Vec<String>,
+}
+
+#[interface(name = "org.kde.StatusNotifierWatcher")]
+impl Watcher {
+    async fn register_status_notifier_item(
+        &mut self,
+        service: &str,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        let sender = header
+            .sender()
+            .ok_or_else(|| fdo::Error::InvalidArgs("missing sender".to_string()))?;
+
+        // Items may register with either their bus name or an object path on the sender
+        let item = if service.starts_with('/') {
+            format!("{sender}{service}")
+        } else {
+            format!("{service}{DEFAULT_ITEM_PATH}")
+        };
+        if self.items.contains(&item) {
+            return Ok(());
+        }
+
+        log::debug!("tray watcher: registered item {item}");
+        self.items.push(item.clone());
+        self.registered_status_notifier_items_changed(&emitter)
+            .await?;
+        Self::status_notifier_item_registered(&emitter, &item).await?;
+        Ok(())
+    }
+
+    async fn register_status_notifier_host(
+        &mut self,
+        service: &str,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        if !self.hosts.iter().any(|h| h == service) {
+            self.hosts.push(service.to_string());
+            self.is_status_notifier_host_registered_changed(&emitter)
+                .await?;
+            Self::status_notifier_host_registered(&emitter).await?;
+        }
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn registered_status_notifier_items(&self) -> Vec<String> {
+        self.items.clone()
+    }
+
+    #[zbus(property)]
+    fn is_status_notifier_host_registered(&self) -> bool {
+        !self.hosts.is_empty()
+    }
+
+    #[zbus(property)]
+    fn protocol_version(&self) -> i32 {
+        0
+    }
+
+    #[zbus(signal)]
+    async fn status_notifier_item_registered(
+        emitter: &SignalEmitter<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_item_unregistered(
+        emitter: &SignalEmitter<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_host_registered(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_host_unregistered(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+}
+
+impl Watcher {
+    /// Forgets all items and hosts owned by `name`, which has left the bus.
+    async fn remove_owner(&mut self, name: &str, emitter: &SignalEmitter<'_>) -> zbus::Result<()> {
+        let prefix = format!("{name}/");
+        let (removed, kept) = std::mem::take(&mut self.items)
+            .into_iter()
+            .partition::<Vec<_>, _>(|item| item.starts_with(&prefix));
+        self.items = kept;
+        if !removed.is_empty() {
+            self.registered_status_notifier_items_changed(emitter)
+                .await?;
+        }
+        for item in removed {
+            log::debug!("tray watcher: unregistered item {item}");
+            Self::status_notifier_item_unregistered(emitter, &item).await?;
+        }
+
+        let hosts_before = self.hosts.len();
+        self.hosts.retain(|h| h != name);
+        if self.hosts.len() != hosts_before {
+            self.is_status_notifier_host_registered_changed(emitter)
+                .await?;
+            Self::status_notifier_host_unregistered(emitter).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Starts hosting a watcher on the session bus for the rest of the session.
+pub async fn host() -> zbus::Result<()> {
+    let connection = zbus::connection::Builder::session()?
+        .serve_at(WATCHER_PATH, Watcher::default())?
+        .name(WATCHER_NAME)?
+        .build()
+        .await?;
+
+    // Unregister items and hosts when their owner disconnects
+    let dbus = fdo::DBusProxy::new(&connection).await?;
+    let mut owner_changes = dbus.receive_name_owner_changed().await?;
+    tokio::spawn(async move {
+        let Ok(iface) = connection
+            .object_server()
+            .interface::<_, Watcher>(WATCHER_PATH)
+            .await
+        else {
+            return;
+        };
+
+        while let Some(signal) = owner_changes.next().await {
+            let Ok(args) = signal.args() else {
+                continue;
+            };
+            if args.new_owner().is_some() {
+                continue;
+            }
+            let name = args.name().to_string();
+            let result = iface
+                .get_mut()
+                .await
+                .remove_owner(&name, iface.signal_emitter())
+                .await;
+            if let Err(e) = result {
+                log::warn!("tray watcher: failed to unregister {name}: {e}");
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,359 @@
+//! Current conditions and a daily forecast from Open-Meteo, see [`open_meteo`].
+//!
+//! Responses are cached on disk, so that restarting limbo doesn't refetch them and the last
+//! known weather is shown while offline.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use jiff::civil::Date;
+use tokio::sync::{Mutex, watch};
+
+use crate::cache;
+use crate::config::types::{General, Unit};
+use crate::message::Message;
+
+mod open_meteo;
+
+/// Interval at which the weather is fetched.
+const FETCH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Weather condition, grouped from the WMO weather interpretation codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Fog,
+    Drizzle,
+    Rain,
+    Snow,
+    Thunderstorm,
+}
+
+impl Condition {
+    fn from_wmo(code: u8) -> Self {
+        match code {
+            0 | 1 => Condition::Clear,
+            2 => Condition::PartlyCloudy,
+            3 => Condition::Cloudy,
+            45 | 48 => Condition::Fog,
+            51..=57 => Condition::Drizzle,
+            61..=67 | 80..=82 => Condition::Rain,
+            71..=77 | 85 | 86 => Condition::Snow,
+            95..=99 => Condition::Thunderstorm,
+            _ => Condition::Cloudy,
+        }
+    }
+}
+
+/// Temperatures and wind speeds are in the configured units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conditions {
+    pub condition: Condition,
+    pub is_day: bool,
+    pub temperature: f32,
+    pub apparent_temperature: f32,
+    pub wind_speed: f32,
+    /// Whether the wind is strong enough to be the most notable thing about the weather.
+    pub windy: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyForecast {
+    pub date: Date,
+    pub condition: Condition,
+    pub max: f32,
+    pub min: f32,
+    pub apparent_max: f32,
+    pub apparent_min: f32,
+    /// In percent.
+    pub precipitation_probability: Option<u8>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WeatherState {
+    /// `None` until the weather was fetched once.
+    pub current: Option<Conditions>,
+    /// Starting today.
+    pub daily: Vec<DailyForecast>,
+    /// Whether the last fetch failed, the weather is then the last known one.
+    pub offline: bool,
+}
+
+/// Where to fetch the weather for.
+#[derive(Debug, Clone)]
+struct Location {
+    lat: f64,
+    lon: f64,
+    imperial: bool,
+}
+
+#[derive(Debug)]
+pub struct Weather {
+    rx: Arc<Mutex<watch::Receiver<WeatherState>>>,
+}
+
+impl Weather {
+    /// Follows the weather at the configured location, unless it isn't `shown` or there is no
+    /// location, in which case nothing is fetched.
+    pub fn new(config: &General, shown: bool) -> Self {
+        if shown && !config.located() {
+            log::warn!("weather: no location configured, set lat and lon in general");
+        }
+        if !shown || !config.located() {
+            let (_, rx) = watch::channel(WeatherState::default());
+            return Self {
+                rx: Arc::new(Mutex::new(rx)),
+            };
+        }
+        Self::with_base_url(config, open_meteo::BASE_URL.to_string(), cache::dir())
+    }
+
+    /// Fetches from the Open-Meteo API at `base_url`, e.g. a local stand-in, caching responses
+    /// in `cache_dir`.
+    pub fn with_base_url(config: &General, base_url: String, cache_dir: Option<PathBuf>) -> Self {
+        let (tx, rx) = watch::channel(WeatherState::default());
+        let location = Location {
+            lat: config.lat,
+            lon: config.lon,
+            imperial: matches!(config.unit, Unit::Imperial),
+        };
+        tokio::spawn(run(base_url, location, cache_dir, tx));
+        Self {
+            rx: Arc::new(Mutex::new(rx)),
+        }
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        iced::Subscription::run_with_id(
+            "weather".to_string(),
+            iced::futures::stream::unfold(self.rx.clone(), |rx| async move {
+                let value = {
+                    let mut rx = rx.lock().await;
+                    if rx.changed().await.is_ok() {
+                        Some(rx.borrow().clone())
+                    } else {
+                        None
+                    }
+                };
+                value.map(|v| (Message::WeatherUpdate(v), rx))
+            }),
+        )
+    }
+}
+
+async fn run(
+    base_url: String,
+    location: Location,
+    cache_dir: Option<PathBuf>,
+    tx: watch::Sender<WeatherState>,
+) {
+    const MIN_BACKOFF: Duration = Duration::from_secs(60);
+
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("weather: failed to create the HTTP client: {e}");
+            return;
+        }
+    };
+    let url = open_meteo::forecast_url(&base_url, location.lat, location.lon, location.imperial);
+    let cache = cache_dir.map(|dir| cache_path(&dir, &url));
+
+    // A fresh enough cached response saves the fetch on startup
+    let mut delay = Duration::ZERO;
+    if let Some((body, age)) = read_cache(cache.as_deref()).await
+        && let Ok(state) = open_meteo::parse(&body, location.imperial)
+    {
+        tx.send_replace(state);
+        delay = FETCH_INTERVAL.saturating_sub(age);
+    }
+
+    let mut backoff = MIN_BACKOFF;
+    while !tx.is_closed() {
+        tokio::time::sleep(delay).await;
+        match fetch(&client, &url, location.imperial).await {
+            Ok((state, body)) => {
+                tx.send_if_modified(|current| {
+                    let changed = *current != state;
+                    *current = state;
+                    changed
+                });
+                write_cache(cache.as_deref(), &body).await;
+                backoff = MIN_BACKOFF;
+                delay = FETCH_INTERVAL;
+            }
+            Err(e) => {
+                log::warn!(
+                    "weather: failed to fetch ({e}), retrying in {}s",
+                    backoff.as_secs()
+                );
+                tx.send_if_modified(|current| !std::mem::replace(&mut current.offline, true));
+                delay = backoff;
+                backoff = (backoff * 2).min(FETCH_INTERVAL);
+            }
+        }
+    }
+}
+
+async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    imperial: bool,
+) -> Result<(WeatherState, String), Box<dyn std::error::Error + Send + Sync>> {
+    let body = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let state = open_meteo::parse(&body, imperial)?;
+    Ok((state, body))
+}
+
+/// The cache file in `dir` for responses of `url`, so that changing the location or units
+/// doesn't show stale weather.
+fn cache_path(dir: &Path, url: &str) -> PathBuf {
+    dir.join(format!("weather-{}.json", cache::file_name(url)))
+}
+
+/// The cached response and its age.
+async fn read_cache(path: Option<&Path>) -> Option<(String, Duration)> {
+    let path = path?;
+    let age = tokio::fs::metadata(path)
+        .await
+        .ok()?
+        .modified()
+        .ok()?
+        .elapsed()
+        .unwrap_or_default();
+    let body = tokio::fs::read_to_string(path).await.ok()?;
+    Some((body, age))
+}
+
+async fn write_cache(path: Option<&Path>, body: &str) {
+    let Some(path) = path else {
+        return;
+    };
+    if let Err(e) = cache::write(path, body.as_bytes()).await {
+        log::warn!("weather: failed to write {}: {e}", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{HttpResponse, HttpStub, TempDir};
+
+    const RESPONSE: &str = r#"{
+        "current": {
+            "temperature_2m": 21.5,
+            "apparent_temperature": 20.0,
+            "weather_code": 61,
+            "is_day": 1,
+            "wind_speed_10m": 12.0
+        },
+        "daily": {
+            "time": ["2024-05-20", "2024-05-21"],
+            "weather_code": [61, 0],
+            "temperature_2m_max": [22.0, 25.0],
+            "temperature_2m_min": [12.0, 14.0],
+            "apparent_temperature_max": [21.0, 24.0],
+            "apparent_temperature_min": [11.0, 13.0],
+            "precipitation_probability_max": [80, null]
+        }
+    }"#;
+
+    fn general() -> General {
+        General {
+            lat: 52.52,
+            lon: 13.41,
+            ..Default::default()
+        }
+    }
+
+    async fn wait_until(
+        rx: &mut watch::Receiver<WeatherState>,
+        done: impl Fn(&WeatherState) -> bool,
+    ) -> WeatherState {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let state = rx.borrow_and_update().clone();
+                if done(&state) {
+                    return state;
+                }
+                rx.changed().await.unwrap();
+            }
+        })
+        .await
+        .expect("timed out waiting for the weather")
+    }
+
+    #[tokio::test]
+    async fn fetches_and_caches_the_weather() {
+        let stub = HttpStub::start(|_| HttpResponse::new(200, RESPONSE)).await;
+        let dir = TempDir::new();
+
+        let weather =
+            Weather::with_base_url(&general(), stub.url.clone(), Some(dir.path().to_path_buf()));
+        let mut rx = weather.rx.lock().await.clone();
+        let state = wait_until(&mut rx, |state| state.current.is_some()).await;
+        let current = state.current.unwrap();
+        assert_eq!(current.condition, Condition::Rain);
+        assert_eq!(current.apparent_temperature, 20.0);
+        assert!(!current.windy);
+        assert_eq!(state.daily.len(), 2);
+        assert_eq!(state.daily[1].precipitation_probability, None);
+        let requests = stub.requests();
+        assert!(
+            requests[0]
+                .path
+                .starts_with("/v1/forecast?latitude=52.52&longitude=13.41&")
+        );
+
+        // The cache file is named after the URL, the same in every run
+        let url = open_meteo::forecast_url(&stub.url, 52.52, 13.41, false);
+        let path = cache_path(dir.path(), &url);
+        // It is written after the state is sent
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !path.exists() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("timed out waiting for the cache file");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), RESPONSE);
+
+        // A restart shows the cached weather without fetching it again
+        drop(weather);
+        let weather =
+            Weather::with_base_url(&general(), stub.url.clone(), Some(dir.path().to_path_buf()));
+        let mut rx = weather.rx.lock().await.clone();
+        wait_until(&mut rx, |state| state.current.is_some()).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(stub.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn reports_failed_fetches() {
+        let stub = HttpStub::start(|_| HttpResponse::new(500, "")).await;
+        let weather = Weather::with_base_url(&general(), stub.url.clone(), None);
+        let mut rx = weather.rx.lock().await.clone();
+        let state = wait_until(&mut rx, |state| state.offline).await;
+        assert_eq!(state.current, None);
+    }
+
+    #[tokio::test]
+    async fn fetches_nothing_unless_shown_somewhere() {
+        let located = Weather::new(&general(), false);
+        let unlocated = Weather::new(&General::default(), true);
+        for weather in [located, unlocated] {
+            let rx = weather.rx.lock().await;
+            assert!(rx.has_changed().is_err());
+        }
+    }
+}
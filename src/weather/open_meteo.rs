@@ -0,0 +1,99 @@
+//! Requests to and responses of the Open-Meteo forecast API.
+//!
+//! See <https://open-meteo.com/en/docs>
+
+use jiff::civil::Date;
+use serde::Deserialize;
+
+use super::{Condition, Conditions, DailyForecast, WeatherState};
+
+pub const BASE_URL: &str = "https://api.open-meteo.com";
+/// Number of days in the forecast, including today.
+const FORECAST_DAYS: u8 = 5;
+/// Wind speed from which it counts as windy, in km/h or mph.
+const WINDY_KMH: f32 = 40.0;
+const WINDY_MPH: f32 = 25.0;
+
+/// The forecast URL for the location at `lat` and `lon`.
+pub fn forecast_url(base_url: &str, lat: f64, lon: f64, imperial: bool) -> String {
+    let mut url = format!(
+        "{base_url}/v1/forecast?latitude={lat}&longitude={lon}\
+         &current=temperature_2m,apparent_temperature,weather_code,is_day,wind_speed_10m\
+         &daily=weather_code,temperature_2m_max,temperature_2m_min,apparent_temperature_max,\
+         apparent_temperature_min,precipitation_probability_max\
+         &timezone=auto&forecast_days={FORECAST_DAYS}"
+    );
+    if imperial {
+        url.push_str("&temperature_unit=fahrenheit&wind_speed_unit=mph");
+    }
+    url
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    current: Current,
+    daily: Daily,
+}
+
+#[derive(Debug, Deserialize)]
+struct Current {
+    temperature_2m: f32,
+    apparent_temperature: f32,
+    weather_code: u8,
+    is_day: u8,
+    wind_speed_10m: f32,
+}
+
+/// Each field holds one value per day.
+#[derive(Debug, Deserialize)]
+struct Daily {
+    time: Vec<String>,
+    weather_code: Vec<u8>,
+    temperature_2m_max: Vec<f32>,
+    temperature_2m_min: Vec<f32>,
+    apparent_temperature_max: Vec<f32>,
+    apparent_temperature_min: Vec<f32>,
+    precipitation_probability_max: Vec<Option<u8>>,
+}
+
+/// Parses a forecast response for the units it was requested in.
+pub fn parse(body: &str, imperial: bool) -> serde_json::Result<WeatherState> {
+    let response: Response = serde_json::from_str(body)?;
+    let current = response.current;
+    let windy = if imperial { WINDY_MPH } else { WINDY_KMH };
+
+    let daily = &response.daily;
+    let days = daily
+        .time
+        .iter()
+        .enumerate()
+        .filter_map(|(i, date)| {
+            Some(DailyForecast {
+                date: date.parse::<Date>().ok()?,
+                condition: Condition::from_wmo(*daily.weather_code.get(i)?),
+                max: *daily.temperature_2m_max.get(i)?,
+                min: *daily.temperature_2m_min.get(i)?,
+                apparent_max: *daily.apparent_temperature_max.get(i)?,
+                apparent_min: *daily.apparent_temperature_min.get(i)?,
+                precipitation_probability: daily
+                    .precipitation_probability_max
+                    .get(i)
+                    .copied()
+                    .flatten(),
+            })
+        })
+        .collect();
+
+    Ok(WeatherState {
+        current: Some(Conditions {
+            condition: Condition::from_wmo(current.weather_code),
+            is_day: current.is_day != 0,
+            temperature: current.temperature_2m,
+            apparent_temperature: current.apparent_temperature,
+            wind_speed: current.wind_speed_10m,
+            windy: current.wind_speed_10m >= windy,
+        }),
+        daily: days,
+        offline: false,
+    })
+}